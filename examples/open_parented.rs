@@ -1,70 +1,66 @@
+use std::any::Any;
+
 use baseview::{
-    Event, EventStatus, PhySize, Window, WindowEvent, WindowHandle, WindowHandler,
-    WindowScalePolicy,
+    Event, EventStatus, Framebuffer, MouseButton, MouseCursor, MouseEvent, Window, WindowEvent,
+    WindowHandle, WindowHandler,
 };
-use std::num::NonZeroU32;
+
+const CHILD_COLORS: [u32; 2] = [0xFFAA0000, 0xFF0000AA];
 
 struct ParentWindowHandler {
-    _ctx: softbuffer::Context,
-    surface: softbuffer::Surface,
-    current_size: PhySize,
+    framebuffer: Framebuffer,
     damaged: bool,
 
-    _child_window: Option<WindowHandle>,
+    child_window: WindowHandle,
+    child_color: usize,
 }
 
 impl ParentWindowHandler {
     pub fn new(window: &mut Window) -> Self {
-        let ctx = unsafe { softbuffer::Context::new(window) }.unwrap();
-        let mut surface = unsafe { softbuffer::Surface::new(&ctx, window) }.unwrap();
-        surface.resize(NonZeroU32::new(512).unwrap(), NonZeroU32::new(512).unwrap()).unwrap();
-
         let window_open_options = baseview::WindowOpenOptions {
             title: "baseview child".into(),
             size: baseview::Size::new(256.0, 256.0),
-            scale: WindowScalePolicy::SystemScaleFactor,
-
-            // TODO: Add an example that uses the OpenGL context
-            #[cfg(feature = "opengl")]
-            gl_config: None,
+            ..Default::default()
         };
         let child_window =
             Window::open_parented(window, window_open_options, ChildWindowHandler::new);
 
-        // TODO: no way to query physical size initially?
+        // The child window keeps its own cursor while the mouse is over it
+        window.set_mouse_cursor(MouseCursor::Crosshair);
+
         Self {
-            _ctx: ctx,
-            surface,
-            current_size: PhySize::new(512, 512),
+            framebuffer: window.create_framebuffer(),
             damaged: true,
-            _child_window: Some(child_window),
+            child_window,
+            child_color: 0,
         }
     }
 }
 
 impl WindowHandler for ParentWindowHandler {
-    fn on_frame(&mut self, _window: &mut Window) {
-        let mut buf = self.surface.buffer_mut().unwrap();
+    fn on_frame(&mut self, window: &mut Window) {
         if self.damaged {
-            buf.fill(0xFFAAAAAA);
+            self.framebuffer.pixels_mut().fill(0xFFAAAAAA);
             self.damaged = false;
         }
-        buf.present().unwrap();
+        window.present(&self.framebuffer);
     }
 
     fn on_event(&mut self, _window: &mut Window, event: Event) -> EventStatus {
         match event {
             Event::Window(WindowEvent::Resized(info)) => {
                 println!("Parent Resized: {:?}", info);
-                let new_size = info.physical_size();
-                self.current_size = new_size;
-
-                if let (Some(width), Some(height)) =
-                    (NonZeroU32::new(new_size.width), NonZeroU32::new(new_size.height))
-                {
-                    self.surface.resize(width, height).unwrap();
-                    self.damaged = true;
-                }
+                self.framebuffer.resize(info.physical_size());
+                self.damaged = true;
+
+                // The child window always covers half of the parent window
+                let size = info.logical_size();
+                self.child_window.resize(baseview::Size::new(size.width / 2.0, size.height / 2.0));
+            }
+            Event::Mouse(MouseEvent::ButtonPressed { button: MouseButton::Left, .. }) => {
+                // Clicking the parent window changes the child window's color
+                self.child_color = (self.child_color + 1) % CHILD_COLORS.len();
+                self.child_window.send_message(CHILD_COLORS[self.child_color]);
             }
             Event::Mouse(e) => println!("Parent Mouse event: {:?}", e),
             Event::Keyboard(e) => println!("Parent Keyboard event: {:?}", e),
@@ -76,46 +72,34 @@ impl WindowHandler for ParentWindowHandler {
 }
 
 struct ChildWindowHandler {
-    _ctx: softbuffer::Context,
-    surface: softbuffer::Surface,
-    current_size: PhySize,
+    framebuffer: Framebuffer,
+    color: u32,
     damaged: bool,
 }
 
 impl ChildWindowHandler {
     pub fn new(window: &mut Window) -> Self {
-        let ctx = unsafe { softbuffer::Context::new(window) }.unwrap();
-        let mut surface = unsafe { softbuffer::Surface::new(&ctx, window) }.unwrap();
-        surface.resize(NonZeroU32::new(512).unwrap(), NonZeroU32::new(512).unwrap()).unwrap();
+        window.set_mouse_cursor(MouseCursor::Hand);
 
-        // TODO: no way to query physical size initially?
-        Self { _ctx: ctx, surface, current_size: PhySize::new(256, 256), damaged: true }
+        Self { framebuffer: window.create_framebuffer(), color: CHILD_COLORS[0], damaged: true }
     }
 }
 
 impl WindowHandler for ChildWindowHandler {
-    fn on_frame(&mut self, _window: &mut Window) {
-        let mut buf = self.surface.buffer_mut().unwrap();
+    fn on_frame(&mut self, window: &mut Window) {
         if self.damaged {
-            buf.fill(0xFFAA0000);
+            self.framebuffer.pixels_mut().fill(self.color);
             self.damaged = false;
         }
-        buf.present().unwrap();
+        window.present(&self.framebuffer);
     }
 
     fn on_event(&mut self, _window: &mut Window, event: Event) -> EventStatus {
         match event {
             Event::Window(WindowEvent::Resized(info)) => {
                 println!("Child Resized: {:?}", info);
-                let new_size = info.physical_size();
-                self.current_size = new_size;
-
-                if let (Some(width), Some(height)) =
-                    (NonZeroU32::new(new_size.width), NonZeroU32::new(new_size.height))
-                {
-                    self.surface.resize(width, height).unwrap();
-                    self.damaged = true;
-                }
+                self.framebuffer.resize(info.physical_size());
+                self.damaged = true;
             }
             Event::Mouse(e) => println!("Child Mouse event: {:?}", e),
             Event::Keyboard(e) => println!("Child Keyboard event: {:?}", e),
@@ -124,17 +108,20 @@ impl WindowHandler for ChildWindowHandler {
 
         EventStatus::Captured
     }
+
+    fn on_message(&mut self, _window: &mut Window, message: Box<dyn Any + Send>) {
+        if let Ok(color) = message.downcast::<u32>() {
+            self.color = *color;
+            self.damaged = true;
+        }
+    }
 }
 
 fn main() {
     let window_open_options = baseview::WindowOpenOptions {
         title: "baseview".into(),
         size: baseview::Size::new(512.0, 512.0),
-        scale: WindowScalePolicy::SystemScaleFactor,
-
-        // TODO: Add an example that uses the OpenGL context
-        #[cfg(feature = "opengl")]
-        gl_config: None,
+        ..Default::default()
     };
 
     Window::open_blocking(window_open_options, ParentWindowHandler::new);
@@ -1,13 +1,10 @@
-use std::num::NonZeroU32;
 use std::time::Duration;
 
 use rtrb::{Consumer, RingBuffer};
 
 #[cfg(target_os = "macos")]
 use baseview::{copy_to_clipboard, MouseEvent};
-use baseview::{
-    Event, EventStatus, PhySize, Window, WindowEvent, WindowHandler, WindowScalePolicy,
-};
+use baseview::{Event, EventStatus, Framebuffer, Window, WindowEvent, WindowHandler};
 
 #[derive(Debug, Clone)]
 enum Message {
@@ -17,20 +14,17 @@ enum Message {
 struct OpenWindowExample {
     rx: Consumer<Message>,
 
-    _ctx: softbuffer::Context,
-    surface: softbuffer::Surface,
-    current_size: PhySize,
+    framebuffer: Framebuffer,
     damaged: bool,
 }
 
 impl WindowHandler for OpenWindowExample {
-    fn on_frame(&mut self, _window: &mut Window) {
-        let mut buf = self.surface.buffer_mut().unwrap();
+    fn on_frame(&mut self, window: &mut Window) {
         if self.damaged {
-            buf.fill(0xFFAAAAAA);
+            self.framebuffer.pixels_mut().fill(0xFFAAAAAA);
             self.damaged = false;
         }
-        buf.present().unwrap();
+        window.present(&self.framebuffer);
 
         while let Ok(message) = self.rx.pop() {
             println!("Message: {:?}", message);
@@ -43,15 +37,8 @@ impl WindowHandler for OpenWindowExample {
             Event::Mouse(MouseEvent::ButtonPressed { .. }) => copy_to_clipboard("This is a test!"),
             Event::Window(WindowEvent::Resized(info)) => {
                 println!("Resized: {:?}", info);
-                let new_size = info.physical_size();
-                self.current_size = new_size;
-
-                if let (Some(width), Some(height)) =
-                    (NonZeroU32::new(new_size.width), NonZeroU32::new(new_size.height))
-                {
-                    self.surface.resize(width, height).unwrap();
-                    self.damaged = true;
-                }
+                self.framebuffer.resize(info.physical_size());
+                self.damaged = true;
             }
             _ => {}
         }
@@ -66,11 +53,7 @@ fn main() {
     let window_open_options = baseview::WindowOpenOptions {
         title: "baseview".into(),
         size: baseview::Size::new(512.0, 512.0),
-        scale: WindowScalePolicy::SystemScaleFactor,
-
-        // TODO: Add an example that uses the OpenGL context
-        #[cfg(feature = "opengl")]
-        gl_config: None,
+        ..Default::default()
     };
 
     let (mut tx, rx) = RingBuffer::new(128);
@@ -83,18 +66,10 @@ fn main() {
         }
     });
 
-    Window::open_blocking(window_open_options, |window| {
-        let ctx = unsafe { softbuffer::Context::new(window) }.unwrap();
-        let mut surface = unsafe { softbuffer::Surface::new(&ctx, window) }.unwrap();
-        surface.resize(NonZeroU32::new(512).unwrap(), NonZeroU32::new(512).unwrap()).unwrap();
-
-        OpenWindowExample {
-            _ctx: ctx,
-            surface,
-            rx,
-            current_size: PhySize::new(512, 512),
-            damaged: true,
-        }
+    Window::open_blocking(window_open_options, |window| OpenWindowExample {
+        rx,
+        framebuffer: window.create_framebuffer(),
+        damaged: true,
     });
 }
 
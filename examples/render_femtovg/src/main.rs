@@ -1,7 +1,7 @@
 use baseview::gl::GlConfig;
 use baseview::{
     Event, EventStatus, MouseEvent, PhyPoint, Size, Window, WindowEvent, WindowHandler, WindowInfo,
-    WindowOpenOptions, WindowScalePolicy,
+    WindowOpenOptions,
 };
 use femtovg::renderer::OpenGl;
 use femtovg::{Canvas, Color};
@@ -15,8 +15,7 @@ struct FemtovgExample {
 
 impl FemtovgExample {
     fn new(window: &mut Window) -> Self {
-        let context = window.gl_context().unwrap();
-        unsafe { context.make_current() };
+        let context = unsafe { window.gl_context().unwrap().make_current_guard() };
 
         let renderer =
             unsafe { OpenGl::new_from_function(|s| context.get_proc_address(s)) }.unwrap();
@@ -25,7 +24,7 @@ impl FemtovgExample {
         // TODO: get actual window width
         canvas.set_size(512, 512, 1.0);
 
-        unsafe { context.make_not_current() };
+        drop(context);
         Self {
             canvas,
             current_size: WindowInfo::from_logical_size(Size { width: 512.0, height: 512.0 }, 1.0),
@@ -41,8 +40,7 @@ impl WindowHandler for FemtovgExample {
             return;
         }
 
-        let context = window.gl_context().unwrap();
-        unsafe { context.make_current() };
+        let context = unsafe { window.gl_context().unwrap().make_current_guard() };
 
         let screen_height = self.canvas.height();
         let screen_width = self.canvas.width();
@@ -71,7 +69,7 @@ impl WindowHandler for FemtovgExample {
         // Tell renderer to execute all drawing commands
         self.canvas.flush();
         context.swap_buffers();
-        unsafe { context.make_not_current() };
+        drop(context);
         self.damaged = false;
     }
 
@@ -98,9 +96,8 @@ fn main() {
     let window_open_options = WindowOpenOptions {
         title: "Femtovg on Baseview".into(),
         size: Size::new(512.0, 512.0),
-        scale: WindowScalePolicy::SystemScaleFactor,
-
         gl_config: Some(GlConfig { alpha_bits: 8, ..GlConfig::default() }),
+        ..Default::default()
     };
 
     Window::open_blocking(window_open_options, FemtovgExample::new);
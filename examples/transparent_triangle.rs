@@ -0,0 +1,150 @@
+use baseview::gl::glow::{self, HasContext};
+use baseview::gl::GlConfig;
+use baseview::{Event, EventStatus, Window, WindowEvent, WindowHandler};
+
+const VERTEX_SHADER: &str = r#"
+#version 150 core
+
+in vec2 position;
+in vec4 color;
+out vec4 v_color;
+
+void main() {
+    v_color = color;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"
+#version 150 core
+
+in vec4 v_color;
+out vec4 frag_color;
+
+void main() {
+    // The compositor expects premultiplied alpha
+    frag_color = vec4(v_color.rgb * v_color.a, v_color.a);
+}
+"#;
+
+/// The vertices' positions and colors. The colors get more transparent towards the bottom.
+#[rustfmt::skip]
+const VERTICES: [f32; 18] = [
+    // x     y     r    g    b    a
+     0.0,  0.8,  1.0, 0.3, 0.2, 0.9,
+    -0.8, -0.8,  0.2, 1.0, 0.3, 0.5,
+     0.8, -0.8,  0.2, 0.3, 1.0, 0.2,
+];
+
+struct TransparentTriangle {
+    gl: glow::Context,
+    program: glow::Program,
+    vertex_array: glow::VertexArray,
+    viewport: (i32, i32),
+}
+
+impl TransparentTriangle {
+    fn new(window: &mut Window) -> Self {
+        if let Some(err) = window.gl_context_error() {
+            panic!("Could not create the OpenGL context: {:?}", err);
+        }
+
+        let context = window.gl_context().expect("The window has no OpenGL context");
+        let gl = unsafe { context.glow_context() };
+
+        unsafe {
+            let _current = context.make_current_guard();
+
+            let program = gl.create_program().unwrap();
+            let shaders =
+                [(glow::VERTEX_SHADER, VERTEX_SHADER), (glow::FRAGMENT_SHADER, FRAGMENT_SHADER)]
+                    .map(|(kind, source)| {
+                        let shader = gl.create_shader(kind).unwrap();
+                        gl.shader_source(shader, source);
+                        gl.compile_shader(shader);
+                        if !gl.get_shader_compile_status(shader) {
+                            panic!(
+                                "Could not compile a shader: {}",
+                                gl.get_shader_info_log(shader)
+                            );
+                        }
+                        gl.attach_shader(program, shader);
+
+                        shader
+                    });
+
+            gl.bind_attrib_location(program, 0, "position");
+            gl.bind_attrib_location(program, 1, "color");
+            gl.link_program(program);
+            if !gl.get_program_link_status(program) {
+                panic!("Could not link the program: {}", gl.get_program_info_log(program));
+            }
+            for shader in shaders {
+                gl.detach_shader(program, shader);
+                gl.delete_shader(shader);
+            }
+
+            let vertex_array = gl.create_vertex_array().unwrap();
+            gl.bind_vertex_array(Some(vertex_array));
+
+            let bytes: Vec<u8> = VERTICES.iter().flat_map(|value| value.to_ne_bytes()).collect();
+            let buffer = gl.create_buffer().unwrap();
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(buffer));
+            gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, &bytes, glow::STATIC_DRAW);
+
+            let stride = 6 * std::mem::size_of::<f32>() as i32;
+            gl.vertex_attrib_pointer_f32(0, 2, glow::FLOAT, false, stride, 0);
+            gl.enable_vertex_attrib_array(0);
+            gl.vertex_attrib_pointer_f32(1, 4, glow::FLOAT, false, stride, 2 * 4);
+            gl.enable_vertex_attrib_array(1);
+
+            let size = window.size().physical_size();
+
+            Self { gl, program, vertex_array, viewport: (size.width as i32, size.height as i32) }
+        }
+    }
+}
+
+impl WindowHandler for TransparentTriangle {
+    fn on_frame(&mut self, window: &mut Window) {
+        let Some(context) = window.gl_context() else { return };
+
+        unsafe {
+            let _current = context.make_current_guard();
+
+            let gl = &self.gl;
+            gl.viewport(0, 0, self.viewport.0, self.viewport.1);
+            // Everything that isn't covered by the triangle shows the desktop
+            gl.clear_color(0.0, 0.0, 0.0, 0.0);
+            gl.clear(glow::COLOR_BUFFER_BIT);
+
+            gl.use_program(Some(self.program));
+            gl.bind_vertex_array(Some(self.vertex_array));
+            gl.draw_arrays(glow::TRIANGLES, 0, 3);
+
+            context.swap_buffers();
+        }
+    }
+
+    fn on_event(&mut self, _window: &mut Window, event: Event) -> EventStatus {
+        if let Event::Window(WindowEvent::Resized(info)) = event {
+            let size = info.physical_size();
+            self.viewport = (size.width as i32, size.height as i32);
+        }
+
+        EventStatus::Captured
+    }
+}
+
+fn main() {
+    let window_open_options = baseview::WindowOpenOptions {
+        title: "baseview transparency".into(),
+        size: baseview::Size::new(512.0, 512.0),
+        transparent: true,
+        drag_n_drop: false,
+        gl_config: Some(GlConfig { alpha_bits: 8, ..GlConfig::default() }),
+        ..Default::default()
+    };
+
+    Window::open_blocking(window_open_options, TransparentTriangle::new);
+}
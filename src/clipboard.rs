@@ -5,6 +5,284 @@ use crate::win as platform;
 #[cfg(target_os = "linux")]
 use crate::x11 as platform;
 
+/// An image that was read from the clipboard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardImage {
+    pub width: u32,
+    pub height: u32,
+    /// The image's pixels in RGBA order with four bytes per pixel, stored row by row starting at
+    /// the top left corner of the image.
+    pub rgba: Vec<u8>,
+}
+
+/// Access to the system clipboard. Both text and images can be stored on the clipboard. Reading
+/// from the clipboard returns `None` if the clipboard doesn't contain data of the requested type,
+/// or if that data could not be read.
+///
+/// On Linux the clipboard's contents are served by a background thread as long as no other
+/// application takes over the clipboard, so the contents remain available after the window has
+/// been closed for as long as the process is running.
+#[derive(Default)]
+pub struct Clipboard {
+    _private: (),
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the clipboard's contents with the given text.
+    pub fn set_text(&mut self, text: &str) {
+        platform::clipboard::set_text(text);
+    }
+
+    /// Read text from the clipboard.
+    pub fn get_text(&self) -> Option<String> {
+        platform::clipboard::get_text()
+    }
+
+    /// Replace the clipboard's contents with an image. `rgba` contains the image's pixels in RGBA
+    /// order, row by row starting at the top left corner. Empty images are ignored, and the
+    /// clipboard's contents stay the same.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rgba` does not contain exactly `width * height * 4` bytes.
+    pub fn set_image(&mut self, rgba: &[u8], width: u32, height: u32) {
+        assert_eq!(
+            rgba.len(),
+            width as usize * height as usize * 4,
+            "The image data does not match the image's size"
+        );
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        platform::clipboard::set_image(rgba, width, height);
+    }
+
+    /// Read an image from the clipboard.
+    pub fn get_image(&self) -> Option<ClipboardImage> {
+        platform::clipboard::get_image()
+    }
+}
+
+/// Replace the clipboard's contents with the given text. This is a shorthand for
+/// [`Clipboard::set_text`].
 pub fn copy_to_clipboard(data: &str) {
-    platform::copy_to_clipboard(data)
+    Clipboard::new().set_text(data)
+}
+
+/// The size of a `BITMAPINFOHEADER`.
+const DIB_HEADER_SIZE: usize = 40;
+/// The size of a `BITMAPFILEHEADER`.
+const BMP_FILE_HEADER_SIZE: usize = 14;
+
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+
+/// Encode an RGBA image as a 32-bit device independent bitmap, which is a BMP file without the
+/// file header. This is the format used for `CF_DIB` on Windows.
+#[allow(dead_code)]
+pub(crate) fn encode_dib(rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let mut dib = Vec::with_capacity(DIB_HEADER_SIZE + rgba.len());
+    dib.extend_from_slice(&(DIB_HEADER_SIZE as u32).to_le_bytes());
+    dib.extend_from_slice(&(width as i32).to_le_bytes());
+    // A negative height would store the rows from top to bottom, but not every application
+    // supports that
+    dib.extend_from_slice(&(height as i32).to_le_bytes());
+    dib.extend_from_slice(&1u16.to_le_bytes());
+    dib.extend_from_slice(&32u16.to_le_bytes());
+    dib.extend_from_slice(&BI_RGB.to_le_bytes());
+    dib.extend_from_slice(&(rgba.len() as u32).to_le_bytes());
+    dib.extend_from_slice(&[0; 16]);
+
+    for row in rgba.chunks_exact(width as usize * 4).rev() {
+        for pixel in row.chunks_exact(4) {
+            dib.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+        }
+    }
+
+    dib
+}
+
+/// Encode an RGBA image as a BMP file.
+#[allow(dead_code)]
+pub(crate) fn encode_bmp(rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let dib = encode_dib(rgba, width, height);
+
+    let mut bmp = Vec::with_capacity(BMP_FILE_HEADER_SIZE + dib.len());
+    bmp.extend_from_slice(b"BM");
+    bmp.extend_from_slice(&((BMP_FILE_HEADER_SIZE + dib.len()) as u32).to_le_bytes());
+    bmp.extend_from_slice(&[0; 4]);
+    bmp.extend_from_slice(&((BMP_FILE_HEADER_SIZE + DIB_HEADER_SIZE) as u32).to_le_bytes());
+    bmp.extend_from_slice(&dib);
+
+    bmp
+}
+
+/// Decode a BMP file containing an uncompressed 24-bit or 32-bit image.
+#[allow(dead_code)]
+pub(crate) fn decode_bmp(bmp: &[u8]) -> Option<ClipboardImage> {
+    if bmp.len() < BMP_FILE_HEADER_SIZE || &bmp[..2] != b"BM" {
+        return None;
+    }
+
+    let pixels_offset = read_u32(bmp, 10)? as usize;
+
+    decode_dib(&bmp[BMP_FILE_HEADER_SIZE..], pixels_offset.checked_sub(BMP_FILE_HEADER_SIZE))
+}
+
+/// Decode a device independent bitmap containing an uncompressed 24-bit or 32-bit image.
+/// `pixels_offset` is the offset of the pixel data from the start of the header, if known.
+pub(crate) fn decode_dib(dib: &[u8], pixels_offset: Option<usize>) -> Option<ClipboardImage> {
+    let header_size = read_u32(dib, 0)? as usize;
+    if header_size < DIB_HEADER_SIZE {
+        return None;
+    }
+
+    let width = read_u32(dib, 4)? as i32;
+    let height = read_u32(dib, 8)? as i32;
+    let bits_per_pixel = u16::from_le_bytes([*dib.get(14)?, *dib.get(15)?]);
+    let compression = read_u32(dib, 16)?;
+    if width <= 0 || height == 0 {
+        return None;
+    }
+
+    // The channel masks for `BI_BITFIELDS` images either follow the header, or they are part of
+    // the larger header versions
+    let (masks, masks_size) = match (compression, bits_per_pixel) {
+        (BI_RGB, 24) => ([0xff0000, 0xff00, 0xff, 0], 0),
+        (BI_RGB, 32) => ([0xff0000, 0xff00, 0xff, 0xff000000], 0),
+        (BI_BITFIELDS, 32) => {
+            let masks_size = if header_size >= DIB_HEADER_SIZE + 12 { 0 } else { 12 };
+            let alpha_mask =
+                if header_size >= DIB_HEADER_SIZE + 16 { read_u32(dib, 52)? } else { 0 };
+            ([read_u32(dib, 40)?, read_u32(dib, 44)?, read_u32(dib, 48)?, alpha_mask], masks_size)
+        }
+        _ => return None,
+    };
+
+    let width = width as u32;
+    let top_down = height < 0;
+    let height = height.unsigned_abs();
+    let bytes_per_pixel = bits_per_pixel as usize / 8;
+    // Rows are padded to a multiple of four bytes
+    let stride = (width as usize * bytes_per_pixel + 3) / 4 * 4;
+
+    let pixels_offset = pixels_offset.unwrap_or(header_size + masks_size);
+    let pixels_end = pixels_offset.checked_add(stride.checked_mul(height as usize)?)?;
+    let pixels = dib.get(pixels_offset..pixels_end)?;
+
+    let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height as usize {
+        let row = if top_down { y } else { height as usize - 1 - y };
+        let row = &pixels[row * stride..][..width as usize * bytes_per_pixel];
+
+        for pixel in row.chunks_exact(bytes_per_pixel) {
+            let mut value = [0; 4];
+            value[..bytes_per_pixel].copy_from_slice(pixel);
+            let value = u32::from_le_bytes(value);
+
+            for mask in masks {
+                rgba.push(extract_channel(value, mask));
+            }
+        }
+    }
+
+    // 32-bit images without an alpha channel usually leave the unused byte set to zero
+    if masks[3] == 0 || rgba.chunks_exact(4).all(|pixel| pixel[3] == 0) {
+        for pixel in rgba.chunks_exact_mut(4) {
+            pixel[3] = 255;
+        }
+    }
+
+    Some(ClipboardImage { width, height, rgba })
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Extract a color channel from a pixel and scale it to eight bits.
+fn extract_channel(value: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+
+    let max = mask >> mask.trailing_zeros();
+    let channel = (value & mask) >> mask.trailing_zeros();
+
+    ((channel as u64 * 255 + max as u64 / 2) / max as u64) as u8
+}
+
+/// Encode an RGBA image as a PNG file. This doesn't compress the image data.
+#[allow(dead_code)]
+pub(crate) fn encode_png(rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+    // Every row starts with the filter type, which is always zero here
+    let mut raw_data = Vec::with_capacity((width as usize * 4 + 1) * height as usize);
+    for row in rgba.chunks_exact(width as usize * 4) {
+        raw_data.push(0);
+        raw_data.extend_from_slice(row);
+    }
+
+    // A zlib stream using uncompressed deflate blocks
+    let mut image_data = vec![0x78, 0x01];
+    let mut blocks = raw_data.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        image_data.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_last_block = blocks.peek().is_none();
+        image_data.push(is_last_block as u8);
+        image_data.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        image_data.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        image_data.extend_from_slice(block);
+    }
+    image_data.extend_from_slice(&adler32(&raw_data).to_be_bytes());
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // Eight bits per channel, RGBA, no interlacing
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    write_png_chunk(&mut png, b"IHDR", &header);
+    write_png_chunk(&mut png, b"IDAT", &image_data);
+    write_png_chunk(&mut png, b"IEND", &[]);
+
+    png
+}
+
+fn write_png_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+
+    let mut crc = 0xffff_ffffu32;
+    for &byte in chunk_type.iter().chain(data) {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    png.extend_from_slice(&(!crc).to_be_bytes());
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+
+    (b << 16) | a
 }
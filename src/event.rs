@@ -1,20 +1,42 @@
+use std::cell::Cell;
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
-use keyboard_types::{KeyboardEvent, Modifiers};
+use keyboard_types::{Code, KeyboardEvent, Modifiers};
 
-use crate::{Point, WindowInfo};
+use crate::{Point, Rect, WindowInfo};
 
+/// A mouse button. The same physical button is reported as the same variant on every platform.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum MouseButton {
     Left,
     Middle,
     Right,
+    /// The first side button, which usually navigates back. This is button 8 on Linux and
+    /// `XBUTTON1` on Windows.
     Back,
+    /// The second side button, which usually navigates forward. This is button 9 on Linux and
+    /// `XBUTTON2` on Windows.
     Forward,
+    /// Any additional button. These are numbered after the five buttons above, so a mouse's sixth
+    /// button is `Other(5)`, its seventh button is `Other(6)`, and so on. On Linux this is the X11
+    /// button number minus 5, since buttons 4 to 7 are used for scrolling. On macOS this is the
+    /// event's `buttonNumber`. Windows only supports five mouse buttons, so this is never
+    /// reported there.
     Other(u8),
 }
 
-/// A scroll movement.
+/// A scroll movement. Positive `y` values correspond to scrolling up.
+///
+/// The unit depends on the platform and the input device:
+///
+/// - On Linux every notch of a mouse wheel results in a [`ScrollDelta::Lines`] of exactly `1.0`.
+/// - On Windows mouse wheels result in [`ScrollDelta::Lines`], where one line corresponds to a
+///   single `WHEEL_DELTA`. High resolution mouse wheels may produce fractional lines.
+/// - On macOS trackpads and other precise scrolling devices result in [`ScrollDelta::Pixels`],
+///   while regular mouse wheels result in [`ScrollDelta::Lines`].
+///
+/// Use [`ScrollDelta::to_pixels`] to convert either kind of delta to a single unit.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ScrollDelta {
     /// A line-based scroll movement
@@ -25,7 +47,7 @@ pub enum ScrollDelta {
         /// The number of vertical lines scrolled
         y: f32,
     },
-    /// A pixel-based scroll movement
+    /// A pixel-based scroll movement, in logical pixels
     Pixels {
         /// The number of horizontal pixels scrolled
         x: f32,
@@ -34,11 +56,52 @@ pub enum ScrollDelta {
     },
 }
 
+impl ScrollDelta {
+    /// Convert this delta to an `(x, y)` pair of logical pixels, using `line_height` as the
+    /// number of logical pixels scrolled per line.
+    pub fn to_pixels(&self, line_height: f32) -> (f32, f32) {
+        match *self {
+            ScrollDelta::Lines { x, y } => (x * line_height, y * line_height),
+            ScrollDelta::Pixels { x, y } => (x, y),
+        }
+    }
+}
+
+/// Where a [`MouseEvent::WheelScrolled`] event is in a scroll gesture. Scrolling on a touchpad
+/// starts with a [`ScrollPhase::Began`] event and ends with a [`ScrollPhase::Ended`] event. When
+/// the platform keeps scrolling after the user lifted their fingers, the momentum scrolling
+/// events that follow use the `Momentum` phases. This can be used to implement snapping once all
+/// scrolling has ended.
+///
+/// Phases are only reported on macOS. Regular mouse wheels, and all scrolling on the other
+/// platforms, always use [`ScrollPhase::Changed`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScrollPhase {
+    /// The user started scrolling.
+    Began,
+    /// The user scrolled, or the scroll wheel was turned.
+    Changed,
+    /// The user stopped scrolling, for instance by lifting their fingers off the touchpad.
+    Ended,
+    /// Momentum scrolling started after the user stopped scrolling.
+    MomentumBegan,
+    /// The content keeps scrolling because of the scroll gesture's momentum.
+    MomentumChanged,
+    /// Momentum scrolling ended, either because it came to a stop or because the user interrupted
+    /// it.
+    MomentumEnded,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum MouseEvent {
-    /// The mouse cursor was moved
+    /// The mouse cursor was moved. This is also sent after a
+    /// [`WindowEvent::Resized`] event while the cursor is inside of the window, since resizing
+    /// the window can change the cursor's position relative to the window.
     CursorMoved {
-        /// The logical coordinates of the mouse position
+        /// The logical coordinates of the mouse position. On macOS and on Linux (with XInput 2)
+        /// this keeps the sub-pixel precision reported by the platform, which is useful for
+        /// precise dragging. [`Point::to_physical`][crate::Point::to_physical] can be used to get
+        /// the pixel-snapped physical position.
         position: Point,
         /// The modifiers that were held down just before the event.
         modifiers: Modifiers,
@@ -64,6 +127,18 @@ pub enum MouseEvent {
     WheelScrolled {
         /// How much was scrolled, in factional lines.
         delta: ScrollDelta,
+        /// Where this event is in a scroll gesture.
+        phase: ScrollPhase,
+        /// The modifiers that were held down just before the event.
+        modifiers: Modifiers,
+    },
+
+    /// The mouse moved during a [relative drag][crate::Window::begin_relative_drag]. The cursor
+    /// itself stays in place during the drag, so this is sent instead of
+    /// [`MouseEvent::CursorMoved`].
+    RawMotion {
+        /// How far the mouse moved since the last event, in logical pixels.
+        delta: Point,
         /// The modifiers that were held down just before the event.
         modifiers: Modifiers,
     },
@@ -78,6 +153,9 @@ pub enum MouseEvent {
     /// May not be available on all platforms.
     CursorLeft,
 
+    /// Something is being dragged over the window. Every `DragEntered` event is followed by
+    /// exactly one [`MouseEvent::DragLeft`] or [`MouseEvent::DragDropped`] event, including when
+    /// the window closes during the drag.
     DragEntered {
         /// The logical coordinates of the mouse position
         position: Point,
@@ -108,14 +186,183 @@ pub enum MouseEvent {
     },
 }
 
+/// The kind of device that generated a mouse event. See
+/// [`Window::pointer_kind`][crate::Window::pointer_kind].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PointerKind {
+    /// A regular mouse, or a device that could not be identified.
+    #[default]
+    Mouse,
+    Touchpad,
+    /// A pen or stylus, for instance on a graphics tablet.
+    Pen,
+    /// A touchscreen.
+    Touch,
+}
+
 #[derive(Debug, Clone)]
 pub enum WindowEvent {
+    /// The window's physical size is about to change, either because the window is being resized
+    /// or because its scale factor changes. This is sent right before the matching
+    /// [`WindowEvent::Resized`] event, so renderers can release or prepare resources that depend
+    /// on the surface's size. `from` is the current window info, and `to` is the window info that
+    /// will be sent with [`WindowEvent::Resized`].
+    ///
+    /// On macOS this is sent before the view changes size when the window is resized, unless the
+    /// window handler resizes the window itself, in which case both events are sent once the
+    /// handler returns. On Windows and Linux the window system has already resized the window at
+    /// this point, but surfaces the application manages itself keep their old size until the
+    /// application reconfigures them.
+    ///
+    /// This is not sent before the initial [`WindowEvent::Resized`] event after opening a window.
+    WillResize {
+        from: WindowInfo,
+        to: WindowInfo,
+    },
     Resized(WindowInfo),
+    /// Part of the window's contents were lost and need to be redrawn, for instance because
+    /// another window was covering it. Multiple damaged areas that are reported at once are
+    /// combined into a single rectangle. This is currently only sent on Linux.
+    Expose(Rect),
+    /// A standalone window has been moved. This contains the new position of the top left corner
+    /// of the window's contents in logical screen coordinates. This is not sent for parented
+    /// windows.
+    Moved(Point),
     Focused,
     Unfocused,
+    /// The window became the main window. This is only sent on macOS, which distinguishes between
+    /// the key window that receives keyboard input (see [`WindowEvent::Focused`]) and the main
+    /// window whose document the user is working on. A panel or an inspector can be the key
+    /// window while the main window stays the same. For parented windows this refers to the
+    /// host's window. The other platforms don't make this distinction, so there
+    /// [`WindowEvent::Focused`] can be used instead.
+    BecameMain,
+    /// The window stopped being the main window. See [`WindowEvent::BecameMain`].
+    ResignedMain,
+    /// The window has been shown with [`Window::show`][crate::Window::show]. On Linux this is also
+    /// sent when the window manager shows the window again, for instance after it was minimized.
+    Shown,
+    /// The window has been hidden with [`Window::hide`][crate::Window::hide]. On Linux this is
+    /// also sent when the window manager hides the window, for instance when it gets minimized.
+    Hidden,
+    /// The contents of the clipboard have changed, either because another application or because
+    /// this application put something on the clipboard. See [`Clipboard`][crate::Clipboard].
+    ///
+    /// On Linux this is sent when the owner of the clipboard changes, so it's not sent when an
+    /// application replaces the contents of a clipboard it already owns. On macOS the clipboard
+    /// is checked for changes once per frame.
+    ClipboardChanged,
+    /// The user asked to close a standalone window, for instance by clicking its close button.
+    /// Returning [`EventStatus::DeferClose`] keeps the window open, so the application can finish
+    /// up (like saving its state) and close the window later with
+    /// [`Window::close`][crate::Window::close]. Otherwise the window closes right away, and
+    /// [`WindowEvent::WillClose`] is sent next.
+    CloseRequested,
     WillClose,
 }
 
+/// The left and right modifier keys that are held down. [`Modifiers`] doesn't distinguish between
+/// the two sides, so this can be used alongside it through
+/// [`Window::modifier_sides`][crate::Window::modifier_sides].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ModifierSides(u8);
+
+impl ModifierSides {
+    pub const SHIFT_LEFT: Self = Self(1 << 0);
+    pub const SHIFT_RIGHT: Self = Self(1 << 1);
+    pub const CONTROL_LEFT: Self = Self(1 << 2);
+    pub const CONTROL_RIGHT: Self = Self(1 << 3);
+    pub const ALT_LEFT: Self = Self(1 << 4);
+    pub const ALT_RIGHT: Self = Self(1 << 5);
+    pub const META_LEFT: Self = Self(1 << 6);
+    pub const META_RIGHT: Self = Self(1 << 7);
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether all of the keys in `other` are held down.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
+
+    /// The side of the modifier key with this physical key code, if it's a modifier key.
+    pub fn from_code(code: Code) -> Option<Self> {
+        match code {
+            Code::ShiftLeft => Some(Self::SHIFT_LEFT),
+            Code::ShiftRight => Some(Self::SHIFT_RIGHT),
+            Code::ControlLeft => Some(Self::CONTROL_LEFT),
+            Code::ControlRight => Some(Self::CONTROL_RIGHT),
+            Code::AltLeft => Some(Self::ALT_LEFT),
+            Code::AltRight => Some(Self::ALT_RIGHT),
+            Code::MetaLeft => Some(Self::META_LEFT),
+            Code::MetaRight => Some(Self::META_RIGHT),
+            _ => None,
+        }
+    }
+
+    /// The side-agnostic modifiers for the keys in this set.
+    pub fn modifiers(self) -> Modifiers {
+        let mut modifiers = Modifiers::empty();
+        for &(sides, modifier) in MODIFIER_SIDES {
+            if self.0 & sides.0 != 0 {
+                modifiers |= modifier;
+            }
+        }
+
+        modifiers
+    }
+
+    /// Forget about the keys whose modifier is not in `modifiers`. Used on platforms where the
+    /// sides are tracked from key events, which may miss key releases while the window doesn't
+    /// have keyboard focus.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn retain_modifiers(&mut self, modifiers: Modifiers) {
+        for &(sides, modifier) in MODIFIER_SIDES {
+            if !modifiers.contains(modifier) {
+                self.remove(sides);
+            }
+        }
+    }
+}
+
+/// Both sides of every modifier key, along with the corresponding side-agnostic modifier.
+const MODIFIER_SIDES: &[(ModifierSides, Modifiers)] = &[
+    (ModifierSides(ModifierSides::SHIFT_LEFT.0 | ModifierSides::SHIFT_RIGHT.0), Modifiers::SHIFT),
+    (
+        ModifierSides(ModifierSides::CONTROL_LEFT.0 | ModifierSides::CONTROL_RIGHT.0),
+        Modifiers::CONTROL,
+    ),
+    (ModifierSides(ModifierSides::ALT_LEFT.0 | ModifierSides::ALT_RIGHT.0), Modifiers::ALT),
+    (ModifierSides(ModifierSides::META_LEFT.0 | ModifierSides::META_RIGHT.0), Modifiers::META),
+];
+
+impl std::ops::BitOr for ModifierSides {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl std::ops::BitOrAssign for ModifierSides {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Event {
     Mouse(MouseEvent),
@@ -135,6 +382,15 @@ pub enum DropEffect {
 pub enum DropData {
     None,
     Files(Vec<PathBuf>),
+    /// Used instead of [`DropData::Files`] when a large number of files is being dragged. These
+    /// paths have not been resolved yet, so they may be relative to the drag source's working
+    /// directory, they may contain symlinks, and the files they point to may not exist. Resolving
+    /// them all at once can take a while, so this is left to the window handler, for instance by
+    /// calling [`Path::canonicalize()`][std::path::Path::canonicalize()] on only the paths it
+    /// needs once the files have been dropped.
+    ///
+    /// This is currently only used on Linux.
+    UnresolvedFiles(Vec<PathBuf>),
 }
 
 /// Return value for [WindowHandler::on_event](`crate::WindowHandler::on_event()`),
@@ -144,7 +400,11 @@ pub enum DropData {
 /// For most event types, this value won't have any effect. This is the case
 /// when there is no clear meaning of passing back the event to the platform,
 /// or it isn't obviously useful. Currently, only [`Event::Keyboard`] variants
-/// are supported.
+/// and the [`MouseEvent::ButtonPressed`], [`MouseEvent::ButtonReleased`], and
+/// [`MouseEvent::WheelScrolled`] mouse events are supported. Ignoring these
+/// mouse events lets the host handle them for parented windows, for instance
+/// to show its own context menu on a right click. Cursor movement and
+/// enter/leave events are always consumed by the window.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum EventStatus {
     /// Event was handled by your window and will not be sent back to the
@@ -159,4 +419,112 @@ pub enum EventStatus {
     /// We are prepared to handle the data in the drag and dropping will
     /// result in [DropEffect]
     AcceptDrop(DropEffect),
+    /// Keep the window open in response to [`WindowEvent::CloseRequested`]. Closing the window
+    /// with [`Window::close`][crate::Window::close] or
+    /// [`WindowHandle::close`][crate::WindowHandle::close] always works, so that can be used as a
+    /// fallback if finishing up takes too long.
+    DeferClose,
+    /// Handle a [`MouseEvent::ButtonPressed`] event like [`EventStatus::Captured`], and keep
+    /// capturing the mouse until that same button is released. Until then the window receives
+    /// [`MouseEvent::CursorMoved`] events even when the cursor is outside of the window, and it
+    /// receives the matching [`MouseEvent::ButtonReleased`] event, even if other buttons are
+    /// pressed and released in the meantime. This is the usual way to implement dragging a
+    /// control. For other events this is the same as [`EventStatus::Captured`].
+    ///
+    /// On macOS the view always keeps receiving the mouse events for a button until it's released,
+    /// so this only differs from [`EventStatus::Captured`] on Windows and Linux. See
+    /// [`Window::set_mouse_capture`][crate::Window::set_mouse_capture] for capturing the mouse
+    /// without a button being held down.
+    CaptureMouse,
+}
+
+/// Converts the platform's event timestamps to [`Instant`]s. The timestamps are in milliseconds
+/// with an arbitrary origin, and they wrap around after about 49.7 days. The first timestamp is
+/// anchored to the current time, and later timestamps are converted relative to that anchor. The
+/// difference to the anchor is treated as signed so events that are slightly older than the
+/// anchor, which can happen when events are delivered out of order, end up before it instead of
+/// about 49.7 days after it.
+pub(crate) struct EventClock {
+    anchor: Cell<Option<(u32, Instant)>>,
+}
+
+impl EventClock {
+    pub fn new() -> Self {
+        Self { anchor: Cell::new(None) }
+    }
+
+    pub fn to_instant(&self, timestamp: u32) -> Instant {
+        let now = Instant::now();
+        let Some((anchor_timestamp, anchor_instant)) = self.anchor.get() else {
+            self.anchor.set(Some((timestamp, now)));
+            return now;
+        };
+
+        let diff = timestamp.wrapping_sub(anchor_timestamp) as i32;
+        let elapsed = Duration::from_millis(u64::from(diff.unsigned_abs()));
+        if diff < 0 {
+            // Older events never move the anchor, since that would shift every later event
+            return anchor_instant.checked_sub(elapsed).unwrap_or(anchor_instant);
+        }
+
+        // An event can't have happened in the future. This happens when the platform's clock runs
+        // faster than ours, so the anchor is moved forward to compensate.
+        let instant = anchor_instant + elapsed;
+        if instant > now {
+            self.anchor.set(Some((timestamp, now)));
+            now
+        } else {
+            instant
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn anchored_clock(timestamp: u32) -> (EventClock, Instant) {
+        let clock = EventClock::new();
+        let anchor = clock.to_instant(timestamp);
+        (clock, anchor)
+    }
+
+    #[test]
+    fn event_clock_converts_relative_to_anchor() {
+        let (clock, anchor) = anchored_clock(10_000);
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert_eq!(clock.to_instant(10_015), anchor + Duration::from_millis(15));
+        assert_eq!(clock.to_instant(10_000), anchor);
+    }
+
+    #[test]
+    fn event_clock_handles_older_events() {
+        let (clock, anchor) = anchored_clock(10_000);
+
+        assert_eq!(clock.to_instant(9_990), anchor - Duration::from_millis(10));
+        // The older event must not have moved the anchor
+        assert_eq!(clock.to_instant(10_000), anchor);
+    }
+
+    #[test]
+    fn event_clock_handles_wrap_around() {
+        let (clock, anchor) = anchored_clock(u32::MAX - 4);
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert_eq!(clock.to_instant(5), anchor + Duration::from_millis(10));
+        assert_eq!(clock.to_instant(u32::MAX - 9), anchor - Duration::from_millis(5));
+    }
+
+    #[test]
+    fn event_clock_never_returns_future_instants() {
+        let (clock, anchor) = anchored_clock(10_000);
+
+        let instant = clock.to_instant(10_000 + 60_000);
+        assert!(instant < anchor + Duration::from_millis(60_000));
+        assert!(instant <= Instant::now());
+        // The anchor moved forward, so later events are relative to the new anchor
+        assert_eq!(clock.to_instant(10_000 + 60_000), instant);
+        assert_eq!(clock.to_instant(10_000 + 59_990), instant - Duration::from_millis(10));
+    }
 }
@@ -0,0 +1,65 @@
+use crate::PhySize;
+
+/// A buffer of pixels that can be drawn to a window without needing a graphics API. This can be
+/// created with [`Window::create_framebuffer`][crate::Window::create_framebuffer] and drawn to the
+/// window with [`Window::present`][crate::Window::present].
+///
+/// Every pixel is a `u32` in the `0x00RRGGBB` format. The upper 8 bits are ignored, and the
+/// pixels are always drawn fully opaque, even when the window uses a visual with an alpha channel.
+/// The pixels are stored row by row, starting at the top left corner of the window.
+pub struct Framebuffer {
+    size: PhySize,
+    pixels: Vec<u32>,
+}
+
+impl Framebuffer {
+    /// Create a new framebuffer with the given size in physical pixels. All pixels start out black.
+    pub fn new(size: PhySize) -> Self {
+        Self { size, pixels: vec![0; size.width as usize * size.height as usize] }
+    }
+
+    /// The size of the framebuffer in physical pixels.
+    pub fn size(&self) -> PhySize {
+        self.size
+    }
+
+    /// Change the size of the framebuffer. This should be done whenever the window gets resized.
+    /// The contents of the framebuffer are unspecified after resizing it.
+    pub fn resize(&mut self, size: PhySize) {
+        self.size = size;
+        self.pixels.resize(size.width as usize * size.height as usize, 0);
+    }
+
+    pub fn pixels(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [u32] {
+        &mut self.pixels
+    }
+
+    /// Copy the framebuffer's contents to a buffer of 8-bit RGBA pixels, for instance to save it
+    /// as an image. The rows start at the top of the window, and alpha is always fully opaque.
+    pub fn to_rgba(&self) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(self.pixels.len() * 4);
+        for pixel in &self.pixels {
+            let [_, r, g, b] = pixel.to_be_bytes();
+            rgba.extend_from_slice(&[r, g, b, 0xff]);
+        }
+
+        rgba
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_rgba_reorders_bytes_and_forces_alpha() {
+        let mut framebuffer = Framebuffer::new(PhySize::new(2, 1));
+        framebuffer.pixels_mut().copy_from_slice(&[0x00_12_34_56, 0x80_ab_cd_ef]);
+
+        assert_eq!(framebuffer.to_rgba(), [0x12, 0x34, 0x56, 0xff, 0xab, 0xcd, 0xef, 0xff]);
+    }
+}
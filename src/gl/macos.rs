@@ -21,10 +21,135 @@ use objc::{msg_send, sel, sel_impl};
 
 use super::{GlConfig, GlError, Profile};
 
+type CGLContextObj = *mut c_void;
+
+const K_CGL_CP_SURFACE_BACKING_SIZE: i32 = 304;
+const K_CGL_CE_SURFACE_BACKING_SIZE: i32 = 305;
+
+#[link(name = "OpenGL", kind = "framework")]
+extern "C" {
+    fn CGLSetParameter(ctx: CGLContextObj, pname: i32, params: *const i32) -> i32;
+    fn CGLEnable(ctx: CGLContextObj, pname: i32) -> i32;
+    fn CGLDisable(ctx: CGLContextObj, pname: i32) -> i32;
+}
+
+/// The `NSOpenGLPixelFormat` attributes for an OpenGL configuration.
+fn pixel_format_attributes(config: &GlConfig) -> Result<Vec<u32>, GlError> {
+    let version = if config.version < (3, 2) && config.profile == Profile::Compatibility {
+        NSOpenGLProfileVersionLegacy
+    } else if config.version == (3, 2) && config.profile == Profile::Core {
+        NSOpenGLProfileVersion3_2Core
+    } else if config.version > (3, 2) && config.profile == Profile::Core {
+        NSOpenGLProfileVersion4_1Core
+    } else {
+        return Err(GlError::VersionNotSupported);
+    };
+
+    #[rustfmt::skip]
+    let mut attrs = vec![
+        NSOpenGLPFAOpenGLProfile as u32, version as u32,
+        NSOpenGLPFAColorSize as u32, (config.red_bits + config.blue_bits + config.green_bits) as u32,
+        NSOpenGLPFAAlphaSize as u32, config.alpha_bits as u32,
+        NSOpenGLPFADepthSize as u32, config.depth_bits as u32,
+        NSOpenGLPFAStencilSize as u32, config.stencil_bits as u32,
+        NSOpenGLPFAAccelerated as u32,
+    ];
+
+    if let Some(samples) = config.samples {
+        #[rustfmt::skip]
+        attrs.extend_from_slice(&[
+            NSOpenGLPFAMultisample as u32,
+            NSOpenGLPFASampleBuffers as u32, 1,
+            NSOpenGLPFASamples as u32, samples as u32,
+        ]);
+    }
+
+    if config.double_buffer {
+        attrs.push(NSOpenGLPFADoubleBuffer as u32);
+    }
+
+    attrs.push(0);
+
+    Ok(attrs)
+}
+
+/// AppKit can't list its pixel formats, so this requests a pixel format for every common
+/// combination of attributes, and lists the pixel formats AppKit picked for those.
+pub fn enumerate_configs() -> Vec<GlConfig> {
+    let mut configs = Vec::new();
+    for alpha_bits in [0, 8] {
+        for depth_bits in [0, 16, 24, 32] {
+            for stencil_bits in [0, 8] {
+                for samples in [None, Some(2), Some(4), Some(8)] {
+                    for double_buffer in [true, false] {
+                        let requested = GlConfig {
+                            alpha_bits,
+                            depth_bits,
+                            stencil_bits,
+                            samples,
+                            double_buffer,
+                            ..GlConfig::default()
+                        };
+
+                        let Some(config) = (unsafe { probe_pixel_format(requested) }) else {
+                            continue;
+                        };
+                        if !configs.contains(&config) {
+                            configs.push(config);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    configs
+}
+
+/// The configuration of the pixel format AppKit picks for `requested`, if there is one.
+unsafe fn probe_pixel_format(requested: GlConfig) -> Option<GlConfig> {
+    let attrs = pixel_format_attributes(&requested).ok()?;
+    let pixel_format = NSOpenGLPixelFormat::alloc(nil).initWithAttributes_(&attrs);
+    if pixel_format == nil {
+        return None;
+    }
+
+    let config = pixel_format_config(pixel_format, requested);
+
+    let () = msg_send![pixel_format, release];
+
+    Some(config)
+}
+
+/// The configuration of `pixel_format`. The fields that can't be queried from the pixel format are
+/// taken from `requested`.
+unsafe fn pixel_format_config(pixel_format: id, requested: GlConfig) -> GlConfig {
+    let attrib = |attribute| {
+        let mut value = 0;
+        pixel_format.getValues_forAttribute_forVirtualScreen_(&mut value, attribute, 0);
+        value
+    };
+
+    GlConfig {
+        alpha_bits: attrib(NSOpenGLPFAAlphaSize) as u8,
+        depth_bits: attrib(NSOpenGLPFADepthSize) as u8,
+        stencil_bits: attrib(NSOpenGLPFAStencilSize) as u8,
+        samples: if attrib(NSOpenGLPFASampleBuffers) > 0 {
+            Some(attrib(NSOpenGLPFASamples) as u8)
+        } else {
+            None
+        },
+        double_buffer: attrib(NSOpenGLPFADoubleBuffer) != 0,
+        ..requested
+    }
+}
+
 pub type CreationFailedError = ();
 pub struct GlContext {
     view: id,
     context: id,
+    /// The configuration of the pixel format the context was created with.
+    config: GlConfig,
 }
 
 impl GlContext {
@@ -41,41 +166,7 @@ impl GlContext {
 
         let parent_view = handle.ns_view as id;
 
-        let version = if config.version < (3, 2) && config.profile == Profile::Compatibility {
-            NSOpenGLProfileVersionLegacy
-        } else if config.version == (3, 2) && config.profile == Profile::Core {
-            NSOpenGLProfileVersion3_2Core
-        } else if config.version > (3, 2) && config.profile == Profile::Core {
-            NSOpenGLProfileVersion4_1Core
-        } else {
-            return Err(GlError::VersionNotSupported);
-        };
-
-        #[rustfmt::skip]
-        let mut attrs = vec![
-            NSOpenGLPFAOpenGLProfile as u32, version as u32,
-            NSOpenGLPFAColorSize as u32, (config.red_bits + config.blue_bits + config.green_bits) as u32,
-            NSOpenGLPFAAlphaSize as u32, config.alpha_bits as u32,
-            NSOpenGLPFADepthSize as u32, config.depth_bits as u32,
-            NSOpenGLPFAStencilSize as u32, config.stencil_bits as u32,
-            NSOpenGLPFAAccelerated as u32,
-        ];
-
-        if config.samples.is_some() {
-            #[rustfmt::skip]
-            attrs.extend_from_slice(&[
-                NSOpenGLPFAMultisample as u32,
-                NSOpenGLPFASampleBuffers as u32, 1,
-                NSOpenGLPFASamples as u32, config.samples.unwrap() as u32,
-            ]);
-        }
-
-        if config.double_buffer {
-            attrs.push(NSOpenGLPFADoubleBuffer as u32);
-        }
-
-        attrs.push(0);
-
+        let attrs = pixel_format_attributes(&config)?;
         let pixel_format = NSOpenGLPixelFormat::alloc(nil).initWithAttributes_(&attrs);
 
         if pixel_format == nil {
@@ -103,9 +194,18 @@ impl GlContext {
             NSOpenGLContextParameter::NSOpenGLCPSwapInterval,
         );
 
+        let config = pixel_format_config(pixel_format, config);
         let () = msg_send![pixel_format, release];
 
-        Ok(GlContext { view, context })
+        Ok(GlContext { view, context, config })
+    }
+
+    pub fn config(&self) -> &GlConfig {
+        &self.config
+    }
+
+    pub fn vsync(&self) -> bool {
+        self.config.vsync
     }
 
     pub unsafe fn make_current(&self) {
@@ -139,6 +239,37 @@ impl GlContext {
             let _: () = msg_send![self.view, setNeedsDisplay: YES];
         }
     }
+
+    /// Surfaces are opaque by default, which means that the alpha channel is ignored when the view
+    /// is composited.
+    pub(crate) fn set_opaque(&self, opaque: bool) {
+        unsafe {
+            self.context.setValues_forParameter_(
+                &(opaque as i32),
+                NSOpenGLContextParameter::NSOpenGLCPSurfaceOpacity,
+            );
+        }
+    }
+
+    /// Render to a surface with a fixed size in physical pixels that gets scaled to fit the view,
+    /// instead of using the view's backing scale factor. This is used to honor
+    /// [`WindowScalePolicy::ScaleFactor`][crate::WindowScalePolicy::ScaleFactor]. Passing `None`
+    /// goes back to the backing scale factor.
+    pub(crate) fn set_surface_size(&self, size: Option<(u32, u32)>) {
+        unsafe {
+            let cgl_context: CGLContextObj = msg_send![self.context, CGLContextObj];
+            match size {
+                Some((width, height)) => {
+                    let dims = [width as i32, height as i32];
+                    CGLSetParameter(cgl_context, K_CGL_CP_SURFACE_BACKING_SIZE, dims.as_ptr());
+                    CGLEnable(cgl_context, K_CGL_CE_SURFACE_BACKING_SIZE);
+                }
+                None => {
+                    CGLDisable(cgl_context, K_CGL_CE_SURFACE_BACKING_SIZE);
+                }
+            }
+        }
+    }
 }
 
 impl Drop for GlContext {
@@ -1,3 +1,4 @@
+use std::cell::UnsafeCell;
 use std::ffi::c_void;
 use std::marker::PhantomData;
 
@@ -21,7 +22,11 @@ mod macos;
 #[cfg(target_os = "macos")]
 use macos as platform;
 
-#[derive(Clone, Debug)]
+/// The version of `glow` used by [`GlContext::glow_context`].
+#[cfg(feature = "glow")]
+pub use glow;
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct GlConfig {
     pub version: (u8, u8),
     pub profile: Profile,
@@ -69,6 +74,20 @@ pub enum GlError {
     CreationFailed(platform::CreationFailedError),
 }
 
+/// List the pixel formats that can be used for OpenGL contexts on this system, as [`GlConfig`]s
+/// that can be passed to [`WindowOpenOptions::gl_config`][crate::WindowOpenOptions::gl_config].
+/// Renderers can use this to pick a supported combination of multisampling, sRGB and alpha bits
+/// before opening a window, instead of requesting a config that can't be created.
+///
+/// Only the fields that describe the pixel format are filled in. The version, profile and vsync
+/// fields are left at their default values since the pixel format doesn't determine those. On
+/// macOS the same goes for `srgb`, and the pixel formats are found by probing common
+/// combinations since AppKit can't enumerate them. Returns an empty list if OpenGL is not
+/// available.
+pub fn enumerate_configs() -> Vec<GlConfig> {
+    platform::enumerate_configs()
+}
+
 pub struct GlContext {
     context: platform::GlContext,
     phantom: PhantomData<*mut ()>,
@@ -99,17 +118,239 @@ impl GlContext {
         self.context.make_not_current();
     }
 
+    /// Make the context current, and make it not current again when the returned guard is
+    /// dropped. Unlike calling [`make_current`][Self::make_current] and
+    /// [`make_not_current`][Self::make_not_current] manually, this also releases the context when
+    /// the render code panics.
+    pub unsafe fn make_current_guard(&self) -> CurrentContext<'_> {
+        self.make_current();
+
+        CurrentContext { context: self }
+    }
+
     pub fn get_proc_address(&self, symbol: &str) -> *const c_void {
         self.context.get_proc_address(symbol)
     }
 
+    /// Load a [`glow::Context`] for this context. The context is made current while loading the
+    /// function pointers, since some platforms only return valid pointers for the current
+    /// context, and it's made not current again afterwards. Like with the functions loaded through
+    /// [`get_proc_address`][Self::get_proc_address], the context needs to be current whenever the
+    /// returned `glow` context is used.
+    ///
+    /// Requires the `glow` feature in addition to the `opengl` feature.
+    #[cfg(feature = "glow")]
+    pub unsafe fn glow_context(&self) -> glow::Context {
+        let context = self.make_current_guard();
+
+        glow::Context::from_loader_function(|symbol| context.get_proc_address(symbol))
+    }
+
     pub fn swap_buffers(&self) {
         self.context.swap_buffers();
     }
 
+    /// Read back the frame that has just been drawn as 8-bit RGBA pixels using `glReadPixels()`,
+    /// for instance to save it as an image. `size` is the window's size in physical pixels. The
+    /// rows start at the top of the window. This should be called after drawing the frame but
+    /// before [`swap_buffers`][Self::swap_buffers], since the back buffer's contents are undefined
+    /// after swapping. The pixel pack state is reset to its defaults while reading the pixels, and
+    /// it's restored afterwards.
+    ///
+    /// See [`Window::render_now`][crate::Window::render_now] for drawing a frame on demand.
+    ///
+    /// # Safety
+    ///
+    /// This context needs to be current on the calling thread, for instance through
+    /// [`make_current_guard`][Self::make_current_guard].
+    pub unsafe fn capture(&self, size: crate::PhySize) -> Vec<u8> {
+        type GetIntegerv = extern "system" fn(u32, *mut i32);
+        type PixelStorei = extern "system" fn(u32, i32);
+        type BindBuffer = extern "system" fn(u32, u32);
+        type ReadPixels = extern "system" fn(i32, i32, i32, i32, u32, u32, *mut c_void);
+        const GL_PACK_ROW_LENGTH: u32 = 0x0D02;
+        const GL_PACK_SKIP_ROWS: u32 = 0x0D03;
+        const GL_PACK_SKIP_PIXELS: u32 = 0x0D04;
+        const GL_PACK_ALIGNMENT: u32 = 0x0D05;
+        const GL_PIXEL_PACK_BUFFER: u32 = 0x88EB;
+        const GL_PIXEL_PACK_BUFFER_BINDING: u32 = 0x88ED;
+        const GL_RGBA: u32 = 0x1908;
+        const GL_UNSIGNED_BYTE: u32 = 0x1401;
+        // The pack parameters and their default values. Other values would change where and how
+        // many bytes `glReadPixels()` writes.
+        const PACK_PARAMETERS: [(u32, i32); 4] = [
+            (GL_PACK_ROW_LENGTH, 0),
+            (GL_PACK_SKIP_ROWS, 0),
+            (GL_PACK_SKIP_PIXELS, 0),
+            (GL_PACK_ALIGNMENT, 4),
+        ];
+
+        let (width, height) = (size.width as usize, size.height as usize);
+        let mut pixels = vec![0u8; width * height * 4];
+        let read_pixels = self.get_proc_address("glReadPixels");
+        let get_integerv = self.get_proc_address("glGetIntegerv");
+        let pixel_storei = self.get_proc_address("glPixelStorei");
+        if read_pixels.is_null()
+            || get_integerv.is_null()
+            || pixel_storei.is_null()
+            || pixels.is_empty()
+        {
+            return pixels;
+        }
+
+        let read_pixels: ReadPixels = std::mem::transmute(read_pixels);
+        let get_integerv: GetIntegerv = std::mem::transmute(get_integerv);
+        let pixel_storei: PixelStorei = std::mem::transmute(pixel_storei);
+        // Pixel buffer objects were only added in OpenGL 2.1
+        let bind_buffer = self.get_proc_address("glBindBuffer");
+        let bind_buffer = (!bind_buffer.is_null())
+            .then(|| std::mem::transmute::<*const c_void, BindBuffer>(bind_buffer));
+
+        let mut saved_parameters = [0; 4];
+        for (&(parameter, default), saved) in PACK_PARAMETERS.iter().zip(&mut saved_parameters) {
+            get_integerv(parameter, saved);
+            pixel_storei(parameter, default);
+        }
+        // With a pixel buffer object bound, the pointer would be treated as an offset into it
+        let mut pack_buffer = 0;
+        if let Some(bind_buffer) = bind_buffer {
+            get_integerv(GL_PIXEL_PACK_BUFFER_BINDING, &mut pack_buffer);
+            bind_buffer(GL_PIXEL_PACK_BUFFER, 0);
+        }
+
+        read_pixels(
+            0,
+            0,
+            width as i32,
+            height as i32,
+            GL_RGBA,
+            GL_UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut c_void,
+        );
+
+        for (&(parameter, _), &saved) in PACK_PARAMETERS.iter().zip(&saved_parameters) {
+            pixel_storei(parameter, saved);
+        }
+        if let Some(bind_buffer) = bind_buffer {
+            bind_buffer(GL_PIXEL_PACK_BUFFER, pack_buffer as u32);
+        }
+
+        // OpenGL's rows start at the bottom of the window
+        let row_len = width * 4;
+        for row in 0..height / 2 {
+            let (top, bottom) = pixels.split_at_mut((height - row - 1) * row_len);
+            top[row * row_len..(row + 1) * row_len].swap_with_slice(&mut bottom[..row_len]);
+        }
+
+        pixels
+    }
+
+    /// Whether [`swap_buffers`][Self::swap_buffers] waits for the display's next vertical blank.
+    /// This is the case when the context was created with [`GlConfig::vsync`] enabled, unless the
+    /// driver does not support changing the swap interval. Rendering in
+    /// [`WindowHandler::on_frame`][crate::WindowHandler::on_frame] is then paced by the display,
+    /// and a [frame interval][crate::Window::set_frame_interval] shorter than the display's
+    /// [refresh period][crate::Window::refresh_rate] does not result in more frames.
+    pub fn vsync(&self) -> bool {
+        self.context.vsync()
+    }
+
+    /// The configuration the context was actually created with. The pixel format the system picks
+    /// for the requested [`GlConfig`] can have more bits per channel, a different number of
+    /// samples, or no sRGB support, so renderers that enable multisampling or sRGB conversion
+    /// conditionally should check this instead of the requested config.
+    ///
+    /// The version and profile are the requested ones, since the context creation fails if those
+    /// aren't supported, and `vsync` matches [`vsync`][Self::vsync]. On macOS the pixel format
+    /// can't be queried for sRGB support, so `srgb` is always the requested value there.
+    pub fn config(&self) -> &GlConfig {
+        self.context.config()
+    }
+
+    /// The display's vertical blank counter, using the `GLX_SGI_video_sync` extension. This can be
+    /// used to tell how many vertical blanks have passed between two frames. Returns `None` if the
+    /// extension is not supported.
+    ///
+    /// # Safety
+    ///
+    /// This context needs to be current on the calling thread, for instance through
+    /// [`make_current_guard`][Self::make_current_guard].
+    #[cfg(target_os = "linux")]
+    pub unsafe fn video_sync_counter(&self) -> Option<u32> {
+        self.context.video_sync_counter()
+    }
+
     /// On macOS the `NSOpenGLView` needs to be resized separtely from our main view.
     #[cfg(target_os = "macos")]
     pub(crate) fn resize(&self, size: cocoa::foundation::NSSize) {
         self.context.resize(size);
     }
+
+    /// Whether the `NSOpenGLView`'s surface ignores its alpha channel on macOS.
+    #[cfg(target_os = "macos")]
+    pub(crate) fn set_opaque(&self, opaque: bool) {
+        self.context.set_opaque(opaque);
+    }
+
+    /// Use a fixed surface size in physical pixels on macOS to honor a forced scale factor.
+    #[cfg(target_os = "macos")]
+    pub(crate) fn set_surface_size(&self, size: Option<(u32, u32)>) {
+        self.context.set_surface_size(size);
+    }
+}
+
+/// Holds a window's OpenGL context. The context is either created when the window is opened, or
+/// later through [`Window::create_gl_context`][crate::Window::create_gl_context] while the window
+/// handler only has shared access to the window. It can only be set while it's empty, so the
+/// references handed out by [`get()`][Self::get()] stay valid until the cell is dropped.
+pub(crate) struct GlContextCell {
+    context: UnsafeCell<Option<GlContext>>,
+}
+
+impl GlContextCell {
+    pub fn new(context: Option<GlContext>) -> Self {
+        Self { context: UnsafeCell::new(context) }
+    }
+
+    pub fn get(&self) -> Option<&GlContext> {
+        // SAFETY: The context is only ever written to while it's `None`, and no references to it
+        //         can exist at that point
+        unsafe { (*self.context.get()).as_ref() }
+    }
+
+    /// Store `context` if the cell is still empty, and return the stored context.
+    pub fn get_or_set(&self, context: GlContext) -> &GlContext {
+        if self.get().is_none() {
+            // SAFETY: See above. `GlContext` is `!Sync`, so this can't race with another thread.
+            unsafe { *self.context.get() = Some(context) };
+        }
+
+        self.get().unwrap()
+    }
+
+    pub fn take(&mut self) -> Option<GlContext> {
+        self.context.get_mut().take()
+    }
+}
+
+/// A [`GlContext`] that is current on this thread. Created with
+/// [`GlContext::make_current_guard`], and makes the context not current again when dropped.
+pub struct CurrentContext<'a> {
+    context: &'a GlContext,
+}
+
+impl<'a> CurrentContext<'a> {
+    pub fn get_proc_address(&self, symbol: &str) -> *const c_void {
+        self.context.get_proc_address(symbol)
+    }
+
+    pub fn swap_buffers(&self) {
+        self.context.swap_buffers();
+    }
+}
+
+impl<'a> Drop for CurrentContext<'a> {
+    fn drop(&mut self) {
+        unsafe { self.context.make_not_current() };
+    }
 }
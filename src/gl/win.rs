@@ -3,9 +3,10 @@ use std::os::windows::ffi::OsStrExt;
 
 use raw_window_handle::RawWindowHandle;
 
-use winapi::shared::minwindef::{HINSTANCE, HMODULE};
+use winapi::shared::minwindef::{ATOM, HINSTANCE, HMODULE};
 use winapi::shared::ntdef::WCHAR;
 use winapi::shared::windef::{HDC, HGLRC, HWND};
+use winapi::um::errhandlingapi::GetLastError;
 use winapi::um::libloaderapi::{FreeLibrary, GetProcAddress, LoadLibraryA};
 use winapi::um::wingdi::{
     wglCreateContext, wglDeleteContext, wglGetProcAddress, wglMakeCurrent, ChoosePixelFormat,
@@ -31,11 +32,18 @@ const WGL_CONTEXT_PROFILE_MASK_ARB: i32 = 0x9126;
 const WGL_CONTEXT_CORE_PROFILE_BIT_ARB: i32 = 0x00000001;
 const WGL_CONTEXT_COMPATIBILITY_PROFILE_BIT_ARB: i32 = 0x00000002;
 
+const ERROR_INVALID_VERSION_ARB: u32 = 0x2095;
+const ERROR_INVALID_PROFILE_ARB: u32 = 0x2096;
+
 // See https://www.khronos.org/registry/OpenGL/extensions/ARB/WGL_ARB_pixel_format.txt
 
 type WglChoosePixelFormatARB =
     extern "system" fn(HDC, *const i32, *const f32, u32, *mut i32, *mut u32) -> i32;
 
+type WglGetPixelFormatAttribivARB =
+    extern "system" fn(HDC, i32, i32, u32, *const i32, *mut i32) -> i32;
+
+const WGL_NUMBER_PIXEL_FORMATS_ARB: i32 = 0x2000;
 const WGL_DRAW_TO_WINDOW_ARB: i32 = 0x2001;
 const WGL_ACCELERATION_ARB: i32 = 0x2003;
 const WGL_SUPPORT_OPENGL_ARB: i32 = 0x2010;
@@ -70,26 +78,35 @@ pub struct GlContext {
     hdc: HDC,
     hglrc: HGLRC,
     gl_library: HMODULE,
+    /// The configuration of the pixel format the context was created with.
+    config: GlConfig,
 }
 
 extern "C" {
     static __ImageBase: IMAGE_DOS_HEADER;
 }
 
-impl GlContext {
-    pub unsafe fn create(parent: &RawWindowHandle, config: GlConfig) -> Result<GlContext, GlError> {
-        let handle = if let RawWindowHandle::Win32(handle) = parent {
-            handle
-        } else {
-            return Err(GlError::InvalidWindowHandle);
-        };
-
-        if handle.hwnd.is_null() {
-            return Err(GlError::InvalidWindowHandle);
-        }
+/// The WGL extension functions needed to create the actual context. These can only be loaded while
+/// a context is current, so they're loaded through a temporary context for a hidden window.
+#[allow(non_snake_case)]
+struct WglExtensions {
+    wglCreateContextAttribsARB: WglCreateContextAttribsARB,
+    wglChoosePixelFormatARB: WglChoosePixelFormatARB,
+    wglGetPixelFormatAttribivARB: Option<WglGetPixelFormatAttribivARB>,
+    wglSwapIntervalEXT: Option<WglSwapIntervalEXT>,
+}
 
-        // Create temporary window and context to load function pointers
+/// The hidden window used to load [`WglExtensions`] and to query pixel formats. It's never shown,
+/// and it's destroyed again when this is dropped.
+struct TempWindow {
+    class: ATOM,
+    hinstance: HINSTANCE,
+    hwnd: HWND,
+    hdc: HDC,
+}
 
+impl TempWindow {
+    unsafe fn create() -> Result<TempWindow, GlError> {
         let class_name_str = format!("raw-gl-context-window-{}", uuid::Uuid::new_v4().to_simple());
         let mut class_name: Vec<WCHAR> = OsStr::new(&class_name_str).encode_wide().collect();
         class_name.push(0);
@@ -109,7 +126,8 @@ impl GlContext {
             return Err(GlError::CreationFailed(()));
         }
 
-        let hwnd_tmp = CreateWindowExW(
+        // Without `WS_VISIBLE` this window never shows up on the screen
+        let hwnd = CreateWindowExW(
             0,
             class as *const WCHAR,
             [0].as_ptr(),
@@ -124,12 +142,29 @@ impl GlContext {
             std::ptr::null_mut(),
         );
 
-        if hwnd_tmp.is_null() {
+        if hwnd.is_null() {
+            UnregisterClassW(class as *const WCHAR, hinstance);
             return Err(GlError::CreationFailed(()));
         }
 
-        let hdc_tmp = GetDC(hwnd_tmp);
+        let hdc = GetDC(hwnd);
 
+        Ok(TempWindow { class, hinstance, hwnd, hdc })
+    }
+}
+
+impl Drop for TempWindow {
+    fn drop(&mut self) {
+        unsafe {
+            ReleaseDC(self.hwnd, self.hdc);
+            DestroyWindow(self.hwnd);
+            UnregisterClassW(self.class as *const WCHAR, self.hinstance);
+        }
+    }
+}
+
+impl WglExtensions {
+    unsafe fn load(window: &TempWindow) -> Result<WglExtensions, GlError> {
         let pfd_tmp = PIXELFORMATDESCRIPTOR {
             nSize: std::mem::size_of::<PIXELFORMATDESCRIPTOR>() as u16,
             nVersion: 1,
@@ -143,17 +178,17 @@ impl GlContext {
             ..std::mem::zeroed()
         };
 
-        SetPixelFormat(hdc_tmp, ChoosePixelFormat(hdc_tmp, &pfd_tmp), &pfd_tmp);
+        let pixel_format_tmp = ChoosePixelFormat(window.hdc, &pfd_tmp);
+        if pixel_format_tmp == 0 || SetPixelFormat(window.hdc, pixel_format_tmp, &pfd_tmp) == 0 {
+            return Err(GlError::CreationFailed(()));
+        }
 
-        let hglrc_tmp = wglCreateContext(hdc_tmp);
+        let hglrc_tmp = wglCreateContext(window.hdc);
         if hglrc_tmp.is_null() {
-            ReleaseDC(hwnd_tmp, hdc_tmp);
-            UnregisterClassW(class as *const WCHAR, hinstance);
-            DestroyWindow(hwnd_tmp);
             return Err(GlError::CreationFailed(()));
         }
 
-        wglMakeCurrent(hdc_tmp, hglrc_tmp);
+        wglMakeCurrent(window.hdc, hglrc_tmp);
 
         #[allow(non_snake_case)]
         let wglCreateContextAttribsARB: Option<WglCreateContextAttribsARB> = {
@@ -179,6 +214,18 @@ impl GlContext {
             }
         };
 
+        #[allow(non_snake_case)]
+        let wglGetPixelFormatAttribivARB: Option<WglGetPixelFormatAttribivARB> = {
+            let symbol = CString::new("wglGetPixelFormatAttribivARB").unwrap();
+            let addr = wglGetProcAddress(symbol.as_ptr());
+            if !addr.is_null() {
+                #[allow(clippy::missing_transmute_annotations)]
+                Some(std::mem::transmute(addr))
+            } else {
+                None
+            }
+        };
+
         #[allow(non_snake_case)]
         let wglSwapIntervalEXT: Option<WglSwapIntervalEXT> = {
             let symbol = CString::new("wglSwapIntervalEXT").unwrap();
@@ -191,18 +238,157 @@ impl GlContext {
             }
         };
 
-        wglMakeCurrent(hdc_tmp, std::ptr::null_mut());
+        wglMakeCurrent(window.hdc, std::ptr::null_mut());
         wglDeleteContext(hglrc_tmp);
-        ReleaseDC(hwnd_tmp, hdc_tmp);
-        UnregisterClassW(class as *const WCHAR, hinstance);
-        DestroyWindow(hwnd_tmp);
+
+        // Drivers without these extensions only support legacy OpenGL 1.1 contexts
+        match (wglCreateContextAttribsARB, wglChoosePixelFormatARB) {
+            (Some(create_context_attribs), Some(choose_pixel_format)) => Ok(WglExtensions {
+                wglCreateContextAttribsARB: create_context_attribs,
+                wglChoosePixelFormatARB: choose_pixel_format,
+                wglGetPixelFormatAttribivARB,
+                wglSwapIntervalEXT,
+            }),
+            _ => Err(GlError::VersionNotSupported),
+        }
+    }
+}
+
+/// List the pixel formats that support hardware accelerated OpenGL rendering to a window.
+pub fn enumerate_configs() -> Vec<GlConfig> {
+    unsafe {
+        let Ok(window) = TempWindow::create() else { return Vec::new() };
+        let Ok(extensions) = WglExtensions::load(&window) else { return Vec::new() };
+        let Some(get_attrib) = extensions.wglGetPixelFormatAttribivARB else { return Vec::new() };
+
+        // The optional multisampling and sRGB attributes may not be supported by the driver, so
+        // every attribute is queried separately. Unsupported attributes are treated as 0.
+        let attrib = |pixel_format, attribute| {
+            let mut value = 0;
+            if get_attrib(window.hdc, pixel_format, 0, 1, &attribute, &mut value) == 0 {
+                0
+            } else {
+                value
+            }
+        };
+
+        let mut configs = Vec::new();
+        // The pixel format index is ignored for this query, but it still needs to be valid
+        for pixel_format in 1..=attrib(1, WGL_NUMBER_PIXEL_FORMATS_ARB) {
+            // These are the same requirements `GlContext::create()` uses
+            if attrib(pixel_format, WGL_DRAW_TO_WINDOW_ARB) == 0
+                || attrib(pixel_format, WGL_SUPPORT_OPENGL_ARB) == 0
+                || attrib(pixel_format, WGL_ACCELERATION_ARB) != WGL_FULL_ACCELERATION_ARB
+                || attrib(pixel_format, WGL_PIXEL_TYPE_ARB) != WGL_TYPE_RGBA_ARB
+            {
+                continue;
+            }
+
+            let config = pixel_format_attributes(
+                |attribute| attrib(pixel_format, attribute),
+                GlConfig::default(),
+            );
+
+            // Pixel formats that only differ in attributes we don't expose are listed once
+            if !configs.contains(&config) {
+                configs.push(config);
+            }
+        }
+
+        configs
+    }
+}
+
+/// The configuration of a pixel format, read through `attrib`. The version, profile and vsync
+/// fields are taken from `requested`, since the pixel format doesn't determine those.
+fn pixel_format_attributes(attrib: impl Fn(i32) -> i32, requested: GlConfig) -> GlConfig {
+    GlConfig {
+        red_bits: attrib(WGL_RED_BITS_ARB) as u8,
+        green_bits: attrib(WGL_GREEN_BITS_ARB) as u8,
+        blue_bits: attrib(WGL_BLUE_BITS_ARB) as u8,
+        alpha_bits: attrib(WGL_ALPHA_BITS_ARB) as u8,
+        depth_bits: attrib(WGL_DEPTH_BITS_ARB) as u8,
+        stencil_bits: attrib(WGL_STENCIL_BITS_ARB) as u8,
+        samples: if attrib(WGL_SAMPLE_BUFFERS_ARB) > 0 {
+            Some(attrib(WGL_SAMPLES_ARB) as u8)
+        } else {
+            None
+        },
+        srgb: attrib(WGL_FRAMEBUFFER_SRGB_CAPABLE_ARB) != 0,
+        double_buffer: attrib(WGL_DOUBLE_BUFFER_ARB) != 0,
+        ..requested
+    }
+}
+
+impl GlContext {
+    pub unsafe fn create(parent: &RawWindowHandle, config: GlConfig) -> Result<GlContext, GlError> {
+        let handle = if let RawWindowHandle::Win32(handle) = parent {
+            handle
+        } else {
+            return Err(GlError::InvalidWindowHandle);
+        };
+
+        if handle.hwnd.is_null() {
+            return Err(GlError::InvalidWindowHandle);
+        }
+
+        // The window doesn't need to be visible for any of this, so windows can be created hidden
+        // and shown once the context has been set up
+        let extensions = WglExtensions::load(&TempWindow::create()?)?;
 
         // Create actual context
 
         let hwnd = handle.hwnd as HWND;
 
         let hdc = GetDC(hwnd);
+        if hdc.is_null() {
+            return Err(GlError::CreationFailed(()));
+        }
 
+        let (hglrc, pixel_format) = match Self::create_context(hdc, &config, &extensions) {
+            Ok(context) => context,
+            Err(err) => {
+                ReleaseDC(hwnd, hdc);
+                return Err(err);
+            }
+        };
+
+        let gl_library_name = CString::new("opengl32.dll").unwrap();
+        let gl_library = LoadLibraryA(gl_library_name.as_ptr());
+
+        wglMakeCurrent(hdc, hglrc);
+        let vsync = match extensions.wglSwapIntervalEXT {
+            Some(swap_interval) => swap_interval(config.vsync as i32) != 0 && config.vsync,
+            None => false,
+        };
+        wglMakeCurrent(hdc, std::ptr::null_mut());
+
+        // Without `WGL_ARB_pixel_format` the pixel format can't be queried, but then it couldn't
+        // have been chosen either
+        let requested = GlConfig { vsync, ..config };
+        let config = match extensions.wglGetPixelFormatAttribivARB {
+            Some(get_attrib) => pixel_format_attributes(
+                |attribute| {
+                    let mut value = 0;
+                    if get_attrib(hdc, pixel_format, 0, 1, &attribute, &mut value) == 0 {
+                        0
+                    } else {
+                        value
+                    }
+                },
+                requested,
+            ),
+            None => requested,
+        };
+
+        Ok(GlContext { hwnd, hdc, hglrc, gl_library, config })
+    }
+
+    /// Set the window's pixel format and create a context matching `config` for it. Returns the
+    /// context along with the pixel format.
+    unsafe fn create_context(
+        hdc: HDC, config: &GlConfig, extensions: &WglExtensions,
+    ) -> Result<(HGLRC, i32), GlError> {
         #[rustfmt::skip]
         let pixel_format_attribs = [
             WGL_DRAW_TO_WINDOW_ARB, 1,
@@ -224,7 +410,7 @@ impl GlContext {
 
         let mut pixel_format = 0;
         let mut num_formats = 0;
-        wglChoosePixelFormatARB.unwrap()(
+        let result = (extensions.wglChoosePixelFormatARB)(
             hdc,
             pixel_format_attribs.as_ptr(),
             std::ptr::null(),
@@ -232,6 +418,9 @@ impl GlContext {
             &mut pixel_format,
             &mut num_formats,
         );
+        if result == 0 || num_formats == 0 {
+            return Err(GlError::CreationFailed(()));
+        }
 
         let mut pfd: PIXELFORMATDESCRIPTOR = std::mem::zeroed();
         DescribePixelFormat(
@@ -240,7 +429,9 @@ impl GlContext {
             std::mem::size_of::<PIXELFORMATDESCRIPTOR>() as u32,
             &mut pfd,
         );
-        SetPixelFormat(hdc, pixel_format, &pfd);
+        if SetPixelFormat(hdc, pixel_format, &pfd) == 0 {
+            return Err(GlError::CreationFailed(()));
+        }
 
         let profile_mask = match config.profile {
             Profile::Core => WGL_CONTEXT_CORE_PROFILE_BIT_ARB,
@@ -255,20 +446,30 @@ impl GlContext {
             0
         ];
 
-        let hglrc =
-            wglCreateContextAttribsARB.unwrap()(hdc, std::ptr::null_mut(), ctx_attribs.as_ptr());
+        let hglrc = (extensions.wglCreateContextAttribsARB)(
+            hdc,
+            std::ptr::null_mut(),
+            ctx_attribs.as_ptr(),
+        );
         if hglrc.is_null() {
-            return Err(GlError::CreationFailed(()));
+            // Some drivers include a facility code in these error codes
+            return match GetLastError() & 0xffff {
+                ERROR_INVALID_VERSION_ARB | ERROR_INVALID_PROFILE_ARB => {
+                    Err(GlError::VersionNotSupported)
+                }
+                _ => Err(GlError::CreationFailed(())),
+            };
         }
 
-        let gl_library_name = CString::new("opengl32.dll").unwrap();
-        let gl_library = LoadLibraryA(gl_library_name.as_ptr());
+        Ok((hglrc, pixel_format))
+    }
 
-        wglMakeCurrent(hdc, hglrc);
-        wglSwapIntervalEXT.unwrap()(config.vsync as i32);
-        wglMakeCurrent(hdc, std::ptr::null_mut());
+    pub fn config(&self) -> &GlConfig {
+        &self.config
+    }
 
-        Ok(GlContext { hwnd, hdc, hglrc, gl_library })
+    pub fn vsync(&self) -> bool {
+        self.config.vsync
     }
 
     pub unsafe fn make_current(&self) {
@@ -1,4 +1,4 @@
-use std::ffi::{c_void, CString};
+use std::ffi::{c_void, CStr, CString};
 use std::os::raw::{c_int, c_ulong};
 
 use x11::glx;
@@ -39,6 +39,10 @@ type GlXCreateContextAttribsARB = unsafe extern "C" fn(
 type GlXSwapIntervalEXT =
     unsafe extern "C" fn(dpy: *mut xlib::Display, drawable: glx::GLXDrawable, interval: i32);
 
+// See https://www.khronos.org/registry/OpenGL/extensions/SGI/GLX_SGI_video_sync.txt
+
+type GlXGetVideoSyncSGI = unsafe extern "C" fn(count: *mut u32) -> c_int;
+
 // See https://www.khronos.org/registry/OpenGL/extensions/ARB/ARB_framebuffer_sRGB.txt
 
 const GLX_FRAMEBUFFER_SRGB_CAPABLE_ARB: i32 = 0x20B2;
@@ -48,10 +52,93 @@ fn get_proc_address(symbol: &str) -> *const c_void {
     unsafe { glx::glXGetProcAddress(symbol.as_ptr() as *const u8).unwrap() as *const c_void }
 }
 
+/// List the framebuffer configs on the default screen that can be used for OpenGL windows. This
+/// uses a separate display connection, so it can be called from any thread.
+pub fn enumerate_configs() -> Vec<GlConfig> {
+    unsafe {
+        let display = xlib::XOpenDisplay(std::ptr::null());
+        if display.is_null() {
+            return Vec::new();
+        }
+
+        let screen = xlib::XDefaultScreen(display);
+        let mut n_configs = 0;
+        let fb_configs = glx::glXGetFBConfigs(display, screen, &mut n_configs);
+
+        let mut configs = Vec::new();
+        if !fb_configs.is_null() {
+            for &fb_config in std::slice::from_raw_parts(fb_configs, n_configs.max(0) as usize) {
+                // Unsupported attributes are left at 0
+                let attrib = |attribute| {
+                    let mut value = 0;
+                    glx::glXGetFBConfigAttrib(display, fb_config, attribute, &mut value);
+                    value
+                };
+
+                // These are the same requirements `get_fb_config_and_visual()` uses
+                if attrib(glx::GLX_X_RENDERABLE) == 0
+                    || attrib(glx::GLX_X_VISUAL_TYPE) != glx::GLX_TRUE_COLOR
+                    || attrib(glx::GLX_DRAWABLE_TYPE) & glx::GLX_WINDOW_BIT == 0
+                    || attrib(glx::GLX_RENDER_TYPE) & glx::GLX_RGBA_BIT == 0
+                {
+                    continue;
+                }
+
+                let config = fb_config_attributes(display, fb_config, GlConfig::default());
+
+                // Configs that only differ in attributes we don't expose are listed once
+                if !configs.contains(&config) {
+                    configs.push(config);
+                }
+            }
+
+            xlib::XFree(fb_configs as *mut c_void);
+        }
+
+        xlib::XCloseDisplay(display);
+
+        configs
+    }
+}
+
+/// The configuration of `fb_config`. The version, profile and vsync fields are taken from
+/// `requested`, since the framebuffer config doesn't determine those.
+unsafe fn fb_config_attributes(
+    display: *mut xlib::_XDisplay, fb_config: glx::GLXFBConfig, requested: GlConfig,
+) -> GlConfig {
+    // Unsupported attributes are left at 0
+    let attrib = |attribute| {
+        let mut value = 0;
+        glx::glXGetFBConfigAttrib(display, fb_config, attribute, &mut value);
+        value
+    };
+
+    GlConfig {
+        red_bits: attrib(glx::GLX_RED_SIZE) as u8,
+        green_bits: attrib(glx::GLX_GREEN_SIZE) as u8,
+        blue_bits: attrib(glx::GLX_BLUE_SIZE) as u8,
+        alpha_bits: attrib(glx::GLX_ALPHA_SIZE) as u8,
+        depth_bits: attrib(glx::GLX_DEPTH_SIZE) as u8,
+        stencil_bits: attrib(glx::GLX_STENCIL_SIZE) as u8,
+        samples: if attrib(glx::GLX_SAMPLE_BUFFERS) > 0 {
+            Some(attrib(glx::GLX_SAMPLES) as u8)
+        } else {
+            None
+        },
+        srgb: attrib(GLX_FRAMEBUFFER_SRGB_CAPABLE_ARB) != 0,
+        double_buffer: attrib(glx::GLX_DOUBLEBUFFER) != 0,
+        ..requested
+    }
+}
+
 pub struct GlContext {
     window: c_ulong,
     display: *mut xlib::_XDisplay,
     context: glx::GLXContext,
+    /// The configuration of the framebuffer config the context was created with.
+    config: GlConfig,
+    /// Only set if the `GLX_SGI_video_sync` extension is supported.
+    get_video_sync: Option<GlXGetVideoSyncSGI>,
 }
 
 /// The frame buffer configuration along with the general OpenGL configuration to somewhat minimize
@@ -150,50 +237,77 @@ impl GlContext {
                 return Err(GlError::CreationFailed(CreationFailedError::MakeCurrentFailed));
             }
 
-            Ok(GlContext { window, display, context })
+            // `glXGetProcAddress()` returns a function pointer even for unsupported extensions
+            let screen = xlib::XDefaultScreen(display);
+            let extensions = glx::glXQueryExtensionsString(display, screen);
+            let supports_video_sync = !extensions.is_null()
+                && CStr::from_ptr(extensions)
+                    .to_string_lossy()
+                    .split(' ')
+                    .any(|extension| extension == "GLX_SGI_video_sync");
+            let get_video_sync = match get_proc_address("glXGetVideoSyncSGI") {
+                addr if supports_video_sync && !addr.is_null() =>
+                {
+                    #[allow(clippy::missing_transmute_annotations)]
+                    Some(std::mem::transmute(addr))
+                }
+                _ => None,
+            };
+
+            Ok(GlContext {
+                window,
+                display,
+                context,
+                config: fb_config_attributes(display, config.fb_config, config.gl_config),
+                get_video_sync,
+            })
         })
     }
 
     /// Find a matching framebuffer config and window visual for the given OpenGL configuration.
     /// This needs to be passed to [Self::create] along with a handle to a window that was created
-    /// using the visual also returned from this function.
+    /// using the visual also returned from this function. When `transparent` is set, a config with
+    /// a 32-bit ARGB visual is preferred so the compositor can blend the window using the alpha
+    /// channel.
     pub unsafe fn get_fb_config_and_visual(
-        display: *mut xlib::_XDisplay, config: GlConfig,
+        display: *mut xlib::_XDisplay, config: GlConfig, transparent: bool,
     ) -> Result<(FbConfig, WindowConfig), GlError> {
         errors::XErrorHandler::handle(display, |error_handler| {
             let screen = xlib::XDefaultScreen(display);
-
-            #[rustfmt::skip]
-                let fb_attribs = [
-                glx::GLX_X_RENDERABLE, 1,
-                glx::GLX_X_VISUAL_TYPE, glx::GLX_TRUE_COLOR,
-                glx::GLX_DRAWABLE_TYPE, glx::GLX_WINDOW_BIT,
-                glx::GLX_RENDER_TYPE, glx::GLX_RGBA_BIT,
-                glx::GLX_RED_SIZE, config.red_bits as i32,
-                glx::GLX_GREEN_SIZE, config.green_bits as i32,
-                glx::GLX_BLUE_SIZE, config.blue_bits as i32,
-                glx::GLX_ALPHA_SIZE, config.alpha_bits as i32,
-                glx::GLX_DEPTH_SIZE, config.depth_bits as i32,
-                glx::GLX_STENCIL_SIZE, config.stencil_bits as i32,
-                glx::GLX_DOUBLEBUFFER, config.double_buffer as i32,
-                glx::GLX_SAMPLE_BUFFERS, config.samples.is_some() as i32,
-                glx::GLX_SAMPLES, config.samples.unwrap_or(0) as i32,
-                GLX_FRAMEBUFFER_SRGB_CAPABLE_ARB, config.srgb as i32,
-                0,
-            ];
+            let fb_attribs = fb_attribs(&config);
 
             let mut n_configs = 0;
-            let fb_config =
+            let fb_configs =
                 glx::glXChooseFBConfig(display, screen, fb_attribs.as_ptr(), &mut n_configs);
 
             error_handler.check()?;
-            if n_configs <= 0 || fb_config.is_null() {
+            if n_configs <= 0 || fb_configs.is_null() {
                 return Err(GlError::CreationFailed(CreationFailedError::InvalidFBConfig));
             }
 
+            // The configs are sorted by how well they match, but the best match often uses a
+            // 24-bit visual even when alpha bits were requested. Those windows are always opaque.
+            let fb_configs = std::slice::from_raw_parts(fb_configs, n_configs as usize);
+            let argb_fb_config = if transparent && config.alpha_bits > 0 {
+                fb_configs.iter().copied().find(|&fb_config| {
+                    let visual = glx::glXGetVisualFromFBConfig(display, fb_config);
+                    if visual.is_null() {
+                        return false;
+                    }
+
+                    let is_argb = (*visual).depth == 32;
+                    xlib::XFree(visual as *mut c_void);
+                    is_argb
+                })
+            } else {
+                None
+            };
+
             // Now that we have a matching framebuffer config, we need to know which visual matches
             // thsi config so the window is compatible with the OpenGL context we're about to create
-            let fb_config = *fb_config;
+            let fb_config = argb_fb_config.unwrap_or(fb_configs[0]);
+            xlib::XFree(fb_configs.as_ptr() as *mut c_void);
+
             let visual = glx::glXGetVisualFromFBConfig(display, fb_config);
             if visual.is_null() {
                 return Err(GlError::CreationFailed(CreationFailedError::NoVisual));
@@ -206,6 +320,68 @@ impl GlContext {
         })
     }
 
+    /// Find a framebuffer config for an existing window that was created with the visual
+    /// `visual_id`. Unlike [`get_fb_config_and_visual()`][Self::get_fb_config_and_visual()] this
+    /// can't pick the visual, so this fails with [`CreationFailedError::NoVisual`] if none of
+    /// the configs matching `config` use the window's visual.
+    pub unsafe fn get_fb_config_for_visual(
+        display: *mut xlib::_XDisplay, config: GlConfig, visual_id: u32,
+    ) -> Result<FbConfig, GlError> {
+        errors::XErrorHandler::handle(display, |error_handler| {
+            let screen = xlib::XDefaultScreen(display);
+            let fb_attribs = fb_attribs(&config);
+
+            let mut n_configs = 0;
+            let fb_configs =
+                glx::glXChooseFBConfig(display, screen, fb_attribs.as_ptr(), &mut n_configs);
+
+            error_handler.check()?;
+            if n_configs <= 0 || fb_configs.is_null() {
+                return Err(GlError::CreationFailed(CreationFailedError::InvalidFBConfig));
+            }
+
+            let mut matching_fb_config = None;
+            for &fb_config in std::slice::from_raw_parts(fb_configs, n_configs as usize) {
+                let visual = glx::glXGetVisualFromFBConfig(display, fb_config);
+                if visual.is_null() {
+                    continue;
+                }
+
+                let matches = (*visual).visualid as u32 == visual_id;
+                xlib::XFree(visual as *mut c_void);
+                if matches {
+                    matching_fb_config = Some(fb_config);
+                    break;
+                }
+            }
+            xlib::XFree(fb_configs as *mut c_void);
+
+            match matching_fb_config {
+                Some(fb_config) => Ok(FbConfig { fb_config, gl_config: config }),
+                None => Err(GlError::CreationFailed(CreationFailedError::NoVisual)),
+            }
+        })
+    }
+
+    pub fn config(&self) -> &GlConfig {
+        &self.config
+    }
+
+    pub fn vsync(&self) -> bool {
+        self.config.vsync
+    }
+
+    pub unsafe fn video_sync_counter(&self) -> Option<u32> {
+        let get_video_sync = self.get_video_sync?;
+
+        let mut count = 0;
+        if get_video_sync(&mut count) == 0 {
+            Some(count)
+        } else {
+            None
+        }
+    }
+
     pub unsafe fn make_current(&self) {
         errors::XErrorHandler::handle(self.display, |error_handler| {
             let res = glx::glXMakeCurrent(self.display, self.window, self.context);
@@ -243,3 +419,27 @@ impl GlContext {
 impl Drop for GlContext {
     fn drop(&mut self) {}
 }
+
+/// The attributes `glXChooseFBConfig()` needs to find the framebuffer configs matching `config`.
+fn fb_attribs(config: &GlConfig) -> [c_int; 29] {
+    #[rustfmt::skip]
+    let fb_attribs = [
+        glx::GLX_X_RENDERABLE, 1,
+        glx::GLX_X_VISUAL_TYPE, glx::GLX_TRUE_COLOR,
+        glx::GLX_DRAWABLE_TYPE, glx::GLX_WINDOW_BIT,
+        glx::GLX_RENDER_TYPE, glx::GLX_RGBA_BIT,
+        glx::GLX_RED_SIZE, config.red_bits as i32,
+        glx::GLX_GREEN_SIZE, config.green_bits as i32,
+        glx::GLX_BLUE_SIZE, config.blue_bits as i32,
+        glx::GLX_ALPHA_SIZE, config.alpha_bits as i32,
+        glx::GLX_DEPTH_SIZE, config.depth_bits as i32,
+        glx::GLX_STENCIL_SIZE, config.stencil_bits as i32,
+        glx::GLX_DOUBLEBUFFER, config.double_buffer as i32,
+        glx::GLX_SAMPLE_BUFFERS, config.samples.is_some() as i32,
+        glx::GLX_SAMPLES, config.samples.unwrap_or(0) as i32,
+        GLX_FRAMEBUFFER_SRGB_CAPABLE_ARB, config.srgb as i32,
+        0,
+    ];
+
+    fb_attribs
+}
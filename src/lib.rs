@@ -1,3 +1,14 @@
+/// Log a warning through the `log` crate when the `log` feature is enabled. Otherwise the warning
+/// is discarded. This is used for errors that baseview recovers from on its own.
+macro_rules! log_warn {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "log")]
+        log::warn!($($arg)*);
+        #[cfg(not(feature = "log"))]
+        let _ = format_args!($($arg)*);
+    }};
+}
+
 #[cfg(target_os = "macos")]
 mod macos;
 #[cfg(target_os = "windows")]
@@ -7,6 +18,7 @@ mod x11;
 
 mod clipboard;
 mod event;
+mod framebuffer;
 mod keyboard;
 mod mouse_cursor;
 mod window;
@@ -16,8 +28,12 @@ mod window_open_options;
 #[cfg(feature = "opengl")]
 pub mod gl;
 
+#[cfg(target_os = "linux")]
+pub use x11::X11EventPump;
+
 pub use clipboard::*;
 pub use event::*;
+pub use framebuffer::Framebuffer;
 pub use mouse_cursor::MouseCursor;
 pub use window::*;
 pub use window_info::*;
@@ -0,0 +1,97 @@
+use cocoa::appkit::{
+    NSPasteboard, NSPasteboardTypePNG, NSPasteboardTypeString, NSPasteboardTypeTIFF,
+};
+use cocoa::base::{id, nil};
+use cocoa::foundation::{NSInteger, NSString, NSUInteger};
+use core_foundation::base::TCFType;
+use core_foundation::data::CFData;
+use objc::{class, msg_send, sel, sel_impl};
+
+use crate::clipboard::{decode_bmp, encode_png};
+use crate::ClipboardImage;
+
+use super::keyboard::from_nsstring;
+
+/// `NSBitmapImageFileTypeBMP`
+const NS_BITMAP_IMAGE_FILE_TYPE_BMP: NSUInteger = 1;
+
+pub fn set_text(text: &str) {
+    unsafe {
+        let pasteboard = NSPasteboard::generalPasteboard(nil);
+
+        let ns_string = NSString::alloc(nil).init_str(text);
+
+        pasteboard.clearContents();
+        pasteboard.setString_forType(ns_string, NSPasteboardTypeString);
+
+        let () = msg_send![ns_string, release];
+    }
+}
+
+pub fn get_text() -> Option<String> {
+    unsafe {
+        let pasteboard = NSPasteboard::generalPasteboard(nil);
+
+        let ns_string = pasteboard.stringForType(NSPasteboardTypeString);
+        if ns_string == nil {
+            None
+        } else {
+            Some(from_nsstring(ns_string))
+        }
+    }
+}
+
+pub fn set_image(rgba: &[u8], width: u32, height: u32) {
+    let png = CFData::from_buffer(&encode_png(rgba, width, height));
+
+    unsafe {
+        let pasteboard = NSPasteboard::generalPasteboard(nil);
+
+        // `CFData` is toll-free bridged with `NSData`
+        pasteboard.clearContents();
+        pasteboard.setData_forType(png.as_concrete_TypeRef() as id, NSPasteboardTypePNG);
+    }
+}
+
+pub fn get_image() -> Option<ClipboardImage> {
+    unsafe {
+        let pasteboard = NSPasteboard::generalPasteboard(nil);
+
+        // Most applications put TIFF images on the pasteboard
+        let mut data = pasteboard.dataForType(NSPasteboardTypePNG);
+        if data == nil {
+            data = pasteboard.dataForType(NSPasteboardTypeTIFF);
+        }
+        if data == nil {
+            return None;
+        }
+
+        // The image gets converted to a BMP file so it can be decoded without any additional
+        // dependencies
+        let image_rep: id = msg_send![class!(NSBitmapImageRep), imageRepWithData: data];
+        if image_rep == nil {
+            return None;
+        }
+
+        let properties: id = msg_send![class!(NSDictionary), dictionary];
+        let bmp: id = msg_send![
+            image_rep,
+            representationUsingType: NS_BITMAP_IMAGE_FILE_TYPE_BMP
+            properties: properties
+        ];
+        if bmp == nil {
+            return None;
+        }
+
+        let bytes: *const u8 = msg_send![bmp, bytes];
+        let length: NSUInteger = msg_send![bmp, length];
+
+        decode_bmp(std::slice::from_raw_parts(bytes, length as usize))
+    }
+}
+
+/// A number that changes every time the clipboard's contents change. AppKit doesn't send any
+/// notifications for this, so this needs to be polled.
+pub(super) fn change_count() -> NSInteger {
+    unsafe { NSPasteboard::generalPasteboard(nil).changeCount() }
+}
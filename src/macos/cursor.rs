@@ -0,0 +1,122 @@
+use cocoa::base::{id, BOOL, YES};
+use cocoa::foundation::{NSPoint, NSSize};
+use objc::runtime::Sel;
+use objc::{class, msg_send};
+
+use crate::MouseCursor;
+
+/// The `NSCursor` class methods that return the cursor, in order of preference. AppKit doesn't
+/// have public cursors for some of these, so the private cursors AppKit uses itself come first,
+/// followed by the closest public cursor in case the private cursor doesn't exist.
+fn cursor_selectors(cursor: MouseCursor) -> &'static [&'static str] {
+    match cursor {
+        MouseCursor::Default => &["arrowCursor"],
+
+        MouseCursor::Hand => &["pointingHandCursor"],
+        MouseCursor::HandGrabbing => &["closedHandCursor"],
+        MouseCursor::Help => &["_helpCursor", "arrowCursor"],
+
+        MouseCursor::Hidden => &[],
+
+        MouseCursor::Text => &["IBeamCursor"],
+        MouseCursor::VerticalText => &["IBeamCursorForVerticalLayout"],
+
+        MouseCursor::Working => &["busyButClickableCursor", "arrowCursor"],
+        MouseCursor::PtrWorking => &["busyButClickableCursor", "arrowCursor"],
+
+        MouseCursor::NotAllowed => &["operationNotAllowedCursor"],
+        MouseCursor::PtrNotAllowed => &["operationNotAllowedCursor"],
+
+        MouseCursor::ZoomIn => &["_zoomInCursor", "arrowCursor"],
+        MouseCursor::ZoomOut => &["_zoomOutCursor", "arrowCursor"],
+
+        MouseCursor::Alias => &["dragLinkCursor"],
+        MouseCursor::Copy => &["dragCopyCursor"],
+        MouseCursor::Move => &["_moveCursor", "openHandCursor"],
+        MouseCursor::AllScroll => &["_moveCursor", "openHandCursor"],
+        MouseCursor::Cell => &["crosshairCursor"],
+        MouseCursor::Crosshair => &["crosshairCursor"],
+
+        MouseCursor::EResize => &["resizeRightCursor"],
+        MouseCursor::NResize => &["resizeUpCursor"],
+        MouseCursor::NeResize => &["_windowResizeNorthEastCursor", "resizeUpDownCursor"],
+        MouseCursor::NwResize => &["_windowResizeNorthWestCursor", "resizeUpDownCursor"],
+        MouseCursor::SResize => &["resizeDownCursor"],
+        MouseCursor::SeResize => &["_windowResizeSouthEastCursor", "resizeUpDownCursor"],
+        MouseCursor::SwResize => &["_windowResizeSouthWestCursor", "resizeUpDownCursor"],
+        MouseCursor::WResize => &["resizeLeftCursor"],
+        MouseCursor::EwResize => &["resizeLeftRightCursor"],
+        MouseCursor::NsResize => &["resizeUpDownCursor"],
+        MouseCursor::NwseResize => &["_windowResizeNorthWestSouthEastCursor", "resizeUpDownCursor"],
+        MouseCursor::NeswResize => &["_windowResizeNorthEastSouthWestCursor", "resizeUpDownCursor"],
+        MouseCursor::ColResize => &["resizeLeftRightCursor"],
+        MouseCursor::RowResize => &["resizeUpDownCursor"],
+    }
+}
+
+/// Get the `NSCursor` for a cursor. The hidden cursor is a cursor with an empty image, since
+/// `[NSCursor hide]` applies to the entire application and it needs to be balanced with
+/// `[NSCursor unhide]`.
+pub(super) unsafe fn ns_cursor(cursor: MouseCursor) -> id {
+    if cursor == MouseCursor::Hidden {
+        let image: id = msg_send![class!(NSImage), alloc];
+        let image: id = msg_send![image, initWithSize: NSSize::new(1.0, 1.0)];
+        let ns_cursor: id = msg_send![class!(NSCursor), alloc];
+        let ns_cursor: id =
+            msg_send![ns_cursor, initWithImage: image hotSpot: NSPoint::new(0.0, 0.0)];
+        let () = msg_send![image, release];
+
+        return msg_send![ns_cursor, autorelease];
+    }
+
+    for name in cursor_selectors(cursor) {
+        let selector = Sel::register(name);
+        let responds: BOOL = msg_send![class!(NSCursor), respondsToSelector: selector];
+        if responds == YES {
+            return msg_send![class!(NSCursor), performSelector: selector];
+        }
+    }
+
+    msg_send![class!(NSCursor), arrowCursor]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The cursors that don't have a public `NSCursor`. These use one of AppKit's undocumented
+    /// cursors, with the closest public cursor as a fallback.
+    const EMULATED: [MouseCursor; 13] = [
+        MouseCursor::Help,
+        MouseCursor::Working,
+        MouseCursor::PtrWorking,
+        MouseCursor::ZoomIn,
+        MouseCursor::ZoomOut,
+        MouseCursor::Move,
+        MouseCursor::AllScroll,
+        MouseCursor::NeResize,
+        MouseCursor::NwResize,
+        MouseCursor::SeResize,
+        MouseCursor::SwResize,
+        MouseCursor::NwseResize,
+        MouseCursor::NeswResize,
+    ];
+
+    #[test]
+    fn native_and_emulated_cursors() {
+        for cursor in MouseCursor::ALL {
+            let selectors = cursor_selectors(cursor);
+            if cursor == MouseCursor::Hidden {
+                assert!(selectors.is_empty());
+                continue;
+            }
+
+            let emulated = EMULATED.contains(&cursor);
+            assert_eq!(selectors.len() > 1, emulated, "{:?}", cursor);
+
+            // Only the last selector is guaranteed to exist, so that one needs to be public
+            let last = selectors.last().unwrap();
+            assert!(!last.starts_with('_'), "{:?} has no public fallback", cursor);
+        }
+    }
+}
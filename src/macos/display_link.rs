@@ -0,0 +1,146 @@
+use std::ffi::c_void;
+use std::ptr;
+
+use core_foundation::base::TCFType;
+use core_foundation::runloop::{
+    kCFRunLoopDefaultMode, CFRunLoop, CFRunLoopSource, CFRunLoopSourceContext,
+    CFRunLoopSourceCreate, CFRunLoopSourceInvalidate, CFRunLoopSourceSignal, CFRunLoopWakeUp,
+};
+
+type CVDisplayLinkRef = *mut c_void;
+type CVReturn = i32;
+type CVOptionFlags = u64;
+type CGDirectDisplayID = u32;
+
+const K_CV_RETURN_SUCCESS: CVReturn = 0;
+
+/// The time stamps passed to this callback are `*const CVTimeStamp`, which aren't used here.
+type CVDisplayLinkOutputCallback = extern "C" fn(
+    display_link: CVDisplayLinkRef,
+    now: *const c_void,
+    output_time: *const c_void,
+    flags_in: CVOptionFlags,
+    flags_out: *mut CVOptionFlags,
+    context: *mut c_void,
+) -> CVReturn;
+
+#[link(name = "CoreVideo", kind = "framework")]
+extern "C" {
+    fn CVDisplayLinkCreateWithActiveCGDisplays(display_link_out: *mut CVDisplayLinkRef)
+        -> CVReturn;
+    fn CVDisplayLinkSetCurrentCGDisplay(
+        display_link: CVDisplayLinkRef, display_id: CGDirectDisplayID,
+    ) -> CVReturn;
+    fn CVDisplayLinkSetOutputCallback(
+        display_link: CVDisplayLinkRef, callback: CVDisplayLinkOutputCallback,
+        user_info: *mut c_void,
+    ) -> CVReturn;
+    fn CVDisplayLinkStart(display_link: CVDisplayLinkRef) -> CVReturn;
+    fn CVDisplayLinkStop(display_link: CVDisplayLinkRef) -> CVReturn;
+    fn CVDisplayLinkRelease(display_link: CVDisplayLinkRef);
+}
+
+/// The run loop source that the display link's thread signals on every refresh.
+struct Signal {
+    source: CFRunLoopSource,
+    run_loop: CFRunLoop,
+}
+
+/// Calls a function on the current thread's run loop for every refresh of a display. This is
+/// used for [`crate::FramePacing::DisplayLink`]. The function is no longer called once this has
+/// been dropped.
+pub(super) struct DisplayLink {
+    display_link: CVDisplayLinkRef,
+    /// This is boxed because the display link's callback holds a pointer to it.
+    signal: Box<Signal>,
+}
+
+impl DisplayLink {
+    /// Start calling `perform` with `info` for every refresh of the display with the given ID, or
+    /// of the main display if this is `None`. This returns `None` if the display link could not be
+    /// created.
+    pub unsafe fn new(
+        display_id: Option<CGDirectDisplayID>, info: *mut c_void,
+        perform: extern "C" fn(info: *const c_void),
+    ) -> Option<Self> {
+        extern "C" fn output_callback(
+            _: CVDisplayLinkRef, _: *const c_void, _: *const c_void, _: CVOptionFlags,
+            _: *mut CVOptionFlags, signal_ptr: *mut c_void,
+        ) -> CVReturn {
+            // This is called on the display link's own thread. Signaling a run loop source is
+            // thread safe, and the run loop calls `perform` on the window's thread.
+            unsafe {
+                let signal = &*(signal_ptr as *const Signal);
+                CFRunLoopSourceSignal(signal.source.as_concrete_TypeRef());
+                CFRunLoopWakeUp(signal.run_loop.as_concrete_TypeRef());
+            }
+
+            K_CV_RETURN_SUCCESS
+        }
+
+        let mut display_link: CVDisplayLinkRef = ptr::null_mut();
+        if CVDisplayLinkCreateWithActiveCGDisplays(&mut display_link) != K_CV_RETURN_SUCCESS
+            || display_link.is_null()
+        {
+            return None;
+        }
+        if let Some(display_id) = display_id {
+            CVDisplayLinkSetCurrentCGDisplay(display_link, display_id);
+        }
+
+        let mut source_context = CFRunLoopSourceContext {
+            version: 0,
+            info,
+            retain: None,
+            release: None,
+            copyDescription: None,
+            equal: None,
+            hash: None,
+            schedule: None,
+            cancel: None,
+            perform,
+        };
+        let source = CFRunLoopSourceCreate(ptr::null(), 0, &mut source_context);
+        if source.is_null() {
+            CVDisplayLinkRelease(display_link);
+            return None;
+        }
+
+        let signal = Box::new(Signal {
+            source: CFRunLoopSource::wrap_under_create_rule(source),
+            run_loop: CFRunLoop::get_current(),
+        });
+        signal.run_loop.add_source(&signal.source, kCFRunLoopDefaultMode);
+
+        let signal_ptr = &*signal as *const Signal as *mut c_void;
+        CVDisplayLinkSetOutputCallback(display_link, output_callback, signal_ptr);
+
+        // Dropping this removes the run loop source again if the display link can't be started
+        let display_link = DisplayLink { display_link, signal };
+        if CVDisplayLinkStart(display_link.display_link) != K_CV_RETURN_SUCCESS {
+            return None;
+        }
+
+        Some(display_link)
+    }
+
+    /// Call the function right away, the same way as on a display refresh.
+    pub fn fire(&self) {
+        unsafe {
+            CFRunLoopSourceSignal(self.signal.source.as_concrete_TypeRef());
+            CFRunLoopWakeUp(self.signal.run_loop.as_concrete_TypeRef());
+        }
+    }
+}
+
+impl Drop for DisplayLink {
+    fn drop(&mut self) {
+        unsafe {
+            // This waits for the output callback to return if it's currently running
+            CVDisplayLinkStop(self.display_link);
+            CVDisplayLinkRelease(self.display_link);
+
+            CFRunLoopSourceInvalidate(self.signal.source.as_concrete_TypeRef());
+        }
+    }
+}
@@ -22,11 +22,12 @@ use std::cell::Cell;
 
 use cocoa::appkit::{NSEvent, NSEventModifierFlags, NSEventType};
 use cocoa::base::id;
-use cocoa::foundation::NSString;
+use cocoa::foundation::{NSInteger, NSString};
 use keyboard_types::{Code, Key, KeyState, KeyboardEvent, Modifiers};
 use objc::{msg_send, sel, sel_impl};
 
 use crate::keyboard::code_to_location;
+use crate::ModifierSides;
 
 pub(crate) fn from_nsstring(s: id) -> String {
     unsafe {
@@ -123,7 +124,7 @@ fn key_code_to_code(key_code: u16) -> Code {
         // Note: in the linked source doc, this is "OSRight"
         0x3d => Code::AltRight,
         0x3e => Code::ControlRight,
-        0x3f => Code::Fn, // No events fired
+        0x3f => Code::Fn, // Only fires `flagsChanged` events
         //0x40 => Code::F17,
         0x41 => Code::NumpadDecimal,
         0x43 => Code::NumpadMultiply,
@@ -296,7 +297,14 @@ impl KeyboardState {
                     // may be pressed, for example).
                     let any_down = raw_mods.bits() & !self.last_mods.get().bits();
                     self.last_mods.set(raw_mods);
-                    if is_modifier_code(code) {
+                    if code == Code::Fn {
+                        // The Fn key has no device dependent bits
+                        if raw_mods.contains(NSEventModifierFlags::NSFunctionKeyMask) {
+                            KeyState::Down
+                        } else {
+                            KeyState::Up
+                        }
+                    } else if is_modifier_code(code) {
                         if any_down == 0 {
                             KeyState::Up
                         } else {
@@ -336,8 +344,60 @@ impl KeyboardState {
             Some(event)
         }
     }
+
+    /// Convert the media and brightness keys, which AppKit reports as `NSSystemDefined` events
+    /// instead of as key events. Returns `None` for any other system defined event.
+    pub(crate) fn process_system_defined_event(&self, event: id) -> Option<KeyboardEvent> {
+        unsafe {
+            let subtype: i16 = msg_send![event, subtype];
+            if subtype != NX_SUBTYPE_AUX_CONTROL_BUTTONS {
+                return None;
+            }
+
+            // The upper 16 bits contain the key type from `ev_keymap.h`, followed by the key state
+            // and a repeat flag
+            let data1: NSInteger = msg_send![event, data1];
+            let key_type = (data1 >> 16) & 0xffff;
+            let key_flags = data1 & 0xffff;
+            let state = match key_flags >> 8 {
+                0x0a => KeyState::Down,
+                0x0b => KeyState::Up,
+                _ => return None,
+            };
+            let repeat = key_flags & 0x1 != 0;
+
+            let (code, key) = match key_type {
+                0 => (Code::AudioVolumeUp, Key::AudioVolumeUp),
+                1 => (Code::AudioVolumeDown, Key::AudioVolumeDown),
+                2 => (Code::BrightnessUp, Key::BrightnessUp),
+                3 => (Code::BrightnessDown, Key::BrightnessDown),
+                6 => (Code::Power, Key::Power),
+                7 => (Code::AudioVolumeMute, Key::AudioVolumeMute),
+                14 => (Code::Eject, Key::Eject),
+                16 => (Code::MediaPlayPause, Key::MediaPlayPause),
+                17 => (Code::MediaTrackNext, Key::MediaTrackNext),
+                18 => (Code::MediaTrackPrevious, Key::MediaTrackPrevious),
+                19 => (Code::MediaFastForward, Key::MediaFastForward),
+                20 => (Code::MediaRewind, Key::MediaRewind),
+                _ => return None,
+            };
+
+            Some(KeyboardEvent {
+                code,
+                key,
+                location: code_to_location(code),
+                modifiers: make_modifiers(event.modifierFlags()),
+                state,
+                is_composing: false,
+                repeat: repeat && state == KeyState::Down,
+            })
+        }
+    }
 }
 
+/// The `NSSystemDefined` event subtype used for the media keys.
+const NX_SUBTYPE_AUX_CONTROL_BUTTONS: i16 = 8;
+
 const MODIFIER_MAP: &[(NSEventModifierFlags, Modifiers)] = &[
     (NSEventModifierFlags::NSShiftKeyMask, Modifiers::SHIFT),
     (NSEventModifierFlags::NSAlternateKeyMask, Modifiers::ALT),
@@ -355,3 +415,26 @@ pub(crate) fn make_modifiers(raw: NSEventModifierFlags) -> Modifiers {
     }
     modifiers
 }
+
+/// The device-dependent modifier flags from `IOLLEvent.h`. AppKit sets these alongside the
+/// device-independent flags, but it doesn't name them.
+const MODIFIER_SIDE_MAP: &[(u64, ModifierSides)] = &[
+    (0x0002, ModifierSides::SHIFT_LEFT),
+    (0x0004, ModifierSides::SHIFT_RIGHT),
+    (0x0001, ModifierSides::CONTROL_LEFT),
+    (0x2000, ModifierSides::CONTROL_RIGHT),
+    (0x0020, ModifierSides::ALT_LEFT),
+    (0x0040, ModifierSides::ALT_RIGHT),
+    (0x0008, ModifierSides::META_LEFT),
+    (0x0010, ModifierSides::META_RIGHT),
+];
+
+pub(crate) fn make_modifier_sides(raw: NSEventModifierFlags) -> ModifierSides {
+    let mut sides = ModifierSides::empty();
+    for &(mask, side) in MODIFIER_SIDE_MAP {
+        if raw.bits() & mask != 0 {
+            sides |= side;
+        }
+    }
+    sides
+}
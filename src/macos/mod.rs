@@ -1,3 +1,6 @@
+pub(crate) mod clipboard;
+mod cursor;
+mod display_link;
 mod keyboard;
 mod view;
 mod window;
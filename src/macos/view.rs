@@ -1,8 +1,8 @@
 use std::ffi::c_void;
 
-use cocoa::appkit::{NSEvent, NSFilenamesPboardType, NSView, NSWindow};
+use cocoa::appkit::{NSEvent, NSEventPhase, NSFilenamesPboardType, NSView, NSWindow};
 use cocoa::base::{id, nil, BOOL, NO, YES};
-use cocoa::foundation::{NSArray, NSPoint, NSRect, NSSize, NSUInteger};
+use cocoa::foundation::{NSArray, NSInteger, NSPoint, NSRect, NSSize, NSUInteger};
 
 use objc::{
     class,
@@ -15,10 +15,11 @@ use uuid::Uuid;
 
 use crate::MouseEvent::{ButtonPressed, ButtonReleased};
 use crate::{
-    DropData, DropEffect, Event, EventStatus, MouseButton, MouseEvent, Point, ScrollDelta, Size,
-    WindowEvent, WindowInfo, WindowOpenOptions,
+    DropData, DropEffect, Event, EventStatus, HitTestResult, MouseButton, MouseEvent, Point,
+    ScrollDelta, ScrollPhase, Size, WindowEvent, WindowInfo, WindowOpenOptions, WindowScalePolicy,
 };
 
+use super::cursor::ns_cursor;
 use super::keyboard::{from_nsstring, make_modifiers};
 use super::window::WindowState;
 use super::{
@@ -33,14 +34,21 @@ pub(super) const BASEVIEW_STATE_IVAR: &str = "baseview_state";
 extern "C" {
     static NSWindowDidBecomeKeyNotification: id;
     static NSWindowDidResignKeyNotification: id;
+    static NSWindowDidMoveNotification: id;
+    static NSWindowDidBecomeMainNotification: id;
+    static NSWindowDidResignMainNotification: id;
 }
 
 macro_rules! add_simple_mouse_class_method {
     ($class:ident, $sel:ident, $event:expr) => {
         #[allow(non_snake_case)]
-        extern "C" fn $sel(this: &Object, _: Sel, _: id){
+        extern "C" fn $sel(this: &Object, _: Sel, event: id){
             let state = unsafe { WindowState::from_view(this) };
 
+            if state.trigger_raw_event(event) {
+                return;
+            }
+
             state.trigger_event(Event::Mouse($event));
         }
 
@@ -52,19 +60,33 @@ macro_rules! add_simple_mouse_class_method {
 }
 
 /// Similar to [add_simple_mouse_class_method!], but this creates its own event object for the
-/// press/release event and adds the active modifier keys to that event.
+/// press/release event and adds the active modifier keys to that event. If the event is ignored,
+/// then it's passed on to the superclass so it can reach the next responder. `$button` is called
+/// with the `NSEvent` to get the event's button.
 macro_rules! add_mouse_button_class_method {
     ($class:ident, $sel:ident, $event_ty:ident, $button:expr) => {
         #[allow(non_snake_case)]
         extern "C" fn $sel(this: &Object, _: Sel, event: id){
             let state = unsafe { WindowState::from_view(this) };
 
+            if state.trigger_raw_event(event) {
+                return;
+            }
+
             let modifiers = unsafe { NSEvent::modifierFlags(event) };
 
-            state.trigger_event(Event::Mouse($event_ty {
-                button: $button,
+            let status = state.trigger_event(Event::Mouse($event_ty {
+                button: $button(event),
                 modifiers: make_modifiers(modifiers),
             }));
+
+            if let EventStatus::Ignored = status {
+                unsafe {
+                    let superclass = msg_send![this, superclass];
+
+                    let () = msg_send![super(this, superclass), $sel:event];
+                }
+            }
         }
 
         $class.add_method(
@@ -80,6 +102,10 @@ macro_rules! add_simple_keyboard_class_method {
         extern "C" fn $sel(this: &Object, _: Sel, event: id){
             let state = unsafe { WindowState::from_view(this) };
 
+            if state.trigger_raw_event(event) {
+                return;
+            }
+
             if let Some(key_event) = state.process_native_key_event(event){
                 let status = state.trigger_event(Event::Keyboard(key_event));
 
@@ -121,17 +147,41 @@ pub(super) unsafe fn create_view(window_options: &WindowOpenOptions) -> id {
 
     view.initWithFrame_(NSRect::new(NSPoint::new(0., 0.), NSSize::new(size.width, size.height)));
 
+    if window_options.coalesce_mouse_moves {
+        // This is an application-wide setting that is enabled by default, but the host may have
+        // disabled it
+        let () = msg_send![class!(NSEvent), setMouseCoalescingEnabled: YES];
+    }
+
     register_notification(view, NSWindowDidBecomeKeyNotification, nil);
     register_notification(view, NSWindowDidResignKeyNotification, nil);
-
-    let _: id = msg_send![
-        view,
-        registerForDraggedTypes: NSArray::arrayWithObjects(nil, &[NSFilenamesPboardType])
-    ];
+    register_notification(view, NSWindowDidMoveNotification, nil);
+    register_notification(view, NSWindowDidBecomeMainNotification, nil);
+    register_notification(view, NSWindowDidResignMainNotification, nil);
+
+    if window_options.drag_n_drop {
+        let _: id = msg_send![
+            view,
+            registerForDraggedTypes: NSArray::arrayWithObjects(nil, &[NSFilenamesPboardType])
+        ];
+    }
 
     view
 }
 
+/// Create an `NSWindow` subclass for windows without decorations. Borderless windows can't become
+/// the key or main window by default.
+pub(super) unsafe fn create_borderless_window_class() -> &'static Class {
+    let class_name = format!("BaseviewNSWindow_{}", Uuid::new_v4().to_simple());
+    let mut class = ClassDecl::new(&class_name, class!(NSWindow)).unwrap();
+
+    class.add_method(sel!(canBecomeKeyWindow), property_yes as extern "C" fn(&Object, Sel) -> BOOL);
+    class
+        .add_method(sel!(canBecomeMainWindow), property_yes as extern "C" fn(&Object, Sel) -> BOOL);
+
+    class.register()
+}
+
 unsafe fn create_view_class() -> &'static Class {
     // Use unique class names so that there are no conflicts between different
     // instances. The class is deleted when the view is released. Previously,
@@ -158,6 +208,12 @@ unsafe fn create_view_class() -> &'static Class {
         sel!(preservesContentInLiveResize),
         property_no as extern "C" fn(&Object, Sel) -> BOOL,
     );
+    // Borderless windows are only moved through `Window::drag_window()` and
+    // `WindowHandler::hit_test()`, dragging the view itself should never move the window
+    class.add_method(
+        sel!(mouseDownCanMoveWindow),
+        property_no as extern "C" fn(&Object, Sel) -> BOOL,
+    );
     class.add_method(
         sel!(acceptsFirstMouse:),
         accepts_first_mouse as extern "C" fn(&Object, Sel, id) -> BOOL,
@@ -177,6 +233,7 @@ unsafe fn create_view_class() -> &'static Class {
         update_tracking_areas as extern "C" fn(&Object, Sel, id),
     );
 
+    class.add_method(sel!(cursorUpdate:), cursor_update as extern "C" fn(&Object, Sel, id));
     class.add_method(sel!(mouseMoved:), mouse_moved as extern "C" fn(&Object, Sel, id));
     class.add_method(sel!(mouseDragged:), mouse_moved as extern "C" fn(&Object, Sel, id));
     class.add_method(sel!(rightMouseDragged:), mouse_moved as extern "C" fn(&Object, Sel, id));
@@ -188,6 +245,8 @@ unsafe fn create_view_class() -> &'static Class {
         sel!(viewDidChangeBackingProperties:),
         view_did_change_backing_properties as extern "C" fn(&Object, Sel, id),
     );
+    class.add_method(sel!(setFrameSize:), set_frame_size as extern "C" fn(&Object, Sel, NSSize));
+    class.add_method(sel!(hitTest:), hit_test as extern "C" fn(&Object, Sel, NSPoint) -> id);
 
     class.add_method(
         sel!(draggingEntered:),
@@ -211,12 +270,12 @@ unsafe fn create_view_class() -> &'static Class {
         handle_notification as extern "C" fn(&Object, Sel, id),
     );
 
-    add_mouse_button_class_method!(class, mouseDown, ButtonPressed, MouseButton::Left);
-    add_mouse_button_class_method!(class, mouseUp, ButtonReleased, MouseButton::Left);
-    add_mouse_button_class_method!(class, rightMouseDown, ButtonPressed, MouseButton::Right);
-    add_mouse_button_class_method!(class, rightMouseUp, ButtonReleased, MouseButton::Right);
-    add_mouse_button_class_method!(class, otherMouseDown, ButtonPressed, MouseButton::Middle);
-    add_mouse_button_class_method!(class, otherMouseUp, ButtonReleased, MouseButton::Middle);
+    class.add_method(sel!(mouseDown:), mouse_down as extern "C" fn(&Object, Sel, id));
+    add_mouse_button_class_method!(class, mouseUp, ButtonReleased, |_| MouseButton::Left);
+    add_mouse_button_class_method!(class, rightMouseDown, ButtonPressed, |_| MouseButton::Right);
+    add_mouse_button_class_method!(class, rightMouseUp, ButtonReleased, |_| MouseButton::Right);
+    add_mouse_button_class_method!(class, otherMouseDown, ButtonPressed, other_mouse_button);
+    add_mouse_button_class_method!(class, otherMouseUp, ButtonReleased, other_mouse_button);
     add_simple_mouse_class_method!(class, mouseEntered, MouseEvent::CursorEntered);
     add_simple_mouse_class_method!(class, mouseExited, MouseEvent::CursorLeft);
 
@@ -256,6 +315,7 @@ extern "C" fn become_first_responder(this: &Object, _sel: Sel) -> BOOL {
 
 extern "C" fn resign_first_responder(this: &Object, _sel: Sel) -> BOOL {
     let state = unsafe { WindowState::from_view(this) };
+    state.window_inner.cursor_confinement.set(None);
     state.trigger_deferrable_event(Event::Window(WindowEvent::Unfocused));
     YES
 }
@@ -263,6 +323,12 @@ extern "C" fn resign_first_responder(this: &Object, _sel: Sel) -> BOOL {
 extern "C" fn window_should_close(this: &Object, _: Sel, _sender: id) -> BOOL {
     let state = unsafe { WindowState::from_view(this) };
 
+    let status = state.trigger_event(Event::Window(WindowEvent::CloseRequested));
+    if status == EventStatus::DeferClose {
+        return NO;
+    }
+
+    state.cancel_drag();
     state.trigger_event(Event::Window(WindowEvent::WillClose));
 
     state.window_inner.close();
@@ -286,26 +352,150 @@ extern "C" fn view_did_change_backing_properties(this: &Object, _: Sel, _: id) {
     unsafe {
         let ns_window: *mut Object = msg_send![this, window];
 
-        let scale_factor: f64 =
-            if ns_window.is_null() { 1.0 } else { NSWindow::backingScaleFactor(ns_window) };
-
         let state = WindowState::from_view(this);
 
-        let bounds: NSRect = msg_send![this, bounds];
+        let backing_scale = (!ns_window.is_null()).then(|| NSWindow::backingScaleFactor(ns_window));
 
-        let new_window_info = WindowInfo::from_logical_size(
-            Size::new(bounds.size.width, bounds.size.height),
-            scale_factor,
-        );
+        // The bounds are in points. The backing store's size is the bounds multiplied by the
+        // backing scale factor, so with the system scale factor the physical size computed here
+        // matches the drawable's size. With a forced scale factor the GL surface is resized to
+        // match the physical size instead.
+        let bounds: NSRect = msg_send![this, bounds];
 
         let window_info = state.window_info.get();
+        let BackingChange { window_info: new_window_info, scale_changed, size_changed } =
+            backing_change(
+                window_info,
+                state.window_inner.scale_policy.get(),
+                backing_scale,
+                Size::new(bounds.size.width, bounds.size.height),
+            );
+        if scale_changed || size_changed {
+            if size_changed {
+                state.trigger_event(Event::Window(WindowEvent::WillResize {
+                    from: window_info,
+                    to: new_window_info,
+                }));
+            }
+            state.window_info.set(new_window_info);
+        }
+
+        if scale_changed {
+            // The OpenGL view only picks up the new backing scale factor when it gets resized
+            state.window_inner.resize_gl_view(bounds.size);
+            state.trigger_scale_factor_changed(new_window_info.scale());
+        }
 
         // Only send the event when the window's size has actually changed to be in line with the
         // other platform implementations
-        if new_window_info.physical_size() != window_info.physical_size() {
-            state.window_info.set(new_window_info);
+        if size_changed {
             state.trigger_event(Event::Window(WindowEvent::Resized(new_window_info)));
+            if let Some(event) = state.window_inner.cursor_moved_event() {
+                state.trigger_event(event);
+            }
+        }
+    }
+}
+
+/// The result of the view's backing properties changing.
+#[derive(Debug)]
+struct BackingChange {
+    window_info: WindowInfo,
+    /// The OpenGL view needs to be resized and the handler needs to be told about the new scale
+    /// factor.
+    scale_changed: bool,
+    /// The handler needs to receive `WillResize` and `Resized` events.
+    size_changed: bool,
+}
+
+/// Compute the view's new window info after its backing properties changed. `backing_scale` is
+/// `None` when the view isn't in a window.
+fn backing_change(
+    old: WindowInfo, policy: WindowScalePolicy, backing_scale: Option<f64>, bounds: Size,
+) -> BackingChange {
+    // A forced scale factor should not be overridden by the backing scale factor
+    let scale_factor = match policy {
+        WindowScalePolicy::ScaleFactor(scale) => scale,
+        WindowScalePolicy::SystemScaleFactor => backing_scale.unwrap_or(1.0),
+    };
+
+    let window_info = WindowInfo::from_logical_size(bounds, scale_factor);
+
+    BackingChange {
+        window_info,
+        scale_changed: window_info.scale() != old.scale(),
+        size_changed: window_info.physical_size() != old.physical_size(),
+    }
+}
+
+/// Called both when the window gets resized through [`Window::resize()`][crate::Window::resize()],
+/// and when a standalone window gets resized by the user.
+extern "C" fn set_frame_size(this: &Object, _: Sel, new_size: NSSize) {
+    unsafe {
+        // The window state is only set after the window handler has been built
+        let state_ptr: *const c_void = *this.get_ivar(BASEVIEW_STATE_IVAR);
+        if state_ptr.is_null() {
+            let superclass = msg_send![this, superclass];
+            let () = msg_send![super(this, superclass), setFrameSize: new_size];
+            return;
+        }
+    }
+
+    let state = unsafe { WindowState::from_view(this) };
+
+    let window_info = state.window_info.get();
+    let new_window_info = WindowInfo::from_logical_size(
+        Size::new(new_size.width, new_size.height),
+        window_info.scale(),
+    );
+    let size_changed = new_window_info.physical_size() != window_info.physical_size();
+
+    // This is sent before the view and its OpenGL surface are resized
+    if size_changed {
+        state.trigger_deferrable_event(Event::Window(WindowEvent::WillResize {
+            from: window_info,
+            to: new_window_info,
+        }));
+    }
+
+    unsafe {
+        let superclass = msg_send![this, superclass];
+        let () = msg_send![super(this, superclass), setFrameSize: new_size];
+    }
+
+    if size_changed {
+        state.window_info.set(new_window_info);
+
+        // This can be called from within the window handler when it calls `Window::resize()`
+        state.trigger_deferrable_event(Event::Window(WindowEvent::Resized(new_window_info)));
+        if let Some(event) = state.window_inner.cursor_moved_event() {
+            state.trigger_deferrable_event(event);
+        }
+    }
+}
+
+/// Mouse events outside of the window's input region are passed on to the superview by not
+/// claiming them here.
+extern "C" fn hit_test(this: &Object, _: Sel, point: NSPoint) -> id {
+    unsafe {
+        let state_ptr: *const c_void = *this.get_ivar(BASEVIEW_STATE_IVAR);
+        if !state_ptr.is_null() {
+            let state = WindowState::from_view(this);
+
+            if let Some(input_region) = &*state.window_inner.input_region.borrow() {
+                // The point is in the superview's coordinate system
+                let superview: id = msg_send![this, superview];
+                let local_point: NSPoint = msg_send![this, convertPoint: point fromView: superview];
+                let local_point = Point::new(local_point.x, local_point.y);
+
+                if !input_region.iter().any(|rect| rect.contains(local_point)) {
+                    return nil;
+                }
+            }
         }
+
+        let superclass = msg_send![this, superclass];
+        msg_send![super(this, superclass), hitTest: point]
     }
 }
 
@@ -386,9 +576,25 @@ extern "C" fn update_tracking_areas(this: &Object, _self: Sel, _: id) {
     }
 }
 
+/// Called when the mouse enters the view, since the tracking area asks for cursor updates.
+extern "C" fn cursor_update(this: &Object, _sel: Sel, _event: id) {
+    let state = unsafe { WindowState::from_view(this) };
+
+    // A window embedded in this view sets its own cursor
+    if state.window_inner.cursor_over_view() {
+        unsafe {
+            let () = msg_send![ns_cursor(state.window_inner.mouse_cursor.get()), set];
+        }
+    }
+}
+
 extern "C" fn mouse_moved(this: &Object, _sel: Sel, event: id) {
     let state = unsafe { WindowState::from_view(this) };
 
+    if state.trigger_raw_event(event) {
+        return;
+    }
+
     let point: NSPoint = unsafe {
         let point = NSEvent::locationInWindow(event);
 
@@ -396,7 +602,27 @@ extern "C" fn mouse_moved(this: &Object, _sel: Sel, event: id) {
     };
     let modifiers = unsafe { NSEvent::modifierFlags(event) };
 
-    let position = Point { x: point.x, y: point.y };
+    // The cursor is detached from the mouse during a relative drag, so only the deltas change.
+    // Like the view's coordinates, these are flipped.
+    if state.window_inner.relative_drag_origin.get().is_some() {
+        let delta = unsafe { Point::new(NSEvent::deltaX(event), NSEvent::deltaY(event)) };
+        if delta != Point::new(0.0, 0.0) {
+            state.trigger_event(Event::Mouse(MouseEvent::RawMotion {
+                delta,
+                modifiers: make_modifiers(modifiers),
+            }));
+        }
+
+        return;
+    }
+
+    let mut position = Point { x: point.x, y: point.y };
+    // Warping the cursor doesn't generate another event, so the confined position is reported
+    // right away
+    if let Some(confined) = state.window_inner.confine_cursor(position) {
+        position = confined;
+    }
+    state.window_inner.cursor_auto_hide.cursor_moved((position.x as i32, position.y as i32));
 
     state.trigger_event(Event::Mouse(MouseEvent::CursorMoved {
         position,
@@ -404,9 +630,85 @@ extern "C" fn mouse_moved(this: &Object, _sel: Sel, event: id) {
     }));
 }
 
+extern "C" fn mouse_down(this: &Object, _sel: Sel, event: id) {
+    let state = unsafe { WindowState::from_view(this) };
+
+    if state.trigger_raw_event(event) {
+        return;
+    }
+
+    let point: NSPoint = unsafe {
+        let point = NSEvent::locationInWindow(event);
+
+        msg_send![this, convertPoint:point fromView:nil]
+    };
+
+    // Resizing the window from an arbitrary point is not supported on macOS, so those areas are
+    // treated as regular client areas
+    if state.hit_test(Point::new(point.x, point.y)) == HitTestResult::TitleBar {
+        unsafe {
+            let window: id = msg_send![this, window];
+            let () = msg_send![window, performWindowDragWithEvent: event];
+        }
+
+        return;
+    }
+
+    let modifiers = unsafe { NSEvent::modifierFlags(event) };
+
+    let status = state.trigger_event(Event::Mouse(ButtonPressed {
+        button: MouseButton::Left,
+        modifiers: make_modifiers(modifiers),
+    }));
+
+    if let EventStatus::Ignored = status {
+        unsafe {
+            let superclass = msg_send![this, superclass];
+
+            let () = msg_send![super(this, superclass), mouseDown: event];
+        }
+    }
+}
+
+/// The button of an `otherMouseDown:` or `otherMouseUp:` event. AppKit numbers the buttons after
+/// the left and right buttons the same way [`MouseButton::Other`] does.
+fn other_mouse_button(event: id) -> MouseButton {
+    match unsafe { NSEvent::buttonNumber(event) } {
+        2 => MouseButton::Middle,
+        3 => MouseButton::Back,
+        4 => MouseButton::Forward,
+        number => MouseButton::Other(number.clamp(0, u8::MAX as NSInteger) as u8),
+    }
+}
+
+/// Convert a scroll event's phase and momentum phase. At most one of these is set, and both are
+/// unset for regular mouse wheels.
+fn scroll_phase(phase: NSEventPhase, momentum_phase: NSEventPhase) -> ScrollPhase {
+    let began = NSEventPhase::NSEventPhaseBegan | NSEventPhase::NSEventPhaseMayBegin;
+    let ended = NSEventPhase::NSEventPhaseEnded | NSEventPhase::NSEventPhaseCancelled;
+
+    if phase.intersects(began) {
+        ScrollPhase::Began
+    } else if phase.intersects(ended) {
+        ScrollPhase::Ended
+    } else if momentum_phase.intersects(began) {
+        ScrollPhase::MomentumBegan
+    } else if momentum_phase.intersects(ended) {
+        ScrollPhase::MomentumEnded
+    } else if momentum_phase != NSEventPhase::NSEventPhaseNone {
+        ScrollPhase::MomentumChanged
+    } else {
+        ScrollPhase::Changed
+    }
+}
+
 extern "C" fn scroll_wheel(this: &Object, _: Sel, event: id) {
     let state = unsafe { WindowState::from_view(this) };
 
+    if state.trigger_raw_event(event) {
+        return;
+    }
+
     let delta = unsafe {
         let x = NSEvent::scrollingDeltaX(event) as f32;
         let y = NSEvent::scrollingDeltaY(event) as f32;
@@ -418,16 +720,34 @@ extern "C" fn scroll_wheel(this: &Object, _: Sel, event: id) {
         }
     };
 
+    let phase = unsafe { scroll_phase(NSEvent::phase(event), NSEvent::momentumPhase(event)) };
+
     let modifiers = unsafe { NSEvent::modifierFlags(event) };
 
-    state.trigger_event(Event::Mouse(MouseEvent::WheelScrolled {
+    let status = state.trigger_event(Event::Mouse(MouseEvent::WheelScrolled {
         delta,
+        phase,
         modifiers: make_modifiers(modifiers),
     }));
+
+    if let EventStatus::Ignored = status {
+        unsafe {
+            let superclass = msg_send![this, superclass];
+
+            let () = msg_send![super(this, superclass), scrollWheel: event];
+        }
+    }
 }
 
-fn get_drag_position(sender: id) -> Point {
-    let point: NSPoint = unsafe { msg_send![sender, draggingLocation] };
+/// The position of a drag relative to the view's top left corner, like the positions of other
+/// mouse events. The dragging location is in the window's coordinate system, which starts at the
+/// window's bottom left corner, so using it as is would put the drag at the wrong position.
+fn get_drag_position(this: &Object, sender: id) -> Point {
+    let point: NSPoint = unsafe {
+        let point: NSPoint = msg_send![sender, draggingLocation];
+
+        msg_send![this, convertPoint:point fromView:nil]
+    };
     Point::new(point.x, point.y)
 }
 
@@ -470,8 +790,12 @@ extern "C" fn dragging_entered(this: &Object, _sel: Sel, sender: id) -> NSUInteg
     let modifiers = state.keyboard_state().last_mods();
     let drop_data = get_drop_data(sender);
 
+    // Every `DragEntered` event is followed by exactly one `DragLeft` or `DragDropped` event
+    state.cancel_drag();
+    state.drag_active.set(true);
+
     let event = MouseEvent::DragEntered {
-        position: get_drag_position(sender),
+        position: get_drag_position(this, sender),
         modifiers: make_modifiers(modifiers),
         data: drop_data,
     };
@@ -485,7 +809,7 @@ extern "C" fn dragging_updated(this: &Object, _sel: Sel, sender: id) -> NSUInteg
     let drop_data = get_drop_data(sender);
 
     let event = MouseEvent::DragMoved {
-        position: get_drag_position(sender),
+        position: get_drag_position(this, sender),
         modifiers: make_modifiers(modifiers),
         data: drop_data,
     };
@@ -502,11 +826,16 @@ extern "C" fn prepare_for_drag_operation(_this: &Object, _sel: Sel, _sender: id)
 
 extern "C" fn perform_drag_operation(this: &Object, _sel: Sel, sender: id) -> BOOL {
     let state = unsafe { WindowState::from_view(this) };
+
+    // The drag may have been cancelled because the window is closing
+    if !state.drag_active.replace(false) {
+        return NO;
+    }
     let modifiers = state.keyboard_state().last_mods();
     let drop_data = get_drop_data(sender);
 
     let event = MouseEvent::DragDropped {
-        position: get_drag_position(sender),
+        position: get_drag_position(this, sender),
         modifiers: make_modifiers(modifiers),
         data: drop_data,
     };
@@ -521,7 +850,7 @@ extern "C" fn perform_drag_operation(this: &Object, _sel: Sel, sender: id) -> BO
 extern "C" fn dragging_exited(this: &Object, _sel: Sel, _sender: id) {
     let state = unsafe { WindowState::from_view(this) };
 
-    on_event(&state, MouseEvent::DragLeft);
+    state.cancel_drag();
 }
 
 extern "C" fn handle_notification(this: &Object, _cmd: Sel, notification: id) {
@@ -534,6 +863,41 @@ extern "C" fn handle_notification(this: &Object, _cmd: Sel, notification: id) {
         // The NSWindow object associated with our NSView.
         let window: id = msg_send![this, window];
 
+        let name: id = msg_send![notification, name];
+        let is_move: BOOL = msg_send![name, isEqualToString: NSWindowDidMoveNotification];
+        if is_move == YES {
+            if notification_object == window && state.window_inner.is_standalone() {
+                let position = content_position(window);
+                state.trigger_deferrable_event(Event::Window(WindowEvent::Moved(position)));
+            }
+
+            return;
+        }
+
+        let became_main: BOOL = msg_send![name, isEqualToString: NSWindowDidBecomeMainNotification];
+        let resigned_main: BOOL =
+            msg_send![name, isEqualToString: NSWindowDidResignMainNotification];
+        if became_main == YES || resigned_main == YES {
+            if notification_object == window {
+                state.trigger_deferrable_event(Event::Window(if became_main == YES {
+                    WindowEvent::BecameMain
+                } else {
+                    WindowEvent::ResignedMain
+                }));
+            }
+
+            return;
+        }
+
+        // Popups close as soon as the user interacts with another window
+        if notification_object == window && state.window_inner.popup {
+            let is_key_window: BOOL = msg_send![window, isKeyWindow];
+            if is_key_window == NO {
+                state.window_inner.close();
+                return;
+            }
+        }
+
         let first_responder: id = msg_send![window, firstResponder];
 
         // Only trigger focus events if the NSWindow that's being notified about is our window,
@@ -542,6 +906,9 @@ extern "C" fn handle_notification(this: &Object, _cmd: Sel, notification: id) {
         // by the becomeFirstResponder and resignFirstResponder methods on the NSView itself.
         if notification_object == window && first_responder == this as *const Object as id {
             let is_key_window: BOOL = msg_send![window, isKeyWindow];
+            if is_key_window == NO {
+                state.window_inner.cursor_confinement.set(None);
+            }
             state.trigger_event(Event::Window(if is_key_window == YES {
                 WindowEvent::Focused
             } else {
@@ -550,3 +917,63 @@ extern "C" fn handle_notification(this: &Object, _cmd: Sel, notification: id) {
         }
     }
 }
+
+/// The position of the top left corner of a window's content area in screen coordinates, with the
+/// origin at the top left corner of the primary screen.
+unsafe fn content_position(window: id) -> Point {
+    let frame: NSRect = msg_send![window, frame];
+    let content_rect: NSRect = msg_send![window, contentRectForFrameRect: frame];
+
+    // AppKit's screen coordinates start at the bottom left corner of the primary screen
+    let screens: id = msg_send![class!(NSScreen), screens];
+    let primary_screen: id = msg_send![screens, objectAtIndex: 0 as NSUInteger];
+    let screen_frame: NSRect = msg_send![primary_screen, frame];
+
+    Point::new(
+        content_rect.origin.x,
+        screen_frame.size.height - (content_rect.origin.y + content_rect.size.height),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::PhySize;
+
+    #[test]
+    fn moving_to_a_retina_display() {
+        let bounds = Size::new(400.0, 300.0);
+        let old = WindowInfo::from_logical_size(bounds, 1.0);
+
+        let change = backing_change(old, WindowScalePolicy::SystemScaleFactor, Some(2.0), bounds);
+        assert!(change.scale_changed && change.size_changed);
+        assert_eq!(change.window_info.scale(), 2.0);
+        assert_eq!(change.window_info.physical_size(), PhySize::new(800, 600));
+
+        // Moving back and forth between displays with the same scale factor changes nothing
+        let change = backing_change(old, WindowScalePolicy::SystemScaleFactor, Some(1.0), bounds);
+        assert!(!change.scale_changed && !change.size_changed);
+    }
+
+    #[test]
+    fn forced_scale_factor_ignores_the_backing_scale() {
+        let bounds = Size::new(400.0, 300.0);
+        let policy = WindowScalePolicy::ScaleFactor(1.5);
+        let old = WindowInfo::from_logical_size(bounds, 1.5);
+
+        let change = backing_change(old, policy, Some(2.0), bounds);
+        assert!(!change.scale_changed && !change.size_changed);
+        assert_eq!(change.window_info.physical_size(), PhySize::new(600, 450));
+    }
+
+    #[test]
+    fn view_without_a_window() {
+        let bounds = Size::new(400.0, 300.0);
+        let old = WindowInfo::from_logical_size(bounds, 2.0);
+
+        let change = backing_change(old, WindowScalePolicy::SystemScaleFactor, None, bounds);
+        assert!(change.scale_changed && change.size_changed);
+        assert_eq!(change.window_info.scale(), 1.0);
+    }
+}
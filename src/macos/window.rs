@@ -1,17 +1,27 @@
+use std::any::Any;
 use std::cell::{Cell, RefCell};
 use std::collections::VecDeque;
 use std::ffi::c_void;
 use std::ptr;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
+use std::time::{Duration, Instant};
 
+use block::ConcreteBlock;
 use cocoa::appkit::{
-    NSApp, NSApplication, NSApplicationActivationPolicyRegular, NSBackingStoreBuffered,
-    NSPasteboard, NSView, NSWindow, NSWindowStyleMask,
+    NSApp, NSApplication, NSApplicationActivationPolicyRegular, NSBackingStoreBuffered, NSColor,
+    NSEvent, NSEventMask, NSEventModifierFlags, NSEventType, NSRequestUserAttentionType, NSView,
+    NSWindow, NSWindowOrderingMode, NSWindowStyleMask,
 };
 use cocoa::base::{id, nil, BOOL, NO, YES};
-use cocoa::foundation::{NSAutoreleasePool, NSPoint, NSRect, NSSize, NSString};
+use cocoa::foundation::{
+    NSAutoreleasePool, NSInteger, NSPoint, NSRect, NSSize, NSString, NSUInteger,
+};
+use core_foundation::base::TCFType;
+use core_foundation::data::{CFData, CFDataRef};
+use core_foundation::date::CFAbsoluteTimeGetCurrent;
 use core_foundation::runloop::{
-    CFRunLoop, CFRunLoopTimer, CFRunLoopTimerContext, __CFRunLoopTimer, kCFRunLoopDefaultMode,
+    __CFRunLoopTimer, kCFRunLoopDefaultMode, CFRunLoop, CFRunLoopRunResult, CFRunLoopTimer,
+    CFRunLoopTimerContext, CFRunLoopTimerSetNextFireDate,
 };
 use keyboard_types::KeyboardEvent;
 use objc::class;
@@ -22,15 +32,22 @@ use raw_window_handle::{
 };
 
 use crate::{
-    Event, EventStatus, MouseCursor, Size, WindowHandler, WindowInfo, WindowOpenOptions,
+    Backdrop, Color, Event, EventStatus, FramePacing, Framebuffer, HitTestResult, ModifierSides,
+    MouseCursor, MouseEvent, PhySize, Point, PointerKind, RawMessage, Rect, ResizeEdge, Size,
+    UserAttention, WindowError, WindowEvent, WindowHandler, WindowInfo, WindowOpenOptions,
     WindowScalePolicy,
 };
 
-use super::keyboard::KeyboardState;
-use super::view::{create_view, BASEVIEW_STATE_IVAR};
+use super::clipboard;
+use super::cursor::ns_cursor;
+use super::display_link::DisplayLink;
+use super::keyboard::{make_modifier_sides, make_modifiers, KeyboardState};
+use super::view::{create_borderless_window_class, create_view, BASEVIEW_STATE_IVAR};
 
+use crate::event::EventClock;
 #[cfg(feature = "opengl")]
-use crate::gl::{GlConfig, GlContext};
+use crate::gl::{GlConfig, GlContext, GlContextCell, GlError};
+use crate::mouse_cursor::CursorAutoHide;
 
 pub struct WindowHandle {
     state: Rc<WindowState>,
@@ -44,11 +61,64 @@ impl WindowHandle {
     pub fn is_open(&self) -> bool {
         self.state.window_inner.open.get()
     }
+
+    pub fn process_pending_events(&self) {
+        if !self.is_open() {
+            return;
+        }
+
+        // Only the run loop's pending sources and timers are handled, including this window's
+        // frame timer. Dequeueing events from `NSApp` would dispatch the events for every window
+        // in the host application.
+        let mode = unsafe { kCFRunLoopDefaultMode };
+        while CFRunLoop::run_in_mode(mode, Duration::ZERO, true)
+            == CFRunLoopRunResult::HandledSource
+        {}
+    }
+
+    pub fn raise(&self) {
+        self.state.window_inner.set_z_order(true);
+    }
+
+    pub fn lower(&self) {
+        self.state.window_inner.set_z_order(false);
+    }
+
+    pub fn send_event(&self, event: Event) {
+        if self.is_open() {
+            self.state.trigger_deferrable_event(event);
+        }
+    }
+
+    pub fn resize(&self, size: Size) {
+        if self.is_open() {
+            let inner = &self.state.window_inner;
+            inner.deferred_tasks.borrow_mut().push_back(WindowTask::Resize(size));
+            // The tasks are handled when the window handler returns if it's currently borrowed
+            if self.state.window_handler.try_borrow_mut().is_ok() {
+                self.state.handle_deferred_tasks();
+            }
+        }
+    }
+
+    pub fn send_message(&self, message: Box<dyn Any + Send>) {
+        if self.is_open() {
+            self.state.trigger_message(message);
+        }
+    }
+
+    pub fn try_raw_window_handle(&self) -> Option<RawWindowHandle> {
+        if self.is_open() {
+            Some(self.state.window_inner.raw_window_handle())
+        } else {
+            None
+        }
+    }
 }
 
 unsafe impl HasRawWindowHandle for WindowHandle {
     fn raw_window_handle(&self) -> RawWindowHandle {
-        self.state.window_inner.raw_window_handle()
+        self.try_raw_window_handle().unwrap_or(RawWindowHandle::AppKit(AppKitWindowHandle::empty()))
     }
 }
 
@@ -58,19 +128,268 @@ pub(super) struct WindowInner {
     /// Only set if we created the parent window, i.e. we are running in
     /// parentless mode
     ns_app: Cell<Option<id>>,
+    /// The number of open standalone windows sharing `ns_app`'s run loop. The application is
+    /// stopped once the last of these windows closes. Only set in parentless mode.
+    app_windows: Option<Rc<Cell<usize>>>,
     /// Only set if we created the parent window, i.e. we are running in
     /// parentless mode
     ns_window: Cell<Option<id>>,
     /// Our subclassed NSView
     ns_view: id,
+    /// The interval between calls to `on_frame`. Used when (re)creating the frame timer.
+    frame_interval: Cell<Duration>,
+    /// Whether `on_frame` is driven by the frame timer or by a display link.
+    frame_pacing: Cell<FramePacing>,
+    /// The scaling policy the window was opened with, or the one set through
+    /// [`Window::set_scale_policy`].
+    pub(super) scale_policy: Cell<WindowScalePolicy>,
+    /// The `NSVisualEffectView` added by [`Window::set_backdrop`], if any. This is owned by
+    /// `ns_view`, which it's a subview of.
+    backdrop_view: Cell<id>,
+    /// The part of the view that receives mouse input, in logical coordinates. The entire view
+    /// receives input if this is not set.
+    pub(super) input_region: RefCell<Option<Vec<Rect>>>,
+    event_clock: EventClock,
+    /// The time at which the last input event was generated.
+    event_timestamp: Cell<Option<Instant>>,
+    /// The modifier keys held down during the last input event.
+    modifier_sides: Cell<ModifierSides>,
+    /// The kind of device that generated the last mouse event.
+    pointer_kind: Cell<PointerKind>,
+    /// The cursor shown while the mouse is over the view. AppKit asks the view to set the cursor
+    /// again whenever the mouse enters it.
+    pub(super) mouse_cursor: Cell<MouseCursor>,
+    pub(super) cursor_auto_hide: CursorAutoHide,
+    /// The origin of the current [relative drag][Window::begin_relative_drag] in logical pixels.
+    /// The cursor is hidden and detached from the mouse during the drag.
+    pub(super) relative_drag_origin: Cell<Option<Point>>,
+    /// The area set with [`Window::confine_cursor`], in logical coordinates.
+    pub(super) cursor_confinement: Cell<Option<Rect>>,
+    /// Requests made through [`Window`] that are handled once the window handler's borrow ends.
+    /// Resizing or closing the window sends events to the view, which would otherwise need to
+    /// borrow the window handler while it's still borrowed.
+    deferred_tasks: RefCell<VecDeque<WindowTask>>,
+    /// Whether the window or view is shown.
+    visible: Cell<bool>,
+    /// Whether showing the window should not make it the key window. See
+    /// [`WindowOpenOptions::no_focus_on_open`].
+    no_focus_on_open: bool,
+    /// Whether this window was opened through [`crate::Window::open_popup`]. Popups are closed
+    /// when they stop being the key window.
+    pub(super) popup: bool,
+    /// The popups opened from this window. These are closed along with this window.
+    popups: RefCell<Vec<Weak<WindowState>>>,
 
     #[cfg(feature = "opengl")]
-    gl_context: Option<GlContext>,
+    gl_context: GlContextCell,
+    /// Why the requested OpenGL context could not be created, if it couldn't.
+    #[cfg(feature = "opengl")]
+    gl_context_error: Option<GlError>,
+    /// [`WindowOpenOptions::transparent`]. This is also needed for OpenGL contexts that are
+    /// created after the window has been opened.
+    #[cfg(feature = "opengl")]
+    transparent: bool,
+}
+
+/// Changes to the window that are made once the window handler returns. See
+/// [`WindowInner::deferred_tasks`].
+enum WindowTask {
+    Resize(Size),
+    SetTitle(String),
+    SetVisible(bool),
+    SetScalePolicy(WindowScalePolicy),
+    Close,
 }
 
 impl WindowInner {
+    fn resize(&self, size: Size) {
+        if self.open.get() {
+            // NOTE: macOS gives you a personal rave if you pass in fractional pixels here. Even
+            // though the size is in fractional pixels.
+            let size = NSSize::new(size.width.round(), size.height.round());
+
+            unsafe { NSView::setFrameSize(self.ns_view, size) };
+            unsafe {
+                let _: () = msg_send![self.ns_view, setNeedsDisplay: YES];
+            }
+
+            // When using OpenGL the `NSOpenGLView` needs to be resized separately? Why? Because
+            // macOS.
+            #[cfg(feature = "opengl")]
+            if let Some(gl_context) = self.gl_context.get() {
+                gl_context.resize(size);
+            }
+            self.update_gl_surface_size(Size::new(size.width, size.height));
+
+            // If this is a standalone window then we'll also need to resize the window itself
+            if let Some(ns_window) = self.ns_window.get() {
+                unsafe { NSWindow::setContentSize_(ns_window, size) };
+            }
+        }
+    }
+
+    /// Move the window or view above or below its siblings.
+    fn set_z_order(&self, above: bool) {
+        if !self.open.get() {
+            return;
+        }
+
+        unsafe {
+            if let Some(ns_window) = self.ns_window.get() {
+                if above {
+                    ns_window.orderFront_(nil);
+                } else {
+                    ns_window.orderBack_(nil);
+                }
+            } else {
+                let superview: id = msg_send![self.ns_view, superview];
+                if superview != nil {
+                    let ordering = if above {
+                        NSWindowOrderingMode::NSWindowAbove
+                    } else {
+                        NSWindowOrderingMode::NSWindowBelow
+                    };
+
+                    // Adding a view that's already a subview of the superview moves it. The view
+                    // is retained so it can't be deallocated while that happens.
+                    let _: id = msg_send![self.ns_view, retain];
+                    let () = msg_send![
+                        superview,
+                        addSubview: self.ns_view
+                        positioned: ordering
+                        relativeTo: nil
+                    ];
+                    let () = msg_send![self.ns_view, release];
+                }
+            }
+        }
+    }
+
+    /// Show or hide the window. Returns `true` if this changed the window's visibility.
+    fn set_visible(&self, visible: bool) -> bool {
+        if !self.open.get() || self.visible.replace(visible) == visible {
+            return false;
+        }
+
+        unsafe {
+            if let Some(ns_window) = self.ns_window.get() {
+                if !visible {
+                    ns_window.orderOut_(nil);
+                } else if self.no_focus_on_open {
+                    ns_window.orderFront_(nil);
+                } else {
+                    ns_window.makeKeyAndOrderFront_(nil);
+                }
+            } else {
+                let hidden = if visible { NO } else { YES };
+                let () = msg_send![self.ns_view, setHidden: hidden];
+            }
+        }
+
+        true
+    }
+
+    fn set_title(&self, title: &str) {
+        // Parented windows don't have a title bar
+        if let Some(ns_window) = self.ns_window.get() {
+            unsafe {
+                let title = NSString::alloc(nil).init_str(title).autorelease();
+                ns_window.setTitle_(title);
+            }
+        }
+    }
+
+    /// Whether this window was opened as a standalone window, i.e. whether we created the
+    /// `NSWindow`.
+    pub(super) fn is_standalone(&self) -> bool {
+        self.ns_window.get().is_some()
+    }
+
+    /// Move the cursor back into the area set with [`Window::confine_cursor`] if `position` lies
+    /// outside of it. This returns the position the cursor was moved to, if it was moved.
+    pub(super) fn confine_cursor(&self, position: Point) -> Option<Point> {
+        let rect = self.cursor_confinement.get()?;
+
+        let confined = Point::new(
+            position.x.clamp(rect.x, rect.x + rect.width.max(0.0)),
+            position.y.clamp(rect.y, rect.y + rect.height.max(0.0)),
+        );
+        if confined == position {
+            return None;
+        }
+
+        self.warp_cursor(confined);
+        Some(confined)
+    }
+
+    /// Move the cursor to `position`, in logical coordinates relative to the view. This does not
+    /// generate a mouse event.
+    pub(super) fn warp_cursor(&self, position: Point) {
+        unsafe {
+            let window: id = msg_send![self.ns_view, window];
+            if window == nil {
+                return;
+            }
+
+            // The view is flipped, so this is relative to the view's top left corner. Quartz's
+            // global coordinates start at the top left corner of the primary screen, while
+            // AppKit's screen coordinates start at its bottom left corner.
+            let position: NSPoint = msg_send![
+                self.ns_view,
+                convertPoint: NSPoint::new(position.x, position.y)
+                toView: nil
+            ];
+            let screen_rect =
+                window.convertRectToScreen_(NSRect::new(position, NSSize::new(0.0, 0.0)));
+            let screens: id = msg_send![class!(NSScreen), screens];
+            let primary_screen: id = msg_send![screens, objectAtIndex: 0 as NSUInteger];
+            let screen_frame: NSRect = msg_send![primary_screen, frame];
+
+            CGWarpMouseCursorPosition(NSPoint::new(
+                screen_rect.origin.x,
+                screen_frame.size.height - screen_rect.origin.y,
+            ));
+        }
+    }
+
+    fn end_relative_drag(&self) {
+        let Some(origin) = self.relative_drag_origin.take() else { return };
+
+        self.warp_cursor(origin);
+        unsafe {
+            // Reattaching the cursor right after warping it also skips the short period during
+            // which macOS ignores mouse movement after a warp
+            CGAssociateMouseAndMouseCursorPosition(1);
+            let () = msg_send![class!(NSCursor), unhide];
+        }
+    }
+
+    /// The `CGDirectDisplayID` of the screen the view's window is on, if it's on a screen.
+    unsafe fn display_id(&self) -> Option<u32> {
+        let window: id = msg_send![self.ns_view, window];
+        if window == nil {
+            return None;
+        }
+        let screen: id = msg_send![window, screen];
+        if screen == nil {
+            return None;
+        }
+
+        let description: id = msg_send![screen, deviceDescription];
+        let key = NSString::alloc(nil).init_str("NSScreenNumber").autorelease();
+        let screen_number: id = msg_send![description, objectForKey: key];
+        if screen_number == nil {
+            return None;
+        }
+
+        let display_id: u32 = msg_send![screen_number, unsignedIntValue];
+        Some(display_id)
+    }
+
     pub(super) fn close(&self) {
         if self.open.get() {
+            // The cursor is hidden for the entire application during the drag
+            self.end_relative_drag();
             self.open.set(false);
 
             unsafe {
@@ -78,10 +397,8 @@ impl WindowInner {
                 let state_ptr: *const c_void = *(*self.ns_view).get_ivar(BASEVIEW_STATE_IVAR);
                 let window_state = Rc::from_raw(state_ptr as *mut WindowState);
 
-                // Cancel the frame timer
-                if let Some(frame_timer) = window_state.frame_timer.take() {
-                    CFRunLoop::get_current().remove_timer(&frame_timer, kCFRunLoopDefaultMode);
-                }
+                window_state.stop_timer();
+                window_state.remove_media_key_monitor();
 
                 // Deregister NSView from NotificationCenter.
                 let notification_center: id =
@@ -90,8 +407,21 @@ impl WindowInner {
 
                 drop(window_state);
 
+                for popup in self.popups.take() {
+                    if let Some(popup) = popup.upgrade() {
+                        popup.window_inner.close();
+                    }
+                }
+
                 // Close the window if in non-parented mode
                 if let Some(ns_window) = self.ns_window.take() {
+                    if self.popup {
+                        let parent_window: id = msg_send![ns_window, parentWindow];
+                        if parent_window != nil {
+                            let () = msg_send![parent_window, removeChildWindow: ns_window];
+                        }
+                    }
+
                     ns_window.close();
                 }
 
@@ -99,15 +429,153 @@ impl WindowInner {
                 self.ns_view.removeFromSuperview();
                 let () = msg_send![self.ns_view as id, release];
 
-                // If in non-parented mode, we want to also quit the app altogether
+                // If in non-parented mode, we want to also quit the app altogether once the last
+                // window has been closed
                 let app = self.ns_app.take();
                 if let Some(app) = app {
-                    app.stop_(app);
+                    let remaining_windows = match &self.app_windows {
+                        Some(app_windows) => {
+                            app_windows.set(app_windows.get().saturating_sub(1));
+                            app_windows.get()
+                        }
+                        None => 0,
+                    };
+
+                    if remaining_windows == 0 {
+                        app.stop_(app);
+                    }
+                }
+            }
+        }
+    }
+
+    /// When the window uses a forced scale factor, the OpenGL surface needs to be rendered at
+    /// that scale instead of at the backing scale factor of the screen the window is on.
+    fn update_gl_surface_size(&self, _size: Size) {
+        #[cfg(feature = "opengl")]
+        if let (Some(gl_context), WindowScalePolicy::ScaleFactor(scale)) =
+            (self.gl_context.get(), self.scale_policy.get())
+        {
+            let physical_size = WindowInfo::from_logical_size(_size, scale).physical_size();
+            gl_context.set_surface_size(Some((physical_size.width, physical_size.height)));
+        }
+    }
+
+    /// Switch to a different scale policy. The view keeps its size in points, so only the OpenGL
+    /// surface needs to be resized.
+    fn set_scale_policy(&self, policy: WindowScalePolicy) {
+        self.scale_policy.set(policy);
+
+        #[cfg(feature = "opengl")]
+        if let (Some(gl_context), WindowScalePolicy::SystemScaleFactor) =
+            (self.gl_context.get(), policy)
+        {
+            gl_context.set_surface_size(None);
+        }
+        let bounds: NSRect = unsafe { msg_send![self.ns_view, bounds] };
+        self.update_gl_surface_size(Size::new(bounds.size.width, bounds.size.height));
+    }
+
+    /// Resize the OpenGL view to the view's current size. This recreates the drawable after the
+    /// backing scale factor has changed.
+    pub(super) fn resize_gl_view(&self, _size: NSSize) {
+        #[cfg(feature = "opengl")]
+        if let Some(gl_context) = self.gl_context.get() {
+            gl_context.resize(_size);
+        }
+    }
+
+    /// The cursor's position relative to the view in logical pixels, if it's inside of the view.
+    fn cursor_position(&self) -> Option<Point> {
+        unsafe {
+            let ns_window: id = msg_send![self.ns_view, window];
+            if ns_window == nil {
+                return None;
+            }
+
+            // The view is flipped, so this is already relative to the top left corner
+            let window_point = ns_window.mouseLocationOutsideOfEventStream();
+            let point = self.ns_view.convertPoint_fromView_(window_point, nil);
+
+            let bounds: NSRect = msg_send![self.ns_view, bounds];
+            if point.x < 0.0
+                || point.y < 0.0
+                || point.x >= bounds.size.width
+                || point.y >= bounds.size.height
+            {
+                return None;
+            }
+
+            Some(Point::new(point.x, point.y))
+        }
+    }
+
+    /// Whether the cursor is over this view, and not over another baseview view embedded in it or
+    /// over another window in front of this one. Every view tracks the mouse across its entire
+    /// bounds, so without this check a window could replace the cursor of a window embedded in it.
+    /// Changing the cursor applies to the entire application, so this should be checked before
+    /// doing so.
+    pub(super) fn cursor_over_view(&self) -> bool {
+        if self.cursor_position().is_none() {
+            return false;
+        }
+
+        unsafe {
+            let ns_window: id = msg_send![self.ns_view, window];
+            if ns_window == nil {
+                return false;
+            }
+
+            // The view can be covered by one of the host's other windows
+            let mouse_location: NSPoint = msg_send![class!(NSEvent), mouseLocation];
+            let window_number: NSInteger = msg_send![ns_window, windowNumber];
+            let window_under_cursor: NSInteger = msg_send![
+                class!(NSWindow),
+                windowNumberAtPoint: mouse_location
+                belowWindowWithWindowNumber: 0 as NSInteger
+            ];
+            if window_under_cursor != window_number {
+                return false;
+            }
+
+            // `hitTest:` takes a point in the coordinate system of the receiver's superview
+            let content_view: id = msg_send![ns_window, contentView];
+            let frame_view: id = msg_send![content_view, superview];
+            let mut point = ns_window.mouseLocationOutsideOfEventStream();
+            if frame_view != nil {
+                point = frame_view.convertPoint_fromView_(point, nil);
+            }
+
+            let mut view: id = msg_send![content_view, hitTest: point];
+            while view != nil {
+                if view == self.ns_view {
+                    return true;
                 }
+                if (*view).class().instance_variable(BASEVIEW_STATE_IVAR).is_some() {
+                    return false;
+                }
+
+                view = msg_send![view, superview];
             }
+
+            false
         }
     }
 
+    /// A `CursorMoved` event for the cursor's current position, if it's inside of the view. The
+    /// cursor's position relative to the view changes when the view gets resized, even if the
+    /// cursor itself didn't move.
+    pub(super) fn cursor_moved_event(&self) -> Option<Event> {
+        let position = self.cursor_position()?;
+        let modifier_flags: NSEventModifierFlags =
+            unsafe { msg_send![class!(NSEvent), modifierFlags] };
+
+        Some(Event::Mouse(MouseEvent::CursorMoved {
+            position,
+            modifiers: make_modifiers(modifier_flags),
+        }))
+    }
+
     fn raw_window_handle(&self) -> RawWindowHandle {
         if self.open.get() {
             let ns_window = self.ns_window.get().unwrap_or(ptr::null_mut()) as *mut c_void;
@@ -128,41 +596,89 @@ pub struct Window<'a> {
 }
 
 impl<'a> Window<'a> {
-    pub fn open_parented<P, H, B>(parent: &P, options: WindowOpenOptions, build: B) -> WindowHandle
+    pub fn try_open_parented<P, H, B>(
+        parent: &P, options: WindowOpenOptions, build: B,
+    ) -> Result<WindowHandle, WindowError>
     where
         P: HasRawWindowHandle,
         H: WindowHandler + 'static,
         B: FnOnce(&mut crate::Window) -> H,
         B: Send + 'static,
     {
+        let handle = if let RawWindowHandle::AppKit(handle) = parent.raw_window_handle() {
+            handle
+        } else {
+            return Err(WindowError::UnsupportedParent);
+        };
+
         let pool = unsafe { NSAutoreleasePool::new(nil) };
 
         let scaling = match options.scale {
             WindowScalePolicy::ScaleFactor(scale) => scale,
-            WindowScalePolicy::SystemScaleFactor => 1.0,
+            WindowScalePolicy::SystemScaleFactor => unsafe {
+                let parent_window: id = msg_send![handle.ns_view as id, window];
+                backing_scale_factor(parent_window)
+            },
         };
 
         let window_info = WindowInfo::from_logical_size(options.size, scaling);
 
-        let handle = if let RawWindowHandle::AppKit(handle) = parent.raw_window_handle() {
-            handle
-        } else {
-            panic!("Not a macOS window");
-        };
-
         let ns_view = unsafe { create_view(&options) };
+        if ns_view == nil {
+            unsafe {
+                let () = msg_send![pool, drain];
+            }
+
+            return Err(WindowError::CreationFailed("could not create the NSView".into()));
+        }
+
+        if !options.visible {
+            unsafe {
+                let () = msg_send![ns_view, setHidden: YES];
+            }
+        }
+
+        #[cfg(feature = "opengl")]
+        let (gl_context, gl_context_error) = Self::create_requested_gl_context(
+            None,
+            ns_view,
+            options.gl_config,
+            options.transparent,
+        );
 
         let window_inner = WindowInner {
             open: Cell::new(true),
             ns_app: Cell::new(None),
+            app_windows: None,
             ns_window: Cell::new(None),
             ns_view,
+            frame_interval: Cell::new(Duration::from_millis(15)),
+            frame_pacing: Cell::new(FramePacing::Timer),
+            scale_policy: Cell::new(options.scale),
+            backdrop_view: Cell::new(nil),
+            input_region: RefCell::new(None),
+            event_clock: EventClock::new(),
+            event_timestamp: Cell::new(None),
+            modifier_sides: Cell::new(ModifierSides::empty()),
+            pointer_kind: Cell::new(PointerKind::Mouse),
+            mouse_cursor: Cell::new(MouseCursor::default()),
+            cursor_auto_hide: CursorAutoHide::new(),
+            relative_drag_origin: Cell::new(None),
+            cursor_confinement: Cell::new(None),
+            deferred_tasks: RefCell::new(VecDeque::new()),
+            visible: Cell::new(options.visible),
+            no_focus_on_open: false,
+            popup: false,
+            popups: RefCell::new(Vec::new()),
 
             #[cfg(feature = "opengl")]
-            gl_context: options
-                .gl_config
-                .map(|gl_config| Self::create_gl_context(None, ns_view, gl_config)),
+            gl_context,
+            #[cfg(feature = "opengl")]
+            gl_context_error,
+            #[cfg(feature = "opengl")]
+            transparent: options.transparent,
         };
+        window_inner.update_gl_surface_size(options.size);
 
         let window_handle = Self::init(window_inner, window_info, build);
 
@@ -172,7 +688,7 @@ impl<'a> Window<'a> {
             let () = msg_send![pool, drain];
         }
 
-        window_handle
+        Ok(window_handle)
     }
 
     pub fn open_blocking<H, B>(options: WindowOpenOptions, build: B)
@@ -181,116 +697,374 @@ impl<'a> Window<'a> {
         B: FnOnce(&mut crate::Window) -> H,
         B: Send + 'static,
     {
-        let pool = unsafe { NSAutoreleasePool::new(nil) };
-
-        // It seems prudent to run NSApp() here before doing other
-        // work. It runs [NSApplication sharedApplication], which is
-        // what is run at the very start of the Xcode-generated main
-        // function of a cocoa app according to:
-        // https://developer.apple.com/documentation/appkit/nsapplication
-        let app = unsafe { NSApp() };
+        let mut application = Application::new();
+        application.open_window(options, build);
+        application.run();
+    }
 
-        unsafe {
-            app.setActivationPolicy_(NSApplicationActivationPolicyRegular);
-        }
+    /// Open a standalone window that runs on `app`'s run loop. `app_windows` keeps track of the
+    /// number of open windows so the application can be stopped when the last one closes.
+    fn open_standalone<H, B>(
+        options: WindowOpenOptions, build: B, app: id, app_windows: Rc<Cell<usize>>,
+    ) -> WindowHandle
+    where
+        H: WindowHandler + 'static,
+        B: FnOnce(&mut crate::Window) -> H,
+        B: Send + 'static,
+    {
+        let pool = unsafe { NSAutoreleasePool::new(nil) };
 
-        let scaling = match options.scale {
-            WindowScalePolicy::ScaleFactor(scale) => scale,
-            WindowScalePolicy::SystemScaleFactor => 1.0,
+        // The content rect is in screen coordinates, which start at the bottom left corner of the
+        // primary screen
+        let origin = match options.position {
+            Some(position) => unsafe {
+                let screens: id = msg_send![class!(NSScreen), screens];
+                let primary_screen: id = msg_send![screens, objectAtIndex: 0 as NSUInteger];
+                let screen_frame: NSRect = msg_send![primary_screen, frame];
+
+                NSPoint::new(
+                    position.x,
+                    screen_frame.size.height - (position.y + options.size.height),
+                )
+            },
+            None => NSPoint::new(0.0, 0.0),
         };
-
-        let window_info = WindowInfo::from_logical_size(options.size, scaling);
-
-        let rect = NSRect::new(
-            NSPoint::new(0.0, 0.0),
-            NSSize::new(window_info.logical_size().width, window_info.logical_size().height),
-        );
+        let rect = NSRect::new(origin, NSSize::new(options.size.width, options.size.height));
 
         let ns_window = unsafe {
-            let ns_window = NSWindow::alloc(nil).initWithContentRect_styleMask_backing_defer_(
+            // Borderless windows can't become the key window by default, so they need to use a
+            // subclass that allows this. Otherwise they would not receive any keyboard input.
+            let (window_class, style_mask) = if options.decorations {
+                (
+                    class!(NSWindow),
+                    NSWindowStyleMask::NSTitledWindowMask
+                        | NSWindowStyleMask::NSClosableWindowMask
+                        | NSWindowStyleMask::NSMiniaturizableWindowMask,
+                )
+            } else {
+                (create_borderless_window_class(), NSWindowStyleMask::NSBorderlessWindowMask)
+            };
+
+            let ns_window: id = msg_send![window_class, alloc];
+            let ns_window = ns_window.initWithContentRect_styleMask_backing_defer_(
                 rect,
-                NSWindowStyleMask::NSTitledWindowMask
-                    | NSWindowStyleMask::NSClosableWindowMask
-                    | NSWindowStyleMask::NSMiniaturizableWindowMask,
+                style_mask,
                 NSBackingStoreBuffered,
                 NO,
             );
-            ns_window.center();
+            if options.position.is_none() {
+                ns_window.center();
+            }
 
             let title = NSString::alloc(nil).init_str(&options.title).autorelease();
             ns_window.setTitle_(title);
 
-            ns_window.makeKeyAndOrderFront_(nil);
+            if options.transparent {
+                ns_window.setOpaque_(NO);
+                ns_window.setBackgroundColor_(NSColor::clearColor(nil));
+            } else if let Some(color) = options.background_color {
+                ns_window.setBackgroundColor_(ns_color(color));
+            }
+
+            if let Some((width, height)) = options.keep_aspect_ratio {
+                ns_window.setContentAspectRatio_(NSSize::new(width as f64, height as f64));
+            }
+
+            if let Some(increments) = options.resize_increments {
+                ns_window
+                    .setContentResizeIncrements_(NSSize::new(increments.width, increments.height));
+            }
+
+            if options.visible && options.no_focus_on_open {
+                ns_window.orderFront_(nil);
+            } else if options.visible {
+                ns_window.makeKeyAndOrderFront_(nil);
+            }
 
             ns_window
         };
 
+        let scaling = match options.scale {
+            WindowScalePolicy::ScaleFactor(scale) => scale,
+            WindowScalePolicy::SystemScaleFactor => unsafe { backing_scale_factor(ns_window) },
+        };
+
+        let window_info = WindowInfo::from_logical_size(options.size, scaling);
+
         let ns_view = unsafe { create_view(&options) };
 
+        #[cfg(feature = "opengl")]
+        let (gl_context, gl_context_error) = Self::create_requested_gl_context(
+            Some(ns_window),
+            ns_view,
+            options.gl_config,
+            options.transparent,
+        );
+
         let window_inner = WindowInner {
             open: Cell::new(true),
             ns_app: Cell::new(Some(app)),
+            app_windows: Some(Rc::clone(&app_windows)),
             ns_window: Cell::new(Some(ns_window)),
             ns_view,
+            frame_interval: Cell::new(Duration::from_millis(15)),
+            frame_pacing: Cell::new(FramePacing::Timer),
+            scale_policy: Cell::new(options.scale),
+            backdrop_view: Cell::new(nil),
+            input_region: RefCell::new(None),
+            event_clock: EventClock::new(),
+            event_timestamp: Cell::new(None),
+            modifier_sides: Cell::new(ModifierSides::empty()),
+            pointer_kind: Cell::new(PointerKind::Mouse),
+            mouse_cursor: Cell::new(MouseCursor::default()),
+            cursor_auto_hide: CursorAutoHide::new(),
+            relative_drag_origin: Cell::new(None),
+            cursor_confinement: Cell::new(None),
+            deferred_tasks: RefCell::new(VecDeque::new()),
+            visible: Cell::new(options.visible),
+            no_focus_on_open: options.no_focus_on_open,
+            popup: false,
+            popups: RefCell::new(Vec::new()),
 
             #[cfg(feature = "opengl")]
-            gl_context: options
-                .gl_config
-                .map(|gl_config| Self::create_gl_context(Some(ns_window), ns_view, gl_config)),
+            gl_context,
+            #[cfg(feature = "opengl")]
+            gl_context_error,
+            #[cfg(feature = "opengl")]
+            transparent: options.transparent,
         };
+        window_inner.update_gl_surface_size(options.size);
 
-        let _ = Self::init(window_inner, window_info, build);
+        app_windows.set(app_windows.get() + 1);
+        let window_handle = Self::init(window_inner, window_info, build);
 
         unsafe {
             ns_window.setContentView_(ns_view);
             ns_window.setDelegate_(ns_view);
 
-            let () = msg_send![pool, drain];
+            // The window was made key before the view existed, so the view needs to be made the
+            // first responder explicitly to receive keyboard input
+            let _: BOOL = msg_send![ns_window, makeFirstResponder: ns_view];
 
-            app.run();
+            let () = msg_send![pool, drain];
         }
+
+        window_handle
     }
 
-    fn init<H, B>(window_inner: WindowInner, window_info: WindowInfo, build: B) -> WindowHandle
+    pub fn open_popup<H, B>(
+        &mut self, options: WindowOpenOptions, position: Point, build: B,
+    ) -> WindowHandle
     where
         H: WindowHandler + 'static,
         B: FnOnce(&mut crate::Window) -> H,
         B: Send + 'static,
     {
-        let mut window = crate::Window::new(Window { inner: &window_inner });
-        let window_handler = Box::new(build(&mut window));
+        let pool = unsafe { NSAutoreleasePool::new(nil) };
 
-        let ns_view = window_inner.ns_view;
+        let owner_window: id = unsafe { msg_send![self.inner.ns_view, window] };
 
-        let window_state = Rc::new(WindowState {
-            window_inner,
-            window_handler: RefCell::new(window_handler),
-            keyboard_state: KeyboardState::new(),
-            frame_timer: Cell::new(None),
-            window_info: Cell::new(window_info),
-            deferred_events: RefCell::default(),
-        });
+        let ns_window = unsafe {
+            let rect = NSRect::new(
+                NSPoint::new(0.0, 0.0),
+                NSSize::new(options.size.width, options.size.height),
+            );
 
-        let window_state_ptr = Rc::into_raw(Rc::clone(&window_state));
+            // The popup needs to become the key window to receive keyboard input, and to know
+            // when the user clicks outside of it
+            let ns_window: id = msg_send![create_borderless_window_class(), alloc];
+            let ns_window = ns_window.initWithContentRect_styleMask_backing_defer_(
+                rect,
+                NSWindowStyleMask::NSBorderlessWindowMask,
+                NSBackingStoreBuffered,
+                NO,
+            );
 
-        unsafe {
-            (*ns_view).set_ivar(BASEVIEW_STATE_IVAR, window_state_ptr as *const c_void);
+            if owner_window != nil {
+                // The view is flipped, so this is relative to the view's top left corner. AppKit's
+                // screen coordinates start at the bottom left corner of the screen.
+                let position: NSPoint = msg_send![
+                    self.inner.ns_view,
+                    convertPoint: NSPoint::new(position.x, position.y)
+                    toView: nil
+                ];
+                let screen_rect =
+                    owner_window.convertRectToScreen_(NSRect::new(position, NSSize::new(0.0, 0.0)));
+                ns_window.setFrameTopLeftPoint_(screen_rect.origin);
+
+                // Child windows stay above their parent, and they move along with it
+                let () = msg_send![
+                    owner_window,
+                    addChildWindow: ns_window
+                    ordered: NSWindowOrderingMode::NSWindowAbove
+                ];
+            }
 
-            WindowState::setup_timer(window_state_ptr);
-        }
+            if options.transparent {
+                ns_window.setOpaque_(NO);
+                ns_window.setBackgroundColor_(NSColor::clearColor(nil));
+            } else if let Some(color) = options.background_color {
+                ns_window.setBackgroundColor_(ns_color(color));
+            }
 
-        WindowHandle { state: window_state }
-    }
+            if options.visible {
+                ns_window.makeKeyAndOrderFront_(nil);
+            }
 
-    pub fn close(&mut self) {
-        self.inner.close();
-    }
+            ns_window
+        };
 
-    pub fn has_focus(&mut self) -> bool {
-        unsafe {
-            let view = self.inner.ns_view.as_mut().unwrap();
-            let window: id = msg_send![view, window];
-            if window == nil {
+        let scaling = match options.scale {
+            WindowScalePolicy::ScaleFactor(scale) => scale,
+            WindowScalePolicy::SystemScaleFactor => unsafe { backing_scale_factor(ns_window) },
+        };
+
+        let window_info = WindowInfo::from_logical_size(options.size, scaling);
+
+        let ns_view = unsafe { create_view(&options) };
+
+        #[cfg(feature = "opengl")]
+        let (gl_context, gl_context_error) = Self::create_requested_gl_context(
+            Some(ns_window),
+            ns_view,
+            options.gl_config,
+            options.transparent,
+        );
+
+        let window_inner = WindowInner {
+            open: Cell::new(true),
+            ns_app: Cell::new(None),
+            app_windows: None,
+            ns_window: Cell::new(Some(ns_window)),
+            ns_view,
+            frame_interval: Cell::new(Duration::from_millis(15)),
+            frame_pacing: Cell::new(FramePacing::Timer),
+            scale_policy: Cell::new(options.scale),
+            backdrop_view: Cell::new(nil),
+            input_region: RefCell::new(None),
+            event_clock: EventClock::new(),
+            event_timestamp: Cell::new(None),
+            modifier_sides: Cell::new(ModifierSides::empty()),
+            pointer_kind: Cell::new(PointerKind::Mouse),
+            mouse_cursor: Cell::new(MouseCursor::default()),
+            cursor_auto_hide: CursorAutoHide::new(),
+            relative_drag_origin: Cell::new(None),
+            cursor_confinement: Cell::new(None),
+            deferred_tasks: RefCell::new(VecDeque::new()),
+            visible: Cell::new(options.visible),
+            no_focus_on_open: false,
+            popup: true,
+            popups: RefCell::new(Vec::new()),
+
+            #[cfg(feature = "opengl")]
+            gl_context,
+            #[cfg(feature = "opengl")]
+            gl_context_error,
+            #[cfg(feature = "opengl")]
+            transparent: options.transparent,
+        };
+        window_inner.update_gl_surface_size(options.size);
+
+        let window_handle = Self::init(window_inner, window_info, build);
+        self.inner.popups.borrow_mut().push(Rc::downgrade(&window_handle.state));
+
+        unsafe {
+            ns_window.setContentView_(ns_view);
+            ns_window.setDelegate_(ns_view);
+
+            let () = msg_send![pool, drain];
+        }
+
+        window_handle
+    }
+
+    fn init<H, B>(window_inner: WindowInner, window_info: WindowInfo, build: B) -> WindowHandle
+    where
+        H: WindowHandler + 'static,
+        B: FnOnce(&mut crate::Window) -> H,
+        B: Send + 'static,
+    {
+        let mut window = crate::Window::new(Window { inner: &window_inner });
+        let window_handler = Box::new(build(&mut window));
+
+        let ns_view = window_inner.ns_view;
+
+        let window_state = Rc::new(WindowState {
+            window_inner,
+            window_handler: RefCell::new(window_handler),
+            keyboard_state: KeyboardState::new(),
+            frame_timer: Cell::new(None),
+            display_link: Cell::new(None),
+            media_key_monitor: Cell::new(nil),
+            window_info: Cell::new(window_info),
+            deferred_events: RefCell::default(),
+            deferred_messages: RefCell::default(),
+            drag_active: Cell::new(false),
+            clipboard_change_count: Cell::new(clipboard::change_count()),
+        });
+
+        let window_state_ptr = Rc::into_raw(Rc::clone(&window_state));
+
+        unsafe {
+            (*ns_view).set_ivar(BASEVIEW_STATE_IVAR, window_state_ptr as *const c_void);
+
+            WindowState::setup_timer(window_state_ptr);
+        }
+        WindowState::add_media_key_monitor(&window_state);
+
+        // Send an initial window resized event so the user is alerted of the correct dpi scaling
+        window_state.trigger_event(Event::Window(WindowEvent::Resized(window_info)));
+
+        WindowHandle { state: window_state }
+    }
+
+    pub fn close(&mut self) {
+        self.inner.deferred_tasks.borrow_mut().push_back(WindowTask::Close);
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.inner.deferred_tasks.borrow_mut().push_back(WindowTask::SetVisible(visible));
+    }
+
+    pub fn scale_policy(&self) -> WindowScalePolicy {
+        self.inner.scale_policy.get()
+    }
+
+    pub fn set_scale_policy(&mut self, policy: WindowScalePolicy) {
+        self.inner.deferred_tasks.borrow_mut().push_back(WindowTask::SetScalePolicy(policy));
+    }
+
+    pub fn set_mouse_capture(&mut self, _capture: bool) {
+        // The view already keeps receiving drag events while a mouse button is held down, and
+        // AppKit has no way to capture the mouse otherwise
+    }
+
+    pub fn confine_cursor(&mut self, rect: Option<Rect>) {
+        // The cursor is moved back into the area on the next mouse moved event
+        self.inner.cursor_confinement.set(rect);
+    }
+
+    pub fn begin_move_drag(&mut self) {
+        unsafe {
+            let Some(ns_window) = self.inner.ns_window.get() else { return };
+
+            // AppKit needs the mouse down event that started the drag
+            let event: id = msg_send![NSApp(), currentEvent];
+            if event != nil {
+                let () = msg_send![ns_window, performWindowDragWithEvent: event];
+            }
+        }
+    }
+
+    pub fn begin_resize_drag(&mut self, _edge: ResizeEdge) {
+        // AppKit doesn't have a way to start resizing a window from an arbitrary point
+    }
+
+    pub fn has_focus(&mut self) -> bool {
+        unsafe {
+            let view = self.inner.ns_view.as_mut().unwrap();
+            let window: id = msg_send![view, window];
+            if window == nil {
                 return false;
             };
             let first_responder: id = msg_send![window, firstResponder];
@@ -310,48 +1084,437 @@ impl<'a> Window<'a> {
         }
     }
 
+    pub fn request_user_attention(&mut self, kind: UserAttention) {
+        let request_type = match kind {
+            UserAttention::Critical => NSRequestUserAttentionType::NSCriticalRequest,
+            UserAttention::Informational => NSRequestUserAttentionType::NSInformationalRequest,
+        };
+
+        // AppKit ignores this while the application is active
+        unsafe {
+            let _: NSInteger = msg_send![NSApp(), requestUserAttention: request_type];
+        }
+    }
+
+    pub fn set_backdrop(&mut self, backdrop: Backdrop) {
+        if self.inner.ns_window.get().is_none() {
+            return;
+        }
+
+        unsafe {
+            let old_view = self.inner.backdrop_view.replace(nil);
+            if old_view != nil {
+                let () = msg_send![old_view, removeFromSuperview];
+            }
+
+            // See `NSVisualEffectMaterial`. These aren't part of the `cocoa` crate.
+            let material: NSInteger = match backdrop {
+                Backdrop::None => return,
+                // `NSVisualEffectMaterialHUDWindow`
+                Backdrop::Blur => 13,
+                // `NSVisualEffectMaterialUnderWindowBackground`
+                Backdrop::Vibrancy => 21,
+            };
+
+            let bounds: NSRect = msg_send![self.inner.ns_view, bounds];
+            let view: id = msg_send![class!(NSVisualEffectView), alloc];
+            let view: id = msg_send![view, initWithFrame: bounds];
+            // `NSViewWidthSizable | NSViewHeightSizable`
+            let () = msg_send![view, setAutoresizingMask: 18 as NSUInteger];
+            let () = msg_send![view, setMaterial: material];
+            // `NSVisualEffectBlendingModeBehindWindow` and `NSVisualEffectStateActive`
+            let () = msg_send![view, setBlendingMode: 0 as NSInteger];
+            let () = msg_send![view, setState: 1 as NSInteger];
+
+            // The effect is drawn below the view's other subviews, like the OpenGL view
+            let () = msg_send![
+                self.inner.ns_view,
+                addSubview: view
+                positioned: NSWindowOrderingMode::NSWindowBelow
+                relativeTo: nil
+            ];
+            let () = msg_send![view, release];
+
+            self.inner.backdrop_view.set(view);
+        }
+    }
+
+    pub fn flush(&mut self) {}
+
+    pub fn has_window_manager(&self) -> bool {
+        true
+    }
+
+    pub fn position(&self) -> Option<Point> {
+        unsafe {
+            let ns_view = self.inner.ns_view;
+            let ns_window: id = msg_send![ns_view, window];
+            if ns_window == nil {
+                return None;
+            }
+
+            let bounds: NSRect = msg_send![ns_view, bounds];
+            let rect_in_window: NSRect = msg_send![ns_view, convertRect: bounds toView: nil];
+            let screen_rect: NSRect = msg_send![ns_window, convertRectToScreen: rect_in_window];
+
+            // AppKit's screen coordinates start at the bottom left corner of the primary screen
+            let screens: id = msg_send![class!(NSScreen), screens];
+            let primary_screen: id = msg_send![screens, objectAtIndex: 0 as NSUInteger];
+            let screen_frame: NSRect = msg_send![primary_screen, frame];
+
+            Some(Point::new(
+                screen_rect.origin.x,
+                screen_frame.size.height - (screen_rect.origin.y + screen_rect.size.height),
+            ))
+        }
+    }
+
+    pub fn window_info(&self) -> WindowInfo {
+        unsafe {
+            let state_ptr: *const c_void = *(*self.inner.ns_view).get_ivar(BASEVIEW_STATE_IVAR);
+            if !state_ptr.is_null() {
+                return (*(state_ptr as *const WindowState)).window_info.get();
+            }
+
+            // The window state only exists once `build` has returned, so until then the size is
+            // computed the same way the view does
+            let ns_window: id = msg_send![self.inner.ns_view, window];
+            let scale = match self.inner.scale_policy.get() {
+                WindowScalePolicy::ScaleFactor(scale) => scale,
+                WindowScalePolicy::SystemScaleFactor if ns_window == nil => 1.0,
+                WindowScalePolicy::SystemScaleFactor => NSWindow::backingScaleFactor(ns_window),
+            };
+            let bounds: NSRect = msg_send![self.inner.ns_view, bounds];
+
+            WindowInfo::from_logical_size(Size::new(bounds.size.width, bounds.size.height), scale)
+        }
+    }
+
     pub fn resize(&mut self, size: Size) {
-        if self.inner.open.get() {
-            // NOTE: macOS gives you a personal rave if you pass in fractional pixels here. Even
-            // though the size is in fractional pixels.
-            let size = NSSize::new(size.width.round(), size.height.round());
+        self.inner.deferred_tasks.borrow_mut().push_back(WindowTask::Resize(size));
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.inner.deferred_tasks.borrow_mut().push_back(WindowTask::SetTitle(title.to_owned()));
+    }
+
+    pub fn event_timestamp(&self) -> Option<Instant> {
+        self.inner.event_timestamp.get()
+    }
+
+    pub fn modifier_sides(&self) -> ModifierSides {
+        self.inner.modifier_sides.get()
+    }
+
+    pub fn pointer_kind(&self) -> PointerKind {
+        self.inner.pointer_kind.get()
+    }
+
+    pub fn cursor_position(&self) -> Option<Point> {
+        self.inner.cursor_position()
+    }
+
+    pub fn set_frame_interval(&mut self, interval: Duration) {
+        self.inner.frame_interval.set(interval);
+
+        // The display link doesn't use the frame interval
+        if self.inner.frame_pacing.get() == FramePacing::Timer {
+            self.restart_timer();
+        }
+    }
+
+    pub fn set_frame_pacing(&mut self, pacing: FramePacing) {
+        if self.inner.frame_pacing.replace(pacing) != pacing {
+            self.restart_timer();
+        }
+    }
+
+    /// Recreate the frame timer or the display link after the frame interval or the frame pacing
+    /// has changed.
+    fn restart_timer(&self) {
+        unsafe {
+            // The window state only exists once `build` has returned. Until then the initial timer
+            // will simply pick up the new settings.
+            let state_ptr: *const c_void = *(*self.inner.ns_view).get_ivar(BASEVIEW_STATE_IVAR);
+            if self.inner.open.get() && !state_ptr.is_null() {
+                let window_state_ptr = state_ptr as *const WindowState;
+                (*window_state_ptr).stop_timer();
+
+                WindowState::setup_timer(window_state_ptr);
+            }
+        }
+    }
+
+    pub fn render_now(&mut self) {
+        unsafe {
+            let state_ptr: *const c_void = *(*self.inner.ns_view).get_ivar(BASEVIEW_STATE_IVAR);
+            if self.inner.open.get() && !state_ptr.is_null() {
+                let window_state = &*(state_ptr as *const WindowState);
+
+                // Firing the frame timer right away calls `on_frame()` once the handler has
+                // returned control to the run loop
+                if let Some(frame_timer) = window_state.frame_timer.take() {
+                    CFRunLoopTimerSetNextFireDate(
+                        frame_timer.as_concrete_TypeRef(),
+                        CFAbsoluteTimeGetCurrent(),
+                    );
+                    window_state.frame_timer.set(Some(frame_timer));
+                }
+                if let Some(display_link) = window_state.display_link.take() {
+                    display_link.fire();
+                    window_state.display_link.set(Some(display_link));
+                }
+            }
+        }
+    }
+
+    pub fn refresh_rate(&self) -> Option<f64> {
+        unsafe {
+            let window: id = msg_send![self.inner.ns_view, window];
+            if window == nil {
+                return None;
+            }
+            let screen: id = msg_send![window, screen];
+            if screen == nil {
+                return None;
+            }
+
+            // `maximumFramesPerSecond` is only available on macOS 12 and up
+            let responds: BOOL =
+                msg_send![screen, respondsToSelector: sel!(maximumFramesPerSecond)];
+            if responds != YES {
+                return None;
+            }
+
+            let fps: NSInteger = msg_send![screen, maximumFramesPerSecond];
+            if fps > 0 {
+                Some(fps as f64)
+            } else {
+                None
+            }
+        }
+    }
 
-            unsafe { NSView::setFrameSize(self.inner.ns_view, size) };
+    pub fn set_input_region(&mut self, region: Option<&[Rect]>) {
+        *self.inner.input_region.borrow_mut() = region.map(|region| region.to_vec());
+    }
+
+    pub fn set_resizable(&mut self, resizable: bool) {
+        if let Some(ns_window) = self.inner.ns_window.get() {
             unsafe {
-                let _: () = msg_send![self.inner.ns_view, setNeedsDisplay: YES];
+                let content_size = NSView::frame(self.inner.ns_view).size;
+
+                let mut style_mask = ns_window.styleMask();
+                style_mask.set(NSWindowStyleMask::NSResizableWindowMask, resizable);
+                ns_window.setStyleMask_(style_mask);
+
+                // Changing the style mask can change the window's frame
+                NSWindow::setContentSize_(ns_window, content_size);
             }
+        }
+    }
 
-            // When using OpenGL the `NSOpenGLView` needs to be resized separately? Why? Because
-            // macOS.
-            #[cfg(feature = "opengl")]
-            if let Some(gl_context) = &self.inner.gl_context {
-                gl_context.resize(size);
+    pub fn physical_size(&self) -> PhySize {
+        let frame = unsafe { NSView::frame(self.inner.ns_view) };
+        let size = Size::new(frame.size.width, frame.size.height);
+
+        WindowInfo::from_logical_size(size, self.scale_factor()).physical_size()
+    }
+
+    pub fn present(&mut self, framebuffer: &Framebuffer) {
+        let size = framebuffer.size();
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
+
+        let bytes: Vec<u8> =
+            framebuffer.pixels().iter().flat_map(|pixel| pixel.to_le_bytes()).collect();
+        // This copies the data, since the image may outlive the framebuffer
+        let data = CFData::from_buffer(&bytes);
+
+        unsafe {
+            let color_space = CGColorSpaceCreateDeviceRGB();
+            let provider = CGDataProviderCreateWithCFData(data.as_concrete_TypeRef());
+            let image = CGImageCreate(
+                size.width as usize,
+                size.height as usize,
+                8,
+                32,
+                size.width as usize * 4,
+                color_space,
+                K_CG_BITMAP_BYTE_ORDER_32_LITTLE | K_CG_IMAGE_ALPHA_NONE_SKIP_FIRST,
+                provider,
+                ptr::null(),
+                false,
+                K_CG_RENDERING_INTENT_DEFAULT,
+            );
+            CGDataProviderRelease(provider);
+            CGColorSpaceRelease(color_space);
+
+            if image.is_null() {
+                return;
             }
 
-            // If this is a standalone window then we'll also need to resize the window itself
-            if let Some(ns_window) = self.inner.ns_window.get() {
-                unsafe { NSWindow::setContentSize_(ns_window, size) };
+            // The view is only made layer-backed once a framebuffer gets presented to it
+            let ns_view = self.inner.ns_view;
+            let () = msg_send![ns_view, setWantsLayer: YES];
+            let layer: id = msg_send![ns_view, layer];
+            let () = msg_send![layer, setContentsScale: self.scale_factor()];
+            let () = msg_send![layer, setContents: image as id];
+
+            // The layer retains the image
+            CGImageRelease(image);
+        }
+    }
+
+    /// The scale factor currently in effect for this window.
+    fn scale_factor(&self) -> f64 {
+        match self.inner.scale_policy.get() {
+            WindowScalePolicy::ScaleFactor(scale) => scale,
+            WindowScalePolicy::SystemScaleFactor => unsafe {
+                let ns_window: id = msg_send![self.inner.ns_view, window];
+                backing_scale_factor(ns_window)
+            },
+        }
+    }
+
+    pub fn set_mouse_cursor(&mut self, mouse_cursor: MouseCursor) {
+        if self.inner.mouse_cursor.replace(mouse_cursor) == mouse_cursor {
+            return;
+        }
+
+        // The cursor is set once the mouse enters the view if it's not over the view right now
+        if self.inner.cursor_over_view() {
+            unsafe {
+                let () = msg_send![ns_cursor(mouse_cursor), set];
+            }
+        }
+    }
+
+    pub fn set_cursor_auto_hide(&mut self, timeout: Option<Duration>) {
+        if self.inner.cursor_auto_hide.set_timeout(timeout) {
+            unsafe {
+                let () = msg_send![class!(NSCursor), setHiddenUntilMouseMoves: NO];
             }
         }
     }
 
-    pub fn set_mouse_cursor(&mut self, _mouse_cursor: MouseCursor) {
-        todo!()
+    pub fn begin_relative_drag(&mut self, origin: Point) {
+        // `NSCursor::hide` calls need to be balanced
+        if self.inner.relative_drag_origin.replace(Some(origin)).is_none() {
+            unsafe {
+                let () = msg_send![class!(NSCursor), hide];
+                CGAssociateMouseAndMouseCursorPosition(0);
+            }
+        }
+    }
+
+    pub fn end_relative_drag(&mut self) {
+        self.inner.end_relative_drag();
+    }
+
+    pub fn ns_view(&self) -> *mut c_void {
+        self.inner.ns_view as *mut c_void
     }
 
     #[cfg(feature = "opengl")]
     pub fn gl_context(&self) -> Option<&GlContext> {
-        self.inner.gl_context.as_ref()
+        self.inner.gl_context.get()
     }
 
     #[cfg(feature = "opengl")]
-    fn create_gl_context(ns_window: Option<id>, ns_view: id, config: GlConfig) -> GlContext {
+    pub fn gl_context_error(&self) -> Option<&GlError> {
+        self.inner.gl_context_error.as_ref()
+    }
+
+    #[cfg(feature = "opengl")]
+    pub fn create_gl_context(&mut self, config: GlConfig) -> Result<&GlContext, GlError> {
+        let inner = self.inner;
+        if let Some(gl_context) = inner.gl_context.get() {
+            return Ok(gl_context);
+        }
+
+        let gl_context = Self::create_gl_context_for_view(
+            inner.ns_window.get(),
+            inner.ns_view,
+            config,
+            inner.transparent,
+        )?;
+        let gl_context = inner.gl_context.get_or_set(gl_context);
+
+        let bounds: NSRect = unsafe { msg_send![inner.ns_view, bounds] };
+        inner.update_gl_surface_size(Size::new(bounds.size.width, bounds.size.height));
+
+        Ok(gl_context)
+    }
+
+    #[cfg(feature = "opengl")]
+    fn create_gl_context_for_view(
+        ns_window: Option<id>, ns_view: id, config: GlConfig, transparent: bool,
+    ) -> Result<GlContext, GlError> {
         let mut handle = AppKitWindowHandle::empty();
         handle.ns_window = ns_window.unwrap_or(ptr::null_mut()) as *mut c_void;
         handle.ns_view = ns_view as *mut c_void;
         let handle = RawWindowHandle::AppKit(handle);
 
-        unsafe { GlContext::create(&handle, config).expect("Could not create OpenGL context") }
+        let gl_context = unsafe { GlContext::create(&handle, config)? };
+        if transparent {
+            gl_context.set_opaque(false);
+        }
+
+        Ok(gl_context)
+    }
+
+    /// Create the OpenGL context requested through [`WindowOpenOptions::gl_config`], if any.
+    /// Returns either the context or the reason why it could not be created.
+    #[cfg(feature = "opengl")]
+    fn create_requested_gl_context(
+        ns_window: Option<id>, ns_view: id, config: Option<GlConfig>, transparent: bool,
+    ) -> (GlContextCell, Option<GlError>) {
+        let Some(config) = config else { return (GlContextCell::new(None), None) };
+
+        match Self::create_gl_context_for_view(ns_window, ns_view, config, transparent) {
+            Ok(gl_context) => (GlContextCell::new(Some(gl_context)), None),
+            Err(err) => (GlContextCell::new(None), Some(err)),
+        }
+    }
+}
+
+pub struct Application {
+    app: id,
+    /// The number of windows opened through this application that are still open.
+    open_windows: Rc<Cell<usize>>,
+}
+
+impl Application {
+    pub fn new() -> Self {
+        // It seems prudent to run NSApp() here before doing other
+        // work. It runs [NSApplication sharedApplication], which is
+        // what is run at the very start of the Xcode-generated main
+        // function of a cocoa app according to:
+        // https://developer.apple.com/documentation/appkit/nsapplication
+        let app = unsafe { NSApp() };
+
+        unsafe {
+            app.setActivationPolicy_(NSApplicationActivationPolicyRegular);
+        }
+
+        Self { app, open_windows: Rc::new(Cell::new(0)) }
+    }
+
+    pub fn open_window<H, B>(&mut self, options: WindowOpenOptions, build: B) -> WindowHandle
+    where
+        H: WindowHandler + 'static,
+        B: FnOnce(&mut crate::Window) -> H,
+        B: Send + 'static,
+    {
+        Window::open_standalone(options, build, self.app, Rc::clone(&self.open_windows))
+    }
+
+    pub fn run(self) {
+        if self.open_windows.get() > 0 {
+            unsafe { self.app.run() };
+        }
     }
 }
 
@@ -360,11 +1523,24 @@ pub(super) struct WindowState {
     window_handler: RefCell<Box<dyn WindowHandler>>,
     keyboard_state: KeyboardState,
     frame_timer: Cell<Option<CFRunLoopTimer>>,
+    /// Used instead of `frame_timer` with [`FramePacing::DisplayLink`].
+    display_link: Cell<Option<DisplayLink>>,
+    /// The event monitor that forwards media keys to the window handler. See
+    /// [`Self::add_media_key_monitor`].
+    media_key_monitor: Cell<id>,
     /// The last known window info for this window.
     pub window_info: Cell<WindowInfo>,
 
     /// Events that will be triggered at the end of `window_handler`'s borrow.
     deferred_events: RefCell<VecDeque<Event>>,
+    /// Messages from [`WindowHandle::send_message`] that will be sent at the end of
+    /// `window_handler`'s borrow.
+    deferred_messages: RefCell<VecDeque<Box<dyn Any + Send>>>,
+    /// Whether the handler has received a `DragEntered` event without a matching `DragLeft` or
+    /// `DragDropped` event.
+    pub(super) drag_active: Cell<bool>,
+    /// The clipboard's change count as of the last frame, used to detect clipboard changes.
+    clipboard_change_count: Cell<NSInteger>,
 }
 
 impl WindowState {
@@ -390,6 +1566,9 @@ impl WindowState {
         let mut window_handler = self.window_handler.borrow_mut();
         let status = window_handler.on_event(&mut window, event);
         self.send_deferred_events(window_handler.as_mut());
+        drop(window_handler);
+
+        self.handle_deferred_tasks();
         status
     }
 
@@ -401,16 +1580,117 @@ impl WindowState {
             let mut window = crate::Window::new(Window { inner: &self.window_inner });
             window_handler.on_event(&mut window, event);
             self.send_deferred_events(window_handler.as_mut());
+            drop(window_handler);
+
+            self.handle_deferred_tasks();
         } else {
             self.deferred_events.borrow_mut().push_back(event);
         }
     }
 
+    /// Send a message to the window handler, or queue it like
+    /// [`trigger_deferrable_event()`][Self::trigger_deferrable_event] if the handler is borrowed.
+    fn trigger_message(&self, message: Box<dyn Any + Send>) {
+        if let Ok(mut window_handler) = self.window_handler.try_borrow_mut() {
+            let mut window = crate::Window::new(Window { inner: &self.window_inner });
+            window_handler.on_message(&mut window, message);
+            self.send_deferred_events(window_handler.as_mut());
+            drop(window_handler);
+
+            self.handle_deferred_tasks();
+        } else {
+            self.deferred_messages.borrow_mut().push_back(message);
+        }
+    }
+
+    pub(super) fn trigger_scale_factor_changed(&self, scale: f64) {
+        let mut window = crate::Window::new(Window { inner: &self.window_inner });
+        let mut window_handler = self.window_handler.borrow_mut();
+        window_handler.on_scale_factor_changed(&mut window, scale);
+        self.send_deferred_events(window_handler.as_mut());
+        drop(window_handler);
+
+        self.handle_deferred_tasks();
+    }
+
+    /// Let the window handler handle a native event before baseview does. Returns `true` if the
+    /// handler consumed the event. Events are not passed to the handler while it's borrowed.
+    ///
+    /// Every input event passes through here first, so this also records the event's timestamp.
+    pub(super) fn trigger_raw_event(&self, ns_event: id) -> bool {
+        // The timestamp is in seconds since the system started
+        let timestamp: f64 = unsafe { msg_send![ns_event, timestamp] };
+        let time = (timestamp * 1000.0) as u64 as u32;
+        let inner = &self.window_inner;
+        inner.event_timestamp.set(Some(inner.event_clock.to_instant(time)));
+        let modifier_flags = unsafe { NSEvent::modifierFlags(ns_event) };
+        inner.modifier_sides.set(make_modifier_sides(modifier_flags));
+        if let Some(pointer_kind) = unsafe { pointer_kind(ns_event) } {
+            inner.pointer_kind.set(pointer_kind);
+        }
+
+        let Ok(mut window_handler) = self.window_handler.try_borrow_mut() else { return false };
+
+        let mut window = crate::Window::new(Window { inner: &self.window_inner });
+        let result =
+            window_handler.on_raw_message(&mut window, RawMessage::AppKit(ns_event as *mut c_void));
+        self.send_deferred_events(window_handler.as_mut());
+        drop(window_handler);
+
+        self.handle_deferred_tasks();
+        result.is_some()
+    }
+
     pub(super) fn trigger_frame(&self) {
         let mut window = crate::Window::new(Window { inner: &self.window_inner });
         let mut window_handler = self.window_handler.borrow_mut();
+
+        let clipboard_change_count = clipboard::change_count();
+        if self.clipboard_change_count.replace(clipboard_change_count) != clipboard_change_count {
+            window_handler.on_event(&mut window, Event::Window(WindowEvent::ClipboardChanged));
+        }
+
         window_handler.on_frame(&mut window);
         self.send_deferred_events(window_handler.as_mut());
+        drop(window_handler);
+
+        // AppKit shows the cursor again once it moves. This hides the cursor for the entire
+        // application, so it's only done while the cursor is over the view and not over the
+        // host's own UI.
+        if self.window_inner.cursor_auto_hide.should_hide(Instant::now())
+            && self.window_inner.cursor_over_view()
+        {
+            unsafe {
+                let () = msg_send![class!(NSCursor), setHiddenUntilMouseMoves: YES];
+            }
+        }
+
+        self.handle_deferred_tasks();
+    }
+
+    /// Run the window handler's hit test. This always returns [`HitTestResult::Client`] for
+    /// parented windows, windows with decorations, or when the handler is currently borrowed.
+    pub(super) fn hit_test(&self, position: Point) -> HitTestResult {
+        let Some(ns_window) = self.window_inner.ns_window.get() else {
+            return HitTestResult::Client;
+        };
+        if unsafe { ns_window.styleMask() }.contains(NSWindowStyleMask::NSTitledWindowMask) {
+            return HitTestResult::Client;
+        }
+
+        match self.window_handler.try_borrow() {
+            Ok(window_handler) => window_handler.hit_test(position),
+            Err(_) => HitTestResult::Client,
+        }
+    }
+
+    /// Send a `DragLeft` event if a drag and drop operation is in progress. This makes sure every
+    /// `DragEntered` event is matched by a `DragLeft` or `DragDropped` event, even if the window
+    /// closes during the drag.
+    pub(super) fn cancel_drag(&self) {
+        if self.drag_active.replace(false) {
+            self.trigger_event(Event::Mouse(MouseEvent::DragLeft));
+        }
     }
 
     pub(super) fn keyboard_state(&self) -> &KeyboardState {
@@ -421,12 +1701,39 @@ impl WindowState {
         self.keyboard_state.process_native_event(event)
     }
 
+    /// Start calling `on_frame()`, either on a timer or through a display link depending on the
+    /// window's frame pacing. [`Self::stop_timer`] needs to be called first if a timer is already
+    /// running.
     unsafe fn setup_timer(window_state_ptr: *const WindowState) {
+        unsafe fn frame_callback(window_state_ptr: *const c_void) {
+            // The window handler may close the window, which releases the view's reference to the
+            // window state
+            let window_state_ptr = window_state_ptr as *const WindowState;
+            Rc::increment_strong_count(window_state_ptr);
+            let window_state = Rc::from_raw(window_state_ptr);
+
+            window_state.trigger_frame();
+        }
+
         extern "C" fn timer_callback(_: *mut __CFRunLoopTimer, window_state_ptr: *mut c_void) {
-            unsafe {
-                let window_state = &*(window_state_ptr as *const WindowState);
+            unsafe { frame_callback(window_state_ptr) };
+        }
 
-                window_state.trigger_frame();
+        extern "C" fn display_link_callback(window_state_ptr: *const c_void) {
+            unsafe { frame_callback(window_state_ptr) };
+        }
+
+        let window_inner = &(*window_state_ptr).window_inner;
+        if window_inner.frame_pacing.get() == FramePacing::DisplayLink {
+            // This falls back to the frame timer if the display link can't be created
+            let display_link = DisplayLink::new(
+                window_inner.display_id(),
+                window_state_ptr as *mut c_void,
+                display_link_callback,
+            );
+            if display_link.is_some() {
+                (*window_state_ptr).display_link.set(display_link);
+                return;
             }
         }
 
@@ -438,19 +1745,132 @@ impl WindowState {
             copyDescription: None,
         };
 
-        let timer = CFRunLoopTimer::new(0.0, 0.015, 0, 0, timer_callback, &mut timer_context);
+        // A `CFRunLoopTimer` with an interval of zero only fires once
+        let interval = (*window_state_ptr).window_inner.frame_interval.get();
+        let interval = interval.max(MIN_FRAME_INTERVAL).as_secs_f64();
+        let timer = CFRunLoopTimer::new(0.0, interval, 0, 0, timer_callback, &mut timer_context);
 
         CFRunLoop::get_current().add_timer(&timer, kCFRunLoopDefaultMode);
 
         (*window_state_ptr).frame_timer.set(Some(timer));
     }
 
+    /// Forward the media keys to the window handler while the view has focus. AppKit reports these
+    /// keys as `NSSystemDefined` events that are never sent to a view, so they need to be picked up
+    /// with a local event monitor instead. Media key events captured by the window handler are not
+    /// passed on to the rest of the application.
+    fn add_media_key_monitor(window_state: &Rc<WindowState>) {
+        let weak_state = Rc::downgrade(window_state);
+        let handler = ConcreteBlock::new(move |event: id| -> id {
+            let window_state = match weak_state.upgrade() {
+                Some(window_state) => window_state,
+                None => return event,
+            };
+
+            // The monitor may also be called from a nested event loop started by the window
+            // handler
+            let has_focus = Window { inner: &window_state.window_inner }.has_focus();
+            if !has_focus || window_state.window_handler.try_borrow_mut().is_err() {
+                return event;
+            }
+
+            let key_event = match window_state.keyboard_state.process_system_defined_event(event) {
+                Some(key_event) => key_event,
+                None => return event,
+            };
+            if window_state.trigger_raw_event(event) {
+                return nil;
+            }
+
+            match window_state.trigger_event(Event::Keyboard(key_event)) {
+                EventStatus::Captured | EventStatus::CaptureMouse => nil,
+                _ => event,
+            }
+        })
+        .copy();
+
+        unsafe {
+            let monitor: id = msg_send![
+                class!(NSEvent),
+                addLocalMonitorForEventsMatchingMask: NSEventMask::NSSystemDefinedMask.bits()
+                handler: &*handler
+            ];
+            let _: id = msg_send![monitor, retain];
+
+            window_state.media_key_monitor.set(monitor);
+        }
+    }
+
+    /// Remove the event monitor added by [`Self::add_media_key_monitor`].
+    fn remove_media_key_monitor(&self) {
+        let monitor = self.media_key_monitor.replace(nil);
+        if monitor != nil {
+            unsafe {
+                NSEvent::removeMonitor_(nil, monitor);
+                let () = msg_send![monitor, release];
+            }
+        }
+    }
+
+    /// Stop the frame timer or the display link started by [`Self::setup_timer`].
+    fn stop_timer(&self) {
+        if let Some(frame_timer) = self.frame_timer.take() {
+            CFRunLoop::get_current().remove_timer(&frame_timer, kCFRunLoopDefaultMode);
+        }
+        self.display_link.take();
+    }
+
+    /// Handle the tasks that were queued while the window handler was borrowed. This must only be
+    /// called once the handler's borrow has ended.
+    fn handle_deferred_tasks(&self) {
+        let inner = &self.window_inner;
+        loop {
+            // The borrow needs to end before the task is handled, since the task may send events
+            // to the view
+            let task = match inner.deferred_tasks.borrow_mut().pop_front() {
+                Some(task) => task,
+                None => break,
+            };
+
+            match task {
+                WindowTask::Resize(size) => inner.resize(size),
+                WindowTask::SetTitle(title) => inner.set_title(&title),
+                WindowTask::SetVisible(visible) => {
+                    if inner.set_visible(visible) {
+                        let event = if visible { WindowEvent::Shown } else { WindowEvent::Hidden };
+                        self.trigger_event(Event::Window(event));
+                    }
+                }
+                WindowTask::SetScalePolicy(policy) => {
+                    inner.set_scale_policy(policy);
+                    // This recomputes the window info and sends the events for the new scale
+                    // factor, if it changed
+                    if inner.open.get() {
+                        unsafe {
+                            let () = msg_send![inner.ns_view, viewDidChangeBackingProperties: nil];
+                        }
+                    }
+                }
+                WindowTask::Close => {
+                    self.cancel_drag();
+                    inner.close();
+                }
+            }
+        }
+    }
+
     fn send_deferred_events(&self, window_handler: &mut dyn WindowHandler) {
         let mut window = crate::Window::new(Window { inner: &self.window_inner });
         loop {
             let next_event = self.deferred_events.borrow_mut().pop_front();
             if let Some(event) = next_event {
                 window_handler.on_event(&mut window, event);
+                continue;
+            }
+
+            let next_message = self.deferred_messages.borrow_mut().pop_front();
+            if let Some(message) = next_message {
+                window_handler.on_message(&mut window, message);
             } else {
                 break;
             }
@@ -470,13 +1890,79 @@ unsafe impl<'a> HasRawDisplayHandle for Window<'a> {
     }
 }
 
-pub fn copy_to_clipboard(string: &str) {
-    unsafe {
-        let pb = NSPasteboard::generalPasteboard(nil);
+/// The shortest interval the frame timer will fire at.
+const MIN_FRAME_INTERVAL: Duration = Duration::from_millis(1);
+
+const K_CG_IMAGE_ALPHA_NONE_SKIP_FIRST: u32 = 6;
+const K_CG_BITMAP_BYTE_ORDER_32_LITTLE: u32 = 2 << 12;
+const K_CG_RENDERING_INTENT_DEFAULT: u32 = 0;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGColorSpaceCreateDeviceRGB() -> *mut c_void;
+    fn CGColorSpaceRelease(space: *mut c_void);
+    fn CGDataProviderCreateWithCFData(data: CFDataRef) -> *mut c_void;
+    fn CGDataProviderRelease(provider: *mut c_void);
+    fn CGImageCreate(
+        width: usize, height: usize, bits_per_component: usize, bits_per_pixel: usize,
+        bytes_per_row: usize, space: *mut c_void, bitmap_info: u32, provider: *mut c_void,
+        decode: *const f64, should_interpolate: bool, intent: u32,
+    ) -> *mut c_void;
+    fn CGImageRelease(image: *mut c_void);
+    fn CGWarpMouseCursorPosition(new_cursor_position: NSPoint) -> i32;
+    fn CGAssociateMouseAndMouseCursorPosition(connected: u32) -> i32;
+}
+
+/// Convert a baseview color to an opaque `NSColor`.
+unsafe fn ns_color(color: Color) -> id {
+    NSColor::colorWithSRGBRed_green_blue_alpha_(
+        nil,
+        f64::from(color.r) / 255.0,
+        f64::from(color.g) / 255.0,
+        f64::from(color.b) / 255.0,
+        1.0,
+    )
+}
 
-        let ns_str = NSString::alloc(nil).init_str(string);
+/// The backing scale factor of an `NSWindow`, or 1.0 if there is no window.
+unsafe fn backing_scale_factor(ns_window: id) -> f64 {
+    if ns_window == nil {
+        1.0
+    } else {
+        NSWindow::backingScaleFactor(ns_window)
+    }
+}
 
-        pb.clearContents();
-        pb.setString_forType(ns_str, cocoa::appkit::NSPasteboardTypeString);
+/// The kind of device that generated a mouse event, or `None` if this is not a mouse event.
+unsafe fn pointer_kind(ns_event: id) -> Option<PointerKind> {
+    // `NSEventSubtypeTouch`, used for mouse events generated by a trackpad
+    const NS_EVENT_SUBTYPE_TOUCH: i16 = 3;
+
+    match ns_event.eventType() {
+        NSEventType::NSLeftMouseDown
+        | NSEventType::NSLeftMouseUp
+        | NSEventType::NSRightMouseDown
+        | NSEventType::NSRightMouseUp
+        | NSEventType::NSOtherMouseDown
+        | NSEventType::NSOtherMouseUp
+        | NSEventType::NSMouseMoved
+        | NSEventType::NSLeftMouseDragged
+        | NSEventType::NSRightMouseDragged
+        | NSEventType::NSOtherMouseDragged => {
+            // Tablet events are sent as mouse events with a tablet subtype
+            let subtype: i16 = msg_send![ns_event, subtype];
+            Some(match subtype {
+                0 => PointerKind::Mouse,
+                NS_EVENT_SUBTYPE_TOUCH => PointerKind::Touchpad,
+                _ => PointerKind::Pen,
+            })
+        }
+        NSEventType::NSTabletPoint | NSEventType::NSTabletProximity => Some(PointerKind::Pen),
+        // Regular mouse wheels scroll by whole lines
+        NSEventType::NSScrollWheel if ns_event.hasPreciseScrollingDeltas() == YES => {
+            Some(PointerKind::Touchpad)
+        }
+        NSEventType::NSScrollWheel => Some(PointerKind::Mouse),
+        _ => None,
     }
 }
@@ -1,3 +1,20 @@
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+/// The cursors that can be set with [`Window::set_mouse_cursor`][crate::Window::set_mouse_cursor].
+/// These follow the CSS cursor names.
+///
+/// Not every platform has a native cursor for every variant, in which case the closest matching
+/// cursor is used instead:
+///
+/// - On Linux the cursor is looked up in the cursor theme by its CSS name and then by the legacy
+///   names older themes use. If the theme doesn't have any of those, the closest glyph from the
+///   core X11 cursor font is used.
+/// - On Windows `HandGrabbing`, `VerticalText`, `ZoomIn`, `ZoomOut`, `Alias`, `Copy`, `Cell`,
+///   `PtrNotAllowed`, and the single-direction resize cursors use the closest system cursor.
+/// - On macOS `Help`, `Working`, `PtrWorking`, `ZoomIn`, `ZoomOut`, `Move`, `AllScroll`, and the
+///   diagonal resize cursors use AppKit's private cursors, falling back to the closest public
+///   cursor if those don't exist. `Cell` uses the crosshair cursor.
 #[derive(Debug, Eq, PartialEq, Clone, Copy, PartialOrd, Ord, Hash)]
 pub enum MouseCursor {
     Default,
@@ -47,3 +64,181 @@ impl Default for MouseCursor {
         Self::Default
     }
 }
+
+#[cfg(test)]
+impl MouseCursor {
+    /// Every cursor, for testing the platforms' cursor mappings.
+    pub(crate) const ALL: [MouseCursor; 33] = [
+        MouseCursor::Default,
+        MouseCursor::Hand,
+        MouseCursor::HandGrabbing,
+        MouseCursor::Help,
+        MouseCursor::Hidden,
+        MouseCursor::Text,
+        MouseCursor::VerticalText,
+        MouseCursor::Working,
+        MouseCursor::PtrWorking,
+        MouseCursor::NotAllowed,
+        MouseCursor::PtrNotAllowed,
+        MouseCursor::ZoomIn,
+        MouseCursor::ZoomOut,
+        MouseCursor::Alias,
+        MouseCursor::Copy,
+        MouseCursor::Move,
+        MouseCursor::AllScroll,
+        MouseCursor::Cell,
+        MouseCursor::Crosshair,
+        MouseCursor::EResize,
+        MouseCursor::NResize,
+        MouseCursor::NeResize,
+        MouseCursor::NwResize,
+        MouseCursor::SResize,
+        MouseCursor::SeResize,
+        MouseCursor::SwResize,
+        MouseCursor::WResize,
+        MouseCursor::EwResize,
+        MouseCursor::NsResize,
+        MouseCursor::NwseResize,
+        MouseCursor::NeswResize,
+        MouseCursor::ColResize,
+        MouseCursor::RowResize,
+    ];
+}
+
+/// Keeps track of when the cursor should be hidden for
+/// [`Window::set_cursor_auto_hide`][crate::Window::set_cursor_auto_hide]. The platforms call
+/// [`CursorAutoHide::cursor_moved`] when the cursor moves and [`CursorAutoHide::should_hide`]
+/// every frame, and they hide or show the cursor based on the results.
+pub(crate) struct CursorAutoHide {
+    timeout: Cell<Option<Duration>>,
+    /// When the cursor last moved, and the whole pixel it moved to. Some platforms send motion
+    /// events without the cursor moving, so those are ignored.
+    last_motion: Cell<(Instant, Option<(i32, i32)>)>,
+    hidden: Cell<bool>,
+}
+
+impl CursorAutoHide {
+    pub fn new() -> Self {
+        Self {
+            timeout: Cell::new(None),
+            last_motion: Cell::new((Instant::now(), None)),
+            hidden: Cell::new(false),
+        }
+    }
+
+    /// Change the idle period after which the cursor gets hidden. Returns `true` if the cursor is
+    /// currently hidden and it should be shown again.
+    pub fn set_timeout(&self, timeout: Option<Duration>) -> bool {
+        self.timeout.set(timeout);
+        self.last_motion.set((Instant::now(), self.last_motion.get().1));
+
+        timeout.is_none() && self.hidden.replace(false)
+    }
+
+    /// Whether the cursor is currently hidden because it hasn't moved for a while.
+    pub fn is_hidden(&self) -> bool {
+        self.hidden.get()
+    }
+
+    /// Called when the cursor moves to `position`. Returns `true` if the cursor is currently
+    /// hidden and it should be shown again.
+    pub fn cursor_moved(&self, position: (i32, i32)) -> bool {
+        if self.last_motion.get().1 == Some(position) {
+            return false;
+        }
+
+        self.last_motion.set((Instant::now(), Some(position)));
+        self.hidden.replace(false)
+    }
+
+    /// Called every frame. Returns `true` if the cursor hasn't moved for long enough that it
+    /// should be hidden now.
+    pub fn should_hide(&self, now: Instant) -> bool {
+        let Some(timeout) = self.timeout.get() else { return false };
+        if self.hidden.get() || now.saturating_duration_since(self.last_motion.get().0) < timeout {
+            return false;
+        }
+
+        self.hidden.set(true);
+        true
+    }
+}
+
+/// The state for [`Window::begin_relative_drag`][crate::Window::begin_relative_drag] on platforms
+/// that implement it by warping the cursor back to the drag's origin after it moves. Positions
+/// are in physical pixels.
+#[cfg(not(target_os = "macos"))]
+pub(crate) struct RelativeDrag {
+    origin: Cell<Option<(i32, i32)>>,
+    /// The position the next motion event is relative to.
+    last_position: Cell<(i32, i32)>,
+    /// Set when the cursor is warped asynchronously, until the motion event caused by the warp
+    /// arrives. Motion events before that are still relative to the position before the warp.
+    warp_pending: Cell<bool>,
+}
+
+#[cfg(not(target_os = "macos"))]
+impl RelativeDrag {
+    pub fn new() -> Self {
+        Self {
+            origin: Cell::new(None),
+            last_position: Cell::new((0, 0)),
+            warp_pending: Cell::new(false),
+        }
+    }
+
+    pub fn begin(&self, origin: (i32, i32)) {
+        self.origin.set(Some(origin));
+        self.last_position.set(origin);
+        self.warp_pending.set(false);
+    }
+
+    /// End the drag. Returns the origin the cursor should be moved back to, or `None` if there
+    /// was no drag.
+    pub fn end(&self) -> Option<(i32, i32)> {
+        self.origin.take()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.origin.get().is_some()
+    }
+
+    /// Called when the cursor moves to `position` during a drag. Returns how far the cursor moved,
+    /// or `None` if it didn't move or if this motion was caused by warping the cursor.
+    pub fn cursor_moved(&self, position: (i32, i32)) -> Option<(i32, i32)> {
+        let origin = self.origin.get()?;
+        if self.warp_pending.get() && position == origin {
+            self.warp_pending.set(false);
+            self.last_position.set(origin);
+            return None;
+        }
+
+        let last_position = self.last_position.replace(position);
+        let delta = (position.0 - last_position.0, position.1 - last_position.1);
+
+        Some(delta).filter(|&delta| delta != (0, 0))
+    }
+
+    /// The position the cursor should be warped to, if it has moved away from the origin and it
+    /// isn't already being warped there. [`RelativeDrag::cursor_warped`] should be called after
+    /// warping the cursor.
+    pub fn warp_target(&self) -> Option<(i32, i32)> {
+        let origin = self.origin.get()?;
+        if self.warp_pending.get() || self.last_position.get() == origin {
+            return None;
+        }
+
+        Some(origin)
+    }
+
+    /// Called after warping the cursor to the origin. If the warp happens asynchronously then the
+    /// positions are only relative to the origin again once the motion event caused by the warp
+    /// arrives.
+    pub fn cursor_warped(&self, synchronous: bool) {
+        if synchronous {
+            self.last_position.set(self.origin.get().unwrap_or_default());
+        } else {
+            self.warp_pending.set(true);
+        }
+    }
+}
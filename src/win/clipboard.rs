@@ -0,0 +1,98 @@
+use std::ptr::{null, null_mut};
+
+use winapi::shared::minwindef::UINT;
+use winapi::um::winbase::{
+    GlobalAlloc, GlobalFree, GlobalLock, GlobalSize, GlobalUnlock, GMEM_MOVEABLE,
+};
+use winapi::um::winuser::{
+    CloseClipboard, EmptyClipboard, GetClipboardData, OpenClipboard, SetClipboardData, CF_DIB,
+    CF_UNICODETEXT,
+};
+
+use crate::clipboard::{decode_dib, encode_dib};
+use crate::ClipboardImage;
+
+/// Replace the clipboard's contents with `data` in the given format.
+unsafe fn set_data(format: UINT, data: &[u8]) {
+    let hglobal = GlobalAlloc(GMEM_MOVEABLE, data.len());
+    if hglobal.is_null() {
+        return;
+    }
+
+    let ptr = GlobalLock(hglobal) as *mut u8;
+    if ptr.is_null() {
+        GlobalFree(hglobal);
+        return;
+    }
+    std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len());
+    GlobalUnlock(hglobal);
+
+    if OpenClipboard(null_mut()) == 0 {
+        GlobalFree(hglobal);
+        return;
+    }
+
+    EmptyClipboard();
+    // The clipboard takes ownership of the memory if this succeeds
+    if SetClipboardData(format, hglobal).is_null() {
+        GlobalFree(hglobal);
+    }
+
+    CloseClipboard();
+}
+
+/// Read the clipboard's contents in the given format.
+unsafe fn get_data(format: UINT) -> Option<Vec<u8>> {
+    if OpenClipboard(null_mut()) == 0 {
+        return None;
+    }
+
+    // The clipboard owns this memory, so it must not be freed
+    let hglobal = GetClipboardData(format);
+    let ptr = if hglobal.is_null() { null() } else { GlobalLock(hglobal) as *const u8 };
+    let data = if ptr.is_null() {
+        None
+    } else {
+        let data = std::slice::from_raw_parts(ptr, GlobalSize(hglobal)).to_vec();
+        GlobalUnlock(hglobal);
+
+        Some(data)
+    };
+
+    CloseClipboard();
+
+    data
+}
+
+pub fn set_text(text: &str) {
+    let data: Vec<u8> =
+        text.encode_utf16().chain(Some(0)).flat_map(|unit| unit.to_ne_bytes()).collect();
+
+    unsafe { set_data(CF_UNICODETEXT, &data) };
+}
+
+pub fn get_text() -> Option<String> {
+    let data = unsafe { get_data(CF_UNICODETEXT)? };
+
+    // The memory may be larger than the null terminated string it contains
+    let text: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|unit| u16::from_ne_bytes([unit[0], unit[1]]))
+        .take_while(|&unit| unit != 0)
+        .collect();
+
+    String::from_utf16(&text).ok()
+}
+
+pub fn set_image(rgba: &[u8], width: u32, height: u32) {
+    // Windows converts this to the other bitmap formats when they are requested
+    let dib = encode_dib(rgba, width, height);
+
+    unsafe { set_data(CF_DIB, &dib) };
+}
+
+pub fn get_image() -> Option<ClipboardImage> {
+    let dib = unsafe { get_data(CF_DIB)? };
+
+    decode_dib(&dib, None)
+}
@@ -11,6 +11,8 @@ pub fn cursor_to_lpcwstr(cursor: MouseCursor) -> LPCWSTR {
     match cursor {
         MouseCursor::Default => IDC_ARROW,
         MouseCursor::Hand => IDC_HAND,
+        // Windows doesn't have system cursors for grabbing, vertical text, zooming, or drag and
+        // drop operations, so the closest matching cursors are used for those instead
         MouseCursor::HandGrabbing => IDC_SIZEALL,
         MouseCursor::Help => IDC_HELP,
         // an empty LPCWSTR results in the cursor being hidden
@@ -52,3 +54,57 @@ pub fn cursor_to_lpcwstr(cursor: MouseCursor) -> LPCWSTR {
         MouseCursor::RowResize => IDC_SIZENS,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Which cursors have a matching system cursor, and which ones use the closest match instead.
+    #[test]
+    fn native_and_emulated_cursors() {
+        let native = [
+            (MouseCursor::Default, IDC_ARROW),
+            (MouseCursor::Hand, IDC_HAND),
+            (MouseCursor::Help, IDC_HELP),
+            (MouseCursor::Text, IDC_IBEAM),
+            (MouseCursor::Working, IDC_WAIT),
+            (MouseCursor::PtrWorking, IDC_APPSTARTING),
+            (MouseCursor::NotAllowed, IDC_NO),
+            (MouseCursor::Move, IDC_SIZEALL),
+            (MouseCursor::AllScroll, IDC_SIZEALL),
+            (MouseCursor::Crosshair, IDC_CROSS),
+            (MouseCursor::EwResize, IDC_SIZEWE),
+            (MouseCursor::NsResize, IDC_SIZENS),
+            (MouseCursor::NwseResize, IDC_SIZENWSE),
+            (MouseCursor::NeswResize, IDC_SIZENESW),
+        ];
+        let emulated = [
+            (MouseCursor::HandGrabbing, IDC_SIZEALL),
+            (MouseCursor::VerticalText, IDC_IBEAM),
+            (MouseCursor::PtrNotAllowed, IDC_NO),
+            (MouseCursor::ZoomIn, IDC_ARROW),
+            (MouseCursor::ZoomOut, IDC_ARROW),
+            (MouseCursor::Alias, IDC_ARROW),
+            (MouseCursor::Copy, IDC_ARROW),
+            (MouseCursor::Cell, IDC_CROSS),
+            (MouseCursor::EResize, IDC_SIZEWE),
+            (MouseCursor::NResize, IDC_SIZENS),
+            (MouseCursor::NeResize, IDC_SIZENESW),
+            (MouseCursor::NwResize, IDC_SIZENWSE),
+            (MouseCursor::SResize, IDC_SIZENS),
+            (MouseCursor::SeResize, IDC_SIZENWSE),
+            (MouseCursor::SwResize, IDC_SIZENESW),
+            (MouseCursor::WResize, IDC_SIZEWE),
+            (MouseCursor::ColResize, IDC_SIZEWE),
+            (MouseCursor::RowResize, IDC_SIZENS),
+        ];
+
+        for (cursor, expected) in native.iter().chain(&emulated) {
+            assert_eq!(cursor_to_lpcwstr(*cursor), *expected, "{:?}", cursor);
+        }
+
+        // Every cursor except for the hidden cursor is listed above
+        assert_eq!(cursor_to_lpcwstr(MouseCursor::Hidden), std::ptr::null());
+        assert_eq!(native.len() + emulated.len() + 1, MouseCursor::ALL.len());
+    }
+}
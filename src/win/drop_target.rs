@@ -1,3 +1,4 @@
+use std::cell::Cell;
 use std::ffi::OsString;
 use std::mem::transmute;
 use std::os::windows::prelude::OsStringExt;
@@ -71,6 +72,9 @@ pub(super) struct DropTarget {
     // and handling drag move events gets awkward on the client end otherwise
     drag_position: Point,
     drop_data: DropData,
+    /// Whether the handler has received a `DragEntered` event without a matching `DragLeft` or
+    /// `DragDropped` event.
+    drag_active: Cell<bool>,
 }
 
 impl DropTarget {
@@ -82,6 +86,15 @@ impl DropTarget {
 
             drag_position: Point::new(0.0, 0.0),
             drop_data: DropData::None,
+            drag_active: Cell::new(false),
+        }
+    }
+
+    /// Send a `DragLeft` event if a drag is in progress. This is used when the window closes
+    /// during a drag, since OLE doesn't call `DragLeave()` when the drop target is revoked.
+    pub(super) fn cancel_drag(&self) {
+        if self.drag_active.replace(false) {
+            self.on_event(None, MouseEvent::DragLeft);
         }
     }
 
@@ -210,6 +223,10 @@ impl DropTarget {
         drop_target.parse_coordinates(pt);
         drop_target.parse_drop_data(&*pDataObj);
 
+        // Every `DragEntered` event is followed by exactly one `DragLeft` or `DragDropped` event
+        drop_target.cancel_drag();
+        drop_target.drag_active.set(true);
+
         let event = MouseEvent::DragEntered {
             position: drop_target.drag_position,
             modifiers,
@@ -246,7 +263,7 @@ impl DropTarget {
 
     unsafe extern "system" fn drag_leave(this: *mut IDropTarget) -> HRESULT {
         let drop_target = &mut *(this as *mut DropTarget);
-        drop_target.on_event(None, MouseEvent::DragLeft);
+        drop_target.cancel_drag();
         S_OK
     }
 
@@ -263,6 +280,12 @@ impl DropTarget {
         let modifiers =
             window_state.keyboard_state().get_modifiers_from_mouse_wparam(grfKeyState as WPARAM);
 
+        // The drag may have been cancelled because the window is closing
+        if !drop_target.drag_active.replace(false) {
+            *pdwEffect = DROPEFFECT_NONE;
+            return S_OK;
+        }
+
         drop_target.parse_coordinates(pt);
         drop_target.parse_drop_data(&*pDataObj);
 
@@ -46,6 +46,8 @@ use winapi::um::winuser::{
     WM_SYSCHAR, WM_SYSKEYDOWN, WM_SYSKEYUP,
 };
 
+use crate::ModifierSides;
+
 const VK_ABNT_C2: INT = 0xc2;
 
 /// A (non-extended) virtual key code.
@@ -102,6 +104,42 @@ unsafe fn is_last_message(hwnd: HWND, msg: UINT, lparam: LPARAM) -> bool {
     avail == 0 || msg.lParam & SCAN_MASK != lparam & SCAN_MASK
 }
 
+/// Windows also treats holding the left Ctrl and Alt keys as [AltGr], in which case
+/// [KeyboardState::get_modifiers()] reports Ctrl and Alt instead. If the key produces a character
+/// with AltGr on the current layout, for instance `@` on a German layout, then report AltGr so the
+/// modifiers agree with the character that gets typed instead of looking like a shortcut.
+/// `key_vals` and `has_altgr` describe the current layout, like the fields on [KeyboardState].
+///
+/// [AltGr]: https://en.wikipedia.org/wiki/AltGr_key
+fn resolve_altgr(
+    key_vals: &HashMap<(VkCode, ShiftState), String>, has_altgr: bool, vk: VkCode,
+    modifiers: Modifiers,
+) -> Modifiers {
+    let ctrl_alt = Modifiers::CONTROL | Modifiers::ALT;
+    if !has_altgr || !modifiers.contains(ctrl_alt) {
+        return modifiers;
+    }
+
+    let mut shift_state = SHIFT_STATE_ALTGR;
+    if modifiers.contains(Modifiers::SHIFT) {
+        shift_state |= SHIFT_STATE_SHIFT;
+    }
+    let altgr_char = key_vals.get(&(vk, shift_state));
+    let is_altgr_char = match altgr_char {
+        Some(s) => {
+            !s.chars().any(char::is_control)
+                && altgr_char != key_vals.get(&(vk, shift_state & !SHIFT_STATE_ALTGR))
+        }
+        None => false,
+    };
+
+    if is_altgr_char {
+        (modifiers & !ctrl_alt) | Modifiers::ALT_GRAPH
+    } else {
+        modifiers
+    }
+}
+
 const MODIFIER_MAP: &[(INT, Modifiers, SHORT)] = &[
     (VK_MENU, Modifiers::ALT, 0x80),
     (VK_CAPITAL, Modifiers::CAPS_LOCK, 0x1),
@@ -111,6 +149,18 @@ const MODIFIER_MAP: &[(INT, Modifiers, SHORT)] = &[
     (VK_SHIFT, Modifiers::SHIFT, 0x80),
 ];
 
+/// The virtual key code for every side of every modifier key.
+const MODIFIER_SIDE_MAP: &[(INT, ModifierSides)] = &[
+    (VK_LSHIFT, ModifierSides::SHIFT_LEFT),
+    (VK_RSHIFT, ModifierSides::SHIFT_RIGHT),
+    (VK_LCONTROL, ModifierSides::CONTROL_LEFT),
+    (VK_RCONTROL, ModifierSides::CONTROL_RIGHT),
+    (VK_LMENU, ModifierSides::ALT_LEFT),
+    (VK_RMENU, ModifierSides::ALT_RIGHT),
+    (VK_LWIN, ModifierSides::META_LEFT),
+    (VK_RWIN, ModifierSides::META_RIGHT),
+];
+
 /// Convert scan code to W3C standard code.
 ///
 /// It's hard to get an authoritative source for this; it's mostly based
@@ -445,7 +495,7 @@ impl KeyboardState {
                 let scan_code = ((lparam & SCAN_MASK) >> 16) as u32;
                 let vk = self.refine_vk(wparam as u8, scan_code);
                 if is_last_message(hwnd, msg, lparam) {
-                    let modifiers = self.get_modifiers();
+                    let modifiers = self.resolve_altgr(vk, self.get_modifiers());
                     let code = scan_to_code(scan_code);
                     let key = vk_to_key(vk).unwrap_or_else(|| self.get_base_key(vk, modifiers));
                     let repeat = (lparam & 0x4000_0000) != 0;
@@ -470,7 +520,7 @@ impl KeyboardState {
             WM_KEYUP | WM_SYSKEYUP => {
                 let scan_code = ((lparam & SCAN_MASK) >> 16) as u32;
                 let vk = self.refine_vk(wparam as u8, scan_code);
-                let modifiers = self.get_modifiers();
+                let modifiers = self.resolve_altgr(vk, self.get_modifiers());
                 let code = scan_to_code(scan_code);
                 let key = vk_to_key(vk).unwrap_or_else(|| self.get_base_key(vk, modifiers));
                 let repeat = false;
@@ -492,9 +542,9 @@ impl KeyboardState {
                 //println!("char wparam {:x} lparam {:x}", wparam, lparam);
                 if is_last_message(hwnd, msg, lparam) {
                     let stash_vk = self.stash_vk.take();
-                    let modifiers = self.get_modifiers();
                     let scan_code = ((lparam & SCAN_MASK) >> 16) as u32;
                     let vk = self.refine_vk(stash_vk.unwrap_or(0), scan_code);
+                    let modifiers = self.resolve_altgr(vk, self.get_modifiers());
                     let code = scan_to_code(scan_code);
                     let key = if self.stash_utf16.is_empty() && wparam < 0x20 {
                         vk_to_key(vk).unwrap_or_else(|| self.get_base_key(vk, modifiers))
@@ -562,6 +612,24 @@ impl KeyboardState {
         }
     }
 
+    /// See [resolve_altgr()].
+    fn resolve_altgr(&self, vk: VkCode, modifiers: Modifiers) -> Modifiers {
+        resolve_altgr(&self.key_vals, self.has_altgr, vk, modifiers)
+    }
+
+    /// Get the left and right modifier keys that are held down. Like [Self::get_modifiers()], this
+    /// is the state at the time of the message that's currently being handled.
+    pub(crate) fn get_modifier_sides(&self) -> ModifierSides {
+        let mut sides = ModifierSides::empty();
+        for &(vk, side) in MODIFIER_SIDE_MAP {
+            if unsafe { GetKeyState(vk) } as u16 & 0x8000 != 0 {
+                sides |= side;
+            }
+        }
+
+        sides
+    }
+
     /// The same as [Self::get_modifiers()], but it reads the Ctrl and Shift state from a mouse
     /// event's wParam parameter. Saves two calls to [GetKeyState()].
     pub(crate) fn get_modifiers_from_mouse_wparam(&self, wparam: WPARAM) -> Modifiers {
@@ -702,3 +770,58 @@ impl KeyboardState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A few keys from the German (QWERTZ) layout, as [KeyboardState::load_keyboard_layout()]
+    /// would load them.
+    fn german_layout() -> HashMap<(VkCode, ShiftState), String> {
+        let keys: &[(u8, &[&str])] = &[
+            // The base, Shift, AltGr, and Shift+AltGr strings for each key
+            (b'Q', &["q", "Q", "@"]),
+            (b'E', &["e", "E", "\u{20ac}"]),
+            (b'7', &["7", "/", "{"]),
+            (b'A', &["a", "A"]),
+        ];
+
+        let mut key_vals = HashMap::new();
+        for &(vk, strings) in keys {
+            for (shift_state, s) in strings.iter().enumerate() {
+                key_vals.insert((vk, shift_state as ShiftState), s.to_string());
+            }
+        }
+
+        key_vals
+    }
+
+    #[test]
+    fn ctrl_alt_with_altgr_character_is_altgr() {
+        let key_vals = german_layout();
+        let ctrl_alt = Modifiers::CONTROL | Modifiers::ALT;
+
+        for vk in [b'Q', b'E', b'7'] {
+            assert_eq!(resolve_altgr(&key_vals, true, vk, ctrl_alt), Modifiers::ALT_GRAPH);
+        }
+        assert_eq!(
+            resolve_altgr(&key_vals, true, b'Q', ctrl_alt | Modifiers::CAPS_LOCK),
+            Modifiers::ALT_GRAPH | Modifiers::CAPS_LOCK
+        );
+    }
+
+    #[test]
+    fn ctrl_alt_without_altgr_character_is_shortcut() {
+        let key_vals = german_layout();
+        let ctrl_alt = Modifiers::CONTROL | Modifiers::ALT;
+
+        // No AltGr character at all
+        assert_eq!(resolve_altgr(&key_vals, true, b'A', ctrl_alt), ctrl_alt);
+        // There's no Shift+AltGr character for Q
+        let ctrl_alt_shift = ctrl_alt | Modifiers::SHIFT;
+        assert_eq!(resolve_altgr(&key_vals, true, b'Q', ctrl_alt_shift), ctrl_alt_shift);
+        // Layouts without AltGr, and keys without Ctrl and Alt, are left alone
+        assert_eq!(resolve_altgr(&key_vals, false, b'Q', ctrl_alt), ctrl_alt);
+        assert_eq!(resolve_altgr(&key_vals, true, b'Q', Modifiers::CONTROL), Modifiers::CONTROL);
+    }
+}
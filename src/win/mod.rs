@@ -1,6 +1,8 @@
+pub(crate) mod clipboard;
 mod cursor;
 mod drop_target;
 mod keyboard;
+mod vblank;
 mod window;
 
 pub use window::*;
@@ -0,0 +1,76 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use winapi::shared::minwindef::{FALSE, UINT};
+use winapi::shared::windef::HWND;
+use winapi::um::dwmapi::{DwmFlush, DwmIsCompositionEnabled};
+use winapi::um::winuser::PostMessageW;
+
+/// A thread that waits for the desktop compositor's frames, and that posts a message to a window
+/// after every frame. This is used for [`crate::FramePacing::DisplayLink`].
+pub(super) struct VblankThread {
+    stop: Arc<AtomicBool>,
+    /// Whether a posted message has not been handled yet. No new messages are posted until then,
+    /// so a slow window handler doesn't cause frames to queue up.
+    pending: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl VblankThread {
+    /// Start posting `msg` to `hwnd` after every compositor frame. This returns `None` if desktop
+    /// composition is disabled, which is only possible on Windows 7.
+    pub fn start(hwnd: HWND, msg: UINT) -> Option<Self> {
+        let mut composition_enabled = FALSE;
+        unsafe {
+            if DwmIsCompositionEnabled(&mut composition_enabled) < 0 || composition_enabled == FALSE
+            {
+                return None;
+            }
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let pending = Arc::new(AtomicBool::new(false));
+
+        // Window handles can be used from any thread
+        let hwnd = hwnd as usize;
+        let thread = {
+            let stop = stop.clone();
+            let pending = pending.clone();
+
+            thread::Builder::new()
+                .name(String::from("baseview-vblank"))
+                .spawn(move || {
+                    while !stop.load(Ordering::Acquire) {
+                        // This blocks until the compositor has presented its next frame
+                        if unsafe { DwmFlush() } < 0 {
+                            thread::sleep(Duration::from_millis(15));
+                        }
+
+                        if !pending.swap(true, Ordering::AcqRel) {
+                            unsafe { PostMessageW(hwnd as HWND, msg, 0, 0) };
+                        }
+                    }
+                })
+                .ok()?
+        };
+
+        Some(Self { stop, pending, thread: Some(thread) })
+    }
+
+    /// Allow the next message to be posted. This should be called when the posted message is
+    /// handled.
+    pub fn frame_handled(&self) {
+        self.pending.store(false, Ordering::Release);
+    }
+}
+
+impl Drop for VblankThread {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -1,30 +1,55 @@
 use winapi::shared::guiddef::GUID;
-use winapi::shared::minwindef::{ATOM, FALSE, LOWORD, LPARAM, LRESULT, UINT, WPARAM};
-use winapi::shared::windef::{HWND, RECT};
+use winapi::shared::minwindef::{ATOM, DWORD, FALSE, LOWORD, LPARAM, LRESULT, TRUE, UINT, WPARAM};
+use winapi::shared::windef::{DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE, HCURSOR, HWND, POINT, RECT};
 use winapi::um::combaseapi::CoCreateGuid;
+use winapi::um::dwmapi::{
+    DwmEnableBlurBehindWindow, DwmExtendFrameIntoClientArea, DwmSetWindowAttribute,
+    DWM_BB_BLURREGION, DWM_BB_ENABLE, DWM_BLURBEHIND,
+};
+use winapi::um::errhandlingapi::GetLastError;
 use winapi::um::ole2::{OleInitialize, RegisterDragDrop, RevokeDragDrop};
 use winapi::um::oleidl::LPDROPTARGET;
+use winapi::um::uxtheme::MARGINS;
+use winapi::um::wingdi::{
+    CombineRgn, CreateRectRgn, CreateSolidBrush, DeleteObject, StretchDIBits, BITMAPINFO,
+    BITMAPINFOHEADER, BI_RGB, DEVMODEW, DIB_RGB_COLORS, RGB, RGN_OR, SRCCOPY,
+};
 use winapi::um::winuser::{
-    AdjustWindowRectEx, CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW,
-    GetDpiForWindow, GetFocus, GetMessageW, GetWindowLongPtrW, LoadCursorW, PostMessageW,
-    RegisterClassW, ReleaseCapture, SetCapture, SetCursor, SetFocus, SetProcessDpiAwarenessContext,
-    SetTimer, SetWindowLongPtrW, SetWindowPos, TrackMouseEvent, TranslateMessage, UnregisterClassW,
-    CS_OWNDC, GET_XBUTTON_WPARAM, GWLP_USERDATA, HTCLIENT, IDC_ARROW, MSG, SWP_NOMOVE,
-    SWP_NOZORDER, TRACKMOUSEEVENT, WHEEL_DELTA, WM_CHAR, WM_CLOSE, WM_CREATE, WM_DPICHANGED,
-    WM_INPUTLANGCHANGE, WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN,
-    WM_MBUTTONUP, WM_MOUSEHWHEEL, WM_MOUSELEAVE, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_NCDESTROY,
-    WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SETCURSOR, WM_SHOWWINDOW, WM_SIZE, WM_SYSCHAR, WM_SYSKEYDOWN,
+    AddClipboardFormatListener, AdjustWindowRectEx, AdjustWindowRectExForDpi, ClientToScreen,
+    ClipCursor, CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW,
+    EnumDisplaySettingsW, FlashWindowEx, GetAncestor, GetCursorPos, GetDC, GetDpiForWindow,
+    GetFocus, GetForegroundWindow, GetMessageExtraInfo, GetMessagePos, GetMessageTime, GetMessageW,
+    GetMonitorInfoW, GetWindowLongPtrW, GetWindowRect, IsWindow, KillTimer, LoadCursorW,
+    MonitorFromWindow, PeekMessageW, PostMessageW, RegisterClassW, ReleaseCapture, ReleaseDC,
+    RemoveClipboardFormatListener, ScreenToClient, SendMessageW, SetCapture, SetCursor,
+    SetCursorPos, SetFocus, SetProcessDpiAwarenessContext, SetThreadDpiAwarenessContext, SetTimer,
+    SetWindowLongPtrW, SetWindowPos, SetWindowRgn, SetWindowTextW, ShowWindow, TrackMouseEvent,
+    TranslateMessage, UnregisterClassW, CS_OWNDC, ENUM_CURRENT_SETTINGS, FLASHWINFO, FLASHW_ALL,
+    FLASHW_TIMERNOFG, FLASHW_TRAY, GA_ROOT, GET_XBUTTON_WPARAM, GWLP_USERDATA, GWL_STYLE, HTBOTTOM,
+    HTBOTTOMLEFT, HTBOTTOMRIGHT, HTCAPTION, HTCLIENT, HTLEFT, HTRIGHT, HTTOP, HTTOPLEFT,
+    HTTOPRIGHT, HWND_BOTTOM, HWND_TOP, IDC_ARROW, MONITORINFOEXW, MONITOR_DEFAULTTONEAREST, MSG,
+    PM_NOREMOVE, PM_REMOVE, SWP_FRAMECHANGED, SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, SWP_NOZORDER,
+    SW_HIDE, SW_SHOW, SW_SHOWNA, TRACKMOUSEEVENT, WHEEL_DELTA, WMSZ_BOTTOM, WMSZ_BOTTOMLEFT,
+    WMSZ_LEFT, WMSZ_TOP, WMSZ_TOPLEFT, WMSZ_TOPRIGHT, WM_CAPTURECHANGED, WM_CHAR,
+    WM_CLIPBOARDUPDATE, WM_CLOSE, WM_CREATE, WM_DPICHANGED, WM_INPUTLANGCHANGE, WM_KEYDOWN,
+    WM_KEYFIRST, WM_KEYLAST, WM_KEYUP, WM_KILLFOCUS, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN,
+    WM_MBUTTONUP, WM_MOUSEFIRST, WM_MOUSEHWHEEL, WM_MOUSELAST, WM_MOUSELEAVE, WM_MOUSEMOVE,
+    WM_MOUSEWHEEL, WM_MOVE, WM_NCDESTROY, WM_NCHITTEST, WM_NCLBUTTONDOWN, WM_RBUTTONDOWN,
+    WM_RBUTTONUP, WM_SETCURSOR, WM_SHOWWINDOW, WM_SIZE, WM_SIZING, WM_SYSCHAR, WM_SYSKEYDOWN,
     WM_SYSKEYUP, WM_TIMER, WM_USER, WM_XBUTTONDOWN, WM_XBUTTONUP, WNDCLASSW, WS_CAPTION, WS_CHILD,
-    WS_CLIPSIBLINGS, WS_MAXIMIZEBOX, WS_MINIMIZEBOX, WS_POPUPWINDOW, WS_SIZEBOX, WS_VISIBLE,
-    XBUTTON1, XBUTTON2,
+    WS_CLIPSIBLINGS, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_MAXIMIZEBOX, WS_MINIMIZEBOX, WS_POPUP,
+    WS_POPUPWINDOW, WS_SIZEBOX, WS_SYSMENU, XBUTTON1, XBUTTON2,
 };
 
+use std::any::Any;
 use std::cell::{Cell, Ref, RefCell, RefMut};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::ffi::{c_void, OsStr};
 use std::os::windows::ffi::OsStrExt;
 use std::ptr::null_mut;
 use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use raw_window_handle::{
     HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle, Win32WindowHandle,
@@ -32,18 +57,38 @@ use raw_window_handle::{
 };
 
 const BV_WINDOW_MUST_CLOSE: UINT = WM_USER + 1;
+/// Posted by `Window::render_now()` to draw a frame once the window handler has returned.
+const BV_RENDER_NOW: UINT = WM_USER + 2;
+/// Posted by the vblank thread to draw a frame when using `FramePacing::DisplayLink`.
+const BV_VBLANK: UINT = WM_USER + 3;
+/// Posted by the `WindowHandle`'s methods after adding a command to the window's command queue.
+/// The message itself doesn't carry any data, so other applications sending this message to a
+/// parented window can't do any harm.
+const BV_HANDLE_COMMAND: UINT = WM_USER + 4;
+
+// The system backdrop attribute used by `Window::set_backdrop()`. This is only supported on
+// Windows 11 version 22H2 and later, and it's not part of `winapi`.
+const DWMWA_SYSTEMBACKDROP_TYPE: DWORD = 38;
+const DWMSBT_AUTO: DWORD = 0;
+const DWMSBT_MAINWINDOW: DWORD = 2;
+const DWMSBT_TRANSIENTWINDOW: DWORD = 3;
 
 use crate::{
-    Event, MouseButton, MouseCursor, MouseEvent, PhyPoint, PhySize, ScrollDelta, Size, WindowEvent,
+    Backdrop, Color, Event, EventStatus, FramePacing, Framebuffer, HitTestResult, ModifierSides,
+    MouseButton, MouseCursor, MouseEvent, PhyPoint, PhySize, Point, PointerKind, RawMessage, Rect,
+    ResizeEdge, ScrollDelta, ScrollPhase, Size, UserAttention, WindowError, WindowEvent,
     WindowHandler, WindowInfo, WindowOpenOptions, WindowScalePolicy,
 };
 
 use super::cursor::cursor_to_lpcwstr;
 use super::drop_target::DropTarget;
 use super::keyboard::KeyboardState;
+use super::vblank::VblankThread;
 
+use crate::event::EventClock;
 #[cfg(feature = "opengl")]
-use crate::gl::GlContext;
+use crate::gl::{GlConfig, GlContext, GlContextCell, GlError};
+use crate::mouse_cursor::{CursorAutoHide, RelativeDrag};
 
 unsafe fn generate_guid() -> String {
     let mut guid: GUID = std::mem::zeroed();
@@ -69,6 +114,9 @@ const WIN_FRAME_TIMER: usize = 4242;
 pub struct WindowHandle {
     hwnd: Option<HWND>,
     is_open: Rc<Cell<bool>>,
+    /// Shared with the window's [`ParentHandle`]. The window handles these commands when it
+    /// receives [`BV_HANDLE_COMMAND`].
+    commands: Arc<Mutex<VecDeque<HandleCommand>>>,
 }
 
 impl WindowHandle {
@@ -83,38 +131,122 @@ impl WindowHandle {
     pub fn is_open(&self) -> bool {
         self.is_open.get()
     }
+
+    pub fn process_pending_events(&self) {
+        let hwnd = match self.hwnd {
+            Some(hwnd) if self.is_open.get() => hwnd,
+            _ => return,
+        };
+
+        unsafe {
+            let mut msg: MSG = std::mem::zeroed();
+            while PeekMessageW(&mut msg, hwnd, 0, 0, PM_REMOVE) != 0 {
+                TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        }
+    }
+
+    pub fn raise(&self) {
+        self.set_z_order(HWND_TOP);
+    }
+
+    pub fn lower(&self) {
+        self.set_z_order(HWND_BOTTOM);
+    }
+
+    pub fn send_event(&self, event: Event) {
+        self.send_command(HandleCommand::SendEvent(event));
+    }
+
+    pub fn resize(&self, size: Size) {
+        self.send_command(HandleCommand::Resize(size));
+    }
+
+    pub fn send_message(&self, message: Box<dyn Any + Send>) {
+        self.send_command(HandleCommand::SendMessage(message));
+    }
+
+    fn send_command(&self, command: HandleCommand) {
+        if let Some(hwnd) = self.hwnd.filter(|_| self.is_open.get()) {
+            self.commands.lock().unwrap().push_back(command);
+            unsafe { PostMessageW(hwnd, BV_HANDLE_COMMAND, 0, 0) };
+        }
+    }
+
+    fn set_z_order(&self, insert_after: HWND) {
+        if let Some(hwnd) = self.hwnd.filter(|_| self.is_open.get()) {
+            unsafe {
+                SetWindowPos(
+                    hwnd,
+                    insert_after,
+                    0,
+                    0,
+                    0,
+                    0,
+                    SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+                )
+            };
+        }
+    }
+
+    pub fn try_raw_window_handle(&self) -> Option<RawWindowHandle> {
+        let hwnd = match self.hwnd {
+            Some(hwnd) if self.is_open.get() => hwnd,
+            _ => return None,
+        };
+
+        let mut handle = Win32WindowHandle::empty();
+        handle.hwnd = hwnd as *mut c_void;
+
+        Some(RawWindowHandle::Win32(handle))
+    }
 }
 
 unsafe impl HasRawWindowHandle for WindowHandle {
     fn raw_window_handle(&self) -> RawWindowHandle {
-        if let Some(hwnd) = self.hwnd {
-            let mut handle = Win32WindowHandle::empty();
-            handle.hwnd = hwnd as *mut c_void;
-
-            RawWindowHandle::Win32(handle)
-        } else {
-            RawWindowHandle::Win32(Win32WindowHandle::empty())
-        }
+        self.try_raw_window_handle().unwrap_or(RawWindowHandle::Win32(Win32WindowHandle::empty()))
     }
 }
 
 struct ParentHandle {
     is_open: Rc<Cell<bool>>,
+    commands: Arc<Mutex<VecDeque<HandleCommand>>>,
 }
 
 impl ParentHandle {
     pub fn new(hwnd: HWND) -> (Self, WindowHandle) {
         let is_open = Rc::new(Cell::new(true));
+        let commands = Arc::new(Mutex::new(VecDeque::new()));
 
-        let handle = WindowHandle { hwnd: Some(hwnd), is_open: Rc::clone(&is_open) };
+        let handle = WindowHandle {
+            hwnd: Some(hwnd),
+            is_open: Rc::clone(&is_open),
+            commands: Arc::clone(&commands),
+        };
+
+        (Self { is_open, commands }, handle)
+    }
+
+    /// Take all commands the [`WindowHandle`] has queued up. The queue isn't locked while the
+    /// commands are handled, so the window handler can send new commands in the meantime.
+    pub fn take_commands(&self) -> VecDeque<HandleCommand> {
+        std::mem::take(&mut *self.commands.lock().unwrap())
+    }
 
-        (Self { is_open }, handle)
+    /// Make the [`WindowHandle`] report the window as closed before the window is destroyed.
+    pub fn mark_closed(&self) {
+        self.is_open.set(false);
     }
 }
 
 impl Drop for ParentHandle {
     fn drop(&mut self) {
         self.is_open.set(false);
+
+        // The `WindowHandle` can outlive the window, and messages that were sent right before the
+        // window got destroyed shouldn't be kept alive until then
+        self.commands.lock().unwrap().clear();
     }
 }
 
@@ -132,22 +264,24 @@ unsafe extern "system" fn wnd_proc(
 
         // If any of the above event handlers caused tasks to be pushed to the deferred tasks list,
         // then we'll try to handle them now
-        loop {
-            // NOTE: This is written like this instead of using a `while let` loop to avoid exending
-            //       the borrow of `window_state.deferred_tasks` into the call of
-            //       `window_state.handle_deferred_task()` since that may also generate additional
-            //       messages.
-            let task = match (*window_state_ptr).deferred_tasks.borrow_mut().pop_front() {
-                Some(task) => task,
-                None => break,
-            };
-
-            (*window_state_ptr).handle_deferred_task(task);
-        }
+        (*window_state_ptr).handle_deferred_tasks();
 
         // NOTE: This is not handled in `wnd_proc_inner` because of the deferred task loop above
         if msg == WM_NCDESTROY {
+            // Popups close along with the window they were opened from
+            for &popup in (*window_state_ptr).popups.borrow().iter() {
+                if IsWindow(popup) != 0 {
+                    PostMessageW(popup, BV_WINDOW_MUST_CLOSE, 0, 0);
+                }
+            }
+
+            (*window_state_ptr).cancel_drag();
+            (*window_state_ptr).vblank_thread.take();
+            if (*window_state_ptr).cursor_confinement.take().is_some() {
+                (*window_state_ptr).apply_cursor_confinement();
+            }
             RevokeDragDrop(hwnd);
+            RemoveClipboardFormatListener(hwnd);
             unregister_wnd_class((*window_state_ptr).window_class);
             SetWindowLongPtrW(hwnd, GWLP_USERDATA, 0);
             drop(Rc::from_raw(window_state_ptr));
@@ -163,11 +297,67 @@ unsafe extern "system" fn wnd_proc(
     DefWindowProcW(hwnd, msg, wparam, lparam)
 }
 
+/// Windows synthesizes mouse messages for pen and touch input, and it marks those messages using
+/// their extra info. See
+/// <https://learn.microsoft.com/en-us/windows/win32/tablet/system-events-and-mouse-messages>.
+fn pointer_kind(extra_info: LPARAM) -> PointerKind {
+    const MI_WP_SIGNATURE: u32 = 0xFF515700;
+    const SIGNATURE_MASK: u32 = 0xFFFFFF00;
+    const TOUCH_FLAG: u32 = 0x80;
+
+    let extra_info = extra_info as u32;
+    if extra_info & SIGNATURE_MASK != MI_WP_SIGNATURE {
+        PointerKind::Mouse
+    } else if extra_info & TOUCH_FLAG != 0 {
+        PointerKind::Touch
+    } else {
+        PointerKind::Pen
+    }
+}
+
+/// The button for a mouse button message. Windows only has the two extra `XBUTTON1` and `XBUTTON2`
+/// buttons, so anything else is ignored.
+fn mouse_button(msg: UINT, wparam: WPARAM) -> Option<MouseButton> {
+    match msg {
+        WM_LBUTTONDOWN | WM_LBUTTONUP => Some(MouseButton::Left),
+        WM_MBUTTONDOWN | WM_MBUTTONUP => Some(MouseButton::Middle),
+        WM_RBUTTONDOWN | WM_RBUTTONUP => Some(MouseButton::Right),
+        WM_XBUTTONDOWN | WM_XBUTTONUP => match GET_XBUTTON_WPARAM(wparam) {
+            XBUTTON1 => Some(MouseButton::Back),
+            XBUTTON2 => Some(MouseButton::Forward),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 /// Our custom `wnd_proc` handler. If the result contains a value, then this is returned after
 /// handling any deferred tasks. otherwise the default window procedure is invoked.
 unsafe fn wnd_proc_inner(
     hwnd: HWND, msg: UINT, wparam: WPARAM, lparam: LPARAM, window_state: &WindowState,
 ) -> Option<LRESULT> {
+    if (WM_MOUSEFIRST..=WM_MOUSELAST).contains(&msg) || (WM_KEYFIRST..=WM_KEYLAST).contains(&msg) {
+        // This is the time the message currently being handled was posted, in milliseconds
+        let time = GetMessageTime() as u32;
+        window_state.event_timestamp.set(Some(window_state.event_clock.to_instant(time)));
+    }
+    if (WM_MOUSEFIRST..=WM_MOUSELAST).contains(&msg) {
+        window_state.pointer_kind.set(pointer_kind(GetMessageExtraInfo()));
+    }
+
+    // The handler gets to see the message first. Messages that are sent while the handler is
+    // already borrowed, for instance because it called a function that sends messages, are
+    // skipped here.
+    if let Ok(mut handler) = window_state.handler.try_borrow_mut() {
+        if let Some(handler) = handler.as_mut() {
+            let mut window = crate::Window::new(window_state.create_window());
+            let message = RawMessage::Win32 { hwnd: hwnd as *mut c_void, msg, wparam, lparam };
+            if let Some(result) = handler.on_raw_message(&mut window, message) {
+                return Some(result);
+            }
+        }
+    }
+
     match msg {
         WM_MOUSEMOVE => {
             let mut window = crate::Window::new(window_state.create_window());
@@ -196,18 +386,48 @@ unsafe fn wnd_proc_inner(
                     .on_event(&mut window, enter_event);
             }
 
+            // If another mouse move is already queued, then this one can be skipped
+            if window_state.coalesce_mouse_moves {
+                let mut next_msg: MSG = std::mem::zeroed();
+                if PeekMessageW(&mut next_msg, hwnd, WM_MOUSEMOVE, WM_MOUSEMOVE, PM_NOREMOVE) != 0 {
+                    return Some(0);
+                }
+            }
+
             let x = (lparam & 0xFFFF) as i16 as i32;
             let y = ((lparam >> 16) & 0xFFFF) as i16 as i32;
 
+            let modifiers =
+                window_state.keyboard_state.borrow().get_modifiers_from_mouse_wparam(wparam);
+
+            if window_state.relative_drag.is_active() {
+                if let Some((dx, dy)) = window_state.relative_drag.cursor_moved((x, y)) {
+                    let scale = window_state.window_info.borrow().scale();
+                    let event = Event::Mouse(MouseEvent::RawMotion {
+                        delta: Point::new(dx as f64 / scale, dy as f64 / scale),
+                        modifiers,
+                    });
+                    window_state
+                        .handler
+                        .borrow_mut()
+                        .as_mut()
+                        .unwrap()
+                        .on_event(&mut window, event);
+                }
+
+                // The handler may have ended the drag in the meantime
+                window_state.warp_to_relative_drag_origin();
+                return Some(0);
+            }
+
             let physical_pos = PhyPoint { x, y };
             let logical_pos = physical_pos.to_logical(&window_state.window_info.borrow());
-            let move_event = Event::Mouse(MouseEvent::CursorMoved {
-                position: logical_pos,
-                modifiers: window_state
-                    .keyboard_state
-                    .borrow()
-                    .get_modifiers_from_mouse_wparam(wparam),
-            });
+            if window_state.cursor_auto_hide.cursor_moved((x, y)) {
+                SetCursor(window_state.load_cursor(window_state.cursor_icon.get()));
+            }
+
+            let move_event =
+                Event::Mouse(MouseEvent::CursorMoved { position: logical_pos, modifiers });
             window_state.handler.borrow_mut().as_mut().unwrap().on_event(&mut window, move_event);
             Some(0)
         }
@@ -233,35 +453,44 @@ unsafe fn wnd_proc_inner(
                 } else {
                     ScrollDelta::Lines { x: value, y: 0.0 }
                 },
+                phase: ScrollPhase::Changed,
                 modifiers: window_state
                     .keyboard_state
                     .borrow()
                     .get_modifiers_from_mouse_wparam(wparam),
             });
 
-            window_state.handler.borrow_mut().as_mut().unwrap().on_event(&mut window, event);
+            let status =
+                window_state.handler.borrow_mut().as_mut().unwrap().on_event(&mut window, event);
 
+            // For child windows the default window procedure forwards the scroll event to the
+            // parent window
+            if status == EventStatus::Ignored {
+                None
+            } else {
+                Some(0)
+            }
+        }
+        // Popups capture the mouse, and they close when the user clicks anywhere outside of them
+        WM_LBUTTONDOWN | WM_MBUTTONDOWN | WM_RBUTTONDOWN | WM_XBUTTONDOWN
+            if window_state.popup && !window_state.contains_client_point(lparam) =>
+        {
+            PostMessageW(hwnd, BV_WINDOW_MUST_CLOSE, 0, 0);
             Some(0)
         }
+        // Another window took the mouse capture away from the popup, for instance because the
+        // user switched to another application
+        WM_CAPTURECHANGED if window_state.popup && lparam as HWND != hwnd => {
+            PostMessageW(hwnd, BV_WINDOW_MUST_CLOSE, 0, 0);
+            None
+        }
         WM_LBUTTONDOWN | WM_LBUTTONUP | WM_MBUTTONDOWN | WM_MBUTTONUP | WM_RBUTTONDOWN
         | WM_RBUTTONUP | WM_XBUTTONDOWN | WM_XBUTTONUP => {
             let mut window = crate::Window::new(window_state.create_window());
 
             let mut mouse_button_counter = window_state.mouse_button_counter.get();
 
-            let button = match msg {
-                WM_LBUTTONDOWN | WM_LBUTTONUP => Some(MouseButton::Left),
-                WM_MBUTTONDOWN | WM_MBUTTONUP => Some(MouseButton::Middle),
-                WM_RBUTTONDOWN | WM_RBUTTONUP => Some(MouseButton::Right),
-                WM_XBUTTONDOWN | WM_XBUTTONUP => match GET_XBUTTON_WPARAM(wparam) {
-                    XBUTTON1 => Some(MouseButton::Back),
-                    XBUTTON2 => Some(MouseButton::Forward),
-                    _ => None,
-                },
-                _ => None,
-            };
-
-            if let Some(button) = button {
+            if let Some(button) = mouse_button(msg, wparam) {
                 let event = match msg {
                     WM_LBUTTONDOWN | WM_MBUTTONDOWN | WM_RBUTTONDOWN | WM_XBUTTONDOWN => {
                         // Capture the mouse cursor on button down
@@ -276,9 +505,16 @@ unsafe fn wnd_proc_inner(
                         }
                     }
                     WM_LBUTTONUP | WM_MBUTTONUP | WM_RBUTTONUP | WM_XBUTTONUP => {
-                        // Release the mouse cursor capture when all buttons are released
+                        // Release the mouse cursor capture when all buttons are released, or
+                        // when the button that requested the capture is released
                         mouse_button_counter = mouse_button_counter.saturating_sub(1);
-                        if mouse_button_counter == 0 {
+                        if window_state.capture_button.get() == Some(button) {
+                            window_state.capture_button.set(None);
+                        }
+                        if mouse_button_counter == 0
+                            && !window_state.mouse_capture.get()
+                            && window_state.capture_button.get().is_none()
+                        {
                             ReleaseCapture();
                         }
 
@@ -297,21 +533,34 @@ unsafe fn wnd_proc_inner(
 
                 window_state.mouse_button_counter.set(mouse_button_counter);
 
-                window_state
+                let status = window_state
                     .handler
                     .borrow_mut()
                     .as_mut()
                     .unwrap()
                     .on_event(&mut window, Event::Mouse(event));
+
+                // The capture from the button press above lasts until this button is released
+                let pressed = matches!(
+                    msg,
+                    WM_LBUTTONDOWN | WM_MBUTTONDOWN | WM_RBUTTONDOWN | WM_XBUTTONDOWN
+                );
+                if status == EventStatus::CaptureMouse && pressed {
+                    window_state.capture_button.set(Some(button));
+                }
+
+                // The default window procedure will, among other things, send a `WM_CONTEXTMENU`
+                // message to the parent window when the right mouse button gets released
+                if status != EventStatus::Ignored {
+                    return Some(0);
+                }
             }
 
             None
         }
         WM_TIMER => {
-            let mut window = crate::Window::new(window_state.create_window());
-
             if wparam == WIN_FRAME_TIMER {
-                window_state.handler.borrow_mut().as_mut().unwrap().on_frame(&mut window);
+                window_state.trigger_frame();
             }
 
             Some(0)
@@ -321,12 +570,24 @@ unsafe fn wnd_proc_inner(
             {
                 let mut window = crate::Window::new(window_state.create_window());
 
+                let status = window_state
+                    .handler
+                    .borrow_mut()
+                    .as_mut()
+                    .unwrap()
+                    .on_event(&mut window, Event::Window(WindowEvent::CloseRequested));
+                if status == EventStatus::DeferClose {
+                    return Some(0);
+                }
+
+                window_state.cancel_drag();
                 window_state
                     .handler
                     .borrow_mut()
                     .as_mut()
                     .unwrap()
                     .on_event(&mut window, Event::Window(WindowEvent::WillClose));
+                window_state.parent_handle.mark_closed();
             }
 
             // DestroyWindow(hwnd);
@@ -349,6 +610,9 @@ unsafe fn wnd_proc_inner(
                     .on_event(&mut window, Event::Keyboard(event));
             }
 
+            // `TranslateMessage()` has already posted the `WM_CHAR` for this key by the time the
+            // `WM_KEYDOWN` gets here, so handling it doesn't prevent AltGr characters from being
+            // delivered. `WM_SYSKEYDOWN` still needs to reach `DefWindowProc()` for Alt+F4.
             if msg != WM_SYSKEYDOWN {
                 Some(0)
             } else {
@@ -361,34 +625,153 @@ unsafe fn wnd_proc_inner(
             let width = (lparam & 0xFFFF) as u16 as u32;
             let height = ((lparam >> 16) & 0xFFFF) as u16 as u32;
 
-            let new_window_info = {
-                let mut window_info = window_state.window_info.borrow_mut();
-                let new_window_info =
-                    WindowInfo::from_physical_size(PhySize { width, height }, window_info.scale());
+            let window_info = *window_state.window_info.borrow();
+            let new_window_info =
+                WindowInfo::from_physical_size(PhySize { width, height }, window_info.scale());
 
-                // Only send the event if anything changed
-                if window_info.physical_size() == new_window_info.physical_size() {
-                    return None;
-                }
+            // Only send the event if anything changed
+            if window_info.physical_size() == new_window_info.physical_size() {
+                return None;
+            }
+
+            {
+                let mut handler = window_state.handler.borrow_mut();
+                let handler = handler.as_mut().unwrap();
+                handler.on_event(
+                    &mut window,
+                    Event::Window(WindowEvent::WillResize {
+                        from: window_info,
+                        to: new_window_info,
+                    }),
+                );
+                *window_state.window_info.borrow_mut() = new_window_info;
+                handler.on_event(&mut window, Event::Window(WindowEvent::Resized(new_window_info)));
+            }
 
-                *window_info = new_window_info;
+            // The cursor's logical position changes along with the window's size and scale, even
+            // if the cursor itself didn't move
+            if let Some(position) = window_state.cursor_position() {
+                let modifiers = window_state.keyboard_state.borrow().get_modifiers();
+                window_state.handler.borrow_mut().as_mut().unwrap().on_event(
+                    &mut window,
+                    Event::Mouse(MouseEvent::CursorMoved { position, modifiers }),
+                );
+            }
 
-                new_window_info
-            };
+            None
+        }
+        WM_MOVE => {
+            // The confined area is relative to the window
+            if window_state.cursor_confinement.get().is_some() {
+                window_state.apply_cursor_confinement();
+            }
+
+            // Child windows move along with their parent, and `WM_MOVE` uses the parent's
+            // client coordinates for them
+            if window_state.dw_style.get() & WS_CHILD != 0 {
+                return None;
+            }
+
+            let mut window = crate::Window::new(window_state.create_window());
+
+            let x = (lparam & 0xFFFF) as u16 as i16 as i32;
+            let y = ((lparam >> 16) & 0xFFFF) as u16 as i16 as i32;
+            let position = PhyPoint::new(x, y).to_logical(&window_state.window_info.borrow());
 
             window_state
                 .handler
                 .borrow_mut()
                 .as_mut()
                 .unwrap()
-                .on_event(&mut window, Event::Window(WindowEvent::Resized(new_window_info)));
+                .on_event(&mut window, Event::Window(WindowEvent::Moved(position)));
+
+            None
+        }
+        WM_KILLFOCUS => {
+            // The cursor should never stay trapped in an unfocused window
+            if window_state.cursor_confinement.take().is_some() {
+                window_state.apply_cursor_confinement();
+            }
 
             None
         }
+        WM_SIZING => {
+            let aspect_ratio = match window_state.keep_aspect_ratio {
+                Some((width, height)) if width > 0 && height > 0 => {
+                    Some((width as f64, height as f64))
+                }
+                _ => None,
+            };
+            let resize_increments = window_state.resize_increments;
+            if aspect_ratio.is_none() && resize_increments.is_none() {
+                return None;
+            }
+
+            // The rectangle includes the window decorations, so we need to subtract those first
+            let mut frame = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+            AdjustWindowRectEx(&mut frame, window_state.dw_style.get(), 0, 0);
+            let frame_width = frame.right - frame.left;
+            let frame_height = frame.bottom - frame.top;
+
+            let rect = &mut *(lparam as *mut RECT);
+            let width = (rect.right - rect.left - frame_width) as f64;
+            let height = (rect.bottom - rect.top - frame_height) as f64;
+
+            // When dragging the top or bottom edge the height determines the new size, otherwise
+            // the width does
+            let edge = wparam as UINT;
+            let (width, height) = match aspect_ratio {
+                Some((ratio_width, ratio_height)) if edge == WMSZ_TOP || edge == WMSZ_BOTTOM => {
+                    (height * ratio_width / ratio_height, height)
+                }
+                Some((ratio_width, ratio_height)) => (width, width * ratio_height / ratio_width),
+                None => (width, height),
+            };
+
+            // The increments are counted from the current size, which is already a whole number
+            // of increments away from the size the window was opened with
+            let (width, height) = match resize_increments {
+                Some(increments) => {
+                    let window_info = window_state.window_info.borrow();
+                    let scale = window_info.scale();
+                    let current_size = window_info.physical_size();
+
+                    (
+                        snap_to_increment(
+                            width,
+                            current_size.width as f64,
+                            increments.width * scale,
+                        ),
+                        snap_to_increment(
+                            height,
+                            current_size.height as f64,
+                            increments.height * scale,
+                        ),
+                    )
+                }
+                None => (width, height),
+            };
+            let width = width.round() as i32 + frame_width;
+            let height = height.round() as i32 + frame_height;
+
+            // Keep the edges opposite to the ones being dragged in place
+            if edge == WMSZ_LEFT || edge == WMSZ_TOPLEFT || edge == WMSZ_BOTTOMLEFT {
+                rect.left = rect.right - width;
+            } else {
+                rect.right = rect.left + width;
+            }
+            if edge == WMSZ_TOP || edge == WMSZ_TOPLEFT || edge == WMSZ_TOPRIGHT {
+                rect.top = rect.bottom - height;
+            } else {
+                rect.bottom = rect.top + height;
+            }
+
+            Some(1)
+        }
         WM_DPICHANGED => {
             // To avoid weirdness with the realtime borrow checker.
-            let new_rect = {
-                if let WindowScalePolicy::SystemScaleFactor = window_state.scale_policy {
+            let scale = {
+                if let WindowScalePolicy::SystemScaleFactor = window_state.scale_policy.get() {
                     let dpi = (wparam & 0xFFFF) as u16 as u32;
                     let scale_factor = dpi as f64 / 96.0;
 
@@ -396,57 +779,125 @@ unsafe fn wnd_proc_inner(
                     *window_info =
                         WindowInfo::from_logical_size(window_info.logical_size(), scale_factor);
 
-                    Some((
-                        RECT {
-                            left: 0,
-                            top: 0,
-                            // todo: check if usize fits into i32
-                            right: window_info.physical_size().width as i32,
-                            bottom: window_info.physical_size().height as i32,
-                        },
-                        window_state.dw_style,
-                    ))
+                    Some((dpi, scale_factor))
                 } else {
                     None
                 }
             };
-            if let Some((mut new_rect, dw_style)) = new_rect {
+            if let Some((dpi, scale)) = scale {
+                let dw_style = window_state.dw_style.get();
+
+                // The system suggests a window rectangle for the new DPI. Without the frame this
+                // is the suggested size of the window's contents.
+                let suggested_rect = *(lparam as *const RECT);
+                let mut frame = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+                adjust_window_rect_for_dpi(&mut frame, dw_style, dpi);
+                let suggested_size = PhySize::new(
+                    ((suggested_rect.right - suggested_rect.left) - (frame.right - frame.left))
+                        .max(0) as u32,
+                    ((suggested_rect.bottom - suggested_rect.top) - (frame.bottom - frame.top))
+                        .max(0) as u32,
+                )
+                .to_logical_with_scale(scale);
+
+                let mut window = crate::Window::new(window_state.create_window());
+                let size = {
+                    let mut handler = window_state.handler.borrow_mut();
+                    let handler = handler.as_mut().unwrap();
+                    handler.on_scale_factor_changed(&mut window, scale);
+                    handler.size_for_scale_factor(&mut window, suggested_size)
+                };
+
+                let physical_size = WindowInfo::from_logical_size(size, scale).physical_size();
+                let mut new_rect = RECT {
+                    left: 0,
+                    top: 0,
+                    // todo: check if usize fits into i32
+                    right: physical_size.width as i32,
+                    bottom: physical_size.height as i32,
+                };
+
                 // Convert this desired "client rectangle" size to the actual "window rectangle"
-                // size (Because of course you have to do that).
-                AdjustWindowRectEx(&mut new_rect, dw_style, 0, 0);
+                // size (Because of course you have to do that). The frame's size depends on the
+                // DPI, and the window still has the old DPI at this point.
+                adjust_window_rect_for_dpi(&mut new_rect, dw_style, dpi);
 
                 // Windows makes us resize the window manually. This will trigger another `WM_SIZE` event,
-                // which we can then send the user the new scale factor.
+                // which we can then send the user the new scale factor. The suggested position
+                // keeps the window in place relative to the cursor when it's being dragged.
                 SetWindowPos(
                     hwnd,
                     hwnd,
-                    new_rect.left,
-                    new_rect.top,
+                    suggested_rect.left,
+                    suggested_rect.top,
                     new_rect.right - new_rect.left,
                     new_rect.bottom - new_rect.top,
-                    SWP_NOZORDER | SWP_NOMOVE,
+                    SWP_NOZORDER,
                 );
             }
 
             None
         }
+        WM_NCHITTEST => {
+            // Custom title bars and resize handles only make sense for standalone windows without
+            // decorations
+            if window_state.dw_style.get() & (WS_CHILD | WS_CAPTION) != 0 {
+                return None;
+            }
+
+            // Let Windows handle the actual window decorations first
+            let result = DefWindowProcW(hwnd, msg, wparam, lparam);
+            if result != HTCLIENT {
+                return Some(result);
+            }
+
+            // These are in screen coordinates
+            let mut point = POINT {
+                x: (lparam & 0xFFFF) as i16 as i32,
+                y: ((lparam >> 16) & 0xFFFF) as i16 as i32,
+            };
+            ScreenToClient(hwnd, &mut point);
+            let position =
+                PhyPoint::new(point.x, point.y).to_logical(&window_state.window_info.borrow());
+
+            // This message can be sent while the handler is already borrowed, for instance when
+            // the handler moves the window
+            let hit_test_result = match window_state.handler.try_borrow() {
+                Ok(handler) => handler.as_ref().map(|handler| handler.hit_test(position)),
+                Err(_) => None,
+            };
+
+            Some(hit_test_code(hit_test_result.unwrap_or(HitTestResult::Client)))
+        }
         // If WM_SETCURSOR returns `None`, WM_SETCURSOR continues to get handled by the outer window(s),
         // If it returns `Some(1)`, the current window decides what the cursor is
-        WM_SETCURSOR => {
-            let low_word = LOWORD(lparam as u32) as isize;
-            let mouse_in_window = low_word == HTCLIENT;
-            if mouse_in_window {
+        WM_SETCURSOR => match set_cursor_action(hwnd, wparam, lparam) {
+            SetCursorAction::LetChildSetCursor => Some(0),
+            SetCursorAction::SetCursor => {
                 // Here we need to set the cursor back to what the state says, since it can have changed when outside the window
-                let cursor =
-                    LoadCursorW(null_mut(), cursor_to_lpcwstr(window_state.cursor_icon.get()));
-                unsafe {
-                    SetCursor(cursor);
-                }
+                let cursor = if window_state.cursor_auto_hide.is_hidden()
+                    || window_state.relative_drag.is_active()
+                {
+                    MouseCursor::Hidden
+                } else {
+                    window_state.cursor_icon.get()
+                };
+                SetCursor(window_state.load_cursor(cursor));
                 Some(1)
-            } else {
-                // Cursor is being changed by some other window, e.g. when having mouse on the borders to resize it
-                None
             }
+            SetCursorAction::Default => None,
+        },
+        WM_CLIPBOARDUPDATE => {
+            // Changing the clipboard from within the handler may cause this message to be sent
+            // while the handler is still borrowed
+            if let Ok(mut handler) = window_state.handler.try_borrow_mut() {
+                if let Some(handler) = handler.as_mut() {
+                    let mut window = crate::Window::new(window_state.create_window());
+                    handler.on_event(&mut window, Event::Window(WindowEvent::ClipboardChanged));
+                }
+            }
+
+            Some(0)
         }
         // NOTE: `WM_NCDESTROY` is handled in the outer function because this deallocates the window
         //        state
@@ -454,11 +905,78 @@ unsafe fn wnd_proc_inner(
             DestroyWindow(hwnd);
             Some(0)
         }
+        BV_RENDER_NOW => {
+            let mut window = crate::Window::new(window_state.create_window());
+            window_state.handler.borrow_mut().as_mut().unwrap().on_frame(&mut window);
+
+            Some(0)
+        }
+        BV_HANDLE_COMMAND => {
+            // A single notification can cover multiple commands, in which case the later
+            // notifications find the queue empty
+            for command in window_state.parent_handle.take_commands() {
+                let mut window = crate::Window::new(window_state.create_window());
+                match command {
+                    HandleCommand::SendEvent(event) => {
+                        window_state
+                            .handler
+                            .borrow_mut()
+                            .as_mut()
+                            .unwrap()
+                            .on_event(&mut window, event);
+                    }
+                    HandleCommand::Resize(size) => {
+                        window_state.handle_deferred_task(WindowTask::Resize(size));
+                    }
+                    HandleCommand::SendMessage(message) => {
+                        window_state
+                            .handler
+                            .borrow_mut()
+                            .as_mut()
+                            .unwrap()
+                            .on_message(&mut window, message);
+                    }
+                }
+            }
+
+            Some(0)
+        }
+        BV_VBLANK => {
+            if let Some(vblank_thread) = window_state.vblank_thread.borrow().as_ref() {
+                vblank_thread.frame_handled();
+            }
+            window_state.trigger_frame();
+
+            Some(0)
+        }
         _ => None,
     }
 }
 
-unsafe fn register_wnd_class() -> ATOM {
+/// Convert a client rectangle to a window rectangle like `AdjustWindowRectEx()`, but for the given
+/// DPI instead of the window's current DPI.
+unsafe fn adjust_window_rect_for_dpi(rect: &mut RECT, dw_style: DWORD, dpi: UINT) {
+    if AdjustWindowRectExForDpi(rect, dw_style, 0, 0, dpi) == 0 {
+        AdjustWindowRectEx(rect, dw_style, 0, 0);
+    }
+}
+
+/// Round a size that's being dragged to the nearest whole number of `increment`s away from
+/// `current`, without letting the size drop to zero.
+fn snap_to_increment(size: f64, current: f64, increment: f64) -> f64 {
+    if increment < 1.0 {
+        return size;
+    }
+
+    let mut snapped = current + ((size - current) / increment).round() * increment;
+    while snapped < 1.0 {
+        snapped += increment;
+    }
+
+    snapped
+}
+
+unsafe fn register_wnd_class(background_color: Option<Color>) -> ATOM {
     // We generate a unique name for the new window class to prevent name collisions
     let class_name_str = format!("Baseview-{}", generate_guid());
     let mut class_name: Vec<u16> = OsStr::new(&class_name_str).encode_wide().collect();
@@ -473,7 +991,11 @@ unsafe fn register_wnd_class() -> ATOM {
         cbWndExtra: 0,
         hIcon: null_mut(),
         hCursor: LoadCursorW(null_mut(), IDC_ARROW),
-        hbrBackground: null_mut(),
+        // The system deletes the brush when the class gets unregistered
+        hbrBackground: match background_color {
+            Some(color) => CreateSolidBrush(RGB(color.r, color.g, color.b)),
+            None => null_mut(),
+        },
         lpszMenuName: null_mut(),
     };
 
@@ -484,6 +1006,23 @@ unsafe fn unregister_wnd_class(wnd_class: ATOM) {
     UnregisterClassW(wnd_class as _, null_mut());
 }
 
+/// Let the desktop compositor blend the window's contents using their alpha channel. Enabling
+/// blur-behind with an empty blur region does exactly that without blurring anything, and unlike
+/// layered windows this also works for OpenGL contexts.
+unsafe fn enable_transparency(hwnd: HWND) {
+    let region = CreateRectRgn(0, 0, -1, -1);
+    let blur_behind = DWM_BLURBEHIND {
+        dwFlags: DWM_BB_ENABLE | DWM_BB_BLURREGION,
+        fEnable: TRUE,
+        hRgnBlur: region,
+        fTransitionOnMaximized: FALSE,
+    };
+
+    // This fails when desktop composition is disabled, in which case the window stays opaque
+    DwmEnableBlurBehindWindow(hwnd, &blur_behind);
+    DeleteObject(region as _);
+}
+
 /// All data associated with the window. This uses internal mutability so the outer struct doesn't
 /// need to be mutably borrowed. Mutably borrowing the entire `WindowState` can be problematic
 /// because of the Windows message loops' reentrant nature. Care still needs to be taken to prevent
@@ -496,16 +1035,56 @@ pub(super) struct WindowState {
     pub hwnd: HWND,
     window_class: ATOM,
     window_info: RefCell<WindowInfo>,
-    _parent_handle: Option<ParentHandle>,
+    parent_handle: ParentHandle,
+    /// [`WindowOpenOptions::transparent`]. Framebuffers need an opaque alpha channel then, since
+    /// the desktop compositor blends the window's contents.
+    transparent: bool,
     keyboard_state: RefCell<KeyboardState>,
     mouse_button_counter: Cell<usize>,
+    /// Whether the mouse has been captured explicitly through `set_mouse_capture()`. If this is
+    /// not set, the mouse is only captured while any of the mouse buttons are held down.
+    mouse_capture: Cell<bool>,
+    /// The button whose `ButtonPressed` event returned `EventStatus::CaptureMouse`. The mouse
+    /// stays captured until this button is released.
+    capture_button: Cell<Option<MouseButton>>,
     mouse_was_outside_window: RefCell<bool>,
     cursor_icon: Cell<MouseCursor>,
+    cursor_auto_hide: CursorAutoHide,
+    relative_drag: RelativeDrag,
+    /// System cursors that have already been loaded, so they don't need to be loaded again every
+    /// time the cursor gets set.
+    cursor_cache: RefCell<HashMap<MouseCursor, HCURSOR>>,
     // Initialized late so the `Window` can hold a reference to this `WindowState`
     handler: RefCell<Option<Box<dyn WindowHandler>>>,
-    _drop_target: RefCell<Option<Rc<DropTarget>>>,
-    scale_policy: WindowScalePolicy,
-    dw_style: u32,
+    drop_target: RefCell<Option<Rc<DropTarget>>>,
+    /// Whether this window was opened through [`crate::Window::open_popup`]. Popups capture the
+    /// mouse so they can close when the user clicks outside of them.
+    popup: bool,
+    /// The popups opened from this window. These are closed along with this window.
+    popups: RefCell<Vec<HWND>>,
+    /// Whether showing the window should not activate it. This is set for popups and for windows
+    /// opened with [`WindowOpenOptions::no_focus_on_open`].
+    no_activate: bool,
+    /// Whether the window is shown, or whether it will be shown once it has been opened.
+    visible: Cell<bool>,
+    scale_policy: Cell<WindowScalePolicy>,
+    dw_style: Cell<u32>,
+    /// The `(width, height)` aspect ratio the user can resize the window with, if any.
+    keep_aspect_ratio: Option<(u32, u32)>,
+    /// The logical size the user can resize the window in steps of, if any.
+    resize_increments: Option<Size>,
+    coalesce_mouse_moves: bool,
+    event_clock: EventClock,
+    /// The time at which the last input message was generated.
+    event_timestamp: Cell<Option<Instant>>,
+    /// The kind of device that generated the last mouse message.
+    pointer_kind: Cell<PointerKind>,
+    /// The area set with [`Window::confine_cursor`], in logical coordinates.
+    cursor_confinement: Cell<Option<Rect>>,
+    /// The frame timer's interval in milliseconds.
+    frame_interval: Cell<u32>,
+    /// Replaces the frame timer while the window uses [`FramePacing::DisplayLink`].
+    vblank_thread: RefCell<Option<VblankThread>>,
 
     /// Tasks that should be executed at the end of `wnd_proc`. This is needed to avoid mutably
     /// borrowing the fields from `WindowState` more than once. For instance, when the window
@@ -515,7 +1094,10 @@ pub(super) struct WindowState {
     pub deferred_tasks: RefCell<VecDeque<WindowTask>>,
 
     #[cfg(feature = "opengl")]
-    pub gl_context: Option<GlContext>,
+    pub gl_context: GlContextCell,
+    /// Why the requested OpenGL context could not be created, if it couldn't.
+    #[cfg(feature = "opengl")]
+    gl_context_error: Option<GlError>,
 }
 
 impl WindowState {
@@ -523,6 +1105,20 @@ impl WindowState {
         Window { state: self }
     }
 
+    /// Call the window handler's `on_frame()`.
+    unsafe fn trigger_frame(&self) {
+        let mut window = crate::Window::new(self.create_window());
+        self.handler.borrow_mut().as_mut().unwrap().on_frame(&mut window);
+
+        // The cursor is only hidden while it's over the window. `WM_SETCURSOR` keeps it hidden
+        // from then on.
+        if self.cursor_auto_hide.should_hide(Instant::now())
+            && !*self.mouse_was_outside_window.borrow()
+        {
+            SetCursor(null_mut());
+        }
+    }
+
     pub(super) fn window_info(&self) -> Ref<WindowInfo> {
         self.window_info.borrow()
     }
@@ -535,55 +1131,441 @@ impl WindowState {
         self.handler.borrow_mut()
     }
 
-    /// Handle a deferred task as described in [`Self::deferred_tasks`].
-    pub(self) fn handle_deferred_task(&self, task: WindowTask) {
-        match task {
-            WindowTask::Resize(size) => {
-                // `self.window_info` will be modified in response to the `WM_SIZE` event that
-                // follows the `SetWindowPos()` call
-                let scaling = self.window_info.borrow().scale();
-                let window_info = WindowInfo::from_logical_size(size, scaling);
+    /// Load the system cursor for `cursor`, or reuse it if it has been loaded before.
+    pub(super) fn load_cursor(&self, cursor: MouseCursor) -> HCURSOR {
+        *self
+            .cursor_cache
+            .borrow_mut()
+            .entry(cursor)
+            .or_insert_with(|| unsafe { LoadCursorW(null_mut(), cursor_to_lpcwstr(cursor)) })
+    }
 
-                // If the window is a standalone window then the size needs to include the window
-                // decorations
-                let mut rect = RECT {
-                    left: 0,
-                    top: 0,
-                    right: window_info.physical_size().width as i32,
-                    bottom: window_info.physical_size().height as i32,
-                };
-                unsafe {
-                    AdjustWindowRectEx(&mut rect, self.dw_style, 0, 0);
-                    SetWindowPos(
-                        self.hwnd,
-                        self.hwnd,
-                        0,
-                        0,
-                        rect.right - rect.left,
-                        rect.bottom - rect.top,
-                        SWP_NOZORDER | SWP_NOMOVE,
-                    )
-                };
-            }
+    /// End a drag and drop operation that's still in progress when the window closes.
+    fn cancel_drag(&self) {
+        // The handler receives the `DragLeft` event, so this borrow needs to end first
+        let drop_target = self.drop_target.borrow().clone();
+        if let Some(drop_target) = drop_target {
+            drop_target.cancel_drag();
         }
     }
-}
 
-/// Tasks that must be deferred until the end of [`wnd_proc()`] to avoid reentrant `WindowState`
-/// borrows. See the docstring on [`WindowState::deferred_tasks`] for more information.
-#[derive(Debug, Clone)]
-pub(super) enum WindowTask {
-    /// Resize the window to the given size. The size is in logical pixels. DPI scaling is applied
-    /// automatically.
-    Resize(Size),
-}
+    /// Move the cursor to a position in physical pixels relative to the window's client area.
+    fn warp_cursor(&self, (x, y): (i32, i32)) {
+        let mut point = POINT { x, y };
+        unsafe {
+            ClientToScreen(self.hwnd, &mut point);
+            SetCursorPos(point.x, point.y);
+        }
+    }
+
+    /// Clip the cursor to the area set with [`Window::confine_cursor`], or release it if no area
+    /// is set. The clip rectangle is in screen coordinates, so this needs to be called again when
+    /// the window moves.
+    fn apply_cursor_confinement(&self) {
+        unsafe {
+            match self.cursor_confinement.get() {
+                Some(rect) => {
+                    let rect = rect.to_physical(&self.window_info.borrow());
+                    let mut top_left = POINT { x: rect.x, y: rect.y };
+                    ClientToScreen(self.hwnd, &mut top_left);
+
+                    let clip_rect = RECT {
+                        left: top_left.x,
+                        top: top_left.y,
+                        right: top_left.x + rect.width as i32,
+                        bottom: top_left.y + rect.height as i32,
+                    };
+                    ClipCursor(&clip_rect);
+                }
+                None => {
+                    ClipCursor(null_mut());
+                }
+            }
+        }
+    }
+
+    /// Move the cursor back to the origin of the [relative drag][Window::begin_relative_drag]
+    /// after it has moved.
+    fn warp_to_relative_drag_origin(&self) {
+        if let Some(origin) = self.relative_drag.warp_target() {
+            self.warp_cursor(origin);
+            // `SetCursorPos()` moves the cursor right away
+            self.relative_drag.cursor_warped(true);
+        }
+    }
+
+    /// Whether the client coordinates in a mouse message's `LPARAM` are inside of the window.
+    fn contains_client_point(&self, lparam: LPARAM) -> bool {
+        let x = (lparam & 0xFFFF) as i16 as i32;
+        let y = ((lparam >> 16) & 0xFFFF) as i16 as i32;
+        let size = self.window_info.borrow().physical_size();
+
+        x >= 0 && y >= 0 && x < size.width as i32 && y < size.height as i32
+    }
+
+    /// The cursor's position relative to the window in logical pixels, if it's inside of the
+    /// window.
+    fn cursor_position(&self) -> Option<Point> {
+        let mut point = POINT { x: 0, y: 0 };
+        unsafe {
+            if GetCursorPos(&mut point) == 0 || ScreenToClient(self.hwnd, &mut point) == 0 {
+                return None;
+            }
+        }
+
+        let window_info = self.window_info.borrow();
+        let physical_size = window_info.physical_size();
+        if point.x < 0
+            || point.y < 0
+            || point.x >= physical_size.width as i32
+            || point.y >= physical_size.height as i32
+        {
+            return None;
+        }
+
+        Some(PhyPoint::new(point.x, point.y).to_logical(&window_info))
+    }
+
+    /// Capture the mouse so a popup also receives button presses outside of its bounds, which
+    /// close the popup.
+    fn capture_popup_mouse(&self) {
+        self.mouse_capture.set(true);
+        unsafe { SetCapture(self.hwnd) };
+    }
+
+    /// Handle all tasks in [`Self::deferred_tasks`] until the queue is empty.
+    pub(self) fn handle_deferred_tasks(&self) {
+        loop {
+            // NOTE: This is written like this instead of using a `while let` loop to avoid exending
+            //       the borrow of `self.deferred_tasks` into the call of
+            //       `self.handle_deferred_task()` since that may also generate additional
+            //       messages.
+            let task = match self.deferred_tasks.borrow_mut().pop_front() {
+                Some(task) => task,
+                None => break,
+            };
+
+            self.handle_deferred_task(task);
+        }
+    }
+
+    /// Handle a deferred task as described in [`Self::deferred_tasks`].
+    pub(self) fn handle_deferred_task(&self, task: WindowTask) {
+        match task {
+            WindowTask::Resize(size) => {
+                // `self.window_info` will be modified in response to the `WM_SIZE` event that
+                // follows the `SetWindowPos()` call
+                let scaling = self.window_info.borrow().scale();
+                let window_info = WindowInfo::from_logical_size(size, scaling);
+
+                // If the window is a standalone window then the size needs to include the window
+                // decorations
+                let mut rect = RECT {
+                    left: 0,
+                    top: 0,
+                    right: window_info.physical_size().width as i32,
+                    bottom: window_info.physical_size().height as i32,
+                };
+                unsafe {
+                    AdjustWindowRectEx(&mut rect, self.dw_style.get(), 0, 0);
+                    SetWindowPos(
+                        self.hwnd,
+                        self.hwnd,
+                        0,
+                        0,
+                        rect.right - rect.left,
+                        rect.bottom - rect.top,
+                        SWP_NOZORDER | SWP_NOMOVE,
+                    )
+                };
+            }
+            WindowTask::SetScalePolicy(policy) => {
+                self.scale_policy.set(policy);
+
+                let scale = match policy {
+                    WindowScalePolicy::SystemScaleFactor => unsafe {
+                        GetDpiForWindow(self.hwnd) as f64 / 96.0
+                    },
+                    WindowScalePolicy::ScaleFactor(scale) => scale,
+                };
+
+                let old_window_info = *self.window_info.borrow();
+                if old_window_info.scale() == scale {
+                    return;
+                }
+                let window_info =
+                    WindowInfo::from_logical_size(old_window_info.logical_size(), scale);
+
+                {
+                    let mut window = crate::Window::new(self.create_window());
+                    let mut handler = self.handler.borrow_mut();
+                    let handler = handler.as_mut().unwrap();
+                    handler.on_event(
+                        &mut window,
+                        Event::Window(WindowEvent::WillResize {
+                            from: old_window_info,
+                            to: window_info,
+                        }),
+                    );
+                    *self.window_info.borrow_mut() = window_info;
+                    handler.on_scale_factor_changed(&mut window, scale);
+                    handler.on_event(&mut window, Event::Window(WindowEvent::Resized(window_info)));
+                }
+
+                // `self.window_info` already matches the new size, so the `WM_SIZE` event caused by
+                // this doesn't result in another `Resized` event
+                let mut rect = RECT {
+                    left: 0,
+                    top: 0,
+                    right: window_info.physical_size().width as i32,
+                    bottom: window_info.physical_size().height as i32,
+                };
+                unsafe {
+                    AdjustWindowRectEx(&mut rect, self.dw_style.get(), 0, 0);
+                    SetWindowPos(
+                        self.hwnd,
+                        self.hwnd,
+                        0,
+                        0,
+                        rect.right - rect.left,
+                        rect.bottom - rect.top,
+                        SWP_NOZORDER | SWP_NOMOVE,
+                    )
+                };
+            }
+            WindowTask::SetResizable(resizable) => {
+                // Parented windows and windows without decorations can't be resized through
+                // their frame
+                let dw_style = self.dw_style.get();
+                if dw_style & WS_CHILD != 0 || dw_style & WS_CAPTION != WS_CAPTION {
+                    return;
+                }
+
+                let resizable_flags = WS_SIZEBOX | WS_MAXIMIZEBOX;
+                let update_style = |style: u32| {
+                    if resizable {
+                        style | resizable_flags
+                    } else {
+                        style & !resizable_flags
+                    }
+                };
+                self.dw_style.set(update_style(dw_style));
+
+                // The frame's size changes with the style, so the window needs to be resized to
+                // keep the same client area size
+                let physical_size = self.window_info.borrow().physical_size();
+                let mut rect = RECT {
+                    left: 0,
+                    top: 0,
+                    right: physical_size.width as i32,
+                    bottom: physical_size.height as i32,
+                };
+                unsafe {
+                    // This may contain other flags like `WS_VISIBLE` that have changed since the
+                    // window was created
+                    let style = GetWindowLongPtrW(self.hwnd, GWL_STYLE) as u32;
+                    SetWindowLongPtrW(self.hwnd, GWL_STYLE, update_style(style) as _);
+
+                    AdjustWindowRectEx(&mut rect, self.dw_style.get(), 0, 0);
+                    SetWindowPos(
+                        self.hwnd,
+                        self.hwnd,
+                        0,
+                        0,
+                        rect.right - rect.left,
+                        rect.bottom - rect.top,
+                        SWP_NOZORDER | SWP_NOMOVE | SWP_NOACTIVATE | SWP_FRAMECHANGED,
+                    )
+                };
+            }
+            WindowTask::BeginMoveResize(hit_test_result) => {
+                if self.dw_style.get() & WS_CHILD != 0 {
+                    return;
+                }
+
+                // The system's modal move/resize loop takes over the mouse, so the button release
+                // that ends the drag is never sent to the window
+                self.mouse_button_counter.set(0);
+                self.mouse_capture.set(false);
+                unsafe {
+                    ReleaseCapture();
+                    // The cursor position is passed along in screen coordinates, just like
+                    // `GetMessagePos()` returns it
+                    SendMessageW(
+                        self.hwnd,
+                        WM_NCLBUTTONDOWN,
+                        hit_test_code(hit_test_result) as WPARAM,
+                        GetMessagePos() as LPARAM,
+                    );
+                }
+            }
+            WindowTask::SetTitle(title) => {
+                let mut title: Vec<u16> = OsStr::new(&title).encode_wide().collect();
+                title.push(0);
+
+                unsafe { SetWindowTextW(self.hwnd, title.as_ptr()) };
+            }
+            WindowTask::SetVisible(visible) => {
+                if self.visible.replace(visible) == visible {
+                    return;
+                }
+
+                if self.no_activate {
+                    // Showing a popup should not take the focus away from its owner
+                    unsafe { ShowWindow(self.hwnd, if visible { SW_SHOWNA } else { SW_HIDE }) };
+                    if visible && self.popup {
+                        self.capture_popup_mouse();
+                    }
+                } else {
+                    unsafe { ShowWindow(self.hwnd, if visible { SW_SHOW } else { SW_HIDE }) };
+                }
+
+                let event = if visible { WindowEvent::Shown } else { WindowEvent::Hidden };
+                let mut window = crate::Window::new(self.create_window());
+                self.handler
+                    .borrow_mut()
+                    .as_mut()
+                    .unwrap()
+                    .on_event(&mut window, Event::Window(event));
+            }
+        }
+    }
+}
+
+pub struct Application {
+    /// The open state of every window opened through this application.
+    windows: Vec<Rc<Cell<bool>>>,
+}
+
+impl Application {
+    pub fn new() -> Self {
+        Self { windows: Vec::new() }
+    }
+
+    pub fn open_window<H, B>(&mut self, options: WindowOpenOptions, build: B) -> WindowHandle
+    where
+        H: WindowHandler + 'static,
+        B: FnOnce(&mut crate::Window) -> H,
+        B: Send + 'static,
+    {
+        let (window_handle, _) = Window::open(WindowKind::Standalone, options, build)
+            .unwrap_or_else(|err| panic!("Could not open the window: {}", err));
+        self.windows.push(Rc::clone(&window_handle.is_open));
+
+        window_handle
+    }
+
+    pub fn run(self) {
+        unsafe {
+            let mut msg: MSG = std::mem::zeroed();
+
+            // Messages for all of the thread's windows are dispatched here
+            while self.windows.iter().any(|is_open| is_open.get()) {
+                let status = GetMessageW(&mut msg, null_mut(), 0, 0);
+
+                if status == -1 || status == 0 {
+                    break;
+                }
+
+                TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        }
+    }
+}
+
+/// The `WM_NCHITTEST` result for a [`HitTestResult`].
+fn hit_test_code(hit_test_result: HitTestResult) -> LRESULT {
+    match hit_test_result {
+        HitTestResult::Client => HTCLIENT,
+        HitTestResult::TitleBar => HTCAPTION,
+        HitTestResult::Resize(ResizeEdge::Top) => HTTOP,
+        HitTestResult::Resize(ResizeEdge::Bottom) => HTBOTTOM,
+        HitTestResult::Resize(ResizeEdge::Left) => HTLEFT,
+        HitTestResult::Resize(ResizeEdge::Right) => HTRIGHT,
+        HitTestResult::Resize(ResizeEdge::TopLeft) => HTTOPLEFT,
+        HitTestResult::Resize(ResizeEdge::TopRight) => HTTOPRIGHT,
+        HitTestResult::Resize(ResizeEdge::BottomLeft) => HTBOTTOMLEFT,
+        HitTestResult::Resize(ResizeEdge::BottomRight) => HTBOTTOMRIGHT,
+    }
+}
+
+/// How a window handles a `WM_SETCURSOR` message.
+#[derive(Debug, PartialEq, Eq)]
+enum SetCursorAction {
+    /// The message was passed on by a child window. Returning `FALSE` lets the child window set its
+    /// own cursor, instead of the parent's cursor showing over the child.
+    LetChildSetCursor,
+    /// The mouse is over the window's client area, so the window sets its own cursor.
+    SetCursor,
+    /// The cursor is being changed by the default window procedure, e.g. when the mouse is on the
+    /// borders to resize the window.
+    Default,
+}
+
+/// Decide how to handle a `WM_SETCURSOR` message sent to `hwnd`. `wparam` is the window that
+/// contains the cursor, and the low word of `lparam` is its hit test result.
+fn set_cursor_action(hwnd: HWND, wparam: WPARAM, lparam: LPARAM) -> SetCursorAction {
+    if wparam as HWND != hwnd {
+        SetCursorAction::LetChildSetCursor
+    } else if LOWORD(lparam as u32) as LRESULT == HTCLIENT {
+        SetCursorAction::SetCursor
+    } else {
+        SetCursorAction::Default
+    }
+}
+
+/// The kind of window [`Window::open`] creates.
+enum WindowKind {
+    Standalone,
+    /// A child window of the given parent window.
+    Parented(HWND),
+    /// A popup window owned by `owner`, placed at `position` in screen coordinates.
+    Popup {
+        owner: HWND,
+        position: POINT,
+    },
+}
+
+/// Commands queued up for the window by a [`WindowHandle`]. See [`BV_HANDLE_COMMAND`].
+enum HandleCommand {
+    /// Send a synthetic event to the window handler.
+    SendEvent(Event),
+    /// Resize the window to a logical size.
+    Resize(Size),
+    /// Send a message to the window handler's `on_message()`.
+    SendMessage(Box<dyn Any + Send>),
+}
+
+/// Tasks that must be deferred until the end of [`wnd_proc()`] to avoid reentrant `WindowState`
+/// borrows. See the docstring on [`WindowState::deferred_tasks`] for more information.
+#[derive(Debug, Clone)]
+pub(super) enum WindowTask {
+    /// Resize the window to the given size. The size is in logical pixels. DPI scaling is applied
+    /// automatically.
+    Resize(Size),
+    /// Add or remove the window's resizable frame while keeping the same size.
+    SetResizable(bool),
+    /// Let the user move or resize the window as if they had pressed the mouse on the given part
+    /// of the window's frame.
+    BeginMoveResize(HitTestResult),
+    /// Change the title shown in the window's title bar.
+    SetTitle(String),
+    /// Show or hide the window, and let the window handler know if that changed its visibility.
+    SetVisible(bool),
+    /// Change the scale policy, and resize the window to keep its logical size if that changed
+    /// the scale factor.
+    SetScalePolicy(WindowScalePolicy),
+}
 
 pub struct Window<'a> {
     state: &'a WindowState,
 }
 
 impl Window<'_> {
-    pub fn open_parented<P, H, B>(parent: &P, options: WindowOpenOptions, build: B) -> WindowHandle
+    pub fn try_open_parented<P, H, B>(
+        parent: &P, options: WindowOpenOptions, build: B,
+    ) -> Result<WindowHandle, WindowError>
     where
         P: HasRawWindowHandle,
         H: WindowHandler + 'static,
@@ -592,12 +1574,12 @@ impl Window<'_> {
     {
         let parent = match parent.raw_window_handle() {
             RawWindowHandle::Win32(h) => h.hwnd as HWND,
-            h => panic!("unsupported parent handle {:?}", h),
+            _ => return Err(WindowError::UnsupportedParent),
         };
 
-        let (window_handle, _) = Self::open(true, parent, options, build);
+        let (window_handle, _) = Self::open(WindowKind::Parented(parent), options, build)?;
 
-        window_handle
+        Ok(window_handle)
     }
 
     pub fn open_blocking<H, B>(options: WindowOpenOptions, build: B)
@@ -606,7 +1588,8 @@ impl Window<'_> {
         B: FnOnce(&mut crate::Window) -> H,
         B: Send + 'static,
     {
-        let (_, hwnd) = Self::open(false, null_mut(), options, build);
+        let (_, hwnd) = Self::open(WindowKind::Standalone, options, build)
+            .unwrap_or_else(|err| panic!("Could not open the window: {}", err));
 
         unsafe {
             let mut msg: MSG = std::mem::zeroed();
@@ -624,9 +1607,29 @@ impl Window<'_> {
         }
     }
 
+    pub fn open_popup<H, B>(
+        &mut self, options: WindowOpenOptions, position: Point, build: B,
+    ) -> WindowHandle
+    where
+        H: WindowHandler + 'static,
+        B: FnOnce(&mut crate::Window) -> H,
+        B: Send + 'static,
+    {
+        let position = position.to_physical(&self.state.window_info.borrow());
+        let mut position = POINT { x: position.x, y: position.y };
+        unsafe { ClientToScreen(self.state.hwnd, &mut position) };
+
+        let kind = WindowKind::Popup { owner: self.state.hwnd, position };
+        let (window_handle, hwnd) = Self::open(kind, options, build)
+            .unwrap_or_else(|err| panic!("Could not open the window: {}", err));
+        self.state.popups.borrow_mut().push(hwnd);
+
+        window_handle
+    }
+
     fn open<H, B>(
-        parented: bool, parent: HWND, options: WindowOpenOptions, build: B,
-    ) -> (WindowHandle, HWND)
+        kind: WindowKind, options: WindowOpenOptions, build: B,
+    ) -> Result<(WindowHandle, HWND), WindowError>
     where
         H: WindowHandler + 'static,
         B: FnOnce(&mut crate::Window) -> H,
@@ -636,8 +1639,16 @@ impl Window<'_> {
             let mut title: Vec<u16> = OsStr::new(&options.title[..]).encode_wide().collect();
             title.push(0);
 
-            let window_class = register_wnd_class();
-            // todo: manage error ^
+            // Transparent windows must not be filled with an opaque background
+            let background_color =
+                if options.transparent { None } else { options.background_color };
+            let window_class = register_wnd_class(background_color);
+            if window_class == 0 {
+                return Err(WindowError::CreationFailed(format!(
+                    "RegisterClassW failed with error code {}",
+                    GetLastError()
+                )));
+            }
 
             let scaling = match options.scale {
                 WindowScalePolicy::SystemScaleFactor => 1.0,
@@ -654,29 +1665,77 @@ impl Window<'_> {
                 bottom: window_info.physical_size().height as i32,
             };
 
-            let flags = if parented {
-                WS_CHILD | WS_VISIBLE
+            let (parent, position) = match kind {
+                WindowKind::Standalone => (null_mut(), POINT { x: 0, y: 0 }),
+                WindowKind::Parented(parent) => (parent, POINT { x: 0, y: 0 }),
+                WindowKind::Popup { owner, position } => (owner, position),
+            };
+            let parented = matches!(kind, WindowKind::Parented(_));
+            let popup = matches!(kind, WindowKind::Popup { .. });
+
+            let mut flags = if parented {
+                WS_CHILD
+            } else if popup {
+                WS_POPUP | WS_CLIPSIBLINGS
+            } else if !options.decorations {
+                WS_POPUP | WS_SYSMENU | WS_MINIMIZEBOX | WS_CLIPSIBLINGS
             } else {
                 WS_POPUPWINDOW
                     | WS_CAPTION
-                    | WS_VISIBLE
                     | WS_SIZEBOX
                     | WS_MINIMIZEBOX
                     | WS_MAXIMIZEBOX
                     | WS_CLIPSIBLINGS
             };
-
             if !parented {
                 AdjustWindowRectEx(&mut rect, flags, FALSE, 0);
             }
 
+            // Only standalone windows can be placed by the user. The requested position is the
+            // position of the window's contents, so the frame needs to be taken into account.
+            let requested_position = match kind {
+                WindowKind::Standalone => options.position,
+                _ => None,
+            };
+            let position = match requested_position {
+                Some(requested_position) => {
+                    let requested_position = requested_position.to_physical(&window_info);
+                    POINT {
+                        x: requested_position.x + rect.left,
+                        y: requested_position.y + rect.top,
+                    }
+                }
+                None => position,
+            };
+
+            // Popups don't show up in the taskbar. Clicking on popups or on windows opened with
+            // `no_focus_on_open` doesn't activate them.
+            let no_activate = popup || (!parented && options.no_focus_on_open);
+            let mut ex_flags = if no_activate { WS_EX_NOACTIVATE } else { 0 };
+            if popup {
+                ex_flags |= WS_EX_TOOLWINDOW;
+            }
+
+            // A window's DPI awareness is fixed when it gets created. Parented windows and popups
+            // are usually part of a host application, so only the thread's DPI awareness is
+            // changed while creating those to avoid affecting the host's own windows. This only
+            // works on Windows 10 unfortunately.
+            let previous_dpi_awareness = if !options.set_dpi_awareness {
+                None
+            } else if let WindowKind::Standalone = kind {
+                SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE);
+                None
+            } else {
+                Some(SetThreadDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE))
+            };
+
             let hwnd = CreateWindowExW(
-                0,
+                ex_flags,
                 window_class as _,
                 title.as_ptr(),
                 flags,
-                0,
-                0,
+                position.x,
+                position.y,
                 rect.right - rect.left,
                 rect.bottom - rect.top,
                 parent as *mut _,
@@ -684,40 +1743,85 @@ impl Window<'_> {
                 null_mut(),
                 null_mut(),
             );
-            // todo: manage error ^
+            let create_error = GetLastError();
 
-            #[cfg(feature = "opengl")]
-            let gl_context: Option<GlContext> = options.gl_config.map(|gl_config| {
-                let mut handle = Win32WindowHandle::empty();
-                handle.hwnd = hwnd as *mut c_void;
-                let handle = RawWindowHandle::Win32(handle);
+            if let Some(previous_dpi_awareness) = previous_dpi_awareness {
+                SetThreadDpiAwarenessContext(previous_dpi_awareness);
+            }
 
-                GlContext::create(&handle, gl_config).expect("Could not create OpenGL context")
-            });
+            if hwnd.is_null() {
+                unregister_wnd_class(window_class);
+                return Err(WindowError::CreationFailed(format!(
+                    "CreateWindowExW failed with error code {}",
+                    create_error
+                )));
+            }
 
+            if options.transparent {
+                enable_transparency(hwnd);
+            }
+
+            // The window is only shown once it has been fully set up, including its OpenGL context
+            #[cfg(feature = "opengl")]
+            let (gl_context, gl_context_error) = match options.gl_config {
+                Some(gl_config) => {
+                    let mut handle = Win32WindowHandle::empty();
+                    handle.hwnd = hwnd as *mut c_void;
+                    let handle = RawWindowHandle::Win32(handle);
+
+                    match GlContext::create(&handle, gl_config) {
+                        Ok(gl_context) => (GlContextCell::new(Some(gl_context)), None),
+                        Err(err) => (GlContextCell::new(None), Some(err)),
+                    }
+                }
+                None => (GlContextCell::new(None), None),
+            };
+
+            // This is also used for standalone windows opened through an `Application`, which needs
+            // to know when its windows have been closed
             let (parent_handle, window_handle) = ParentHandle::new(hwnd);
-            let parent_handle = if parented { Some(parent_handle) } else { None };
 
             let window_state = Rc::new(WindowState {
                 hwnd,
                 window_class,
                 window_info: RefCell::new(window_info),
-                _parent_handle: parent_handle,
+                parent_handle,
+                transparent: options.transparent,
                 keyboard_state: RefCell::new(KeyboardState::new()),
                 mouse_button_counter: Cell::new(0),
+                mouse_capture: Cell::new(false),
+                capture_button: Cell::new(None),
                 mouse_was_outside_window: RefCell::new(true),
                 cursor_icon: Cell::new(MouseCursor::Default),
+                cursor_auto_hide: CursorAutoHide::new(),
+                relative_drag: RelativeDrag::new(),
+                cursor_cache: RefCell::new(HashMap::new()),
                 // The Window refers to this `WindowState`, so this `handler` needs to be
                 // initialized later
                 handler: RefCell::new(None),
-                _drop_target: RefCell::new(None),
-                scale_policy: options.scale,
-                dw_style: flags,
+                drop_target: RefCell::new(None),
+                popup,
+                popups: RefCell::new(Vec::new()),
+                no_activate,
+                visible: Cell::new(options.visible),
+                scale_policy: Cell::new(options.scale),
+                dw_style: Cell::new(flags),
+                keep_aspect_ratio: options.keep_aspect_ratio,
+                resize_increments: options.resize_increments,
+                coalesce_mouse_moves: options.coalesce_mouse_moves,
+                event_clock: EventClock::new(),
+                event_timestamp: Cell::new(None),
+                pointer_kind: Cell::new(PointerKind::Mouse),
+                cursor_confinement: Cell::new(None),
+                frame_interval: Cell::new(15),
+                vblank_thread: RefCell::new(None),
 
                 deferred_tasks: RefCell::new(VecDeque::with_capacity(4)),
 
                 #[cfg(feature = "opengl")]
                 gl_context,
+                #[cfg(feature = "opengl")]
+                gl_context_error,
             });
 
             let handler = {
@@ -727,11 +1831,6 @@ impl Window<'_> {
             };
             *window_state.handler.borrow_mut() = Some(Box::new(handler));
 
-            // Only works on Windows 10 unfortunately.
-            SetProcessDpiAwarenessContext(
-                winapi::shared::windef::DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE,
-            );
-
             // Now we can get the actual dpi of the window.
             let new_rect = if let WindowScalePolicy::SystemScaleFactor = options.scale {
                 // Only works on Windows 10 unfortunately.
@@ -757,34 +1856,82 @@ impl Window<'_> {
                 None
             };
 
-            let drop_target = Rc::new(DropTarget::new(Rc::downgrade(&window_state)));
-            *window_state._drop_target.borrow_mut() = Some(drop_target.clone());
-
             OleInitialize(null_mut());
-            RegisterDragDrop(hwnd, Rc::as_ptr(&drop_target) as LPDROPTARGET);
+            if options.drag_n_drop {
+                let drop_target = Rc::new(DropTarget::new(Rc::downgrade(&window_state)));
+                *window_state.drop_target.borrow_mut() = Some(drop_target.clone());
+
+                let result = RegisterDragDrop(hwnd, Rc::as_ptr(&drop_target) as LPDROPTARGET);
+                if result < 0 {
+                    log_warn!("Could not register the window as a drop target: {:#x}", result);
+                }
+            }
 
-            SetWindowLongPtrW(hwnd, GWLP_USERDATA, Rc::into_raw(window_state) as *const _ as _);
+            let window_state_ptr = Rc::into_raw(window_state);
+            SetWindowLongPtrW(hwnd, GWLP_USERDATA, window_state_ptr as *const _ as _);
             SetTimer(hwnd, WIN_FRAME_TIMER, 15, None);
+            AddClipboardFormatListener(hwnd);
 
             if let Some(mut new_rect) = new_rect {
                 // Convert this desired"client rectangle" size to the actual "window rectangle"
                 // size (Because of course you have to do that).
                 AdjustWindowRectEx(&mut new_rect, flags, 0, 0);
 
+                // The requested position was converted to physical coordinates using the wrong scale
+                // factor, so the window needs to be moved as well
+                let (x, y, move_flags) = match requested_position {
+                    Some(requested_position) => {
+                        let requested_position = requested_position
+                            .to_physical(&(*window_state_ptr).window_info.borrow());
+                        (
+                            requested_position.x + new_rect.left,
+                            requested_position.y + new_rect.top,
+                            0,
+                        )
+                    }
+                    None => (0, 0, SWP_NOMOVE),
+                };
+
                 // Windows makes us resize the window manually. This will trigger another `WM_SIZE` event,
                 // which we can then send the user the new scale factor.
                 SetWindowPos(
                     hwnd,
                     hwnd,
-                    new_rect.left,
-                    new_rect.top,
+                    x,
+                    y,
                     new_rect.right - new_rect.left,
                     new_rect.bottom - new_rect.top,
-                    SWP_NOZORDER | SWP_NOMOVE,
+                    SWP_NOZORDER | move_flags,
                 );
             }
 
-            (window_handle, hwnd)
+            // Send an initial window resized event so the user is alerted of the correct dpi
+            // scaling. The `WM_SIZE` caused by the `SetWindowPos()` call above won't have sent one
+            // since `window_info` was already up to date at that point.
+            let window_state = &*window_state_ptr;
+            {
+                let mut window = crate::Window::new(window_state.create_window());
+                let window_info = *window_state.window_info.borrow();
+
+                window_state
+                    .handler
+                    .borrow_mut()
+                    .as_mut()
+                    .unwrap()
+                    .on_event(&mut window, Event::Window(WindowEvent::Resized(window_info)));
+            }
+            window_state.handle_deferred_tasks();
+
+            // The window handler may have already shown or hidden the window
+            if options.visible && window_state.visible.get() {
+                // Showing a popup should not take the focus away from its owner
+                ShowWindow(hwnd, if no_activate { SW_SHOWNA } else { SW_SHOW });
+                if popup {
+                    window_state.capture_popup_mouse();
+                }
+            }
+
+            Ok((window_handle, hwnd))
         }
     }
 
@@ -794,6 +1941,81 @@ impl Window<'_> {
         }
     }
 
+    pub fn set_visible(&mut self, visible: bool) {
+        // This sends an event to the window handler, so it can't happen while the handler is
+        // still running
+        let task = WindowTask::SetVisible(visible);
+        self.state.deferred_tasks.borrow_mut().push_back(task);
+    }
+
+    pub fn scale_policy(&self) -> WindowScalePolicy {
+        self.state.scale_policy.get()
+    }
+
+    pub fn set_scale_policy(&mut self, policy: WindowScalePolicy) {
+        // This sends events to the window handler, so it can't happen while the handler is still
+        // running
+        let task = WindowTask::SetScalePolicy(policy);
+        self.state.deferred_tasks.borrow_mut().push_back(task);
+    }
+
+    pub fn set_mouse_capture(&mut self, capture: bool) {
+        // Popups always capture the mouse
+        if self.state.popup {
+            return;
+        }
+
+        self.state.mouse_capture.set(capture);
+        unsafe {
+            if capture {
+                SetCapture(self.state.hwnd);
+            } else if self.state.mouse_button_counter.get() == 0 {
+                ReleaseCapture();
+            }
+        }
+    }
+
+    pub fn confine_cursor(&mut self, rect: Option<Rect>) {
+        // The cursor clip is shared by all applications, so it's only released if it was set
+        let previous = self.state.cursor_confinement.replace(rect);
+        if rect.is_some() || previous.is_some() {
+            self.state.apply_cursor_confinement();
+        }
+    }
+
+    pub fn begin_relative_drag(&mut self, origin: Point) {
+        let origin = origin.to_physical(&self.state.window_info.borrow());
+        self.state.relative_drag.begin((origin.x, origin.y));
+        unsafe {
+            SetCursor(null_mut());
+        }
+        self.set_mouse_capture(true);
+    }
+
+    pub fn end_relative_drag(&mut self) {
+        let Some(origin) = self.state.relative_drag.end() else { return };
+
+        self.state.warp_cursor(origin);
+        self.set_mouse_capture(false);
+        if !self.state.cursor_auto_hide.is_hidden() {
+            unsafe {
+                SetCursor(self.state.load_cursor(self.state.cursor_icon.get()));
+            }
+        }
+    }
+
+    pub fn begin_move_drag(&mut self) {
+        // This enters a modal loop that sends messages to the window while the handler is still
+        // borrowed
+        let task = WindowTask::BeginMoveResize(HitTestResult::TitleBar);
+        self.state.deferred_tasks.borrow_mut().push_back(task);
+    }
+
+    pub fn begin_resize_drag(&mut self, edge: ResizeEdge) {
+        let task = WindowTask::BeginMoveResize(HitTestResult::Resize(edge));
+        self.state.deferred_tasks.borrow_mut().push_back(task);
+    }
+
     pub fn has_focus(&mut self) -> bool {
         let focused_window = unsafe { GetFocus() };
         focused_window == self.state.hwnd
@@ -805,6 +2027,170 @@ impl Window<'_> {
         }
     }
 
+    pub fn request_user_attention(&mut self, kind: UserAttention) {
+        // Only top level windows show up in the taskbar
+        let hwnd = unsafe { GetAncestor(self.state.hwnd, GA_ROOT) };
+        if unsafe { GetForegroundWindow() } == hwnd {
+            return;
+        }
+
+        // The taskbar button stays highlighted after flashing until the window is activated
+        let (flags, count) = match kind {
+            UserAttention::Critical => (FLASHW_ALL | FLASHW_TIMERNOFG, 0),
+            UserAttention::Informational => (FLASHW_TRAY, 1),
+        };
+
+        let mut flash_info = FLASHWINFO {
+            cbSize: std::mem::size_of::<FLASHWINFO>() as UINT,
+            hwnd,
+            dwFlags: flags,
+            uCount: count,
+            dwTimeout: 0,
+        };
+        unsafe {
+            FlashWindowEx(&mut flash_info);
+        }
+    }
+
+    pub fn set_backdrop(&mut self, backdrop: Backdrop) {
+        if self.state.dw_style.get() & WS_CHILD != 0 {
+            return;
+        }
+
+        let backdrop_type = match backdrop {
+            Backdrop::None => DWMSBT_AUTO,
+            Backdrop::Blur => DWMSBT_TRANSIENTWINDOW,
+            Backdrop::Vibrancy => DWMSBT_MAINWINDOW,
+        };
+        // The backdrop is drawn behind the window's frame, so the frame needs to extend into the
+        // entire client area for it to show through the window's contents
+        let inset = if backdrop == Backdrop::None { 0 } else { -1 };
+        let margins = MARGINS {
+            cxLeftWidth: inset,
+            cxRightWidth: inset,
+            cyTopHeight: inset,
+            cyBottomHeight: inset,
+        };
+
+        // Older versions of Windows reject the attribute, in which case nothing changes
+        unsafe {
+            DwmExtendFrameIntoClientArea(self.state.hwnd, &margins);
+            DwmSetWindowAttribute(
+                self.state.hwnd,
+                DWMWA_SYSTEMBACKDROP_TYPE,
+                &backdrop_type as *const DWORD as *const c_void,
+                std::mem::size_of::<DWORD>() as DWORD,
+            );
+        }
+    }
+
+    pub fn flush(&mut self) {}
+
+    pub fn has_window_manager(&self) -> bool {
+        true
+    }
+
+    pub fn event_timestamp(&self) -> Option<Instant> {
+        self.state.event_timestamp.get()
+    }
+
+    pub fn modifier_sides(&self) -> ModifierSides {
+        self.state.keyboard_state.borrow().get_modifier_sides()
+    }
+
+    pub fn pointer_kind(&self) -> PointerKind {
+        self.state.pointer_kind.get()
+    }
+
+    pub fn cursor_position(&self) -> Option<Point> {
+        self.state.cursor_position()
+    }
+
+    pub fn set_frame_interval(&mut self, interval: Duration) {
+        let millis = interval.as_millis().clamp(1, u32::MAX as u128) as u32;
+        self.state.frame_interval.set(millis);
+
+        // Calling `SetTimer()` with an existing timer ID replaces that timer. The timer is stopped
+        // while the vblank thread drives the frames.
+        if self.state.vblank_thread.borrow().is_none() {
+            unsafe {
+                SetTimer(self.state.hwnd, WIN_FRAME_TIMER, millis, None);
+            }
+        }
+    }
+
+    pub fn set_frame_pacing(&mut self, pacing: FramePacing) {
+        let hwnd = self.state.hwnd;
+        let mut vblank_thread = self.state.vblank_thread.borrow_mut();
+
+        unsafe {
+            match pacing {
+                FramePacing::Timer => {
+                    if vblank_thread.take().is_some() {
+                        SetTimer(hwnd, WIN_FRAME_TIMER, self.state.frame_interval.get(), None);
+                    }
+                }
+                FramePacing::DisplayLink => {
+                    if vblank_thread.is_none() {
+                        // The frame timer keeps running if there's no compositor to wait for
+                        *vblank_thread = VblankThread::start(hwnd, BV_VBLANK);
+                        if vblank_thread.is_some() {
+                            KillTimer(hwnd, WIN_FRAME_TIMER);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn render_now(&mut self) {
+        // Posted messages are handled before `WM_TIMER`, so this won't wait for the frame timer
+        unsafe {
+            PostMessageW(self.state.hwnd, BV_RENDER_NOW, 0, 0);
+        }
+    }
+
+    pub fn refresh_rate(&self) -> Option<f64> {
+        unsafe {
+            let monitor = MonitorFromWindow(self.state.hwnd, MONITOR_DEFAULTTONEAREST);
+            let mut monitor_info: MONITORINFOEXW = std::mem::zeroed();
+            monitor_info.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+            if GetMonitorInfoW(monitor, &mut monitor_info as *mut _ as *mut _) == 0 {
+                return None;
+            }
+
+            let mut dev_mode: DEVMODEW = std::mem::zeroed();
+            dev_mode.dmSize = std::mem::size_of::<DEVMODEW>() as u16;
+            if EnumDisplaySettingsW(
+                monitor_info.szDevice.as_ptr(),
+                ENUM_CURRENT_SETTINGS,
+                &mut dev_mode,
+            ) == 0
+            {
+                return None;
+            }
+
+            // Values of 0 and 1 indicate the hardware's default refresh rate
+            match dev_mode.dmDisplayFrequency {
+                0 | 1 => None,
+                frequency => Some(frequency as f64),
+            }
+        }
+    }
+
+    pub fn window_info(&self) -> WindowInfo {
+        *self.state.window_info.borrow()
+    }
+
+    pub fn position(&self) -> Option<Point> {
+        let mut point = POINT { x: 0, y: 0 };
+        if unsafe { ClientToScreen(self.state.hwnd, &mut point) } == 0 {
+            return None;
+        }
+
+        Some(PhyPoint::new(point.x, point.y).to_logical(&self.state.window_info.borrow()))
+    }
+
     pub fn resize(&mut self, size: Size) {
         // To avoid reentrant event handler calls we'll defer the actual resizing until after the
         // event has been handled
@@ -812,17 +2198,155 @@ impl Window<'_> {
         self.state.deferred_tasks.borrow_mut().push_back(task);
     }
 
+    pub fn set_title(&mut self, title: &str) {
+        // This sends `WM_SETTEXT` to the window
+        let task = WindowTask::SetTitle(title.to_owned());
+        self.state.deferred_tasks.borrow_mut().push_back(task);
+    }
+
+    pub fn set_resizable(&mut self, resizable: bool) {
+        // Changing the style results in `WM_SIZE` and other messages being sent to the window
+        let task = WindowTask::SetResizable(resizable);
+        self.state.deferred_tasks.borrow_mut().push_back(task);
+    }
+
+    pub fn set_input_region(&mut self, region: Option<&[Rect]>) {
+        let hwnd = self.state.hwnd;
+
+        let Some(region) = region else {
+            unsafe { SetWindowRgn(hwnd, null_mut(), TRUE) };
+            return;
+        };
+
+        unsafe {
+            // Window regions are relative to the window's frame, not to its client area
+            let mut window_rect: RECT = std::mem::zeroed();
+            let mut client_origin = POINT { x: 0, y: 0 };
+            GetWindowRect(hwnd, &mut window_rect);
+            ClientToScreen(hwnd, &mut client_origin);
+            let offset_x = client_origin.x - window_rect.left;
+            let offset_y = client_origin.y - window_rect.top;
+
+            let window_info = *self.state.window_info.borrow();
+            let hrgn = CreateRectRgn(0, 0, 0, 0);
+            for rect in region {
+                let rect = rect.to_physical(&window_info);
+                let x = rect.x + offset_x;
+                let y = rect.y + offset_y;
+                let rect_hrgn = CreateRectRgn(x, y, x + rect.width as i32, y + rect.height as i32);
+                CombineRgn(hrgn, hrgn, rect_hrgn, RGN_OR);
+                DeleteObject(rect_hrgn as _);
+            }
+
+            // The system takes ownership of the region
+            SetWindowRgn(hwnd, hrgn, TRUE);
+        }
+    }
+
+    pub fn physical_size(&self) -> PhySize {
+        self.state.window_info.borrow().physical_size()
+    }
+
+    pub fn present(&mut self, framebuffer: &Framebuffer) {
+        let size = framebuffer.size();
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
+
+        let window_size = self.physical_size();
+
+        unsafe {
+            let mut bitmap_info: BITMAPINFO = std::mem::zeroed();
+            bitmap_info.bmiHeader.biSize = std::mem::size_of::<BITMAPINFOHEADER>() as u32;
+            bitmap_info.bmiHeader.biWidth = size.width as i32;
+            // A negative height means that the rows are stored from top to bottom
+            bitmap_info.bmiHeader.biHeight = -(size.height as i32);
+            bitmap_info.bmiHeader.biPlanes = 1;
+            bitmap_info.bmiHeader.biBitCount = 32;
+            bitmap_info.bmiHeader.biCompression = BI_RGB;
+
+            // GDI copies the upper 8 bits as they are, and the compositor uses those as the alpha
+            // channel for transparent windows
+            let opaque_pixels: Vec<u32>;
+            let pixels = if self.state.transparent {
+                opaque_pixels =
+                    framebuffer.pixels().iter().map(|pixel| pixel | 0xff00_0000).collect();
+                &opaque_pixels[..]
+            } else {
+                framebuffer.pixels()
+            };
+
+            let hdc = GetDC(self.state.hwnd);
+            StretchDIBits(
+                hdc,
+                0,
+                0,
+                window_size.width as i32,
+                window_size.height as i32,
+                0,
+                0,
+                size.width as i32,
+                size.height as i32,
+                pixels.as_ptr() as *const c_void,
+                &bitmap_info,
+                DIB_RGB_COLORS,
+                SRCCOPY,
+            );
+            ReleaseDC(self.state.hwnd, hdc);
+        }
+    }
+
     pub fn set_mouse_cursor(&mut self, mouse_cursor: MouseCursor) {
         self.state.cursor_icon.set(mouse_cursor);
-        unsafe {
-            let cursor = LoadCursorW(null_mut(), cursor_to_lpcwstr(mouse_cursor));
-            SetCursor(cursor);
+
+        // The new cursor is shown once the cursor moves again, or when the relative drag ends.
+        // While the cursor is outside of the window, which includes being over a child window,
+        // `WM_SETCURSOR` sets the cursor once it enters the window.
+        if !self.state.cursor_auto_hide.is_hidden()
+            && !self.state.relative_drag.is_active()
+            && !*self.state.mouse_was_outside_window.borrow()
+        {
+            unsafe {
+                SetCursor(self.state.load_cursor(mouse_cursor));
+            }
         }
     }
 
+    pub fn set_cursor_auto_hide(&mut self, timeout: Option<Duration>) {
+        if self.state.cursor_auto_hide.set_timeout(timeout)
+            && !*self.state.mouse_was_outside_window.borrow()
+        {
+            unsafe {
+                SetCursor(self.state.load_cursor(self.state.cursor_icon.get()));
+            }
+        }
+    }
+
+    pub fn hwnd(&self) -> *mut c_void {
+        self.state.hwnd as *mut c_void
+    }
+
     #[cfg(feature = "opengl")]
     pub fn gl_context(&self) -> Option<&GlContext> {
-        self.state.gl_context.as_ref()
+        self.state.gl_context.get()
+    }
+
+    #[cfg(feature = "opengl")]
+    pub fn gl_context_error(&self) -> Option<&GlError> {
+        self.state.gl_context_error.as_ref()
+    }
+
+    #[cfg(feature = "opengl")]
+    pub fn create_gl_context(&mut self, config: GlConfig) -> Result<&GlContext, GlError> {
+        if let Some(gl_context) = self.state.gl_context.get() {
+            return Ok(gl_context);
+        }
+
+        let mut handle = Win32WindowHandle::empty();
+        handle.hwnd = self.state.hwnd as *mut c_void;
+        let gl_context = unsafe { GlContext::create(&RawWindowHandle::Win32(handle), config)? };
+
+        Ok(self.state.gl_context.get_or_set(gl_context))
     }
 }
 
@@ -841,6 +2365,50 @@ unsafe impl HasRawDisplayHandle for Window<'_> {
     }
 }
 
-pub fn copy_to_clipboard(_data: &str) {
-    todo!()
+#[cfg(test)]
+mod tests {
+    use winapi::um::winuser::MK_CONTROL;
+
+    use super::*;
+
+    #[test]
+    fn mouse_button_maps_buttons() {
+        assert_eq!(mouse_button(WM_LBUTTONDOWN, 0), Some(MouseButton::Left));
+        assert_eq!(mouse_button(WM_MBUTTONUP, 0), Some(MouseButton::Middle));
+        assert_eq!(mouse_button(WM_RBUTTONDOWN, 0), Some(MouseButton::Right));
+        // The X button is stored in the high word of `wparam`, and the low word contains the
+        // modifier keys
+        let xbutton = |button: u16| ((button as WPARAM) << 16) | MK_CONTROL;
+        assert_eq!(mouse_button(WM_XBUTTONDOWN, xbutton(XBUTTON1)), Some(MouseButton::Back));
+        assert_eq!(mouse_button(WM_XBUTTONUP, xbutton(XBUTTON2)), Some(MouseButton::Forward));
+        assert_eq!(mouse_button(WM_XBUTTONDOWN, xbutton(3)), None);
+    }
+
+    /// A parent window with an embedded child window, where the mouse is over the child.
+    #[test]
+    fn set_cursor_action_keeps_child_cursor() {
+        let parent = 0x10 as HWND;
+        let child = 0x20 as HWND;
+        let lparam = |hit_test: LRESULT| hit_test as LPARAM | ((WM_MOUSEMOVE as LPARAM) << 16);
+
+        // The child gets the message first and sets its own cursor
+        assert_eq!(
+            set_cursor_action(child, child as WPARAM, lparam(HTCLIENT)),
+            SetCursorAction::SetCursor
+        );
+        // The parent receives the same message from `DefWindowProc`, and must not override it
+        assert_eq!(
+            set_cursor_action(parent, child as WPARAM, lparam(HTCLIENT)),
+            SetCursorAction::LetChildSetCursor
+        );
+        // Over the parent itself the parent sets its own cursor, except on its borders
+        assert_eq!(
+            set_cursor_action(parent, parent as WPARAM, lparam(HTCLIENT)),
+            SetCursorAction::SetCursor
+        );
+        assert_eq!(
+            set_cursor_action(parent, parent as WPARAM, lparam(HTLEFT)),
+            SetCursorAction::Default
+        );
+    }
 }
@@ -1,12 +1,17 @@
+use std::any::Any;
+use std::error::Error;
+use std::ffi::c_void;
+use std::fmt;
 use std::marker::PhantomData;
+use std::time::{Duration, Instant};
 
 use raw_window_handle::{
     HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle,
 };
 
-use crate::event::{Event, EventStatus};
-use crate::window_open_options::WindowOpenOptions;
-use crate::{MouseCursor, Size};
+use crate::event::{Event, EventStatus, ModifierSides, PointerKind};
+use crate::window_open_options::{WindowOpenOptions, WindowScalePolicy};
+use crate::{Framebuffer, MouseCursor, Point, Rect, Size, WindowInfo};
 
 #[cfg(target_os = "macos")]
 use crate::macos as platform;
@@ -32,21 +37,286 @@ impl WindowHandle {
     }
 
     /// Returns `true` if the window is still open, and returns `false`
-    /// if the window was closed/dropped.
+    /// if the window was closed/dropped. This includes windows closed by the user, in which case
+    /// this returns `false` as soon as the window handler has received
+    /// [`WindowEvent::WillClose`][crate::WindowEvent::WillClose].
     pub fn is_open(&self) -> bool {
         self.window_handle.is_open()
     }
+
+    /// Process all events that are currently queued for the window right away, instead of
+    /// waiting for the window's event loop to get to them. This is mostly useful in tests and when
+    /// integrating with a host's event loop.
+    ///
+    /// This will call into the window's [`WindowHandler`], so this must not be called from within
+    /// that same window's handler. On macOS this only runs the run loop's pending sources and
+    /// timers, like the window's frame timer, since input events can't be dispatched to a single
+    /// window without also dispatching them to the rest of the application. On Linux all windows
+    /// share a single event loop thread, and this blocks until that thread has processed the
+    /// events.
+    pub fn process_pending_events(&self) {
+        self.window_handle.process_pending_events();
+    }
+
+    /// Move the window above its siblings. For parented windows these are the other child windows
+    /// of the same parent window, so this can be used to control the stacking order when
+    /// embedding multiple windows in a single parent. Standalone windows are moved above the
+    /// application's other windows, although the platform may not move them above other
+    /// applications' windows.
+    pub fn raise(&self) {
+        self.window_handle.raise();
+    }
+
+    /// Move the window below its siblings. See [`WindowHandle::raise`].
+    pub fn lower(&self) {
+        self.window_handle.lower();
+    }
+
+    /// Send a synthetic event to the window's [`WindowHandler::on_event`], the same way as events
+    /// coming from the platform. This can be used to drive a window from automated UI tests. The
+    /// platform itself never sees these events, so for instance a synthetic mouse event doesn't
+    /// move the cursor.
+    ///
+    /// On macOS the event is handled right away unless the window handler is currently handling
+    /// another event. On Windows and Linux the event is handled by the window's event loop, and
+    /// [`WindowHandle::process_pending_events`] can be used to wait for that. Events sent to a
+    /// window that has been closed are dropped.
+    pub fn send_event(&self, event: Event) {
+        self.window_handle.send_event(event);
+    }
+
+    /// Resize the window to the given logical size, the same way as [`Window::resize`]. This lets
+    /// the owner of a parented window resize it from outside of its window handler, for instance
+    /// when the parent window gets resized. The window handler receives a
+    /// [`WindowEvent::Resized`][crate::WindowEvent::Resized] event once the window has been
+    /// resized.
+    pub fn resize(&self, size: Size) {
+        self.window_handle.resize(size);
+    }
+
+    /// Send an arbitrary message to the window's [`WindowHandler::on_message`]. Like with
+    /// [`WindowHandle::send_event`], on Windows and Linux the message is handled by the window's
+    /// event loop. Messages sent to a window that has been closed, and messages that are still
+    /// queued up when the window closes, are dropped.
+    pub fn send_message<M: Any + Send>(&self, message: M) {
+        self.window_handle.send_message(Box::new(message));
+    }
+
+    /// The window's raw window handle, or `None` if the window has been closed. Prefer this over
+    /// [`HasRawWindowHandle::raw_window_handle`], which returns an empty handle (with a null
+    /// window pointer or ID) once the window has been closed instead.
+    pub fn try_raw_window_handle(&self) -> Option<RawWindowHandle> {
+        self.window_handle.try_raw_window_handle()
+    }
 }
 
+/// Returns an empty handle once the window has been closed. Use
+/// [`WindowHandle::try_raw_window_handle`] to detect this.
 unsafe impl HasRawWindowHandle for WindowHandle {
     fn raw_window_handle(&self) -> RawWindowHandle {
         self.window_handle.raw_window_handle()
     }
 }
 
+/// Runs multiple standalone windows at the same time, for instance a main window and a separate
+/// settings window. [`Window::open_blocking`] can be used instead when there's only a single
+/// window.
+pub struct Application {
+    application: platform::Application,
+    // so that Application is !Send on all platforms
+    phantom: PhantomData<*mut ()>,
+}
+
+impl Application {
+    pub fn new() -> Self {
+        Self { application: platform::Application::new(), phantom: PhantomData }
+    }
+
+    /// Open a new standalone window as part of this application. Depending on the platform, the
+    /// window may only start handling events once [`Application::run`] gets called.
+    pub fn open_window<H, B>(&mut self, options: WindowOpenOptions, build: B) -> WindowHandle
+    where
+        H: WindowHandler + 'static,
+        B: FnOnce(&mut Window) -> H,
+        B: Send + 'static,
+    {
+        WindowHandle::new(self.application.open_window::<H, B>(options, build))
+    }
+
+    /// Run the application until all of its windows have been closed.
+    pub fn run(self) {
+        self.application.run();
+    }
+}
+
+impl Default for Application {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub trait WindowHandler {
     fn on_frame(&mut self, window: &mut Window);
     fn on_event(&mut self, window: &mut Window, event: Event) -> EventStatus;
+
+    /// Determine what kind of area the given position (in logical pixels) belongs to. This can be
+    /// used to implement custom title bars and resize handles for standalone windows opened with
+    /// [`WindowOpenOptions::decorations`][crate::WindowOpenOptions::decorations] set to `false`.
+    /// It is only honoured for those windows, and not for parented windows or windows with
+    /// decorations. Areas that are not [`HitTestResult::Client`] don't receive button presses.
+    ///
+    /// On macOS resize edges are treated as client areas.
+    fn hit_test(&self, _position: Point) -> HitTestResult {
+        HitTestResult::Client
+    }
+
+    /// Receive the platform's native messages before baseview handles them. This can be used to
+    /// handle messages baseview doesn't translate into [`Event`]s, for instance for accessibility
+    /// or input method support. Returning `Some` stops baseview from handling the message, and
+    /// returning `None` lets baseview handle it as usual.
+    ///
+    /// This is not called for messages that arrive while the handler is already handling another
+    /// message or event.
+    fn on_raw_message(&mut self, _window: &mut Window, _message: RawMessage) -> Option<RawResult> {
+        None
+    }
+
+    /// Called when the window's scale factor changes, for instance because the window was moved
+    /// to a display with a different pixel density. A
+    /// [`WindowEvent::Resized`][crate::WindowEvent::Resized] event with the new physical size
+    /// follows. This is called when the window uses
+    /// [`WindowScalePolicy::SystemScaleFactor`][crate::WindowScalePolicy::SystemScaleFactor], and
+    /// when the scale factor is changed through [`Window::set_scale_policy`]. On Linux the system
+    /// scale factor does not change while the window is open.
+    ///
+    /// On macOS the OpenGL context's drawable is resized to match the new scale factor before
+    /// this is called.
+    fn on_scale_factor_changed(&mut self, _window: &mut Window, _new_scale: f64) {}
+
+    /// Choose the window's new logical size after the system scale factor changed. This is called
+    /// right after [`on_scale_factor_changed`][Self::on_scale_factor_changed] with the size the
+    /// system suggests for the new scale factor, and the window is resized to the returned size.
+    /// Windows with size constraints or a fixed aspect ratio can clamp the suggested size here, so
+    /// the window doesn't jump to an awkward size when it's dragged to another display.
+    ///
+    /// This is only called on Windows, where the system suggests a new window size when the
+    /// window moves to a display with a different DPI. On macOS the logical size stays the same
+    /// across displays, and on Linux the system scale factor does not change while the window is
+    /// open.
+    fn size_for_scale_factor(&mut self, _window: &mut Window, suggested_size: Size) -> Size {
+        suggested_size
+    }
+
+    /// Handle a message sent with [`WindowHandle::send_message`]. The message can be downcast to
+    /// its original type with [`Box::downcast`].
+    fn on_message(&mut self, _window: &mut Window, _message: Box<dyn Any + Send>) {}
+}
+
+/// A native message received by a window. See [`WindowHandler::on_raw_message`].
+#[derive(Debug, Clone, Copy)]
+pub enum RawMessage {
+    /// A message sent to the window's window procedure.
+    #[cfg(target_os = "windows")]
+    Win32 { hwnd: *mut c_void, msg: u32, wparam: usize, lparam: isize },
+    /// A pointer to an `xcb_generic_event_t` received by the window's connection. For events
+    /// larger than 32 bytes the event's additional data directly follows the struct. The pointer
+    /// is only valid for the duration of the call.
+    #[cfg(target_os = "linux")]
+    Xcb(*const c_void),
+    /// An `NSEvent` for a mouse or keyboard event that was sent to the window's view.
+    #[cfg(target_os = "macos")]
+    AppKit(*mut c_void),
+}
+
+/// The result of handling a [`RawMessage`]. On Windows this is returned from the window
+/// procedure. It is not used on the other platforms.
+pub type RawResult = isize;
+
+/// The result of [`WindowHandler::hit_test`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitTestResult {
+    /// A regular part of the window. Mouse events are sent to the window handler.
+    Client,
+    /// Dragging this area moves the window, like a title bar would.
+    TitleBar,
+    /// Dragging this area resizes the window from the given edge.
+    Resize(ResizeEdge),
+}
+
+/// An edge or corner of a window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResizeEdge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// The reason a window could not be opened. See [`Window::try_open_parented`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WindowError {
+    /// The parent window's handle is not supported on this platform, like a Wayland handle on
+    /// Linux.
+    UnsupportedParent,
+    /// Could not connect to the X server. Contains the underlying error message.
+    ConnectionFailed(String),
+    /// The platform could not create the window, for instance because the system ran out of
+    /// resources. Contains the underlying error message.
+    CreationFailed(String),
+}
+
+impl fmt::Display for WindowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WindowError::UnsupportedParent => write!(f, "unsupported parent window handle"),
+            WindowError::ConnectionFailed(err) => {
+                write!(f, "could not connect to the X server: {}", err)
+            }
+            WindowError::CreationFailed(err) => write!(f, "could not create the window: {}", err),
+        }
+    }
+}
+
+impl Error for WindowError {}
+
+/// How insistently [`Window::request_user_attention`] should get the user's attention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserAttention {
+    /// Keep drawing attention to the window until the user switches to it. On macOS the dock icon
+    /// keeps bouncing, and on Windows the window keeps flashing in the taskbar.
+    Critical,
+    /// Draw attention to the window once. On macOS the dock icon bounces once, and on Windows the
+    /// taskbar button flashes and then stays highlighted until the user switches to the window.
+    Informational,
+}
+
+/// The effect drawn behind a window's contents. See [`Window::set_backdrop`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backdrop {
+    /// No effect. The window's contents are drawn as they are.
+    None,
+    /// Blur whatever is behind the window.
+    Blur,
+    /// The platform's translucent material for window backgrounds. On macOS this blends the
+    /// blurred background with the system's vibrancy tint, and on Windows this uses the Mica
+    /// material. On Linux this is the same as [`Backdrop::Blur`].
+    Vibrancy,
+}
+
+/// How the calls to [`WindowHandler::on_frame`] are scheduled. See [`Window::set_frame_pacing`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FramePacing {
+    /// Call `on_frame` on a timer, using the interval set with [`Window::set_frame_interval`].
+    Timer,
+    /// Call `on_frame` once for every refresh of the display. On macOS this uses a
+    /// `CVDisplayLink`, and on Windows frames are timed by waiting for the desktop compositor's
+    /// next frame. On Linux the timer interval is set to the display's refresh period instead,
+    /// so frames are paced at the right rate but not aligned to vertical blanking.
+    DisplayLink,
 }
 
 pub struct Window<'a> {
@@ -67,6 +337,9 @@ impl<'a> Window<'a> {
         Window { window, phantom: PhantomData }
     }
 
+    /// Open a window embedded in `parent`. This panics if the window could not be opened, see
+    /// [`Window::try_open_parented`] for a version that returns an error instead. This includes
+    /// Wayland parent windows on Linux, which are not supported.
     pub fn open_parented<P, H, B>(parent: &P, options: WindowOpenOptions, build: B) -> WindowHandle
     where
         P: HasRawWindowHandle,
@@ -74,8 +347,24 @@ impl<'a> Window<'a> {
         B: FnOnce(&mut Window) -> H,
         B: Send + 'static,
     {
-        let window_handle = platform::Window::open_parented::<P, H, B>(parent, options, build);
-        WindowHandle::new(window_handle)
+        Self::try_open_parented::<P, H, B>(parent, options, build)
+            .unwrap_or_else(|err| panic!("Could not open the window: {}", err))
+    }
+
+    /// Open a window embedded in `parent`, or return an error if the window could not be opened.
+    /// Plugins should prefer this over [`Window::open_parented`] so a failure to open the editor
+    /// doesn't take down the host. `build` is not called if the window could not be opened.
+    pub fn try_open_parented<P, H, B>(
+        parent: &P, options: WindowOpenOptions, build: B,
+    ) -> Result<WindowHandle, WindowError>
+    where
+        P: HasRawWindowHandle,
+        H: WindowHandler + 'static,
+        B: FnOnce(&mut Window) -> H,
+        B: Send + 'static,
+    {
+        let window_handle = platform::Window::try_open_parented::<P, H, B>(parent, options, build)?;
+        Ok(WindowHandle::new(window_handle))
     }
 
     pub fn open_blocking<H, B>(options: WindowOpenOptions, build: B)
@@ -87,21 +376,329 @@ impl<'a> Window<'a> {
         platform::Window::open_blocking::<H, B>(options, build)
     }
 
-    /// Close the window
+    /// Open a popup window, like a tooltip, a dropdown, or a context menu, that floats on top of
+    /// this window. `position` is the position of the popup's top left corner in logical
+    /// coordinates relative to this window's top left corner. The popup may extend past this
+    /// window's bounds. The popup does not have any decorations, so
+    /// [`WindowOpenOptions::decorations`] is ignored.
+    ///
+    /// The popup closes automatically when the user clicks anywhere outside of it, and when this
+    /// window closes. On macOS the popup becomes the key window so it can receive keyboard input,
+    /// and it also closes when it stops being the key window. On Windows and Linux popups don't
+    /// receive keyboard input, which keeps going to this window instead.
+    pub fn open_popup<H, B>(
+        &mut self, options: WindowOpenOptions, position: Point, build: B,
+    ) -> WindowHandle
+    where
+        H: WindowHandler + 'static,
+        B: FnOnce(&mut Window) -> H,
+        B: Send + 'static,
+    {
+        let window_handle = self.window.open_popup::<H, B>(options, position, build);
+        WindowHandle::new(window_handle)
+    }
+
+    /// Close the window. When this is called from within the window handler, the window is closed
+    /// once the handler returns.
     pub fn close(&mut self) {
         self.window.close();
     }
 
+    /// The window's current size and scale factor. This is the same information that was last
+    /// sent to the window handler in a [`WindowEvent::Resized`][crate::WindowEvent::Resized]
+    /// event, and it can be queried at any time. Right after calling [`Window::resize`] this may
+    /// still return the old size, until the platform has actually resized the window.
+    pub fn size(&self) -> WindowInfo {
+        self.window.window_info()
+    }
+
+    /// The position of the top left corner of the window's contents in logical screen
+    /// coordinates. Unlike [`WindowEvent::Moved`][crate::WindowEvent::Moved] this also works for
+    /// parented windows, and it can already be queried from the `build` closure passed to the
+    /// functions that open a window. Returns `None` if the position can't be determined, for
+    /// instance on macOS when a parented view has not been added to a window yet.
+    pub fn position(&self) -> Option<Point> {
+        self.window.position()
+    }
+
     /// Resize the window to the given size. The size is always in logical pixels. DPI scaling will
     /// automatically be accounted for.
+    ///
+    /// When this is called from within the window handler, the window is only resized once the
+    /// handler returns, so the handler is never called again while it's still running. Resizing
+    /// may also happen asynchronously on some platforms. Once the window has been resized, the
+    /// window handler receives a [`WindowEvent::Resized`][crate::WindowEvent::Resized] event with
+    /// the window's new size, which may differ from the requested size if the host or the window
+    /// manager constrains it. No event is sent if the window already had the requested size.
     pub fn resize(&mut self, size: Size) {
         self.window.resize(size);
     }
 
+    /// Change the title of a standalone window. Like with [`Window::resize`], the title is changed
+    /// once the window handler returns. This has no effect on parented windows.
+    pub fn set_title(&mut self, title: &str) {
+        self.window.set_title(title);
+    }
+
+    /// Allow or disallow the user to resize a standalone window. Standalone windows are resizable
+    /// by default on Windows and Linux, and they are not resizable by default on macOS. The
+    /// window keeps its current size when this is changed, and [`Window::resize`] can still be
+    /// used on windows that are not resizable. This has no effect on parented windows. On Windows,
+    /// standalone windows without decorations can only be resized through
+    /// [`WindowHandler::hit_test`], so this has no effect on those windows either.
+    pub fn set_resizable(&mut self, resizable: bool) {
+        self.window.set_resizable(resizable);
+    }
+
+    /// Show or hide the window. This can be used together with
+    /// [`WindowOpenOptions::visible`] to only show the window once it's ready to be drawn. Hidden
+    /// windows keep running, and they can be shown again later. The window handler receives a
+    /// [`WindowEvent::Shown`][crate::WindowEvent::Shown] or
+    /// [`WindowEvent::Hidden`][crate::WindowEvent::Hidden] event once the window's visibility has
+    /// changed. Nothing happens if the window is already shown or hidden.
+    pub fn set_visible(&mut self, visible: bool) {
+        self.window.set_visible(visible);
+    }
+
+    /// Show the window. Same as `set_visible(true)`, see [`Window::set_visible`].
+    pub fn show(&mut self) {
+        self.set_visible(true);
+    }
+
+    /// Hide the window. Same as `set_visible(false)`, see [`Window::set_visible`].
+    pub fn hide(&mut self) {
+        self.set_visible(false);
+    }
+
+    /// Restrict the part of the window that receives mouse input to the given rectangles, in
+    /// logical coordinates. Mouse events outside of these rectangles go to the window below the
+    /// window instead. Passing `None` makes the entire window receive mouse input again. This can
+    /// be used for non-rectangular and partially transparent windows.
+    ///
+    /// On Windows this also clips the window's visible contents to the region. On macOS the
+    /// events outside of the region are passed on to the parent view, so for standalone windows
+    /// those events are discarded instead.
+    pub fn set_input_region(&mut self, region: Option<&[Rect]>) {
+        self.window.set_input_region(region);
+    }
+
+    /// Query the current position of the mouse cursor relative to the window, in logical pixels.
+    /// Returns `None` if the cursor is outside of the window.
+    pub fn cursor_position(&self) -> Option<Point> {
+        self.window.cursor_position()
+    }
+
+    /// The time at which the most recent mouse or keyboard event was generated, as reported by
+    /// the platform. While handling such an event, this is that event's timestamp, which can be
+    /// more accurate than [`Instant::now`] for velocity-based gestures and for detecting double
+    /// clicks. Returns `None` if the window has not received any input events yet.
+    ///
+    /// This is only updated for native mouse and keyboard events. While handling any other event,
+    /// like a [`WindowEvent`][crate::WindowEvent], an event that baseview generates on its own, or
+    /// an event that was forwarded to the window from elsewhere, this still returns the timestamp
+    /// of the last native input event.
+    pub fn event_timestamp(&self) -> Option<Instant> {
+        self.window.event_timestamp()
+    }
+
+    /// The left and right modifier keys that were held down during the most recent mouse or
+    /// keyboard event. While handling such an event, these are the same keys as the event's
+    /// side-agnostic [`Modifiers`][keyboard_types::Modifiers]. Pressing or releasing a modifier
+    /// key is already reflected in its own keyboard event.
+    ///
+    /// On Linux the sides are tracked from key events, so modifier keys that were pressed while
+    /// the window did not have keyboard focus are not included.
+    pub fn modifier_sides(&self) -> ModifierSides {
+        self.window.modifier_sides()
+    }
+
+    /// The kind of device that generated the most recent mouse event. While handling such an
+    /// event, this is the device that generated that event. This is [`PointerKind::Mouse`] if the
+    /// window has not received any mouse events yet, or if the platform can't tell what kind of
+    /// device was used.
+    ///
+    /// On Windows touchpads can't be told apart from mice. On macOS scrolling with a Magic Mouse
+    /// is reported as a touchpad. On Linux this requires XInput 2, and the kind of device is
+    /// partly guessed from the device's name.
+    pub fn pointer_kind(&self) -> PointerKind {
+        self.window.pointer_kind()
+    }
+
+    /// Change how often [`WindowHandler::on_frame`] gets called. This defaults to 15 milliseconds.
+    /// Increasing the interval while the window is in the background can significantly reduce
+    /// the window's CPU usage. On macOS intervals shorter than a millisecond are rounded up to a
+    /// millisecond.
+    pub fn set_frame_interval(&mut self, interval: Duration) {
+        self.window.set_frame_interval(interval);
+    }
+
+    /// Change how calls to [`WindowHandler::on_frame`] are scheduled. This defaults to
+    /// [`FramePacing::Timer`]. With [`FramePacing::DisplayLink`] frames follow the display's
+    /// refresh rate, and the frame interval is ignored until the pacing is set back to
+    /// [`FramePacing::Timer`]. If the platform can't follow the display, for instance because the
+    /// refresh rate can't be determined, the frame timer keeps being used.
+    pub fn set_frame_pacing(&mut self, pacing: FramePacing) {
+        self.window.set_frame_pacing(pacing);
+    }
+
+    /// Draw a frame right away instead of waiting for the next frame interval. Since the window
+    /// handler is still running when this is called, [`WindowHandler::on_frame`] is called as
+    /// soon as the current callback returns, before any other events are handled. This does not
+    /// affect the regular frame cadence.
+    ///
+    /// This can be used to export the window's current state, for instance by reading back the
+    /// frame with [`GlContext::capture`][crate::gl::GlContext::capture] before swapping buffers,
+    /// or with [`Framebuffer::to_rgba`] when drawing to a framebuffer.
+    pub fn render_now(&mut self) {
+        self.window.render_now();
+    }
+
+    /// The refresh rate in Hz of the display the window is currently on, if it can be determined.
+    /// This can be used to decide on a present mode or on how often to render, since
+    /// [`WindowHandler::on_frame`] is not synchronized to the display.
+    pub fn refresh_rate(&self) -> Option<f64> {
+        self.window.refresh_rate()
+    }
+
+    /// Create a [`Framebuffer`] with the same size as the window. This can be used to draw to the
+    /// window in software, without having to use a graphics API.
+    pub fn create_framebuffer(&self) -> Framebuffer {
+        Framebuffer::new(self.window.physical_size())
+    }
+
+    /// Draw the framebuffer's contents to the window. The framebuffer should have the same size
+    /// as the window in physical pixels. If it does not, then how the contents are positioned
+    /// and scaled depends on the platform.
+    pub fn present(&mut self, framebuffer: &Framebuffer) {
+        self.window.present(framebuffer);
+    }
+
+    /// Set the cursor shown while the mouse is over the window. Every window keeps its own
+    /// cursor, so when a window is embedded in another window, the embedded window shows its own
+    /// cursor while the mouse is over it, and the outer window's cursor is shown again once the
+    /// mouse leaves the embedded window. Windows start out with [`MouseCursor::Default`] instead
+    /// of inheriting their parent's cursor.
     pub fn set_mouse_cursor(&mut self, cursor: MouseCursor) {
         self.window.set_mouse_cursor(cursor);
     }
 
+    /// Hide the mouse cursor once it hasn't moved over the window for the given amount of time,
+    /// and show it again as soon as it moves. This is useful for media players and other
+    /// applications that go fullscreen. Passing `None` disables this again. The idle time is
+    /// checked every frame, so the cursor may be hidden up to one frame interval later than
+    /// requested. On macOS this hides the cursor for the entire application until it moves.
+    pub fn set_cursor_auto_hide(&mut self, timeout: Option<Duration>) {
+        self.window.set_cursor_auto_hide(timeout);
+    }
+
+    /// The scaling policy this window was opened with, or the one last set with
+    /// [`Window::set_scale_policy`]. The scale factor that is currently in effect can be queried
+    /// through the [`WindowInfo`][crate::WindowInfo] passed along with
+    /// [`WindowEvent::Resized`][crate::WindowEvent::Resized].
+    pub fn scale_policy(&self) -> WindowScalePolicy {
+        self.window.scale_policy()
+    }
+
+    /// Change the window's scaling policy, for instance to implement a UI scale setting. The
+    /// window keeps its logical size, so its physical size changes along with the scale factor.
+    /// If the scale factor changed, [`WindowHandler::on_scale_factor_changed`] is called and a
+    /// [`WindowEvent::Resized`][crate::WindowEvent::Resized] event is sent once the window handler
+    /// returns. Switching back to
+    /// [`WindowScalePolicy::SystemScaleFactor`][crate::WindowScalePolicy::SystemScaleFactor] uses
+    /// the system's current scale factor.
+    ///
+    /// On macOS the view's size is measured in points, so changing the scale factor only changes
+    /// the size of the OpenGL surface.
+    pub fn set_scale_policy(&mut self, policy: WindowScalePolicy) {
+        self.window.set_scale_policy(policy);
+    }
+
+    /// Shorthand for calling [`Window::set_scale_policy`] with
+    /// [`WindowScalePolicy::ScaleFactor`][crate::WindowScalePolicy::ScaleFactor].
+    pub fn set_scale_factor(&mut self, scale: f64) {
+        self.set_scale_policy(WindowScalePolicy::ScaleFactor(scale));
+    }
+
+    /// Capture the mouse so the window keeps receiving mouse events, even when the cursor leaves
+    /// the window, until the capture is released again.
+    ///
+    /// While any mouse button is held down, the window already receives all mouse events on
+    /// every platform. On Windows baseview captures the mouse on button presses, on Linux the X
+    /// server implicitly grabs the pointer for the duration of the press, and on macOS the view
+    /// keeps receiving drag events. So a drag that leaves the window keeps producing
+    /// [`MouseEvent::CursorMoved`][crate::MouseEvent::CursorMoved] events without calling this
+    /// function. Capturing the mouse without any buttons being held down is not supported on
+    /// macOS, and this function does nothing there. To keep the mouse captured until a specific
+    /// button is released, return [`EventStatus::CaptureMouse`][crate::EventStatus::CaptureMouse]
+    /// from its [`MouseEvent::ButtonPressed`][crate::MouseEvent::ButtonPressed] event instead.
+    pub fn set_mouse_capture(&mut self, capture: bool) {
+        self.window.set_mouse_capture(capture);
+    }
+
+    /// Keep the cursor inside of `rect`, in logical coordinates relative to the window's top left
+    /// corner, or release the cursor again when this is `None`. The cursor is released
+    /// automatically when the window loses the keyboard focus, so the user can't get trapped.
+    ///
+    /// On Windows the cursor is clipped to the rectangle. On macOS and Linux the cursor is moved
+    /// back into the rectangle whenever the mouse moves it outside, so it may briefly be seen
+    /// outside of it.
+    pub fn confine_cursor(&mut self, rect: Option<Rect>) {
+        self.window.confine_cursor(rect);
+    }
+
+    /// Start a relative drag, as used for knobs and sliders. The cursor is hidden and kept in
+    /// place while the mouse is captured, and mouse movement is reported as
+    /// [`MouseEvent::RawMotion`][crate::MouseEvent::RawMotion] events instead of
+    /// [`MouseEvent::CursorMoved`][crate::MouseEvent::CursorMoved]. This means the drag isn't
+    /// limited by the edges of the screen. [`Window::end_relative_drag`] moves the cursor back to
+    /// `origin` and shows it again.
+    ///
+    /// This should be called while handling a
+    /// [`MouseEvent::ButtonPressed`][crate::MouseEvent::ButtonPressed] event, with `origin` being
+    /// the cursor's current position in logical pixels. On Linux and Windows the cursor is moved
+    /// back to the origin after every motion event, and on macOS the cursor is detached from the
+    /// mouse for the duration of the drag.
+    pub fn begin_relative_drag(&mut self, origin: Point) {
+        self.window.begin_relative_drag(origin);
+    }
+
+    /// End the drag started with [`Window::begin_relative_drag`], typically when the mouse button
+    /// is released. This moves the cursor back to the drag's origin, shows it again, and releases
+    /// the mouse capture. Does nothing if there is no drag.
+    pub fn end_relative_drag(&mut self) {
+        self.window.end_relative_drag();
+    }
+
+    /// Let the user move the window by dragging it, as if they had pressed the mouse on the
+    /// window's title bar. This can be used to implement custom title bars, and it should be
+    /// called while handling a [`MouseEvent::ButtonPressed`][crate::MouseEvent::ButtonPressed]
+    /// event for the left mouse button. The window may not receive the matching button release
+    /// event. [`WindowHandler::hit_test`] can be used instead when the title bar's location is
+    /// known ahead of time.
+    ///
+    /// This does nothing for parented windows.
+    pub fn begin_move_drag(&mut self) {
+        self.window.begin_move_drag();
+    }
+
+    /// Start moving the window with the mouse. Same as [`Window::begin_move_drag`]. This is
+    /// mainly useful for standalone windows opened without
+    /// [`WindowOpenOptions::decorations`], which don't have a title bar the user can drag.
+    pub fn drag_window(&mut self) {
+        self.begin_move_drag();
+    }
+
+    /// Let the user resize the window by dragging the given edge, as if they had pressed the mouse
+    /// on that edge of the window's frame. This should be called while handling a
+    /// [`MouseEvent::ButtonPressed`][crate::MouseEvent::ButtonPressed] event for the left mouse
+    /// button, just like [`Window::begin_move_drag`].
+    ///
+    /// This does nothing for parented windows. Resizing a window from an arbitrary point is not
+    /// supported on macOS, so this does nothing there either.
+    pub fn begin_resize_drag(&mut self, edge: ResizeEdge) {
+        self.window.begin_resize_drag(edge);
+    }
+
     pub fn has_focus(&mut self) -> bool {
         self.window.has_focus()
     }
@@ -110,12 +707,121 @@ impl<'a> Window<'a> {
         self.window.focus()
     }
 
+    /// Draw the user's attention to the window when it's in the background, for instance when a
+    /// long running task has finished. This does nothing if the window is already focused.
+    ///
+    /// On Windows, parented windows flash their top level window instead. On macOS this bounces
+    /// the application's dock icon, which for parented windows is the host's icon. On Linux this
+    /// has no effect on parented windows, and how the window is highlighted depends on the window
+    /// manager.
+    pub fn request_user_attention(&mut self, kind: UserAttention) {
+        self.window.request_user_attention(kind);
+    }
+
+    /// Draw a blurred or translucent backdrop behind a standalone window. The backdrop only shows
+    /// through the parts of the window that are drawn transparently, so the window's contents
+    /// need to leave those parts clear. This is best-effort, and it has no effect on parented
+    /// windows or on systems that don't support the requested backdrop.
+    ///
+    /// On macOS this places an `NSVisualEffectView` behind the view's contents. On Windows this
+    /// uses the system backdrop materials, which require Windows 11 version 22H2 or later. On
+    /// Linux this sets the `_KDE_NET_WM_BLUR_BEHIND_REGION` property, which is only honored by
+    /// compositors that support it, like KWin.
+    pub fn set_backdrop(&mut self, backdrop: Backdrop) {
+        self.window.set_backdrop(backdrop);
+    }
+
+    /// Send the requests made through this window to the display server right away.
+    ///
+    /// On Linux the requests made while handling an event or drawing a frame are buffered, and
+    /// they're sent all at once when baseview is done handling the events and frames that are
+    /// due. This only needs to be called when the changes should take effect before the window
+    /// handler returns, for instance before doing some long running work. On the other platforms
+    /// changes are applied right away, and this does nothing.
+    pub fn flush(&mut self) {
+        self.window.flush();
+    }
+
+    /// Whether a window manager is running. Without one, standalone windows on Linux don't get
+    /// a title bar or borders, so the user can't close them and
+    /// [`WindowEvent::CloseRequested`][crate::WindowEvent::CloseRequested] is never sent.
+    /// Requests that go through the window manager, like [`Window::request_user_attention`] and
+    /// [`Window::begin_move_drag`], have no effect either. Applications can use this to offer
+    /// their own controls instead.
+    ///
+    /// On Linux this asks the X server every time it's called, so it also notices window
+    /// managers that are started or that exit while the window is open. This always returns
+    /// `true` on Windows and macOS.
+    pub fn has_window_manager(&self) -> bool {
+        self.window.has_window_manager()
+    }
+
+    /// The window's underlying `NSView`, for embedding native controls as subviews.
+    ///
+    /// This is an advanced API. The pointer is only valid for as long as the window is open, and
+    /// baseview relies on the view's delegate, tracking areas, and instance variables staying
+    /// untouched.
+    #[cfg(target_os = "macos")]
+    pub fn ns_view(&self) -> *mut c_void {
+        self.window.ns_view()
+    }
+
+    /// The window's underlying `HWND`, for embedding native controls as child windows.
+    ///
+    /// This is an advanced API. The handle is only valid for as long as the window is open, and
+    /// baseview relies on the window procedure and `GWLP_USERDATA` staying untouched.
+    #[cfg(target_os = "windows")]
+    pub fn hwnd(&self) -> *mut c_void {
+        self.window.hwnd()
+    }
+
+    /// The window's X11 window ID, for embedding native controls as child windows.
+    ///
+    /// This is an advanced API. The ID is only valid for as long as the window is open.
+    #[cfg(target_os = "linux")]
+    pub fn x11_window_id(&self) -> u32 {
+        self.window.x11_window_id()
+    }
+
     /// If provided, then an OpenGL context will be created for this window. You'll be able to
     /// access this context through [crate::Window::gl_context].
+    ///
+    /// This returns `None` if the context could not be created, for instance because the
+    /// requested OpenGL version is not supported. The window is still opened in that case, and
+    /// [`Window::gl_context_error`] returns the reason.
     #[cfg(feature = "opengl")]
     pub fn gl_context(&self) -> Option<&crate::gl::GlContext> {
         self.window.gl_context()
     }
+
+    /// Why the OpenGL context requested through
+    /// [`WindowOpenOptions::gl_config`][crate::WindowOpenOptions::gl_config] could not be created.
+    /// The window handler can use this to fall back to software rendering, or to reopen the window
+    /// with a lower OpenGL version. Returns `None` if the context was created or if no context was requested.
+    #[cfg(feature = "opengl")]
+    pub fn gl_context_error(&self) -> Option<&crate::gl::GlError> {
+        self.window.gl_context_error()
+    }
+
+    /// Create an OpenGL context for a window that was opened without one, for instance after
+    /// deciding at runtime whether to use OpenGL or software rendering. If the window already has
+    /// an OpenGL context then that context is returned instead. Like the context requested
+    /// through [`WindowOpenOptions::gl_config`][crate::WindowOpenOptions::gl_config], the context
+    /// can afterwards be accessed through [`Window::gl_context`].
+    ///
+    /// On Linux the window's visual is chosen when it's opened, and it can't be changed later.
+    /// Windows opened without a `gl_config` use a 32-bit visual when available, which is
+    /// compatible with most configs that use 8 bits per color channel, including the default
+    /// [`GlConfig`][crate::gl::GlConfig]. When the config can't be used with the window's visual
+    /// this returns [`GlError::CreationFailed`][crate::gl::GlError::CreationFailed], and opening
+    /// the window with a `gl_config` is the only option. On Windows a window's pixel format can
+    /// only be set once, so this can only succeed once per window.
+    #[cfg(feature = "opengl")]
+    pub fn create_gl_context(
+        &mut self, config: crate::gl::GlConfig,
+    ) -> Result<&crate::gl::GlContext, crate::gl::GlError> {
+        self.window.create_gl_context(config)
+    }
 }
 
 unsafe impl<'a> HasRawWindowHandle for Window<'a> {
@@ -1,4 +1,17 @@
+use std::ops::{Add, AddAssign, Div, Mul, Sub, SubAssign};
+
 /// The info about the window
+///
+/// A window's size is available both in logical pixels and in physical pixels. Logical sizes are
+/// what layouts and mouse positions are expressed in, and they don't depend on the display's
+/// pixel density. Physical sizes are the actual number of pixels in the window's backing store,
+/// which is what the size of a framebuffer, GL viewport, or swapchain should be set to. The
+/// physical size is the logical size multiplied by [`scale`][Self::scale], rounded to the nearest
+/// integer. So on a Retina display with a scale factor of 2.0, a window with a logical size of
+/// 400x300 has a physical size of 800x600.
+///
+/// Every [`WindowEvent::Resized`][crate::WindowEvent::Resized] event carries a `WindowInfo` whose
+/// physical size matches the size of the window's drawable area.
 #[derive(Debug, Copy, Clone)]
 pub struct WindowInfo {
     logical_size: Size,
@@ -8,6 +21,8 @@ pub struct WindowInfo {
 }
 
 impl WindowInfo {
+    /// Create the info for a window with the given logical size. The physical size is computed
+    /// by multiplying the logical size by `scale` and rounding the result.
     pub fn from_logical_size(logical_size: Size, scale: f64) -> Self {
         let scale_recip = if scale == 1.0 { 1.0 } else { 1.0 / scale };
 
@@ -19,28 +34,31 @@ impl WindowInfo {
         Self { logical_size, physical_size, scale, scale_recip }
     }
 
+    /// Create the info for a window with the given physical size. The logical size is computed by
+    /// dividing the physical size by `scale`.
     pub fn from_physical_size(physical_size: PhySize, scale: f64) -> Self {
         let scale_recip = if scale == 1.0 { 1.0 } else { 1.0 / scale };
 
         let logical_size = Size {
-            width: f64::from(physical_size.width) * scale_recip,
-            height: f64::from(physical_size.height) * scale_recip,
+            width: f64::from(physical_size.width) / scale,
+            height: f64::from(physical_size.height) / scale,
         };
 
         Self { logical_size, physical_size, scale, scale_recip }
     }
 
-    /// The logical size of the window
+    /// The logical size of the window, in logical pixels
     pub fn logical_size(&self) -> Size {
         self.logical_size
     }
 
-    /// The physical size of the window
+    /// The physical size of the window, in physical pixels. Use this to size framebuffers and
+    /// graphics surfaces.
     pub fn physical_size(&self) -> PhySize {
         self.physical_size
     }
 
-    /// The scale factor of the window
+    /// The scale factor of the window, or the number of physical pixels per logical pixel
     pub fn scale(&self) -> f64 {
         self.scale
     }
@@ -49,9 +67,28 @@ impl WindowInfo {
     pub fn scale_recip(&self) -> f64 {
         self.scale_recip
     }
+
+    /// The window's logical DPI, for instance for font hinting. This is the scale factor relative
+    /// to the 96 DPI that Windows and X11 consider to be unscaled, so a scale factor of 1.5 is
+    /// 144 DPI. With the system scale factor this is the DPI reported by `GetDpiForWindow()` on
+    /// Windows and `Xft.dpi` on Linux. On macOS, where a scale factor of 2.0 means a Retina
+    /// display, this is 192. This is not the display's physical pixel density.
+    pub fn dpi(&self) -> f64 {
+        // The platforms' DPI values are whole numbers, and rounding undoes any precision lost by
+        // dividing them by 96 to get the scale factor. Forced fractional scale factors may not
+        // map to a whole number.
+        let dpi = self.scale * 96.0;
+        if (dpi - dpi.round()).abs() < 1e-9 {
+            dpi.round()
+        } else {
+            dpi
+        }
+    }
 }
 
-/// A point in logical coordinates
+/// A point in logical coordinates. Logical coordinates are physical coordinates divided by the
+/// window's scale factor, so they can have a fractional part. For instance, with a scale factor of
+/// 2.0 the physical position (101, 50) is the logical position (50.5, 25.0).
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Point {
     pub x: f64,
@@ -67,10 +104,13 @@ impl Point {
     /// Convert to actual physical coordinates
     #[inline]
     pub fn to_physical(&self, window_info: &WindowInfo) -> PhyPoint {
-        PhyPoint {
-            x: (self.x * window_info.scale()).round() as i32,
-            y: (self.y * window_info.scale()).round() as i32,
-        }
+        self.to_physical_with_scale(window_info.scale())
+    }
+
+    /// Convert to actual physical coordinates using the given scale factor
+    #[inline]
+    pub fn to_physical_with_scale(&self, scale: f64) -> PhyPoint {
+        PhyPoint { x: (self.x * scale).round() as i32, y: (self.y * scale).round() as i32 }
     }
 }
 
@@ -87,14 +127,21 @@ impl PhyPoint {
         Self { x, y }
     }
 
-    /// Convert to logical coordinates
+    /// Convert to logical coordinates. This divides by the scale factor without rounding, so the
+    /// result may have a fractional part.
     #[inline]
     pub fn to_logical(&self, window_info: &WindowInfo) -> Point {
         Point {
-            x: f64::from(self.x) * window_info.scale_recip(),
-            y: f64::from(self.y) * window_info.scale_recip(),
+            x: f64::from(self.x) / window_info.scale(),
+            y: f64::from(self.y) / window_info.scale(),
         }
     }
+
+    /// Convert to logical coordinates using the given scale factor
+    #[inline]
+    pub fn to_logical_with_scale(&self, scale: f64) -> Point {
+        Point { x: f64::from(self.x) / scale, y: f64::from(self.y) / scale }
+    }
 }
 
 /// A size in logical coordinates
@@ -113,9 +160,16 @@ impl Size {
     /// Convert to actual physical size
     #[inline]
     pub fn to_physical(&self, window_info: &WindowInfo) -> PhySize {
+        self.to_physical_with_scale(window_info.scale())
+    }
+
+    /// Convert to actual physical size using the given scale factor. This rounds the same way
+    /// [`WindowInfo::from_logical_size`] does.
+    #[inline]
+    pub fn to_physical_with_scale(&self, scale: f64) -> PhySize {
         PhySize {
-            width: (self.width * window_info.scale()).round() as u32,
-            height: (self.height * window_info.scale()).round() as u32,
+            width: (self.width * scale).round() as u32,
+            height: (self.height * scale).round() as u32,
         }
     }
 }
@@ -137,8 +191,277 @@ impl PhySize {
     #[inline]
     pub fn to_logical(&self, window_info: &WindowInfo) -> Size {
         Size {
-            width: f64::from(self.width) * window_info.scale_recip(),
-            height: f64::from(self.height) * window_info.scale_recip(),
+            width: f64::from(self.width) / window_info.scale(),
+            height: f64::from(self.height) / window_info.scale(),
         }
     }
+
+    /// Convert to logical size using the given scale factor
+    #[inline]
+    pub fn to_logical_with_scale(&self, scale: f64) -> Size {
+        Size { width: f64::from(self.width) / scale, height: f64::from(self.height) / scale }
+    }
+}
+
+/// A rectangle in logical coordinates
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Rect {
+    /// Create a new rectangle in logical coordinates
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// Convert to actual physical coordinates. The resulting rectangle covers every physical
+    /// pixel that is at least partially covered by this rectangle.
+    #[inline]
+    pub fn to_physical(&self, window_info: &WindowInfo) -> PhyRect {
+        let scale = window_info.scale();
+        let x = (self.x * scale).floor();
+        let y = (self.y * scale).floor();
+
+        PhyRect {
+            x: x as i32,
+            y: y as i32,
+            width: (((self.x + self.width) * scale).ceil() - x) as u32,
+            height: (((self.y + self.height) * scale).ceil() - y) as u32,
+        }
+    }
+
+    /// Whether the point lies within this rectangle
+    #[inline]
+    pub fn contains(&self, point: Point) -> bool {
+        point.x >= self.x
+            && point.y >= self.y
+            && point.x < self.x + self.width
+            && point.y < self.y + self.height
+    }
+}
+
+/// A rectangle in actual physical coordinates
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PhyRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl PhyRect {
+    /// Create a new rectangle in actual physical coordinates
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// The smallest rectangle that contains both this rectangle and `other`
+    pub fn union(&self, other: &PhyRect) -> PhyRect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width as i32).max(other.x + other.width as i32);
+        let bottom = (self.y + self.height as i32).max(other.y + other.height as i32);
+
+        PhyRect { x, y, width: (right - x) as u32, height: (bottom - y) as u32 }
+    }
+
+    /// Convert to logical coordinates
+    #[inline]
+    pub fn to_logical(&self, window_info: &WindowInfo) -> Rect {
+        Rect {
+            x: f64::from(self.x) / window_info.scale(),
+            y: f64::from(self.y) / window_info.scale(),
+            width: f64::from(self.width) / window_info.scale(),
+            height: f64::from(self.height) / window_info.scale(),
+        }
+    }
+}
+
+macro_rules! impl_vector_ops {
+    ($type:ident { $($field:ident),+ }) => {
+        impl Add for $type {
+            type Output = Self;
+
+            #[inline]
+            fn add(self, other: Self) -> Self {
+                Self { $($field: self.$field + other.$field),+ }
+            }
+        }
+
+        impl Sub for $type {
+            type Output = Self;
+
+            #[inline]
+            fn sub(self, other: Self) -> Self {
+                Self { $($field: self.$field - other.$field),+ }
+            }
+        }
+
+        impl AddAssign for $type {
+            #[inline]
+            fn add_assign(&mut self, other: Self) {
+                *self = *self + other;
+            }
+        }
+
+        impl SubAssign for $type {
+            #[inline]
+            fn sub_assign(&mut self, other: Self) {
+                *self = *self - other;
+            }
+        }
+    };
+}
+
+macro_rules! impl_scalar_ops {
+    ($type:ident { $($field:ident),+ }) => {
+        impl Mul<f64> for $type {
+            type Output = Self;
+
+            #[inline]
+            fn mul(self, factor: f64) -> Self {
+                Self { $($field: self.$field * factor),+ }
+            }
+        }
+
+        impl Div<f64> for $type {
+            type Output = Self;
+
+            #[inline]
+            fn div(self, divisor: f64) -> Self {
+                Self { $($field: self.$field / divisor),+ }
+            }
+        }
+    };
+}
+
+impl_vector_ops!(Point { x, y });
+impl_vector_ops!(Size { width, height });
+impl_vector_ops!(PhyPoint { x, y });
+impl_scalar_ops!(Point { x, y });
+impl_scalar_ops!(Size { width, height });
+
+/// Offsets a point by a size
+impl Add<Size> for Point {
+    type Output = Point;
+
+    #[inline]
+    fn add(self, size: Size) -> Point {
+        Point { x: self.x + size.width, y: self.y + size.height }
+    }
+}
+
+impl From<(f64, f64)> for Point {
+    fn from((x, y): (f64, f64)) -> Self {
+        Self { x, y }
+    }
+}
+
+impl From<(i32, i32)> for PhyPoint {
+    fn from((x, y): (i32, i32)) -> Self {
+        Self { x, y }
+    }
+}
+
+impl From<(f64, f64)> for Size {
+    fn from((width, height): (f64, f64)) -> Self {
+        Self { width, height }
+    }
+}
+
+impl From<(u32, u32)> for PhySize {
+    fn from((width, height): (u32, u32)) -> Self {
+        Self { width, height }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn physical_points_keep_their_fractional_logical_position() {
+        let window_info = WindowInfo::from_logical_size(Size::new(400.0, 300.0), 2.0);
+        assert_eq!(PhyPoint::new(101, 50).to_logical(&window_info), Point::new(50.5, 25.0));
+
+        let window_info = WindowInfo::from_logical_size(Size::new(400.0, 300.0), 1.5);
+        assert_eq!(PhyPoint::new(3, 4).to_logical(&window_info), Point::new(2.0, 4.0 / 1.5));
+    }
+
+    #[test]
+    fn fractional_scales_round_trip() {
+        for &scale in &[1.25, 1.5, 1.75, 2.25] {
+            let window_info = WindowInfo::from_logical_size(Size::new(400.0, 300.0), scale);
+            for x in 0..64 {
+                for y in 0..4 {
+                    let physical = PhyPoint::new(x, y);
+                    let logical = physical.to_logical(&window_info);
+                    assert_eq!(logical.to_physical(&window_info), physical, "scale {}", scale);
+                    assert_eq!(physical.to_logical_with_scale(scale), logical);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn window_info_physical_size_matches_the_drawable_size() {
+        // X11 and Windows report the drawable's size in physical pixels. On macOS the drawable's
+        // size is the view's size in points multiplied by the scale factor and rounded.
+        for &scale in &[1.0, 1.25, 1.5, 2.0, 3.0] {
+            for &(width, height) in &[(1, 1), (333, 250), (1001, 777), (1920, 1080)] {
+                let drawable = PhySize::new(width, height);
+                let from_physical = WindowInfo::from_physical_size(drawable, scale);
+                assert_eq!(from_physical.physical_size(), drawable);
+                assert_eq!(from_physical.logical_size().to_physical_with_scale(scale), drawable);
+
+                let points = Size::new(width as f64 / 2.0, height as f64 / 3.0);
+                let from_logical = WindowInfo::from_logical_size(points, scale);
+                assert_eq!(from_logical.physical_size(), points.to_physical_with_scale(scale));
+                assert_eq!(from_logical.logical_size(), points);
+            }
+        }
+    }
+
+    #[test]
+    fn point_and_size_arithmetic() {
+        let point = Point::new(10.0, 20.0);
+        let size = Size::new(4.0, 8.0);
+
+        assert_eq!(point + Point::new(1.0, 2.0), Point::new(11.0, 22.0));
+        assert_eq!(point - Point::new(1.0, 2.0), Point::new(9.0, 18.0));
+        assert_eq!(point * 2.0, Point::new(20.0, 40.0));
+        assert_eq!(point / 2.0, Point::new(5.0, 10.0));
+        assert_eq!(point + size, Point::new(14.0, 28.0));
+        assert_eq!(size - Size::new(1.0, 1.0), Size::new(3.0, 7.0));
+        assert_eq!(size * 0.5, Size::new(2.0, 4.0));
+        assert_eq!(PhyPoint::new(3, 4) - PhyPoint::new(5, 1), PhyPoint::new(-2, 3));
+
+        let mut point = point;
+        point += Point::new(1.0, 1.0);
+        point -= Point::new(0.5, 0.5);
+        assert_eq!(point, Point::new(10.5, 20.5));
+    }
+
+    #[test]
+    fn conversions_agree_with_window_info() {
+        let scale = 1.5;
+        let window_info = WindowInfo::from_logical_size(Size::new(400.0, 300.0), scale);
+
+        let point = Point::new(10.5, 7.25);
+        assert_eq!(point.to_physical(&window_info), point.to_physical_with_scale(scale));
+        let size = Size::new(100.5, 33.3);
+        assert_eq!(size.to_physical(&window_info), size.to_physical_with_scale(scale));
+        let phy_size = PhySize::new(151, 50);
+        assert_eq!(phy_size.to_logical(&window_info), phy_size.to_logical_with_scale(scale));
+        assert_eq!(window_info.logical_size().to_physical(&window_info), PhySize::new(600, 450));
+
+        assert_eq!(Point::from((1.0, 2.0)), Point::new(1.0, 2.0));
+        assert_eq!(PhyPoint::from((1, 2)), PhyPoint::new(1, 2));
+        assert_eq!(Size::from((1.0, 2.0)), Size::new(1.0, 2.0));
+        assert_eq!(PhySize::from((1, 2)), PhySize::new(1, 2));
+    }
 }
@@ -1,18 +1,51 @@
-use crate::Size;
+use crate::{Point, Size};
 
 /// The dpi scaling policy of the window
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum WindowScalePolicy {
     /// Use the system's dpi scale factor
     SystemScaleFactor,
-    /// Use the given dpi scale factor (e.g. `1.0` = 96 dpi)
+    /// Use the given dpi scale factor (e.g. `1.0` = 96 dpi). On macOS the window and its OpenGL
+    /// surface will also be rendered at this scale, so `ScaleFactor(1.0)` opts out of Retina
+    /// scaling.
     ScaleFactor(f64),
 }
 
+/// An opaque color in the sRGB color space
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+}
+
 /// The options for opening a new window
+///
+/// Use [`WindowOpenOptions::default()`] to only set the options that matter to the application:
+///
+/// ```
+/// let options = baseview::WindowOpenOptions {
+///     title: "My window".into(),
+///     size: baseview::Size::new(512.0, 512.0),
+///     ..Default::default()
+/// };
+/// ```
 pub struct WindowOpenOptions {
     pub title: String,
 
+    /// An identifier for the application, which Linux desktops use to group the application's
+    /// windows in the taskbar and to match them with the application's `.desktop` file. This
+    /// should be the same as the `.desktop` file's `StartupWMClass`, or its name without the
+    /// extension. On Linux this sets the `WM_CLASS` property of standalone windows and popups.
+    /// This has no effect on the other platforms.
+    pub app_id: Option<String>,
+
     /// The logical size of the window.
     ///
     /// These dimensions will be scaled by the scaling policy specified in `scale`. Mouse
@@ -22,8 +55,110 @@ pub struct WindowOpenOptions {
     /// The dpi scaling policy
     pub scale: WindowScalePolicy,
 
+    /// Whether the window should be made DPI aware on Windows, so it's drawn at the display's
+    /// actual resolution instead of being stretched by the system. For standalone windows this
+    /// sets the DPI awareness of the entire process. For parented windows and popups only the
+    /// window itself is made DPI aware, so a host's own windows are not affected. Disable this
+    /// when the application or the host manages DPI awareness itself, for instance through its
+    /// manifest. This has no effect on the other platforms. Defaults to `true`.
+    pub set_dpi_awareness: bool,
+
+    /// Where a standalone window should be opened, as the position of the top left corner of the
+    /// window's contents in logical screen coordinates. This is the same position that
+    /// [`crate::WindowEvent::Moved`] reports, so it can be used to restore a saved position. If
+    /// this is `None`, the platform decides where the window is placed. This has no effect on
+    /// parented windows.
+    ///
+    /// Screen coordinates span all monitors, so this can also place a window on another monitor.
+    /// Baseview cannot enumerate monitors yet, so there is no way to open a window on a specific
+    /// monitor without knowing where that monitor is.
+    pub position: Option<Point>,
+
+    /// Whether the window should be shown as soon as it has been created. When this is set to
+    /// `false`, the window stays hidden until [`crate::Window::set_visible`] is called. Defaults to
+    /// `true`.
+    pub visible: bool,
+
+    /// If set, opening or showing a standalone window does not take the keyboard focus away from
+    /// the window that currently has it. This is useful for tool palettes and meters. The window
+    /// still receives mouse input. On Windows and Linux clicking the window does not give it the
+    /// keyboard focus either. This has no effect on parented windows.
+    pub no_focus_on_open: bool,
+
+    /// Whether a standalone window should have the platform's title bar and borders. Windows
+    /// without decorations can implement their own title bar and resize handles through
+    /// [`crate::WindowHandler::hit_test`]. This has no effect on parented windows. Defaults to
+    /// `true`.
+    pub decorations: bool,
+
+    /// If set, the window will keep this `(width, height)` aspect ratio while the user resizes
+    /// it. This only affects standalone windows, and it does not apply to [`crate::Window::resize`].
+    pub keep_aspect_ratio: Option<(u32, u32)>,
+
+    /// If set, the user can only resize the window in steps of this logical size, for instance to
+    /// keep a grid of cells filled. The steps are counted from the window's current size, so the
+    /// window's size always differs from the size it was opened with or last resized to through
+    /// [`crate::Window::resize`] by a whole number of increments. This only affects standalone
+    /// windows.
+    pub resize_increments: Option<Size>,
+
+    /// If set, consecutive [`crate::MouseEvent::CursorMoved`] events that arrive at the same time
+    /// are combined, and only the latest cursor position is sent to the window handler. This can
+    /// reduce the number of events considerably when using mice with high polling rates.
+    pub coalesce_mouse_moves: bool,
+
+    /// If set, the window is filled with this color until the window handler draws its first
+    /// frame. This avoids a flash of black or of garbage while a renderer is still being set up.
+    /// On macOS this only applies to standalone windows.
+    pub background_color: Option<Color>,
+
+    /// Whether the window's OpenGL output is blended with whatever is behind the window using its
+    /// alpha channel. This requires a `gl_config` with `alpha_bits` greater than zero, and the
+    /// alpha channel is expected to be premultiplied. The window starts out fully transparent, so
+    /// `background_color` is ignored. Only OpenGL output is blended, and
+    /// [`Framebuffer`][crate::Framebuffer]s presented with
+    /// [`Window::present`][crate::Window::present] are always drawn opaque.
+    ///
+    /// On X11 this needs a compositing window manager and a 32-bit visual. On Windows this uses
+    /// the desktop compositor's blur-behind mode with an empty blur region, which requires desktop
+    /// composition to be enabled.
+    pub transparent: bool,
+
+    /// Whether the window should accept files that are dragged onto it. When this is disabled,
+    /// the window does not register itself as a drop target, so drags over the window are left
+    /// to the platform or to the parent window, and the window handler does not receive any
+    /// drag and drop events. Defaults to `true`.
+    pub drag_n_drop: bool,
+
     /// If provided, then an OpenGL context will be created for this window. You'll be able to
     /// access this context through [crate::Window::gl_context].
     #[cfg(feature = "opengl")]
     pub gl_config: Option<crate::gl::GlConfig>,
 }
+
+impl Default for WindowOpenOptions {
+    /// A visible 640x480 window with decorations and drag and drop support that uses the system's
+    /// scale factor. All other options are disabled.
+    fn default() -> Self {
+        Self {
+            title: String::new(),
+            app_id: None,
+            size: Size::new(640.0, 480.0),
+            scale: WindowScalePolicy::SystemScaleFactor,
+            set_dpi_awareness: true,
+            position: None,
+            visible: true,
+            no_focus_on_open: false,
+            decorations: true,
+            keep_aspect_ratio: None,
+            resize_increments: None,
+            coalesce_mouse_moves: false,
+            background_color: None,
+            transparent: false,
+            drag_n_drop: true,
+
+            #[cfg(feature = "opengl")]
+            gl_config: None,
+        }
+    }
+}
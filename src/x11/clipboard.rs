@@ -0,0 +1,409 @@
+//! X11 doesn't store the clipboard's contents anywhere. Instead, the owner of the `CLIPBOARD`
+//! selection sends the contents to other applications when they ask for it. Every call here uses
+//! its own connection and an invisible window, and setting the clipboard's contents spawns a
+//! thread that serves those contents until another application takes over the clipboard.
+
+use std::error::Error;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ChangeWindowAttributesAux, ConnectionExt as _, CreateWindowAux, EventMask,
+    PropMode, Property, PropertyNotifyEvent, SelectionNotifyEvent, SelectionRequestEvent, Window,
+    WindowClass, SELECTION_NOTIFY_EVENT,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::{COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT, CURRENT_TIME, NONE};
+
+use crate::clipboard::{decode_bmp, encode_bmp, encode_png};
+use crate::ClipboardImage;
+
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        CLIPBOARD,
+        TARGETS,
+        INCR,
+        UTF8_STRING,
+        TEXT_PLAIN_UTF8: b"text/plain;charset=utf-8",
+        IMAGE_PNG: b"image/png",
+        IMAGE_BMP: b"image/bmp",
+        BASEVIEW_CLIPBOARD,
+    }
+}
+
+/// The clipboard's contents converted to a target format, along with that target.
+type TargetData = (Atom, Vec<u8>);
+
+/// How long to wait for the clipboard's owner to send the clipboard's contents.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The size of a `ChangeProperty` request without its data.
+const CHANGE_PROPERTY_HEADER_SIZE: usize = 24;
+
+/// Contents that are too large for a single request are sent in chunks using the `INCR`
+/// protocol. The requestor deletes the property after reading every chunk, and the next chunk is
+/// written to the property in response. An empty chunk ends the transfer.
+struct IncrTransfer {
+    requestor: Window,
+    property: Atom,
+    /// The index of the contents being sent in the list of supported targets.
+    contents_index: usize,
+    /// How many bytes have been sent so far.
+    offset: usize,
+}
+
+struct ClipboardWindow {
+    conn: RustConnection,
+    window: Window,
+    atoms: Atoms,
+}
+
+impl ClipboardWindow {
+    fn new() -> Result<Self, Box<dyn Error>> {
+        let (conn, screen) = x11rb::connect(None)?;
+        let atoms = Atoms::new(&conn)?;
+
+        let window = conn.generate_id()?;
+        let root = conn.setup().roots[screen].root;
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_ONLY,
+            COPY_FROM_PARENT,
+            // Property changes are needed to receive contents that are sent in chunks
+            &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+        )?;
+
+        let atoms = atoms.reply()?;
+
+        Ok(Self { conn, window, atoms })
+    }
+
+    /// Ask the clipboard's owner to convert the clipboard's contents to the first of `targets`
+    /// it supports.
+    fn get(&self, targets: &[Atom]) -> Result<Option<TargetData>, Box<dyn Error>> {
+        for &target in targets {
+            self.conn.convert_selection(
+                self.window,
+                self.atoms.CLIPBOARD,
+                target,
+                self.atoms.BASEVIEW_CLIPBOARD,
+                CURRENT_TIME,
+            )?;
+            self.conn.flush()?;
+
+            let Some(property) = self.wait_for_selection_notify()? else { continue };
+
+            let reply = self
+                .conn
+                .get_property(true, self.window, property, AtomEnum::ANY, 0, u32::MAX)?
+                .reply()?;
+            if reply.type_ == self.atoms.INCR {
+                // Deleting the property above asked the owner to start sending the chunks
+                self.conn.flush()?;
+                return Ok(self.receive_incr(property)?.map(|data| (target, data)));
+            }
+
+            return Ok(Some((target, reply.value)));
+        }
+
+        Ok(None)
+    }
+
+    /// Receive contents that are sent in chunks using the `INCR` protocol. Every chunk is written
+    /// to `property`, and deleting the property asks the owner for the next chunk. Returns `None`
+    /// if the owner stops responding.
+    fn receive_incr(&self, property: Atom) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        let mut data = Vec::new();
+        loop {
+            if !self.wait_for_new_value(property)? {
+                return Ok(None);
+            }
+
+            let reply = self
+                .conn
+                .get_property(true, self.window, property, AtomEnum::ANY, 0, u32::MAX)?
+                .reply()?;
+            self.conn.flush()?;
+
+            // An empty chunk ends the transfer
+            if reply.value.is_empty() {
+                return Ok(Some(data));
+            }
+            data.extend_from_slice(&reply.value);
+        }
+    }
+
+    /// Wait for the clipboard's owner to write the next chunk of an `INCR` transfer to
+    /// `property`. Returns `false` if that doesn't happen in time.
+    fn wait_for_new_value(&self, property: Atom) -> Result<bool, Box<dyn Error>> {
+        let deadline = Instant::now() + TIMEOUT;
+        while Instant::now() < deadline {
+            match self.conn.poll_for_event()? {
+                Some(Event::PropertyNotify(event))
+                    if event.window == self.window
+                        && event.atom == property
+                        && event.state == Property::NEW_VALUE =>
+                {
+                    return Ok(true);
+                }
+                Some(_) => {}
+                None => thread::sleep(Duration::from_millis(5)),
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Wait for the clipboard's owner to respond to a conversion request. Returns the property
+    /// the contents were stored in, or `None` if the conversion failed.
+    fn wait_for_selection_notify(&self) -> Result<Option<Atom>, Box<dyn Error>> {
+        let deadline = Instant::now() + TIMEOUT;
+        while Instant::now() < deadline {
+            match self.conn.poll_for_event()? {
+                Some(Event::SelectionNotify(event)) if event.requestor == self.window => {
+                    return Ok(if event.property == NONE { None } else { Some(event.property) });
+                }
+                Some(_) => {}
+                None => thread::sleep(Duration::from_millis(5)),
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Take over the clipboard, and serve `contents` until another application takes over the
+    /// clipboard. `contents` contains the clipboard's contents for every supported target.
+    /// `ready` is signaled once this window owns the clipboard.
+    fn serve(
+        &self, contents: &[TargetData], ready: mpsc::SyncSender<()>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.conn.set_selection_owner(self.window, self.atoms.CLIPBOARD, CURRENT_TIME)?;
+        let owner = self.conn.get_selection_owner(self.atoms.CLIPBOARD)?.reply()?.owner;
+        if owner != self.window {
+            return Ok(());
+        }
+
+        let _ = ready.send(());
+
+        // Transfers that are still in progress are finished after losing the clipboard
+        let mut owns_clipboard = true;
+        let mut transfers = Vec::new();
+        while owns_clipboard || !transfers.is_empty() {
+            match self.conn.wait_for_event()? {
+                Event::SelectionRequest(request) if owns_clipboard => {
+                    self.handle_request(&request, contents, &mut transfers)?
+                }
+                Event::PropertyNotify(event) => {
+                    self.continue_transfer(&event, contents, &mut transfers)?
+                }
+                // The requestor won't read the rest of the contents anymore
+                Event::DestroyNotify(event) => {
+                    transfers.retain(|transfer| transfer.requestor != event.window)
+                }
+                Event::SelectionClear(_) => owns_clipboard = false,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The largest amount of data that can be written to a property in a single request.
+    fn max_chunk_size(&self) -> usize {
+        self.conn.maximum_request_bytes() - CHANGE_PROPERTY_HEADER_SIZE
+    }
+
+    fn handle_request(
+        &self, request: &SelectionRequestEvent, contents: &[TargetData],
+        transfers: &mut Vec<IncrTransfer>,
+    ) -> Result<(), Box<dyn Error>> {
+        // Obsolete clients don't specify a property
+        let property = if request.property == NONE { request.target } else { request.property };
+
+        let converted = if request.target == self.atoms.TARGETS {
+            let targets: Vec<Atom> = std::iter::once(self.atoms.TARGETS)
+                .chain(contents.iter().map(|(target, _)| *target))
+                .collect();
+            self.conn.change_property32(
+                PropMode::REPLACE,
+                request.requestor,
+                property,
+                AtomEnum::ATOM,
+                &targets,
+            )?;
+
+            true
+        } else if let Some(contents_index) =
+            contents.iter().position(|(target, _)| *target == request.target)
+        {
+            let (target, data) = &contents[contents_index];
+            if data.len() > self.max_chunk_size() {
+                // The requestor's property changes are needed to know when to send the next chunk
+                self.conn.change_window_attributes(
+                    request.requestor,
+                    &ChangeWindowAttributesAux::new()
+                        .event_mask(EventMask::PROPERTY_CHANGE | EventMask::STRUCTURE_NOTIFY),
+                )?;
+                self.conn.change_property32(
+                    PropMode::REPLACE,
+                    request.requestor,
+                    property,
+                    self.atoms.INCR,
+                    &[data.len() as u32],
+                )?;
+
+                transfers.push(IncrTransfer {
+                    requestor: request.requestor,
+                    property,
+                    contents_index,
+                    offset: 0,
+                });
+            } else {
+                self.conn.change_property8(
+                    PropMode::REPLACE,
+                    request.requestor,
+                    property,
+                    *target,
+                    data,
+                )?;
+            }
+
+            true
+        } else {
+            false
+        };
+
+        self.conn.send_event(
+            false,
+            request.requestor,
+            EventMask::NO_EVENT,
+            SelectionNotifyEvent {
+                response_type: SELECTION_NOTIFY_EVENT,
+                sequence: 0,
+                time: request.time,
+                requestor: request.requestor,
+                selection: request.selection,
+                target: request.target,
+                property: if converted { property } else { NONE },
+            },
+        )?;
+        self.conn.flush()?;
+
+        Ok(())
+    }
+
+    /// Send the next chunk of an `INCR` transfer once the requestor has deleted the previous one.
+    fn continue_transfer(
+        &self, event: &PropertyNotifyEvent, contents: &[TargetData],
+        transfers: &mut Vec<IncrTransfer>,
+    ) -> Result<(), Box<dyn Error>> {
+        if event.state != Property::DELETE {
+            return Ok(());
+        }
+        let Some(index) = transfers.iter().position(|transfer| {
+            transfer.requestor == event.window && transfer.property == event.atom
+        }) else {
+            return Ok(());
+        };
+
+        let transfer = &mut transfers[index];
+        let (target, data) = &contents[transfer.contents_index];
+        let chunk_end = (transfer.offset + self.max_chunk_size()).min(data.len());
+        let chunk = &data[transfer.offset..chunk_end];
+        self.conn.change_property8(
+            PropMode::REPLACE,
+            transfer.requestor,
+            transfer.property,
+            *target,
+            chunk,
+        )?;
+        transfer.offset = chunk_end;
+
+        if chunk.is_empty() {
+            let transfer = transfers.swap_remove(index);
+            // This fails if the requestor's window is already gone, which doesn't matter here
+            self.conn.change_window_attributes(
+                transfer.requestor,
+                &ChangeWindowAttributesAux::new().event_mask(EventMask::NO_EVENT),
+            )?;
+        }
+        self.conn.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Spawn a thread that owns the clipboard and serves the contents returned by `contents`. This
+/// returns once the thread has taken over the clipboard.
+fn set_contents<F>(contents: F)
+where
+    F: FnOnce(&Atoms) -> Vec<TargetData> + Send + 'static,
+{
+    let (ready_sender, ready_receiver) = mpsc::sync_channel(1);
+    thread::spawn(move || {
+        let Ok(clipboard_window) = ClipboardWindow::new() else { return };
+        let contents = contents(&clipboard_window.atoms);
+
+        if let Err(err) = clipboard_window.serve(&contents, ready_sender) {
+            log_warn!("Could not serve the clipboard's contents: {}", err);
+        }
+    });
+
+    // This fails if the clipboard could not be taken over
+    let _ = ready_receiver.recv();
+}
+
+pub fn set_text(text: &str) {
+    // `STRING` is encoded as Latin-1, so it's only offered when the text can be represented that way
+    let latin1: Option<Vec<u8>> =
+        text.chars().map(|c| (u32::from(c) <= 0xff).then(|| c as u8)).collect();
+    let text = text.as_bytes().to_vec();
+    set_contents(move |atoms| {
+        let mut contents = vec![(atoms.UTF8_STRING, text.clone()), (atoms.TEXT_PLAIN_UTF8, text)];
+        if let Some(latin1) = latin1 {
+            contents.push((AtomEnum::STRING.into(), latin1));
+        }
+
+        contents
+    });
+}
+
+pub fn get_text() -> Option<String> {
+    let clipboard_window = ClipboardWindow::new().ok()?;
+    let atoms = &clipboard_window.atoms;
+
+    let targets = [atoms.UTF8_STRING, atoms.TEXT_PLAIN_UTF8, AtomEnum::STRING.into()];
+    let (target, data) = clipboard_window.get(&targets).ok()??;
+    if target == u32::from(AtomEnum::STRING) {
+        // `STRING` is encoded as Latin-1
+        Some(data.iter().map(|&byte| byte as char).collect())
+    } else {
+        String::from_utf8(data).ok()
+    }
+}
+
+pub fn set_image(rgba: &[u8], width: u32, height: u32) {
+    let png = encode_png(rgba, width, height);
+    let bmp = encode_bmp(rgba, width, height);
+    set_contents(move |atoms| vec![(atoms.IMAGE_PNG, png), (atoms.IMAGE_BMP, bmp)]);
+}
+
+pub fn get_image() -> Option<ClipboardImage> {
+    let clipboard_window = ClipboardWindow::new().ok()?;
+
+    // Decoding PNG images is not supported, but most applications also offer images as BMPs
+    let (_, data) = clipboard_window.get(&[clipboard_window.atoms.IMAGE_BMP]).ok()??;
+
+    decode_bmp(&data)
+}
@@ -18,6 +18,31 @@ fn create_empty_cursor(conn: &XCBConnection, screen: usize) -> Result<Cursor, Bo
     Ok(cursor_id)
 }
 
+/// Create one of the cursors from the legacy X11 core cursor font, like `XCreateFontCursor()`
+/// does. These are available on every X server, regardless of the cursor theme.
+fn create_font_cursor(conn: &XCBConnection, glyph: u16) -> Result<Cursor, Box<dyn Error>> {
+    let cursor_id = conn.generate_id()?;
+    let font_id = conn.generate_id()?;
+    conn.open_font(font_id, b"cursor")?;
+    // The glyph after each cursor is its mask
+    conn.create_glyph_cursor(
+        cursor_id,
+        font_id,
+        font_id,
+        glyph,
+        glyph + 1,
+        0,
+        0,
+        0,
+        u16::MAX,
+        u16::MAX,
+        u16::MAX,
+    )?;
+    conn.close_font(font_id)?;
+
+    Ok(cursor_id)
+}
+
 fn load_cursor(
     conn: &XCBConnection, cursor_handle: &CursorHandle, name: &str,
 ) -> Result<Option<Cursor>, Box<dyn Error>> {
@@ -42,59 +67,159 @@ fn load_first_existing_cursor(
     Ok(None)
 }
 
+/// The names to look up in the cursor theme, in order of preference. The freedesktop (CSS) names
+/// come first, followed by the legacy names older themes use.
+fn theme_names(cursor: MouseCursor) -> &'static [&'static str] {
+    match cursor {
+        MouseCursor::Default => &["default", "left_ptr"],
+
+        MouseCursor::Hand => &["pointer", "hand2", "hand1"],
+        MouseCursor::HandGrabbing => &["grabbing", "closedhand", "fleur"],
+        MouseCursor::Help => &["help", "question_arrow"],
+
+        MouseCursor::Hidden => &[],
+
+        MouseCursor::Text => &["text", "xterm"],
+        MouseCursor::VerticalText => &["vertical-text", "text", "xterm"],
+
+        MouseCursor::Working => &["wait", "watch"],
+        MouseCursor::PtrWorking => &["progress", "left_ptr_watch", "watch"],
+
+        MouseCursor::NotAllowed => &["not-allowed", "crossed_circle"],
+        MouseCursor::PtrNotAllowed => &["no-drop", "dnd-no-drop", "not-allowed", "crossed_circle"],
+
+        MouseCursor::ZoomIn => &["zoom-in"],
+        MouseCursor::ZoomOut => &["zoom-out"],
+
+        MouseCursor::Alias => &["alias", "dnd-link", "link"],
+        MouseCursor::Copy => &["copy", "dnd-copy"],
+        MouseCursor::Move => &["move", "dnd-move", "fleur"],
+        MouseCursor::AllScroll => &["all-scroll", "fleur"],
+        MouseCursor::Cell => &["cell", "plus"],
+        MouseCursor::Crosshair => &["crosshair", "cross"],
+
+        MouseCursor::EResize => &["e-resize", "right_side"],
+        MouseCursor::NResize => &["n-resize", "top_side"],
+        MouseCursor::NeResize => &["ne-resize", "top_right_corner"],
+        MouseCursor::NwResize => &["nw-resize", "top_left_corner"],
+        MouseCursor::SResize => &["s-resize", "bottom_side"],
+        MouseCursor::SeResize => &["se-resize", "bottom_right_corner"],
+        MouseCursor::SwResize => &["sw-resize", "bottom_left_corner"],
+        MouseCursor::WResize => &["w-resize", "left_side"],
+        MouseCursor::EwResize => &["ew-resize", "h_double_arrow", "sb_h_double_arrow"],
+        MouseCursor::NsResize => &["ns-resize", "v_double_arrow", "sb_v_double_arrow"],
+        MouseCursor::NwseResize => &["nwse-resize", "bd_double_arrow", "size_bdiag"],
+        MouseCursor::NeswResize => &["nesw-resize", "fd_double_arrow", "size_fdiag"],
+        MouseCursor::ColResize => &["col-resize", "split_h", "h_double_arrow"],
+        MouseCursor::RowResize => &["row-resize", "split_v", "v_double_arrow"],
+    }
+}
+
+/// The closest matching glyph from the core cursor font (see `X11/cursorfont.h`), used when the
+/// cursor theme doesn't contain any of the names from [`theme_names()`].
+fn font_glyph(cursor: MouseCursor) -> u16 {
+    const XC_X_CURSOR: u16 = 0;
+    const XC_BOTTOM_LEFT_CORNER: u16 = 12;
+    const XC_BOTTOM_RIGHT_CORNER: u16 = 14;
+    const XC_BOTTOM_SIDE: u16 = 16;
+    const XC_CROSSHAIR: u16 = 34;
+    const XC_FLEUR: u16 = 52;
+    const XC_HAND2: u16 = 60;
+    const XC_LEFT_PTR: u16 = 68;
+    const XC_LEFT_SIDE: u16 = 70;
+    const XC_PLUS: u16 = 90;
+    const XC_QUESTION_ARROW: u16 = 92;
+    const XC_RIGHT_SIDE: u16 = 96;
+    const XC_SB_H_DOUBLE_ARROW: u16 = 108;
+    const XC_SB_V_DOUBLE_ARROW: u16 = 116;
+    const XC_SIZING: u16 = 120;
+    const XC_TOP_LEFT_CORNER: u16 = 134;
+    const XC_TOP_RIGHT_CORNER: u16 = 136;
+    const XC_TOP_SIDE: u16 = 138;
+    const XC_WATCH: u16 = 150;
+    const XC_XTERM: u16 = 152;
+
+    match cursor {
+        MouseCursor::Default | MouseCursor::Hidden => XC_LEFT_PTR,
+
+        MouseCursor::Hand => XC_HAND2,
+        MouseCursor::HandGrabbing => XC_FLEUR,
+        MouseCursor::Help => XC_QUESTION_ARROW,
+
+        MouseCursor::Text | MouseCursor::VerticalText => XC_XTERM,
+
+        MouseCursor::Working | MouseCursor::PtrWorking => XC_WATCH,
+
+        MouseCursor::NotAllowed | MouseCursor::PtrNotAllowed => XC_X_CURSOR,
+
+        MouseCursor::ZoomIn | MouseCursor::ZoomOut => XC_PLUS,
+
+        MouseCursor::Alias | MouseCursor::Copy => XC_LEFT_PTR,
+        MouseCursor::Move | MouseCursor::AllScroll => XC_FLEUR,
+        MouseCursor::Cell => XC_PLUS,
+        MouseCursor::Crosshair => XC_CROSSHAIR,
+
+        MouseCursor::EResize => XC_RIGHT_SIDE,
+        MouseCursor::NResize => XC_TOP_SIDE,
+        MouseCursor::NeResize => XC_TOP_RIGHT_CORNER,
+        MouseCursor::NwResize => XC_TOP_LEFT_CORNER,
+        MouseCursor::SResize => XC_BOTTOM_SIDE,
+        MouseCursor::SeResize => XC_BOTTOM_RIGHT_CORNER,
+        MouseCursor::SwResize => XC_BOTTOM_LEFT_CORNER,
+        MouseCursor::WResize => XC_LEFT_SIDE,
+        MouseCursor::EwResize | MouseCursor::ColResize => XC_SB_H_DOUBLE_ARROW,
+        MouseCursor::NsResize | MouseCursor::RowResize => XC_SB_V_DOUBLE_ARROW,
+        MouseCursor::NwseResize | MouseCursor::NeswResize => XC_SIZING,
+    }
+}
+
+/// Load the cursor for `cursor`. This first tries the names from the current cursor theme, and it
+/// falls back to the core cursor font so every cursor resolves to something visible.
 pub(super) fn get_xcursor(
     conn: &XCBConnection, screen: usize, cursor_handle: &CursorHandle, cursor: MouseCursor,
 ) -> Result<Cursor, Box<dyn Error>> {
-    let load = |name: &str| load_cursor(conn, cursor_handle, name);
-    let loadn = |names: &[&str]| load_first_existing_cursor(conn, cursor_handle, names);
-
-    let cursor = match cursor {
-        MouseCursor::Default => None, // catch this in the fallback case below
-
-        MouseCursor::Hand => loadn(&["hand2", "hand1"])?,
-        MouseCursor::HandGrabbing => loadn(&["closedhand", "grabbing"])?,
-        MouseCursor::Help => load("question_arrow")?,
-
-        MouseCursor::Hidden => Some(create_empty_cursor(conn, screen)?),
-
-        MouseCursor::Text => loadn(&["text", "xterm"])?,
-        MouseCursor::VerticalText => load("vertical-text")?,
-
-        MouseCursor::Working => load("watch")?,
-        MouseCursor::PtrWorking => load("left_ptr_watch")?,
-
-        MouseCursor::NotAllowed => load("crossed_circle")?,
-        MouseCursor::PtrNotAllowed => loadn(&["no-drop", "crossed_circle"])?,
-
-        MouseCursor::ZoomIn => load("zoom-in")?,
-        MouseCursor::ZoomOut => load("zoom-out")?,
-
-        MouseCursor::Alias => load("link")?,
-        MouseCursor::Copy => load("copy")?,
-        MouseCursor::Move => load("move")?,
-        MouseCursor::AllScroll => load("all-scroll")?,
-        MouseCursor::Cell => load("plus")?,
-        MouseCursor::Crosshair => load("crosshair")?,
-
-        MouseCursor::EResize => load("right_side")?,
-        MouseCursor::NResize => load("top_side")?,
-        MouseCursor::NeResize => load("top_right_corner")?,
-        MouseCursor::NwResize => load("top_left_corner")?,
-        MouseCursor::SResize => load("bottom_side")?,
-        MouseCursor::SeResize => load("bottom_right_corner")?,
-        MouseCursor::SwResize => load("bottom_left_corner")?,
-        MouseCursor::WResize => load("left_side")?,
-        MouseCursor::EwResize => load("h_double_arrow")?,
-        MouseCursor::NsResize => load("v_double_arrow")?,
-        MouseCursor::NwseResize => loadn(&["bd_double_arrow", "size_bdiag"])?,
-        MouseCursor::NeswResize => loadn(&["fd_double_arrow", "size_fdiag"])?,
-        MouseCursor::ColResize => loadn(&["split_h", "h_double_arrow"])?,
-        MouseCursor::RowResize => loadn(&["split_v", "v_double_arrow"])?,
-    };
-
-    if let Some(cursor) = cursor {
-        Ok(cursor)
-    } else {
-        Ok(load("left_ptr")?.unwrap_or(x11rb::NONE))
+    if cursor == MouseCursor::Hidden {
+        return create_empty_cursor(conn, screen);
+    }
+
+    match load_first_existing_cursor(conn, cursor_handle, theme_names(cursor))? {
+        Some(cursor) => Ok(cursor),
+        None => create_font_cursor(conn, font_glyph(cursor)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_visible_cursor_has_a_fallback() {
+        for cursor in MouseCursor::ALL {
+            if cursor == MouseCursor::Hidden {
+                continue;
+            }
+
+            assert!(!theme_names(cursor).is_empty(), "{:?} has no theme names", cursor);
+
+            // Every cursor font glyph is followed by its mask, and the font has 154 glyphs
+            let glyph = font_glyph(cursor);
+            assert!(glyph % 2 == 0 && glyph < 154, "{:?} uses invalid glyph {}", cursor, glyph);
+        }
+    }
+
+    #[test]
+    fn freedesktop_names_come_first() {
+        let table = [
+            (MouseCursor::HandGrabbing, "grabbing"),
+            (MouseCursor::AllScroll, "all-scroll"),
+            (MouseCursor::NotAllowed, "not-allowed"),
+            (MouseCursor::PtrNotAllowed, "no-drop"),
+            (MouseCursor::ColResize, "col-resize"),
+            (MouseCursor::RowResize, "row-resize"),
+        ];
+
+        for (cursor, name) in table {
+            assert_eq!(theme_names(cursor).first(), Some(&name), "{:?}", cursor);
+        }
     }
 }
@@ -0,0 +1,618 @@
+//! Support for receiving files through the XDND drag and drop protocol. See
+//! <https://www.freedesktop.org/wiki/Specifications/XDND/> for the specification.
+//!
+//! The data being dragged is only known after it has been requested from the source, so the
+//! `DragEntered` event is sent once that data has arrived, instead of when the drag first enters
+//! the window.
+
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fmt;
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+
+use keyboard_types::Modifiers;
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ClientMessageEvent, ConnectionExt as _, EventMask, SelectionNotifyEvent,
+    Timestamp, Window as XWindow,
+};
+use x11rb::{CURRENT_TIME, NONE};
+
+use crate::x11::keyboard::key_mods;
+use crate::x11::{Window, WindowInner};
+use crate::{DropData, DropEffect, Event, EventStatus, MouseEvent, PhyPoint, Point, WindowHandler};
+
+/// The newest version of the protocol that's supported.
+pub(super) const XDND_VERSION: u32 = 5;
+
+/// Drops with more files than this are sent as [`DropData::UnresolvedFiles`], since
+/// canonicalizing every path would block the event loop for too long.
+const MAX_RESOLVED_FILES: usize = 64;
+
+/// The largest `text/uri-list` that will be read from the source, in bytes. Larger drops are
+/// rejected so a misbehaving source can't make us allocate an arbitrary amount of memory.
+const MAX_DATA_SIZE: u32 = 16 * 1024 * 1024;
+
+pub(super) enum DragNDropState {
+    /// No drag operation is in progress.
+    NoCurrentSession,
+    /// The source doesn't offer any data that can be handled, so every position message is
+    /// rejected until the drag leaves the window.
+    PermanentlyRejected { source: XWindow, version: u8 },
+    /// The source offers files, but they haven't been requested yet. They're requested once the
+    /// first position message arrives.
+    Entered { source: XWindow, version: u8 },
+    /// The files have been requested from the source. The reply to the last position message is
+    /// sent once they arrive.
+    WaitingForData { source: XWindow, version: u8, position: Point, modifiers: Modifiers },
+    /// The files have arrived and `DragEntered` has been sent to the handler.
+    Ready { source: XWindow, version: u8, data: DropData, effect: Option<DropEffect> },
+}
+
+impl DragNDropState {
+    /// The source window and the protocol version of the current drag operation.
+    fn session(&self) -> Option<(XWindow, u8)> {
+        match *self {
+            DragNDropState::NoCurrentSession => None,
+            DragNDropState::PermanentlyRejected { source, version }
+            | DragNDropState::Entered { source, version }
+            | DragNDropState::WaitingForData { source, version, .. }
+            | DragNDropState::Ready { source, version, .. } => Some((source, version)),
+        }
+    }
+
+    fn source(&self) -> Option<XWindow> {
+        self.session().map(|(source, _)| source)
+    }
+
+    /// End the current drag operation. If the handler has seen the drag enter, then this returns
+    /// the dragged data and the effect the handler accepted last, and the handler must be sent
+    /// exactly one `DragLeft` or `DragDropped` event for it.
+    fn end(&mut self) -> Option<(DropData, Option<DropEffect>)> {
+        match std::mem::replace(self, DragNDropState::NoCurrentSession) {
+            DragNDropState::Ready { data, effect, .. } => Some((data, effect)),
+            _ => None,
+        }
+    }
+
+    /// Start a new drag operation. A new drag can start without the previous one having left, in
+    /// which case this returns what [`end()`][Self::end()] returns for the previous drag.
+    fn begin(
+        &mut self, source: XWindow, version: u8, offers_files: bool,
+    ) -> Option<(DropData, Option<DropEffect>)> {
+        let previous = self.end();
+        *self = if offers_files {
+            DragNDropState::Entered { source, version }
+        } else {
+            DragNDropState::PermanentlyRejected { source, version }
+        };
+
+        previous
+    }
+
+    /// Store the data that arrived while waiting for it. This returns the position and modifiers
+    /// for the `DragEntered` event, or `None` if the data wasn't being waited on. The handler's
+    /// response should be stored with [`set_effect()`][Self::set_effect()].
+    fn receive_data(&mut self, data: DropData) -> Option<(Point, Modifiers)> {
+        let DragNDropState::WaitingForData { source, version, position, modifiers } = *self else {
+            return None;
+        };
+
+        *self = DragNDropState::Ready { source, version, data, effect: None };
+        Some((position, modifiers))
+    }
+
+    fn set_effect(&mut self, new_effect: Option<DropEffect>) {
+        if let DragNDropState::Ready { effect, .. } = self {
+            *effect = new_effect;
+        }
+    }
+
+    pub fn handle_enter_event(
+        &mut self, window: &WindowInner, handler: &mut dyn WindowHandler,
+        event: &ClientMessageEvent,
+    ) -> Result<(), Box<dyn Error>> {
+        let data = event.data.as_data32();
+        let source = data[0];
+        let version = (data[1] >> 24) as u8;
+
+        let conn = &window.xcb_connection.conn;
+        let atoms = &window.xcb_connection.atoms;
+
+        // Sources that offer more than three types list them in a property instead
+        let offers_files = if data[1] & 1 != 0 {
+            let reply = conn
+                .get_property(false, source, atoms.XdndTypeList, AtomEnum::ATOM, 0, u32::MAX)?
+                .reply()?;
+
+            reply.value32().map_or(false, |mut types| types.any(|t| t == atoms.TextUriList))
+        } else {
+            data[2..5].contains(&atoms.TextUriList)
+        };
+
+        // A new drag that starts without the previous one having left still needs a matching
+        // `DragLeft` event
+        if self.begin(source, version, offers_files).is_some() {
+            send_drag_left(window, handler);
+        }
+
+        Ok(())
+    }
+
+    pub fn handle_position_event(
+        &mut self, window: &WindowInner, handler: &mut dyn WindowHandler,
+        event: &ClientMessageEvent,
+    ) -> Result<(), Box<dyn Error>> {
+        let data = event.data.as_data32();
+        let source = data[0];
+        if self.source() != Some(source) {
+            return Ok(());
+        }
+
+        let conn = &window.xcb_connection.conn;
+
+        // The position is in root window coordinates. The pointer query provides both the offset
+        // to the window's coordinates and the modifiers, which are not part of the message.
+        let pointer = conn.query_pointer(window.window_id)?.reply()?;
+        let root_position = PhyPoint::new((data[2] >> 16) as i16 as i32, data[2] as i16 as i32);
+        let position = PhyPoint::new(
+            root_position.x - (pointer.root_x - pointer.win_x) as i32,
+            root_position.y - (pointer.root_y - pointer.win_y) as i32,
+        )
+        .to_logical(&window.window_info);
+        let modifiers = key_mods(pointer.mask);
+
+        match self {
+            DragNDropState::NoCurrentSession => {}
+            DragNDropState::PermanentlyRejected { source, version } => {
+                send_status_event(window, *source, *version, None)?;
+            }
+            DragNDropState::Entered { source, version } => {
+                // The timestamp was only added in version 1
+                let timestamp: Timestamp = if *version >= 1 { data[3] } else { CURRENT_TIME };
+
+                let atoms = &window.xcb_connection.atoms;
+                conn.convert_selection(
+                    window.window_id,
+                    atoms.XdndSelection,
+                    atoms.TextUriList,
+                    atoms.BASEVIEW_DND_DATA,
+                    timestamp,
+                )?;
+
+                *self = DragNDropState::WaitingForData {
+                    source: *source,
+                    version: *version,
+                    position,
+                    modifiers,
+                };
+            }
+            DragNDropState::WaitingForData {
+                position: last_position,
+                modifiers: last_mods,
+                ..
+            } => {
+                *last_position = position;
+                *last_mods = modifiers;
+            }
+            DragNDropState::Ready { source, version, data, effect } => {
+                let event = MouseEvent::DragMoved { position, modifiers, data: data.clone() };
+                *effect = accepted_effect(handler.on_event(
+                    &mut crate::Window::new(Window { inner: window }),
+                    Event::Mouse(event),
+                ));
+
+                send_status_event(window, *source, *version, *effect)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn handle_selection_notify_event(
+        &mut self, window: &WindowInner, handler: &mut dyn WindowHandler,
+        event: &SelectionNotifyEvent,
+    ) -> Result<(), Box<dyn Error>> {
+        let DragNDropState::WaitingForData { source, version, .. } = *self else {
+            return Ok(());
+        };
+
+        let data = if event.property == NONE {
+            Err(ParseError::ConversionFailed.into())
+        } else {
+            fetch_dnd_data(window, event.property)
+        };
+
+        let data = match data {
+            Ok(data) => data,
+            Err(err) => {
+                log_warn!("Could not read the data dragged onto the window: {}", err);
+                *self = DragNDropState::PermanentlyRejected { source, version };
+
+                return send_status_event(window, source, version, None);
+            }
+        };
+
+        let Some((position, modifiers)) = self.receive_data(data.clone()) else {
+            return Ok(());
+        };
+
+        let event = MouseEvent::DragEntered { position, modifiers, data };
+        let effect = accepted_effect(
+            handler
+                .on_event(&mut crate::Window::new(Window { inner: window }), Event::Mouse(event)),
+        );
+        self.set_effect(effect);
+
+        send_status_event(window, source, version, effect)
+    }
+
+    pub fn handle_leave_event(
+        &mut self, window: &WindowInner, handler: &mut dyn WindowHandler,
+        event: &ClientMessageEvent,
+    ) {
+        if self.source() == Some(event.data.as_data32()[0]) {
+            self.reset(window, handler);
+        }
+    }
+
+    pub fn handle_drop_event(
+        &mut self, window: &WindowInner, handler: &mut dyn WindowHandler,
+        event: &ClientMessageEvent,
+    ) -> Result<(), Box<dyn Error>> {
+        let source = event.data.as_data32()[0];
+        let version = match self.session() {
+            Some((current_source, version)) if current_source == source => version,
+            _ => return Ok(()),
+        };
+
+        match self.end() {
+            Some((data, Some(_))) => {
+                // The position isn't part of the drop message, so the current pointer position is
+                // used instead
+                let conn = &window.xcb_connection.conn;
+                let pointer =
+                    conn.query_pointer(window.window_id).ok().and_then(|c| c.reply().ok());
+                let Some(pointer) = pointer else {
+                    // The handler has seen the drag enter, so it still needs to see it leave
+                    send_drag_left(window, handler);
+
+                    return send_finished_event(window, source, version, None);
+                };
+
+                let position = PhyPoint::new(pointer.win_x as i32, pointer.win_y as i32)
+                    .to_logical(&window.window_info);
+
+                let event =
+                    MouseEvent::DragDropped { position, modifiers: key_mods(pointer.mask), data };
+                let effect = accepted_effect(handler.on_event(
+                    &mut crate::Window::new(Window { inner: window }),
+                    Event::Mouse(event),
+                ));
+
+                send_finished_event(window, source, version, effect)
+            }
+            Some((_, None)) => {
+                // The drop was never accepted, so the handler only needs to know that the drag
+                // has left
+                send_drag_left(window, handler);
+
+                send_finished_event(window, source, version, None)
+            }
+            None => send_finished_event(window, source, version, None),
+        }
+    }
+
+    /// End the current drag operation, if there is one, and send a `DragLeft` event if the
+    /// handler has seen the drag enter. This is also used when the window closes during a drag.
+    pub fn reset(&mut self, window: &WindowInner, handler: &mut dyn WindowHandler) {
+        if self.end().is_some() {
+            send_drag_left(window, handler);
+        }
+    }
+}
+
+fn send_drag_left(window: &WindowInner, handler: &mut dyn WindowHandler) {
+    handler.on_event(
+        &mut crate::Window::new(Window { inner: window }),
+        Event::Mouse(MouseEvent::DragLeft),
+    );
+}
+
+fn accepted_effect(status: EventStatus) -> Option<DropEffect> {
+    match status {
+        EventStatus::AcceptDrop(effect) => Some(effect),
+        _ => None,
+    }
+}
+
+/// The XDND action corresponding to a drop effect.
+fn effect_to_action(window: &WindowInner, effect: Option<DropEffect>) -> Atom {
+    let atoms = &window.xcb_connection.atoms;
+    match effect {
+        Some(DropEffect::Copy) => atoms.XdndActionCopy,
+        Some(DropEffect::Move) => atoms.XdndActionMove,
+        Some(DropEffect::Link) => atoms.XdndActionLink,
+        // XDND has no equivalent for scrolling
+        Some(DropEffect::Scroll) => atoms.XdndActionPrivate,
+        None => NONE,
+    }
+}
+
+/// Reply to a position message, telling the source whether the drop would be accepted.
+fn send_status_event(
+    window: &WindowInner, source: XWindow, version: u8, effect: Option<DropEffect>,
+) -> Result<(), Box<dyn Error>> {
+    // The empty rectangle makes the source send a position message for every movement. The
+    // action was only added in version 2.
+    let action = if version >= 2 { effect_to_action(window, effect) } else { NONE };
+    let data = [window.window_id, effect.is_some() as u32, 0, 0, action];
+
+    send_client_message(window, source, window.xcb_connection.atoms.XdndStatus, data)
+}
+
+/// Tell the source that the drop has been handled.
+fn send_finished_event(
+    window: &WindowInner, source: XWindow, version: u8, effect: Option<DropEffect>,
+) -> Result<(), Box<dyn Error>> {
+    // Whether the drop was accepted and the performed action were only added in version 5
+    let data = if version >= 5 {
+        [window.window_id, effect.is_some() as u32, effect_to_action(window, effect), 0, 0]
+    } else {
+        [window.window_id, 0, 0, 0, 0]
+    };
+
+    send_client_message(window, source, window.xcb_connection.atoms.XdndFinished, data)
+}
+
+fn send_client_message(
+    window: &WindowInner, destination: XWindow, message_type: Atom, data: [u32; 5],
+) -> Result<(), Box<dyn Error>> {
+    let conn = &window.xcb_connection.conn;
+    conn.send_event(
+        false,
+        destination,
+        EventMask::NO_EVENT,
+        ClientMessageEvent::new(32, destination, message_type, data),
+    )?;
+
+    Ok(())
+}
+
+/// Read the `text/uri-list` the source stored in the property, and convert it to a list of paths.
+/// The paths are only canonicalized when there aren't too many of them. Paths that can't be
+/// canonicalized, such as broken symlinks or paths in directories that can't be read, are kept as
+/// they are so the other files can still be dropped.
+fn fetch_dnd_data(window: &WindowInner, property: Atom) -> Result<DropData, Box<dyn Error>> {
+    let conn = &window.xcb_connection.conn;
+    // The length is in 32-bit units. The property is only deleted if it was read in its entirety.
+    let reply = conn
+        .get_property(true, window.window_id, property, AtomEnum::ANY, 0, MAX_DATA_SIZE / 4)?
+        .reply()?;
+    if reply.bytes_after > 0 {
+        conn.delete_property(window.window_id, property)?;
+
+        return Err(ParseError::DataTooLarge.into());
+    }
+
+    Ok(resolve_paths(parse_data(&reply.value)?))
+}
+
+/// Canonicalize the dropped paths. Paths that can't be canonicalized, like broken symlinks, are
+/// kept as is. Large drops are left unresolved, see [`MAX_RESOLVED_FILES`].
+fn resolve_paths(paths: Vec<PathBuf>) -> DropData {
+    if paths.len() > MAX_RESOLVED_FILES {
+        return DropData::UnresolvedFiles(paths);
+    }
+
+    let files = paths
+        .into_iter()
+        .map(|path| match path.canonicalize() {
+            Ok(canonical_path) => canonical_path,
+            Err(err) => {
+                log_warn!("Could not canonicalize the dropped path {:?}: {}", path, err);
+                path
+            }
+        })
+        .collect();
+
+    DropData::Files(files)
+}
+
+#[derive(Debug)]
+enum ParseError {
+    ConversionFailed,
+    DataTooLarge,
+    EmptyData,
+    NotAFileUri,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::ConversionFailed => write!(f, "The source could not provide the data"),
+            ParseError::DataTooLarge => write!(f, "The dropped data is too large"),
+            ParseError::EmptyData => write!(f, "The dropped data does not contain any URIs"),
+            ParseError::NotAFileUri => write!(f, "The dropped data contains a non-file URI"),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Parse a `text/uri-list` as defined in RFC 2483. Every URI needs to be a `file://` URI. The
+/// paths are returned as is, without resolving them.
+fn parse_data(data: &[u8]) -> Result<Vec<PathBuf>, ParseError> {
+    let mut paths = Vec::new();
+    for line in data.split(|&byte| byte == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        // Lines starting with a `#` are comments
+        if line.is_empty() || line.starts_with(b"#") {
+            continue;
+        }
+
+        let uri = line.strip_prefix(b"file://").ok_or(ParseError::NotAFileUri)?;
+        // The host name is ignored, since there's nothing sensible to do with a file on another
+        // machine anyways
+        let path_start =
+            uri.iter().position(|&byte| byte == b'/').ok_or(ParseError::NotAFileUri)?;
+        let path = percent_decode(&uri[path_start..]);
+
+        paths.push(PathBuf::from(OsStr::from_bytes(&path)));
+    }
+
+    if paths.is_empty() {
+        return Err(ParseError::EmptyData);
+    }
+
+    Ok(paths)
+}
+
+fn percent_decode(input: &[u8]) -> Vec<u8> {
+    fn hex_value(digit: u8) -> Option<u8> {
+        (digit as char).to_digit(16).map(|value| value as u8)
+    }
+
+    let mut output = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        let escaped = match input.get(i..i + 3) {
+            Some([b'%', high, low]) => hex_value(*high).zip(hex_value(*low)),
+            _ => None,
+        };
+
+        match escaped {
+            Some((high, low)) => {
+                output.push(high << 4 | low);
+                i += 3;
+            }
+            None => {
+                output.push(input[i]);
+                i += 1;
+            }
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+    use std::os::unix::fs::symlink;
+
+    #[test]
+    fn resolve_paths_keeps_unresolvable_paths() {
+        let dir = std::env::temp_dir().join(format!("baseview-dnd-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let canonical_dir = dir.canonicalize().unwrap();
+
+        let file = dir.join("file.txt");
+        fs::write(&file, b"").unwrap();
+        let broken_link = dir.join("broken-link");
+        let _ = fs::remove_file(&broken_link);
+        symlink(dir.join("does-not-exist"), &broken_link).unwrap();
+
+        // `dir/./file.txt` should be canonicalized, while the broken symlink is kept as is
+        let dropped = vec![dir.join(".").join("file.txt"), broken_link.clone()];
+        let files = match resolve_paths(dropped) {
+            DropData::Files(files) => files,
+            data => panic!("Expected resolved files, got {:?}", data),
+        };
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(files, vec![canonical_dir.join("file.txt"), broken_link]);
+    }
+
+    #[test]
+    fn resolve_paths_leaves_large_drops_unresolved() {
+        let paths = vec![PathBuf::from("relative/path"); MAX_RESOLVED_FILES + 1];
+        match resolve_paths(paths.clone()) {
+            DropData::UnresolvedFiles(unresolved) => assert_eq!(unresolved, paths),
+            data => panic!("Expected unresolved files, got {:?}", data),
+        }
+    }
+
+    /// Counts the events the handler would receive, and checks that every `DragEntered` is
+    /// followed by exactly one `DragLeft` or `DragDropped`.
+    #[derive(Default)]
+    struct Balance {
+        entered: usize,
+        ended: usize,
+    }
+
+    impl Balance {
+        fn receive_data(&mut self, state: &mut DragNDropState) {
+            if state.receive_data(DropData::Files(Vec::new())).is_some() {
+                assert_eq!(self.entered, self.ended, "DragEntered sent twice");
+                self.entered += 1;
+                state.set_effect(Some(DropEffect::Copy));
+            }
+        }
+
+        fn end(&mut self, ended: Option<(DropData, Option<DropEffect>)>) {
+            if ended.is_some() {
+                self.ended += 1;
+                assert_eq!(self.entered, self.ended, "DragLeft sent without DragEntered");
+            }
+        }
+    }
+
+    fn wait_for_data(state: &mut DragNDropState) {
+        let Some((source, version)) = state.session() else { return };
+        *state = DragNDropState::WaitingForData {
+            source,
+            version,
+            position: Point::new(0.0, 0.0),
+            modifiers: Modifiers::empty(),
+        };
+    }
+
+    #[test]
+    fn drag_enter_and_leave_are_balanced() {
+        let mut state = DragNDropState::NoCurrentSession;
+        let mut balance = Balance::default();
+
+        // A drag that leaves normally
+        balance.end(state.begin(1, XDND_VERSION as u8, true));
+        wait_for_data(&mut state);
+        balance.receive_data(&mut state);
+        balance.end(state.end());
+        assert_eq!((balance.entered, balance.ended), (1, 1));
+
+        // A drag that's interrupted by a new drag without leaving, and then dropped
+        balance.end(state.begin(1, XDND_VERSION as u8, true));
+        wait_for_data(&mut state);
+        balance.receive_data(&mut state);
+        balance.end(state.begin(2, XDND_VERSION as u8, true));
+        assert_eq!((balance.entered, balance.ended), (2, 2));
+        wait_for_data(&mut state);
+        balance.receive_data(&mut state);
+        balance.receive_data(&mut state);
+        balance.end(state.end());
+        assert_eq!((balance.entered, balance.ended), (3, 3));
+
+        // A drag that leaves before its data arrives never enters
+        balance.end(state.begin(3, XDND_VERSION as u8, true));
+        wait_for_data(&mut state);
+        balance.end(state.end());
+        balance.receive_data(&mut state);
+        assert_eq!((balance.entered, balance.ended), (3, 3));
+
+        // A rejected drag never enters either
+        balance.end(state.begin(4, XDND_VERSION as u8, false));
+        balance.receive_data(&mut state);
+        balance.end(state.end());
+        assert_eq!((balance.entered, balance.ended), (3, 3));
+
+        // Closing the window ends the drag a second time, which must not send another event
+        balance.end(state.begin(5, XDND_VERSION as u8, true));
+        wait_for_data(&mut state);
+        balance.receive_data(&mut state);
+        balance.end(state.end());
+        balance.end(state.end());
+        assert_eq!((balance.entered, balance.ended), (4, 4));
+    }
+}
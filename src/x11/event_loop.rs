@@ -1,132 +1,349 @@
+use crate::x11::drag_n_drop::DragNDropState;
 use crate::x11::keyboard::{convert_key_press_event, convert_key_release_event, key_mods};
-use crate::x11::{ParentHandle, Window, WindowInner};
+use crate::x11::{HandleCommand, ParentHandle, Window, WindowInner};
 use crate::{
-    Event, MouseButton, MouseEvent, PhyPoint, PhySize, ScrollDelta, WindowEvent, WindowHandler,
-    WindowInfo,
+    Event, EventStatus, HitTestResult, MouseButton, MouseEvent, PhyPoint, PhyRect, PhySize, Point,
+    PointerKind, RawMessage, ScrollDelta, ScrollPhase, Size, WindowEvent, WindowHandler,
+    WindowInfo, WindowScalePolicy,
+};
+use keyboard_types::Modifiers;
+use std::any::Any;
+use std::ffi::c_void;
+use std::os::unix::io::RawFd;
+use std::sync::mpsc;
+use std::time::Instant;
+use x11rb::protocol::xinput::{self, Fp1616};
+use x11rb::protocol::xproto::{
+    Atom, ButtonPressEvent, ClientMessageEvent, KeyButMask, Window as XWindow, BUTTON_PRESS_EVENT,
+    BUTTON_RELEASE_EVENT,
 };
-use std::error::Error;
-use std::os::fd::AsRawFd;
-use std::time::{Duration, Instant};
-use x11rb::connection::Connection;
 use x11rb::protocol::Event as XEvent;
 
+/// The state of a single window. All windows share the same X11 connection, and their event loops
+/// are driven by the [`Runner`][super::runner::Runner], which routes every event to the window it
+/// belongs to.
 pub(super) struct EventLoop {
     handler: Box<dyn WindowHandler>,
     window: WindowInner,
     parent_handle: Option<ParentHandle>,
+    /// Dropped when the window closes. This unblocks threads waiting for the window to close.
+    _close_notifier: mpsc::SyncSender<()>,
 
     new_physical_size: Option<PhySize>,
-    frame_interval: Duration,
+    exposed_area: Option<PhyRect>,
+    /// Set when a `ConfigureNotify` event may have moved a standalone window.
+    maybe_moved: bool,
+    /// The last known position of a standalone window in physical screen coordinates.
+    position: Option<PhyPoint>,
+    /// When coalescing mouse moves, only the last of a run of consecutive motion events is
+    /// handled.
+    pending_motion: Option<XEvent>,
+    drag_n_drop: DragNDropState,
+    last_frame: Instant,
     event_loop_running: bool,
 }
 
 impl EventLoop {
     pub fn new(
         window: WindowInner, handler: impl WindowHandler + 'static,
-        parent_handle: Option<ParentHandle>,
+        parent_handle: Option<ParentHandle>, close_notifier: mpsc::SyncSender<()>,
     ) -> Self {
+        let position = if window.parent_id.is_none() { window.screen_position() } else { None };
+
         Self {
             window,
             handler: Box::new(handler),
             parent_handle,
-            frame_interval: Duration::from_millis(15),
-            event_loop_running: false,
+            _close_notifier: close_notifier,
+            event_loop_running: true,
             new_physical_size: None,
+            exposed_area: None,
+            maybe_moved: false,
+            position,
+            pending_motion: None,
+            drag_n_drop: DragNDropState::NoCurrentSession,
+            last_frame: Instant::now(),
+        }
+    }
+
+    pub fn window_id(&self) -> XWindow {
+        self.window.window_id
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.event_loop_running
+    }
+
+    /// The window this popup belongs to, if the window is a popup.
+    pub fn popup_owner(&self) -> Option<XWindow> {
+        self.window.popup_owner
+    }
+
+    /// Close the window right away.
+    pub fn close(&mut self) {
+        if self.event_loop_running {
+            self.handle_must_close();
         }
     }
 
-    #[inline]
-    fn drain_xcb_events(&mut self) -> Result<(), Box<dyn Error>> {
+    /// Send an event to the window handler. The [`crate::Window`] passed to the handler only
+    /// holds a reference to the window's state, so creating one per event costs nothing.
+    fn send_event(&mut self, event: Event) -> EventStatus {
+        self.handler.on_event(&mut crate::Window::new(Window { inner: &self.window }), event)
+    }
+
+    /// Reset the state used to coalesce events. This is called before draining the connection's
+    /// event queue.
+    pub fn begin_drain(&mut self) {
         // the X server has a tendency to send spurious/extraneous configure notify events when a
         // window is resized, and we need to batch those together and just send one resize event
         // when they've all been coalesced.
         self.new_physical_size = None;
+        // Expose events are coalesced the same way
+        self.exposed_area = None;
+        self.maybe_moved = false;
+        self.pending_motion = None;
+    }
 
-        while let Some(event) = self.window.xcb_connection.conn.poll_for_event()? {
-            self.handle_xcb_event(event);
+    /// Send cursor motion as [`MouseEvent::RawMotion`] events during a
+    /// [relative drag][crate::Window::begin_relative_drag], and move the cursor back to the
+    /// drag's origin. Returns `false` if there is no drag and the motion should be handled
+    /// normally.
+    fn handle_relative_motion(&mut self, position: PhyPoint, modifiers: Modifiers) -> bool {
+        if !self.window.relative_drag.is_active() {
+            return false;
         }
 
-        if let Some(size) = self.new_physical_size.take() {
-            self.window.window_info =
-                WindowInfo::from_physical_size(size, self.window.window_info.scale());
+        if let Some((x, y)) = self.window.relative_drag.cursor_moved((position.x, position.y)) {
+            let scale = self.window.window_info.scale();
+            self.send_event(Event::Mouse(MouseEvent::RawMotion {
+                delta: Point::new(x as f64 / scale, y as f64 / scale),
+                modifiers,
+            }));
+        }
+
+        // The handler may have ended the drag in the meantime
+        self.window.warp_to_relative_drag_origin();
+
+        true
+    }
+
+    /// Handle an event that belongs to this window. `raw_event` is the event before parsing, which
+    /// is passed to [`WindowHandler::on_raw_message`].
+    pub fn handle_raw_event(&mut self, raw_event: &[u8], event: XEvent) {
+        let raw_message = RawMessage::Xcb(raw_event.as_ptr() as *const c_void);
+        if self
+            .handler
+            .on_raw_message(&mut crate::Window::new(Window { inner: &self.window }), raw_message)
+            .is_some()
+        {
+            return;
+        }
 
-            let window_info = self.window.window_info;
+        // During a relative drag every motion event is needed to tell which ones were caused by
+        // warping the cursor
+        if self.window.coalesce_mouse_moves && !self.window.relative_drag.is_active() {
+            if let XEvent::MotionNotify(_) | XEvent::XinputMotion(_) = event {
+                self.pending_motion = Some(event);
+                return;
+            }
+        }
 
-            self.handler.on_event(
-                &mut crate::Window::new(Window { inner: &self.window }),
-                Event::Window(WindowEvent::Resized(window_info)),
-            );
+        if let Some(motion) = self.pending_motion.take() {
+            self.handle_xcb_event(motion);
         }
 
-        Ok(())
+        self.handle_xcb_event(event);
+    }
+
+    /// Send an event from [`WindowHandle::send_event`][crate::WindowHandle::send_event] to the
+    /// window handler.
+    pub fn send_synthetic_event(&mut self, event: Event) {
+        self.send_event(event);
+        self.window.handle_deferred_tasks();
+    }
+
+    /// Resize the window for [`WindowHandle::resize`][crate::WindowHandle::resize].
+    pub fn resize(&mut self, size: Size) {
+        self.window.resize(size);
     }
 
-    // Event loop
-    // FIXME: poll() acts fine on linux, sometimes funky on *BSD. XCB upstream uses a define to
-    // switch between poll() and select() (the latter of which is fine on *BSD), and we should do
-    // the same.
-    pub fn run(&mut self) -> Result<(), Box<dyn Error>> {
-        use nix::poll::*;
-
-        let xcb_fd = self.window.xcb_connection.conn.as_raw_fd();
-
-        let mut last_frame = Instant::now();
-        self.event_loop_running = true;
-
-        while self.event_loop_running {
-            // We'll try to keep a consistent frame pace. If the last frame couldn't be processed in
-            // the expected frame time, this will throttle down to prevent multiple frames from
-            // being queued up. The conditional here is needed because event handling and frame
-            // drawing is interleaved. The `poll()` function below will wait until the next frame
-            // can be drawn, or until the window receives an event. We thus need to manually check
-            // if it's already time to draw a new frame.
-            let next_frame = last_frame + self.frame_interval;
-            if Instant::now() >= next_frame {
-                self.handler.on_frame(&mut crate::Window::new(Window { inner: &self.window }));
-                last_frame = Instant::max(next_frame, Instant::now() - self.frame_interval);
+    /// Send a message from [`WindowHandle::send_message`][crate::WindowHandle::send_message] to
+    /// the window handler.
+    pub fn send_message(&mut self, message: Box<dyn Any + Send>) {
+        self.handler.on_message(&mut crate::Window::new(Window { inner: &self.window }), message);
+        self.window.handle_deferred_tasks();
+    }
+
+    /// Send the events that were coalesced while draining the connection's event queue.
+    pub fn end_drain(&mut self) {
+        if let Some(motion) = self.pending_motion.take() {
+            self.handle_xcb_event(motion);
+        }
+
+        if let Some(size) = self.new_physical_size.take() {
+            let old_window_info = self.window.window_info;
+            let window_info = WindowInfo::from_physical_size(size, old_window_info.scale());
+
+            self.send_event(Event::Window(WindowEvent::WillResize {
+                from: old_window_info,
+                to: window_info,
+            }));
+            self.window.window_info = window_info;
+
+            self.send_event(Event::Window(WindowEvent::Resized(window_info)));
+
+            // The cursor's logical position changes along with the window's size and scale, even
+            // if the cursor itself didn't move
+            if let Some((position, state)) = self.window.query_cursor() {
+                self.send_event(Event::Mouse(MouseEvent::CursorMoved {
+                    position,
+                    modifiers: key_mods(state),
+                }));
             }
+        }
 
-            let mut fds = [PollFd::new(xcb_fd, PollFlags::POLLIN)];
+        if self.maybe_moved {
+            // With reparenting window managers the coordinates in `ConfigureNotify` events are
+            // relative to the window's frame, so the position is queried instead
+            let position = self.window.screen_position();
+            if let Some(new_position) = position.filter(|_| position != self.position) {
+                self.position = position;
 
-            // Check for any events in the internal buffers
-            // before going to sleep:
-            self.drain_xcb_events()?;
+                let position = new_position.to_logical(&self.window.window_info);
 
-            // FIXME: handle errors
-            poll(&mut fds, next_frame.duration_since(Instant::now()).subsec_millis() as i32)
-                .unwrap();
+                self.send_event(Event::Window(WindowEvent::Moved(position)));
+            }
+        }
 
-            if let Some(revents) = fds[0].revents() {
-                if revents.contains(PollFlags::POLLERR) {
-                    panic!("xcb connection poll error");
-                }
+        if let Some(area) = self.exposed_area.take() {
+            let area = area.to_logical(&self.window.window_info);
 
-                if revents.contains(PollFlags::POLLIN) {
-                    self.drain_xcb_events()?;
-                }
+            self.send_event(Event::Window(WindowEvent::Expose(area)));
+        }
+
+        self.window.handle_deferred_tasks();
+        self.handle_scale_policy_change();
+    }
+
+    /// Apply the scale policy set through [`Window::set_scale_policy`][crate::Window::set_scale_policy].
+    /// The window keeps its logical size, so its physical size changes along with the scale
+    /// factor.
+    fn handle_scale_policy_change(&mut self) {
+        let policy = match self.window.new_scale_policy.take() {
+            Some(policy) => policy,
+            None => return,
+        };
+        self.window.scale_policy = policy;
+
+        let scale = match policy {
+            WindowScalePolicy::SystemScaleFactor => {
+                self.window.xcb_connection.get_scaling().unwrap_or(1.0)
             }
+            WindowScalePolicy::ScaleFactor(scale) => scale,
+        };
+        if scale == self.window.window_info.scale() {
+            return;
+        }
 
-            // Check if the parents's handle was dropped (such as when the host
-            // requested the window to close)
-            //
-            // FIXME: This will need to be changed from just setting an atomic to somehow
-            // synchronizing with the window being closed (using a synchronous channel, or
-            // by joining on the event loop thread).
-            if let Some(parent_handle) = &self.parent_handle {
-                if parent_handle.parent_did_drop() {
-                    self.handle_must_close();
-                    self.window.close_requested.set(false);
-                }
+        let old_window_info = self.window.window_info;
+        let logical_size = old_window_info.logical_size();
+        let window_info = WindowInfo::from_logical_size(logical_size, scale);
+
+        self.send_event(Event::Window(WindowEvent::WillResize {
+            from: old_window_info,
+            to: window_info,
+        }));
+        self.window.window_info = window_info;
+
+        self.handler.on_scale_factor_changed(
+            &mut crate::Window::new(Window { inner: &self.window }),
+            scale,
+        );
+        self.send_event(Event::Window(WindowEvent::Resized(window_info)));
+
+        // The `ConfigureNotify` event for this resize matches the new window info, so it doesn't
+        // cause another `Resized` event
+        self.window.resize(logical_size);
+        self.window.handle_deferred_tasks();
+    }
+
+    /// The time at which the next frame should be drawn.
+    pub fn next_frame(&self) -> Instant {
+        if self.window.render_requested.get() {
+            // This is in the past, so the runner won't block
+            self.last_frame
+        } else {
+            self.last_frame + self.window.frame_interval()
+        }
+    }
+
+    /// Draw a frame if it's time to do so.
+    pub fn frame(&mut self, now: Instant) {
+        // We'll try to keep a consistent frame pace. If the last frame couldn't be processed in
+        // the expected frame time, this will throttle down to prevent multiple frames from
+        // being queued up. The conditional here is needed because event handling and frame
+        // drawing is interleaved. The runner's `poll()` call will wait until the next frame can
+        // be drawn, or until a window receives an event. We thus need to manually check if it's
+        // already time to draw a new frame.
+        let frame_interval = self.window.frame_interval();
+        let next_frame = self.last_frame + frame_interval;
+        let render_requested = self.window.render_requested.replace(false);
+        if render_requested || now >= next_frame {
+            self.handler.on_frame(&mut crate::Window::new(Window { inner: &self.window }));
+            self.window.handle_deferred_tasks();
+            self.handle_scale_policy_change();
+            self.window.update_cursor_auto_hide(now);
+
+            // Frames requested with `Window::render_now()` don't count towards the frame pacing
+            if now >= next_frame {
+                self.last_frame = Instant::max(next_frame, Instant::now() - frame_interval);
             }
+        }
+    }
+
+    /// The file descriptor that becomes readable when the window's [`WindowHandle`] wants the
+    /// event loop to wake up.
+    ///
+    /// [`WindowHandle`]: crate::WindowHandle
+    pub fn wake_fd(&self) -> Option<RawFd> {
+        self.parent_handle.as_ref().map(|parent_handle| parent_handle.wake_fd())
+    }
+
+    /// Return the commands the window's [`WindowHandle`] has sent.
+    ///
+    /// [`WindowHandle`]: crate::WindowHandle
+    pub fn take_commands(&self) -> Vec<HandleCommand> {
+        match &self.parent_handle {
+            Some(parent_handle) => parent_handle.take_commands(),
+            None => Vec::new(),
+        }
+    }
 
-            // Check if the user has requested the window to close
-            if self.window.close_requested.get() {
+    /// Close the window if the host or the window handler asked for it.
+    pub fn handle_close_requests(&mut self) {
+        if !self.event_loop_running {
+            return;
+        }
+
+        // Check if the parents's handle was dropped (such as when the host
+        // requested the window to close)
+        //
+        // FIXME: This will need to be changed from just setting an atomic to somehow
+        // synchronizing with the window being closed (using a synchronous channel, or
+        // by joining on the event loop thread).
+        if let Some(parent_handle) = &self.parent_handle {
+            if parent_handle.parent_did_drop() {
                 self.handle_must_close();
                 self.window.close_requested.set(false);
             }
         }
 
-        Ok(())
+        // Check if the user has requested the window to close
+        if self.window.close_requested.get() {
+            self.handle_must_close();
+            self.window.close_requested.set(false);
+        }
     }
 
     fn handle_xcb_event(&mut self, event: XEvent) {
@@ -150,16 +367,97 @@ impl EventLoop {
         //   the keyboard modifier keys at the time of the event.
         //   http://rtbo.github.io/rust-xcb/src/xcb/ffi/xproto.rs.html#445
 
+        // The core events don't say which device generated them
+        match &event {
+            XEvent::XinputMotion(event)
+            | XEvent::XinputButtonPress(event)
+            | XEvent::XinputButtonRelease(event) => {
+                let pointer_kind = self.window.xcb_connection.pointer_kind(event.sourceid);
+                self.window.pointer_kind.set(pointer_kind);
+            }
+            XEvent::MotionNotify(_) | XEvent::ButtonPress(_) | XEvent::ButtonRelease(_) => {
+                self.window.pointer_kind.set(PointerKind::Mouse);
+            }
+            _ => {}
+        }
+
+        // Button events received through XInput 2 are handled exactly like core button events
+        let event = match event {
+            XEvent::XinputButtonPress(event) => {
+                XEvent::ButtonPress(core_button_event(BUTTON_PRESS_EVENT, &event))
+            }
+            XEvent::XinputButtonRelease(event) => {
+                XEvent::ButtonRelease(core_button_event(BUTTON_RELEASE_EVENT, &event))
+            }
+            event => event,
+        };
+
+        let input_event = match &event {
+            XEvent::MotionNotify(event) => Some((event.time, event.state)),
+            XEvent::XinputMotion(event) => Some((event.time, core_state(event))),
+            XEvent::EnterNotify(event) | XEvent::LeaveNotify(event) => {
+                Some((event.time, event.state))
+            }
+            XEvent::ButtonPress(event) | XEvent::ButtonRelease(event) => {
+                Some((event.time, event.state))
+            }
+            XEvent::KeyPress(event) | XEvent::KeyRelease(event) => Some((event.time, event.state)),
+            _ => None,
+        };
+        if let Some((time, state)) = input_event {
+            self.window.set_event_time(time);
+            self.window.retain_modifier_sides(key_mods(state));
+        }
+
         match event {
             ////
             // window
             ////
             XEvent::ClientMessage(event) => {
-                if event.format == 32
-                    && event.data.as_data32()[0]
-                        == self.window.xcb_connection.atoms.WM_DELETE_WINDOW
-                {
+                let atoms = &self.window.xcb_connection.atoms;
+                if event.format != 32 {
+                    return;
+                }
+
+                if is_close_request(&event, atoms.WM_DELETE_WINDOW) {
                     self.handle_close_requested();
+                    return;
+                }
+
+                let window = &self.window;
+                let handler = &mut *self.handler;
+                let result = if event.type_ == atoms.XdndEnter {
+                    self.drag_n_drop.handle_enter_event(window, handler, &event)
+                } else if event.type_ == atoms.XdndPosition {
+                    self.drag_n_drop.handle_position_event(window, handler, &event)
+                } else if event.type_ == atoms.XdndDrop {
+                    self.drag_n_drop.handle_drop_event(window, handler, &event)
+                } else {
+                    if event.type_ == atoms.XdndLeave {
+                        self.drag_n_drop.handle_leave_event(window, handler, &event);
+                    }
+
+                    Ok(())
+                };
+
+                if let Err(err) = result {
+                    log_warn!("Could not handle an XDND message: {}", err);
+                }
+            }
+
+            XEvent::SelectionNotify(event) => {
+                if event.requestor == self.window.window_id
+                    && event.selection == self.window.xcb_connection.atoms.XdndSelection
+                {
+                    let result = self.drag_n_drop.handle_selection_notify_event(
+                        &self.window,
+                        &mut *self.handler,
+                        &event,
+                    );
+
+                    if let Err(err) = result {
+                        log_warn!("Could not handle the dropped data: {}", err);
+                    }
                 }
             }
 
@@ -171,6 +469,44 @@ impl EventLoop {
                 {
                     self.new_physical_size = Some(new_physical_size);
                 }
+
+                if self.window.parent_id.is_none() {
+                    self.maybe_moved = true;
+                }
+            }
+
+            // `visible` starts out as `WindowOpenOptions::visible`, so the window getting mapped
+            // when it opens doesn't count as being shown
+            XEvent::MapNotify(_) | XEvent::UnmapNotify(_) => {
+                let visible = matches!(event, XEvent::MapNotify(_));
+                if self.window.visible.replace(visible) != visible {
+                    let event = if visible { WindowEvent::Shown } else { WindowEvent::Hidden };
+                    self.send_event(Event::Window(event));
+                }
+            }
+
+            XEvent::FocusIn(_) => self.window.set_urgency_hint(false),
+
+            // The cursor should never stay trapped in an unfocused window
+            XEvent::FocusOut(_) => self.window.cursor_confinement.set(None),
+
+            // The clipboard's owner changed, or the owner went away
+            XEvent::XfixesSelectionNotify(_) => {
+                self.send_event(Event::Window(WindowEvent::ClipboardChanged));
+            }
+
+            XEvent::Expose(event) => {
+                let area = PhyRect::new(
+                    event.x as i32,
+                    event.y as i32,
+                    event.width as u32,
+                    event.height as u32,
+                );
+
+                self.exposed_area = Some(match self.exposed_area {
+                    Some(exposed_area) => exposed_area.union(&area),
+                    None => area,
+                });
             }
 
             ////
@@ -178,80 +514,140 @@ impl EventLoop {
             ////
             XEvent::MotionNotify(event) => {
                 let physical_pos = PhyPoint::new(event.event_x as i32, event.event_y as i32);
+                if self.handle_relative_motion(physical_pos, key_mods(event.state)) {
+                    return;
+                }
+
+                // The motion event for the warp reports the confined position
+                if self.window.confine_cursor(physical_pos) {
+                    return;
+                }
+
                 let logical_pos = physical_pos.to_logical(&self.window.window_info);
+                self.window.cursor_moved(physical_pos);
 
-                self.handler.on_event(
-                    &mut crate::Window::new(Window { inner: &self.window }),
-                    Event::Mouse(MouseEvent::CursorMoved {
-                        position: logical_pos,
-                        modifiers: key_mods(event.state),
-                    }),
-                );
+                self.send_event(Event::Mouse(MouseEvent::CursorMoved {
+                    position: logical_pos,
+                    modifiers: key_mods(event.state),
+                }));
+            }
+
+            XEvent::XinputMotion(event) => {
+                let physical_pos =
+                    Point::new(fp1616_to_f64(event.event_x), fp1616_to_f64(event.event_y));
+                let pixel_pos = PhyPoint::new(physical_pos.x as i32, physical_pos.y as i32);
+                if self.handle_relative_motion(pixel_pos, key_mods(core_state(&event))) {
+                    return;
+                }
+                if self.window.confine_cursor(pixel_pos) {
+                    return;
+                }
+
+                let logical_pos = physical_pos / self.window.window_info.scale();
+                self.window.cursor_moved(pixel_pos);
+
+                self.send_event(Event::Mouse(MouseEvent::CursorMoved {
+                    position: logical_pos,
+                    modifiers: key_mods(core_state(&event)),
+                }));
             }
 
             XEvent::EnterNotify(event) => {
-                self.handler.on_event(
-                    &mut crate::Window::new(Window { inner: &self.window }),
-                    Event::Mouse(MouseEvent::CursorEntered),
-                );
+                self.send_event(Event::Mouse(MouseEvent::CursorEntered));
                 // since no `MOTION_NOTIFY` event is generated when `ENTER_NOTIFY` is generated,
                 // we generate a CursorMoved as well, so the mouse position from here isn't lost
                 let physical_pos = PhyPoint::new(event.event_x as i32, event.event_y as i32);
                 let logical_pos = physical_pos.to_logical(&self.window.window_info);
-                self.handler.on_event(
-                    &mut crate::Window::new(Window { inner: &self.window }),
-                    Event::Mouse(MouseEvent::CursorMoved {
-                        position: logical_pos,
-                        modifiers: key_mods(event.state),
-                    }),
-                );
+                self.window.cursor_moved(physical_pos);
+                self.send_event(Event::Mouse(MouseEvent::CursorMoved {
+                    position: logical_pos,
+                    modifiers: key_mods(event.state),
+                }));
             }
 
-            XEvent::LeaveNotify(_) => {
-                self.handler.on_event(
-                    &mut crate::Window::new(Window { inner: &self.window }),
-                    Event::Mouse(MouseEvent::CursorLeft),
-                );
+            XEvent::LeaveNotify(event) => {
+                // A fast movement can leave the window without a motion event outside of the
+                // confined area
+                let physical_pos = PhyPoint::new(event.event_x as i32, event.event_y as i32);
+                if !self.window.confine_cursor(physical_pos) {
+                    self.send_event(Event::Mouse(MouseEvent::CursorLeft));
+                }
+            }
+
+            // Popups close when the user clicks anywhere outside of them
+            XEvent::ButtonPress(event)
+                if self.window.popup_owner.is_some()
+                    && !self.window.contains(event.event_x, event.event_y) =>
+            {
+                self.handle_must_close();
             }
 
             XEvent::ButtonPress(event) => match event.detail {
                 4..=7 => {
-                    self.handler.on_event(
-                        &mut crate::Window::new(Window { inner: &self.window }),
-                        Event::Mouse(MouseEvent::WheelScrolled {
-                            delta: match event.detail {
-                                4 => ScrollDelta::Lines { x: 0.0, y: 1.0 },
-                                5 => ScrollDelta::Lines { x: 0.0, y: -1.0 },
-                                6 => ScrollDelta::Lines { x: -1.0, y: 0.0 },
-                                7 => ScrollDelta::Lines { x: 1.0, y: 0.0 },
-                                _ => unreachable!(),
-                            },
-                            modifiers: key_mods(event.state),
-                        }),
-                    );
+                    let status = self.send_event(Event::Mouse(MouseEvent::WheelScrolled {
+                        delta: match event.detail {
+                            4 => ScrollDelta::Lines { x: 0.0, y: 1.0 },
+                            5 => ScrollDelta::Lines { x: 0.0, y: -1.0 },
+                            6 => ScrollDelta::Lines { x: -1.0, y: 0.0 },
+                            7 => ScrollDelta::Lines { x: 1.0, y: 0.0 },
+                            _ => unreachable!(),
+                        },
+                        phase: ScrollPhase::Changed,
+                        modifiers: key_mods(event.state),
+                    }));
+
+                    if status == EventStatus::Ignored {
+                        self.window.forward_button_event(event);
+                    }
                 }
                 detail => {
-                    let button_id = mouse_id(detail);
-                    self.handler.on_event(
-                        &mut crate::Window::new(Window { inner: &self.window }),
-                        Event::Mouse(MouseEvent::ButtonPressed {
-                            button: button_id,
-                            modifiers: key_mods(event.state),
-                        }),
-                    );
+                    let Some(button_id) = mouse_id(detail) else {
+                        return;
+                    };
+
+                    // Standalone windows without decorations can use custom title bars and resize
+                    // handles
+                    if detail == 1 && self.window.parent_id.is_none() && !self.window.decorated {
+                        let physical_pos =
+                            PhyPoint::new(event.event_x as i32, event.event_y as i32);
+                        let hit_test_result = self
+                            .handler
+                            .hit_test(physical_pos.to_logical(&self.window.window_info));
+                        if hit_test_result != HitTestResult::Client {
+                            self.window.begin_move_resize(
+                                hit_test_result,
+                                event.root_x,
+                                event.root_y,
+                                detail,
+                            );
+                            return;
+                        }
+                    }
+
+                    let status = self.send_event(Event::Mouse(MouseEvent::ButtonPressed {
+                        button: button_id,
+                        modifiers: key_mods(event.state),
+                    }));
+
+                    if status == EventStatus::CaptureMouse {
+                        self.window.capture_until_released(button_id);
+                    } else if status == EventStatus::Ignored {
+                        self.window.forward_button_event(event);
+                    }
                 }
             },
 
             XEvent::ButtonRelease(event) => {
-                if !(4..=7).contains(&event.detail) {
-                    let button_id = mouse_id(event.detail);
-                    self.handler.on_event(
-                        &mut crate::Window::new(Window { inner: &self.window }),
-                        Event::Mouse(MouseEvent::ButtonReleased {
-                            button: button_id,
-                            modifiers: key_mods(event.state),
-                        }),
-                    );
+                if let Some(button_id) = mouse_id(event.detail) {
+                    let status = self.send_event(Event::Mouse(MouseEvent::ButtonReleased {
+                        button: button_id,
+                        modifiers: key_mods(event.state),
+                    }));
+                    self.window.button_released(button_id);
+
+                    if status == EventStatus::Ignored {
+                        self.window.forward_button_event(event);
+                    }
                 }
             }
 
@@ -259,17 +655,15 @@ impl EventLoop {
             // keys
             ////
             XEvent::KeyPress(event) => {
-                self.handler.on_event(
-                    &mut crate::Window::new(Window { inner: &self.window }),
-                    Event::Keyboard(convert_key_press_event(&event)),
-                );
+                let key_event = convert_key_press_event(&event);
+                self.window.update_modifier_sides(&key_event);
+                self.send_event(Event::Keyboard(key_event));
             }
 
             XEvent::KeyRelease(event) => {
-                self.handler.on_event(
-                    &mut crate::Window::new(Window { inner: &self.window }),
-                    Event::Keyboard(convert_key_release_event(&event)),
-                );
+                let key_event = convert_key_release_event(&event);
+                self.window.update_modifier_sides(&key_event);
+                self.send_event(Event::Keyboard(key_event));
             }
 
             _ => {}
@@ -277,27 +671,179 @@ impl EventLoop {
     }
 
     fn handle_close_requested(&mut self) {
-        // FIXME: handler should decide whether window stays open or not
-        self.handle_must_close();
+        if close_requested(|event| self.send_event(event)) {
+            self.handle_must_close();
+        }
     }
 
     fn handle_must_close(&mut self) {
-        self.handler.on_event(
-            &mut crate::Window::new(Window { inner: &self.window }),
-            Event::Window(WindowEvent::WillClose),
+        self.drag_n_drop.reset(&self.window, &mut *self.handler);
+
+        let window = &self.window;
+        let handler = &mut *self.handler;
+        will_close(
+            |event| handler.on_event(&mut crate::Window::new(Window { inner: window }), event),
+            self.parent_handle.as_ref(),
         );
 
         self.event_loop_running = false;
     }
 }
 
-fn mouse_id(id: u8) -> MouseButton {
+/// Whether a `ClientMessage` is the window manager asking the window to close, for instance
+/// because the user clicked the window's close button.
+fn is_close_request(event: &ClientMessageEvent, wm_delete_window: Atom) -> bool {
+    event.format == 32 && event.data.as_data32()[0] == wm_delete_window
+}
+
+/// Ask the window handler whether the window should close. The handler can keep the window open
+/// and close it later through `Window::close()`.
+fn close_requested(mut send_event: impl FnMut(Event) -> EventStatus) -> bool {
+    send_event(Event::Window(WindowEvent::CloseRequested)) != EventStatus::DeferClose
+}
+
+/// Let the window handler know the window is closing, and mark the window as closed.
+fn will_close(
+    mut send_event: impl FnMut(Event) -> EventStatus, parent_handle: Option<&ParentHandle>,
+) {
+    send_event(Event::Window(WindowEvent::WillClose));
+
+    // Other threads holding a `WindowHandle` should see the window as closed right away,
+    // regardless of whether the host, the user or the window handler closed it
+    if let Some(parent_handle) = parent_handle {
+        parent_handle.mark_closed();
+    }
+}
+
+fn fp1616_to_f64(value: Fp1616) -> f64 {
+    value as f64 / (1 << 16) as f64
+}
+
+/// The core protocol's modifier and button state for an XInput 2 pointer event.
+fn core_state(event: &xinput::ButtonPressEvent) -> KeyButMask {
+    // The first bit of the button mask is unused, and the core state only includes the first five
+    // buttons
+    let buttons = event.button_mask.first().map_or(0, |mask| (mask >> 1) & 0x1f);
+
+    KeyButMask::from((event.mods.effective & 0xff | buttons << 8) as u16)
+}
+
+/// Convert an XInput 2 button event to the equivalent core protocol button event.
+fn core_button_event(response_type: u8, event: &xinput::ButtonPressEvent) -> ButtonPressEvent {
+    ButtonPressEvent {
+        response_type,
+        detail: event.detail as u8,
+        sequence: event.sequence,
+        time: event.time,
+        root: event.root,
+        event: event.event,
+        child: event.child,
+        root_x: (event.root_x >> 16) as i16,
+        root_y: (event.root_y >> 16) as i16,
+        event_x: (event.event_x >> 16) as i16,
+        event_y: (event.event_y >> 16) as i16,
+        state: core_state(event),
+        same_screen: true,
+    }
+}
+
+/// Returns `None` for the scroll wheel buttons 4 to 7, and for button 0. That button doesn't
+/// exist, but it can still show up in synthetic events.
+fn mouse_id(id: u8) -> Option<MouseButton> {
     match id {
-        1 => MouseButton::Left,
-        2 => MouseButton::Middle,
-        3 => MouseButton::Right,
-        8 => MouseButton::Back,
-        9 => MouseButton::Forward,
-        id => MouseButton::Other(id),
+        1 => Some(MouseButton::Left),
+        2 => Some(MouseButton::Middle),
+        3 => Some(MouseButton::Right),
+        8 => Some(MouseButton::Back),
+        9 => Some(MouseButton::Forward),
+        // Buttons 4 to 7 are scroll events, so the buttons after those follow on from `Forward`
+        10.. => Some(MouseButton::Other(id - 5)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WM_PROTOCOLS: Atom = 100;
+    const WM_DELETE_WINDOW: Atom = 101;
+
+    #[test]
+    fn mouse_id_maps_buttons() {
+        assert_eq!(mouse_id(1), Some(MouseButton::Left));
+        assert_eq!(mouse_id(2), Some(MouseButton::Middle));
+        assert_eq!(mouse_id(3), Some(MouseButton::Right));
+        assert_eq!(mouse_id(8), Some(MouseButton::Back));
+        assert_eq!(mouse_id(9), Some(MouseButton::Forward));
+        assert_eq!(mouse_id(10), Some(MouseButton::Other(5)));
+        assert_eq!(mouse_id(255), Some(MouseButton::Other(250)));
+    }
+
+    /// The user closing a window through the window manager, like clicking its close button.
+    /// This follows the same steps as [`EventLoop::handle_xcb_event`] for a `WM_DELETE_WINDOW`
+    /// message.
+    fn close_through_window_manager(
+        deferred: bool, parent_handle: &ParentHandle,
+    ) -> Vec<WindowEvent> {
+        let event = ClientMessageEvent::new(32, 1, WM_PROTOCOLS, [WM_DELETE_WINDOW, 0, 0, 0, 0]);
+        assert!(is_close_request(&event, WM_DELETE_WINDOW));
+
+        let mut events = Vec::new();
+        let mut send_event = |event| {
+            let Event::Window(event) = event else { panic!("unexpected event: {:?}", event) };
+            let status = match event {
+                WindowEvent::CloseRequested if deferred => EventStatus::DeferClose,
+                _ => EventStatus::Captured,
+            };
+            events.push(event);
+
+            status
+        };
+
+        if close_requested(&mut send_event) {
+            will_close(&mut send_event, Some(parent_handle));
+        }
+
+        events
+    }
+
+    #[test]
+    fn window_manager_close_closes_the_window_handle() {
+        let (parent_handle, window_handle) = ParentHandle::new().unwrap();
+
+        let events = close_through_window_manager(false, &parent_handle);
+        assert!(matches!(events[..], [WindowEvent::CloseRequested, WindowEvent::WillClose]));
+        // The window handle sees the window as closed before the event loop has been torn down
+        assert!(!window_handle.is_open());
+    }
+
+    #[test]
+    fn deferred_window_manager_close_keeps_the_window_open() {
+        let (parent_handle, window_handle) = ParentHandle::new().unwrap();
+
+        let events = close_through_window_manager(true, &parent_handle);
+        assert!(matches!(events[..], [WindowEvent::CloseRequested]));
+        assert!(window_handle.is_open());
+    }
+
+    #[test]
+    fn close_request_requires_wm_delete_window() {
+        let event =
+            ClientMessageEvent::new(32, 1, WM_PROTOCOLS, [WM_DELETE_WINDOW + 1, 0, 0, 0, 0]);
+        assert!(!is_close_request(&event, WM_DELETE_WINDOW));
+        // The atom has to be sent as a 32-bit value
+        let mut data = [0u8; 20];
+        data[..4].copy_from_slice(&WM_DELETE_WINDOW.to_ne_bytes());
+        let event = ClientMessageEvent::new(8, 1, WM_PROTOCOLS, data);
+        assert!(!is_close_request(&event, WM_DELETE_WINDOW));
+    }
+
+    #[test]
+    fn mouse_id_ignores_invalid_and_scroll_buttons() {
+        assert_eq!(mouse_id(0), None);
+        for id in 4..=7 {
+            assert_eq!(mouse_id(id), None);
+        }
     }
 }
@@ -0,0 +1,76 @@
+use std::marker::PhantomData;
+use std::os::unix::io::RawFd;
+use std::rc::Rc;
+use std::time::Instant;
+
+use super::runner::{self, Runner};
+use super::XcbConnection;
+use crate::WindowError;
+
+/// Lets a host that already runs its own event loop drive baseview's windows from that loop,
+/// instead of baseview running them on a separate thread. This is only available on Linux.
+///
+/// Creating an `X11EventPump` connects to the X server and makes the current thread responsible
+/// for every window that's opened from it afterwards, including the windows those windows open
+/// in turn. The host should call [`X11EventPump::dispatch`] whenever [`X11EventPump::fd`] becomes
+/// readable, when [`X11EventPump::next_frame`] has passed, and after opening windows or using
+/// their [`WindowHandle`][crate::WindowHandle]s. Windows that are opened from other threads keep using
+/// baseview's own thread.
+///
+/// Dropping the `X11EventPump` closes all of its windows.
+pub struct X11EventPump {
+    runner: Runner,
+    // The windows can only be driven from the thread they were opened on
+    phantom: PhantomData<*mut ()>,
+}
+
+impl X11EventPump {
+    /// Connect to the X server and let the current thread drive the windows opened from it. This
+    /// panics if the current thread already drives baseview's windows, for instance when this is
+    /// called from within a window handler.
+    pub fn new() -> Result<Self, WindowError> {
+        assert!(
+            !runner::is_runner_thread(),
+            "The current thread already drives baseview's windows"
+        );
+
+        let connection =
+            XcbConnection::new().map_err(|err| WindowError::ConnectionFailed(err.to_string()))?;
+
+        Ok(X11EventPump { runner: Runner::new(Rc::new(connection)), phantom: PhantomData })
+    }
+
+    /// The file descriptor of the X11 connection. This becomes readable when the windows have
+    /// received new events.
+    pub fn fd(&self) -> RawFd {
+        self.runner.xcb_fd()
+    }
+
+    /// The time at which [`X11EventPump::dispatch`] should be called again so the windows can draw
+    /// their next frame, or `None` if there are no open windows.
+    pub fn next_frame(&self) -> Option<Instant> {
+        self.runner.next_frame()
+    }
+
+    /// Handle all pending events without blocking. This calls into the window handlers, so this
+    /// must not be called from within a window handler.
+    ///
+    /// Returns [`WindowError::ConnectionFailed`] if the connection to the X server was lost. All
+    /// of the pump's windows are closed then, and a new `X11EventPump` needs to be created to open
+    /// new windows.
+    pub fn dispatch(&mut self) -> Result<(), WindowError> {
+        self.runner.dispatch().map_err(|err| {
+            // The windows can't be used without a working connection
+            self.runner.close_all();
+
+            WindowError::ConnectionFailed(err.to_string())
+        })
+    }
+}
+
+impl Drop for X11EventPump {
+    fn drop(&mut self) {
+        self.runner.close_all();
+        runner::clear_context();
+    }
+}
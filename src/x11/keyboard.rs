@@ -383,10 +383,27 @@ pub(super) fn key_mods(mods: KeyButMask) -> Modifiers {
     ret
 }
 
+/// The modifier that a modifier key itself toggles, if `code` is a modifier key. The modifier state
+/// in X11 key events is the state from right before the event, so this is used to make pressing
+/// Shift report Shift as being held down and releasing it report it as released, like on the other
+/// platforms.
+fn code_to_modifier(code: Code) -> Option<Modifiers> {
+    match code {
+        Code::ShiftLeft | Code::ShiftRight => Some(Modifiers::SHIFT),
+        Code::ControlLeft | Code::ControlRight => Some(Modifiers::CONTROL),
+        Code::AltLeft | Code::AltRight => Some(Modifiers::ALT),
+        Code::MetaLeft | Code::MetaRight => Some(Modifiers::META),
+        _ => None,
+    }
+}
+
 pub(super) fn convert_key_press_event(key_press: &KeyPressEvent) -> KeyboardEvent {
     let hw_keycode = key_press.detail;
     let code = hardware_keycode_to_code(hw_keycode.into());
-    let modifiers = key_mods(key_press.state);
+    let mut modifiers = key_mods(key_press.state);
+    if let Some(modifier) = code_to_modifier(code) {
+        modifiers.insert(modifier);
+    }
     let key = code_to_key(code, modifiers);
     let location = code_to_location(code);
     let state = KeyState::Down;
@@ -397,10 +414,76 @@ pub(super) fn convert_key_press_event(key_press: &KeyPressEvent) -> KeyboardEven
 pub(super) fn convert_key_release_event(key_release: &KeyReleaseEvent) -> KeyboardEvent {
     let hw_keycode = key_release.detail;
     let code = hardware_keycode_to_code(hw_keycode.into());
-    let modifiers = key_mods(key_release.state);
+    let mut modifiers = key_mods(key_release.state);
+    if let Some(modifier) = code_to_modifier(code) {
+        // This is not entirely accurate when both the left and the right key are held down
+        modifiers.remove(modifier);
+    }
     let key = code_to_key(code, modifiers);
     let location = code_to_location(code);
     let state = KeyState::Up;
 
     KeyboardEvent { code, key, modifiers, location, state, repeat: false, is_composing: false }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use x11rb::protocol::xproto::KEY_PRESS_EVENT;
+
+    fn key_event(keycode: u8, state: KeyButMask) -> KeyPressEvent {
+        KeyPressEvent {
+            response_type: KEY_PRESS_EVENT,
+            detail: keycode,
+            sequence: 0,
+            time: 0,
+            root: 0,
+            event: 0,
+            child: 0,
+            root_x: 0,
+            root_y: 0,
+            event_x: 0,
+            event_y: 0,
+            state,
+            same_screen: true,
+        }
+    }
+
+    #[test]
+    fn modifier_codes() {
+        let table = [
+            (Code::ShiftLeft, Some(Modifiers::SHIFT)),
+            (Code::ShiftRight, Some(Modifiers::SHIFT)),
+            (Code::ControlLeft, Some(Modifiers::CONTROL)),
+            (Code::ControlRight, Some(Modifiers::CONTROL)),
+            (Code::AltLeft, Some(Modifiers::ALT)),
+            (Code::AltRight, Some(Modifiers::ALT)),
+            (Code::MetaLeft, Some(Modifiers::META)),
+            (Code::MetaRight, Some(Modifiers::META)),
+            (Code::CapsLock, None),
+            (Code::KeyA, None),
+            (Code::Space, None),
+        ];
+
+        for (code, modifier) in table {
+            assert_eq!(code_to_modifier(code), modifier, "{:?}", code);
+        }
+    }
+
+    #[test]
+    fn lone_shift_press_and_release() {
+        // Keycode 0x32 is the left Shift key. X11 reports the modifier state from before the event.
+        let press = convert_key_press_event(&key_event(0x32, KeyButMask::default()));
+        assert_eq!(press.code, Code::ShiftLeft);
+        assert_eq!(press.key, Key::Shift);
+        assert_eq!(press.state, KeyState::Down);
+        assert_eq!(press.location, Location::Left);
+        assert_eq!(press.modifiers, Modifiers::SHIFT);
+
+        let release = convert_key_release_event(&key_event(0x32, KeyButMask::SHIFT));
+        assert_eq!(release.code, Code::ShiftLeft);
+        assert_eq!(release.state, KeyState::Up);
+        assert_eq!(release.modifiers, Modifiers::empty());
+    }
+}
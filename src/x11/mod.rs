@@ -4,7 +4,12 @@ use xcb_connection::XcbConnection;
 mod window;
 pub use window::*;
 
+pub(crate) mod clipboard;
 mod cursor;
+mod drag_n_drop;
 mod event_loop;
+mod event_pump;
+pub use event_pump::X11EventPump;
 mod keyboard;
+mod runner;
 mod visual_info;
@@ -0,0 +1,501 @@
+//! Every window in the process shares a single X11 connection, and all of their event loops run on
+//! a single thread called the runner thread. This avoids opening a display connection and spawning
+//! a thread for every window, which adds up quickly when a host opens many plugin windows. The
+//! runner thread is started when the first window is opened, and it exits once the last window
+//! has closed.
+//!
+//! Since all windows share a thread, a window handler that blocks also blocks every other window,
+//! and a window handler that panics takes down every other window with it.
+//!
+//! Hosts that run their own event loop can use an [`X11EventPump`][super::X11EventPump] instead,
+//! which turns the host's thread into a runner thread for the windows opened from it.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::rc::Rc;
+use std::sync::{mpsc, Mutex, PoisonError};
+use std::thread;
+use std::time::Instant;
+
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::xproto::{ConfigureWindowAux, ConnectionExt as _, Window as XWindow};
+use x11rb::protocol::Event as XEvent;
+
+use super::event_loop::EventLoop;
+use super::{HandleCommand, XcbConnection};
+use crate::WindowError;
+
+type Task = Box<dyn FnOnce() + Send>;
+
+/// Used to send tasks to the runner thread from other threads.
+struct RunnerHandle {
+    tasks: mpsc::Sender<Task>,
+    /// Writing to this socket wakes up the runner so it can run `tasks`.
+    waker: UnixStream,
+}
+
+impl RunnerHandle {
+    fn wake(&self) {
+        // If the socket's buffer is full then the runner has already been woken up
+        let _ = (&self.waker).write(&[0]);
+    }
+}
+
+/// The handle to the current runner thread, if it's running.
+// `Mutex::new()` is only `const` since Rust 1.63, but let-else already requires Rust 1.65
+#[clippy::msrv = "1.63"]
+static RUNNER: Mutex<Option<RunnerHandle>> = Mutex::new(None);
+
+thread_local! {
+    /// Only set on the runner thread.
+    static CONTEXT: RefCell<Option<RunnerContext>> = const { RefCell::new(None) };
+}
+
+struct RunnerContext {
+    connection: Rc<XcbConnection>,
+    /// Windows that have been opened but that haven't been added to the runner yet. Window
+    /// handlers can open new windows while the runner is handling another window's events.
+    new_windows: Vec<EventLoop>,
+}
+
+/// Run `task` on the runner thread, starting the runner thread if it isn't running. When this is
+/// called from the runner thread, `task` runs immediately. Returns an error if the runner thread
+/// needed to be started but it could not connect to the X server.
+pub(super) fn run_on_runner_thread(
+    task: impl FnOnce() + Send + 'static,
+) -> Result<(), WindowError> {
+    if is_runner_thread() {
+        task();
+        return Ok(());
+    }
+
+    let mut runner = RUNNER.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut task: Task = Box::new(task);
+    if let Some(handle) = &*runner {
+        // This fails if the runner thread has panicked, in which case a new one is started
+        match handle.tasks.send(task) {
+            Ok(()) => {
+                handle.wake();
+                return Ok(());
+            }
+            Err(mpsc::SendError(returned_task)) => task = returned_task,
+        }
+    }
+
+    let handle = spawn_runner_thread()?;
+    let _ = handle.tasks.send(task);
+    handle.wake();
+    *runner = Some(handle);
+
+    Ok(())
+}
+
+/// Whether this is the runner thread. Waiting for the runner from the runner thread would
+/// deadlock.
+pub(super) fn is_runner_thread() -> bool {
+    CONTEXT.with(|context| context.borrow().is_some())
+}
+
+/// The connection shared by all windows. This may only be called from the runner thread.
+pub(super) fn connection() -> Rc<XcbConnection> {
+    CONTEXT.with(|context| {
+        let context = context.borrow();
+        let context = context.as_ref().expect("Windows can only be opened on the runner thread");
+
+        Rc::clone(&context.connection)
+    })
+}
+
+/// Stop treating the current thread as a runner thread. This closes the connection once the last
+/// reference to it is gone.
+pub(super) fn clear_context() {
+    CONTEXT.with(|context| context.borrow_mut().take());
+}
+
+/// Let the runner handle a newly opened window's events. This may only be called from the runner
+/// thread.
+pub(super) fn add_window(event_loop: EventLoop) {
+    CONTEXT.with(|context| {
+        let mut context = context.borrow_mut();
+        let context = context.as_mut().expect("Windows can only be opened on the runner thread");
+
+        context.new_windows.push(event_loop);
+    });
+}
+
+/// Start the runner thread, and wait until it has connected to the X server.
+fn spawn_runner_thread() -> Result<RunnerHandle, WindowError> {
+    let (tasks, tasks_receiver) = mpsc::channel();
+    let (waker, wake_receiver) = UnixStream::pair().expect("Could not create the runner's waker");
+    waker.set_nonblocking(true).expect("Could not create the runner's waker");
+    wake_receiver.set_nonblocking(true).expect("Could not create the runner's waker");
+    let (connected, connected_receiver) = mpsc::sync_channel(1);
+
+    thread::spawn(move || {
+        let connection = match XcbConnection::new() {
+            Ok(connection) => {
+                let _ = connected.send(Ok(()));
+                Rc::new(connection)
+            }
+            Err(err) => {
+                let _ = connected.send(Err(WindowError::ConnectionFailed(err.to_string())));
+                return;
+            }
+        };
+
+        let mut runner = Runner::new(connection);
+        runner.tasks = Some(TaskReceiver { tasks: tasks_receiver, wake_receiver });
+        if let Err(err) = runner.run() {
+            log_warn!("The X11 event loop stopped, closing all windows: {}", err);
+
+            // The windows can't be used without a working connection, so they're closed instead
+            // of taking down the host. The next window that gets opened starts a new runner thread
+            // with a new connection.
+            *RUNNER.lock().unwrap_or_else(PoisonError::into_inner) = None;
+            runner.close_all();
+        }
+
+        drop(runner);
+        clear_context();
+    });
+
+    connected_receiver.recv().unwrap_or_else(|_| {
+        Err(WindowError::ConnectionFailed("the runner thread panicked".into()))
+    })?;
+
+    Ok(RunnerHandle { tasks, waker })
+}
+
+pub(super) struct Runner {
+    connection: Rc<XcbConnection>,
+    windows: HashMap<XWindow, EventLoop>,
+    /// The tasks sent from other threads. This is only set on the shared runner thread, runners
+    /// driven by an [`X11EventPump`][super::X11EventPump] don't receive tasks.
+    tasks: Option<TaskReceiver>,
+}
+
+struct TaskReceiver {
+    tasks: mpsc::Receiver<Task>,
+    /// Becomes readable when a task has been sent. See [`RunnerHandle::wake`].
+    wake_receiver: UnixStream,
+}
+
+impl Runner {
+    /// Create a runner for the current thread, which makes this thread the runner thread for the
+    /// windows opened from it.
+    pub(super) fn new(connection: Rc<XcbConnection>) -> Self {
+        CONTEXT.with(|context| {
+            *context.borrow_mut() =
+                Some(RunnerContext { connection: Rc::clone(&connection), new_windows: Vec::new() });
+        });
+
+        Runner { connection, windows: HashMap::new(), tasks: None }
+    }
+
+    /// The X11 connection's file descriptor, which becomes readable when there are new events.
+    pub(super) fn xcb_fd(&self) -> RawFd {
+        self.connection.conn.as_raw_fd()
+    }
+
+    // FIXME: poll() acts fine on linux, sometimes funky on *BSD. XCB upstream uses a define to
+    // switch between poll() and select() (the latter of which is fine on *BSD), and we should do
+    // the same.
+    fn run(&mut self) -> Result<(), Box<dyn Error>> {
+        use nix::poll::*;
+
+        let xcb_fd = self.connection.conn.as_raw_fd();
+
+        loop {
+            self.run_tasks();
+            adopt_new_windows(&mut self.windows);
+            if self.windows.is_empty() && self.try_exit() {
+                return Ok(());
+            }
+
+            self.frame();
+
+            // Check for any events in the internal buffers
+            // before going to sleep:
+            self.drain_events()?;
+
+            // The requests made while drawing frames and handling events are sent in one go before
+            // going to sleep
+            self.connection.conn.flush()?;
+
+            let window_fds: Vec<(XWindow, RawFd)> = self
+                .windows
+                .values()
+                .filter_map(|window| window.wake_fd().map(|fd| (window.window_id(), fd)))
+                .collect();
+
+            let wake_fd = self.tasks.as_ref().map_or(-1, |tasks| tasks.wake_receiver.as_raw_fd());
+            let mut fds = vec![
+                PollFd::new(xcb_fd, PollFlags::POLLIN),
+                PollFd::new(wake_fd, PollFlags::POLLIN),
+            ];
+            fds.extend(window_fds.iter().map(|&(_, fd)| PollFd::new(fd, PollFlags::POLLIN)));
+
+            // The window handlers may have changed their frame intervals in the meantime
+            let timeout = match self.next_frame() {
+                Some(next_frame) => {
+                    next_frame.saturating_duration_since(Instant::now()).as_millis()
+                }
+                None => 0,
+            };
+
+            match poll(&mut fds, timeout.min(i32::MAX as u128) as i32) {
+                Ok(_) => {}
+                // Interrupted by a signal, so the loop simply starts over
+                Err(nix::errno::Errno::EINTR) => continue,
+                Err(err) => return Err(err.into()),
+            }
+
+            if let Some(revents) = fds[0].revents() {
+                if revents.intersects(PollFlags::POLLERR | PollFlags::POLLHUP) {
+                    return Err("the X11 connection was lost".into());
+                }
+
+                if revents.contains(PollFlags::POLLIN) {
+                    self.drain_events()?;
+                }
+            }
+
+            // A window handle wants us to do something
+            for (&(window_id, _), fd) in window_fds.iter().zip(&fds[2..]) {
+                if fd.revents().map_or(false, |r| !r.is_empty()) {
+                    self.handle_commands(window_id)?;
+                }
+            }
+
+            self.close_windows();
+        }
+    }
+
+    /// Handle everything that's currently pending without blocking: draw the windows whose next
+    /// frame is due, handle the queued X11 events and the window handles' commands, and close the
+    /// windows that were asked to close. This is used by [`X11EventPump`][super::X11EventPump],
+    /// which lets the host's event loop decide when to do this.
+    pub(super) fn dispatch(&mut self) -> Result<(), Box<dyn Error>> {
+        adopt_new_windows(&mut self.windows);
+
+        self.frame();
+        self.drain_events()?;
+
+        let window_ids: Vec<XWindow> = self.windows.keys().copied().collect();
+        for window_id in window_ids {
+            self.handle_commands(window_id)?;
+        }
+
+        self.close_windows();
+        self.connection.conn.flush()?;
+
+        Ok(())
+    }
+
+    /// The time at which the next window should draw a frame, or `None` if there are no windows.
+    pub(super) fn next_frame(&self) -> Option<Instant> {
+        self.windows.values().map(EventLoop::next_frame).min()
+    }
+
+    /// Close all windows right away.
+    pub(super) fn close_all(&mut self) {
+        adopt_new_windows(&mut self.windows);
+        for window in self.windows.values_mut() {
+            window.close();
+        }
+
+        self.windows.clear();
+    }
+
+    /// Draw a frame for every window whose next frame is due.
+    fn frame(&mut self) {
+        let now = Instant::now();
+        for window in self.windows.values_mut() {
+            window.frame(now);
+        }
+    }
+
+    /// Close the windows that were asked to close, along with their popups.
+    fn close_windows(&mut self) {
+        for window in self.windows.values_mut() {
+            window.handle_close_requests();
+        }
+
+        // Dropping a window's event loop destroys the window
+        self.windows.retain(|_, window| window.is_running());
+        self.close_orphaned_popups();
+    }
+
+    /// Run the tasks sent from other threads, like opening new windows.
+    fn run_tasks(&mut self) {
+        let Some(tasks) = &self.tasks else { return };
+
+        let mut buf = [0; 64];
+        while matches!((&tasks.wake_receiver).read(&mut buf), Ok(n) if n > 0) {}
+
+        while let Ok(task) = tasks.tasks.try_recv() {
+            task();
+        }
+    }
+
+    /// Called when there are no windows left. Returns `true` if the runner thread should exit.
+    fn try_exit(&mut self) -> bool {
+        let mut runner = RUNNER.lock().unwrap_or_else(PoisonError::into_inner);
+
+        // A task may have been sent since the last check. New tasks can't be sent while the lock
+        // is held, so this can't miss any tasks.
+        if let Some(Ok(task)) = self.tasks.as_ref().map(|tasks| tasks.tasks.try_recv()) {
+            drop(runner);
+            task();
+
+            return false;
+        }
+
+        *runner = None;
+
+        true
+    }
+
+    /// Popups close along with the window they belong to.
+    fn close_orphaned_popups(&mut self) {
+        loop {
+            let orphaned_popups: Vec<XWindow> = self
+                .windows
+                .values()
+                .filter(|window| {
+                    window.popup_owner().map_or(false, |owner| !self.windows.contains_key(&owner))
+                })
+                .map(EventLoop::window_id)
+                .collect();
+            if orphaned_popups.is_empty() {
+                return;
+            }
+
+            // Closing these popups may in turn orphan their own popups
+            for window_id in orphaned_popups {
+                if let Some(mut window) = self.windows.remove(&window_id) {
+                    window.close();
+                }
+            }
+        }
+    }
+
+    fn drain_events(&mut self) -> Result<(), Box<dyn Error>> {
+        for window in self.windows.values_mut() {
+            window.begin_drain();
+        }
+
+        let conn = &self.connection.conn;
+        while let Some(raw_event) = conn.poll_for_raw_event()? {
+            let event = conn.parse_event(&raw_event)?;
+            if let XEvent::XinputHierarchy(_) = event {
+                self.connection.clear_pointer_kinds();
+                continue;
+            }
+
+            match event_window(&event) {
+                Some(window_id) => {
+                    if let Some(window) = self.windows.get_mut(&window_id) {
+                        window.handle_raw_event(&raw_event, event);
+                    }
+                }
+                // Events that aren't tied to a specific window are sent to every window
+                None => {
+                    for window in self.windows.values_mut() {
+                        window.handle_raw_event(&raw_event, event.clone());
+                    }
+                }
+            }
+
+            // The window handlers may have opened new windows whose events are already queued
+            adopt_new_windows(&mut self.windows);
+        }
+
+        for window in self.windows.values_mut() {
+            window.end_drain();
+        }
+
+        Ok(())
+    }
+
+    fn handle_commands(&mut self, window_id: XWindow) -> Result<(), Box<dyn Error>> {
+        let commands = match self.windows.get(&window_id) {
+            Some(window) => window.take_commands(),
+            None => return Ok(()),
+        };
+
+        for command in commands {
+            match command {
+                HandleCommand::ProcessPendingEvents(done) => {
+                    // This round trip makes sure we've received every event the X server has
+                    // generated up to this point
+                    self.connection.conn.get_input_focus()?.reply()?;
+                    self.drain_events()?;
+
+                    let _ = done.send(());
+                }
+                HandleCommand::Restack(stack_mode) => {
+                    let aux = ConfigureWindowAux::new().stack_mode(stack_mode);
+                    self.connection.conn.configure_window(window_id, &aux)?;
+                }
+                HandleCommand::SendEvent(event) => {
+                    if let Some(window) = self.windows.get_mut(&window_id) {
+                        window.send_synthetic_event(event);
+                    }
+                }
+                HandleCommand::Resize(size) => {
+                    if let Some(window) = self.windows.get_mut(&window_id) {
+                        window.resize(size);
+                    }
+                }
+                HandleCommand::SendMessage(message) => {
+                    if let Some(window) = self.windows.get_mut(&window_id) {
+                        window.send_message(message);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn adopt_new_windows(windows: &mut HashMap<XWindow, EventLoop>) {
+    let new_windows = CONTEXT.with(|context| match context.borrow_mut().as_mut() {
+        Some(context) => std::mem::take(&mut context.new_windows),
+        None => Vec::new(),
+    });
+
+    for window in new_windows {
+        windows.insert(window.window_id(), window);
+    }
+}
+
+/// The window an event was sent to, or `None` if the event isn't tied to a specific window.
+fn event_window(event: &XEvent) -> Option<XWindow> {
+    match event {
+        XEvent::KeyPress(event) | XEvent::KeyRelease(event) => Some(event.event),
+        XEvent::ButtonPress(event) | XEvent::ButtonRelease(event) => Some(event.event),
+        XEvent::MotionNotify(event) => Some(event.event),
+        XEvent::XinputMotion(event)
+        | XEvent::XinputButtonPress(event)
+        | XEvent::XinputButtonRelease(event) => Some(event.event),
+        XEvent::EnterNotify(event) | XEvent::LeaveNotify(event) => Some(event.event),
+        XEvent::FocusIn(event) | XEvent::FocusOut(event) => Some(event.event),
+        XEvent::Expose(event) => Some(event.window),
+        XEvent::ConfigureNotify(event) => Some(event.event),
+        XEvent::MapNotify(event) => Some(event.event),
+        XEvent::UnmapNotify(event) => Some(event.event),
+        XEvent::ReparentNotify(event) => Some(event.event),
+        XEvent::DestroyNotify(event) => Some(event.event),
+        XEvent::PropertyNotify(event) => Some(event.window),
+        XEvent::ClientMessage(event) => Some(event.window),
+        XEvent::SelectionNotify(event) => Some(event.requestor),
+        XEvent::XfixesSelectionNotify(event) => Some(event.window),
+        _ => None,
+    }
+}
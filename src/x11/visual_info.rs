@@ -1,4 +1,5 @@
 use crate::x11::xcb_connection::XcbConnection;
+use crate::Color;
 use std::error::Error;
 use x11rb::connection::Connection;
 use x11rb::protocol::xproto::{
@@ -9,6 +10,10 @@ use x11rb::COPY_FROM_PARENT;
 pub(super) struct WindowVisualConfig {
     #[cfg(feature = "opengl")]
     pub fb_config: Option<crate::gl::x11::FbConfig>,
+    /// Set when an OpenGL context was requested but no matching framebuffer config could be
+    /// found. The window is then created without OpenGL support.
+    #[cfg(feature = "opengl")]
+    pub gl_error: Option<crate::gl::GlError>,
 
     pub visual_depth: u8,
     pub visual_id: Visualid,
@@ -19,18 +24,31 @@ pub(super) struct WindowVisualConfig {
 impl WindowVisualConfig {
     #[cfg(feature = "opengl")]
     pub fn find_best_visual_config_for_gl(
-        connection: &XcbConnection, gl_config: Option<crate::gl::GlConfig>,
+        connection: &XcbConnection, gl_config: Option<crate::gl::GlConfig>, transparent: bool,
     ) -> Result<Self, Box<dyn Error>> {
         let Some(gl_config) = gl_config else { return Self::find_best_visual_config(connection) };
 
         // SAFETY: TODO
-        let (fb_config, window_config) = unsafe {
-            crate::gl::platform::GlContext::get_fb_config_and_visual(connection.dpy, gl_config)
-        }
-        .expect("Could not fetch framebuffer config");
+        let result = unsafe {
+            crate::gl::platform::GlContext::get_fb_config_and_visual(
+                connection.dpy,
+                gl_config,
+                transparent,
+            )
+        };
+        let (fb_config, window_config) = match result {
+            Ok(config) => config,
+            Err(err) => {
+                let mut config = Self::find_best_visual_config(connection)?;
+                config.gl_error = Some(err);
+
+                return Ok(config);
+            }
+        };
 
         Ok(Self {
             fb_config: Some(fb_config),
+            gl_error: None,
             visual_depth: window_config.depth,
             visual_id: window_config.visual,
             color_map: Some(create_color_map(connection, window_config.visual)?),
@@ -43,6 +61,8 @@ impl WindowVisualConfig {
             Some(visual_id) => Ok(Self {
                 #[cfg(feature = "opengl")]
                 fb_config: None,
+                #[cfg(feature = "opengl")]
+                gl_error: None,
                 visual_id,
                 visual_depth: 32,
                 color_map: Some(create_color_map(connection, visual_id)?),
@@ -50,10 +70,41 @@ impl WindowVisualConfig {
         }
     }
 
+    /// The pixel value for a color in this visual, or `None` if this is not a true color visual.
+    /// Visuals with an alpha channel get a fully opaque pixel.
+    pub fn color_to_pixel(&self, screen: &Screen, color: Color) -> Option<u32> {
+        let (visual_id, depth) = if self.visual_id == COPY_FROM_PARENT {
+            (screen.root_visual, screen.root_depth)
+        } else {
+            (self.visual_id, self.visual_depth)
+        };
+
+        let visual = screen
+            .allowed_depths
+            .iter()
+            .flat_map(|depth| &depth.visuals)
+            .find(|visual| visual.visual_id == visual_id)?;
+        if visual.class != VisualClass::TRUE_COLOR {
+            return None;
+        }
+
+        let rgb_mask = visual.red_mask | visual.green_mask | visual.blue_mask;
+        let alpha_mask = if depth == 32 { !rgb_mask } else { 0 };
+
+        Some(
+            scale_to_mask(color.r, visual.red_mask)
+                | scale_to_mask(color.g, visual.green_mask)
+                | scale_to_mask(color.b, visual.blue_mask)
+                | alpha_mask,
+        )
+    }
+
     const fn copy_from_parent() -> Self {
         Self {
             #[cfg(feature = "opengl")]
             fb_config: None,
+            #[cfg(feature = "opengl")]
+            gl_error: None,
             visual_depth: COPY_FROM_PARENT as u8,
             visual_id: COPY_FROM_PARENT,
             color_map: None,
@@ -61,6 +112,20 @@ impl WindowVisualConfig {
     }
 }
 
+/// Scale an 8-bit color channel to the channel's mask, so that 0xff fills the entire mask even
+/// when the channel has more or fewer than 8 bits.
+fn scale_to_mask(value: u8, mask: u32) -> u32 {
+    if mask == 0 {
+        return 0;
+    }
+
+    let shift = mask.trailing_zeros();
+    let max = u64::from(mask >> shift);
+    let scaled = (u64::from(value) * max + 127) / 255;
+
+    ((scaled as u32) << shift) & mask
+}
+
 // For this 32-bit depth to work, you also need to define a color map and set a border
 // pixel: https://cgit.freedesktop.org/xorg/xserver/tree/dix/window.c#n818
 fn create_color_map(
@@ -92,3 +157,27 @@ fn find_visual_for_depth(screen: &Screen, depth: u8) -> Option<Visualid> {
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channels_are_scaled_to_their_mask() {
+        // 8-bit channels are passed through as is
+        assert_eq!(scale_to_mask(0x12, 0x00ff_0000), 0x0012_0000);
+        assert_eq!(scale_to_mask(0xff, 0x0000_00ff), 0x0000_00ff);
+
+        // 10-bit channels, like in a 30-bit visual
+        assert_eq!(scale_to_mask(0xff, 0x3ff0_0000), 0x3ff0_0000);
+        assert_eq!(scale_to_mask(0x80, 0x000f_fc00), 0x202 << 10);
+        assert_eq!(scale_to_mask(0x00, 0x0000_03ff), 0);
+
+        // 5 and 6-bit channels, like in a 16-bit visual
+        assert_eq!(scale_to_mask(0xff, 0xf800), 0xf800);
+        assert_eq!(scale_to_mask(0xff, 0x07e0), 0x07e0);
+        assert_eq!(scale_to_mask(0x80, 0x001f), 0x10);
+
+        assert_eq!(scale_to_mask(0xff, 0), 0);
+    }
+}
@@ -1,38 +1,59 @@
-use std::cell::Cell;
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
 use std::error::Error;
 use std::ffi::c_void;
+use std::io::{self, Read, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
 use std::sync::Arc;
-use std::thread;
+use std::time::{Duration, Instant};
 
+use keyboard_types::{KeyState, KeyboardEvent, Modifiers};
 use raw_window_handle::{
     HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle, XlibDisplayHandle,
     XlibWindowHandle,
 };
 
-use x11rb::connection::Connection;
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::shape::{ConnectionExt as _, SK, SO};
 use x11rb::protocol::xproto::{
-    AtomEnum, ChangeWindowAttributesAux, ConfigureWindowAux, ConnectionExt as _, CreateGCAux,
-    CreateWindowAux, EventMask, PropMode, Visualid, Window as XWindow, WindowClass,
+    AtomEnum, ButtonPressEvent, ChangeWindowAttributesAux, ClientMessageEvent, ClipOrdering,
+    Colormap, ConfigureWindowAux, ConnectionExt as _, CreateGCAux, CreateWindowAux, EventMask,
+    Gcontext, GrabMode, Gravity, ImageFormat, KeyButMask, PropMode, Rectangle, StackMode,
+    Timestamp, Visualid, Window as XWindow, WindowClass, BUTTON_PRESS_EVENT,
 };
 use x11rb::wrapper::ConnectionExt as _;
+use x11rb::COPY_FROM_PARENT;
 
-use super::XcbConnection;
+use super::{runner, XcbConnection};
 use crate::{
-    Event, MouseCursor, Size, WindowEvent, WindowHandler, WindowInfo, WindowOpenOptions,
-    WindowScalePolicy,
+    Backdrop, Event, FramePacing, Framebuffer, HitTestResult, ModifierSides, MouseButton,
+    MouseCursor, PhyPoint, PhySize, Point, PointerKind, Rect, ResizeEdge, Size, UserAttention,
+    WindowError, WindowEvent, WindowHandler, WindowInfo, WindowOpenOptions, WindowScalePolicy,
 };
 
+use super::drag_n_drop::XDND_VERSION;
+use crate::event::EventClock;
 #[cfg(feature = "opengl")]
-use crate::gl::{platform, GlContext};
+use crate::gl::{platform, GlConfig, GlContext, GlContextCell, GlError};
+use crate::mouse_cursor::{CursorAutoHide, RelativeDrag};
 use crate::x11::event_loop::EventLoop;
 use crate::x11::visual_info::WindowVisualConfig;
+use std::rc::Rc;
+use x11rb::properties::{AspectRatio, WmHints, WmSizeHints, WmSizeHintsSpecification};
+use x11rb::protocol::xfixes::{ConnectionExt as _, SelectionEventMask};
+use x11rb::protocol::xinput::{self, ConnectionExt as _, XIEventMask};
 
 pub struct WindowHandle {
     raw_window_handle: Option<RawWindowHandle>,
     close_requested: Arc<AtomicBool>,
     is_open: Arc<AtomicBool>,
+    commands: mpsc::Sender<HandleCommand>,
+    /// Writing to this socket wakes up the event loop so it can handle `commands`.
+    waker: UnixStream,
 }
 
 impl WindowHandle {
@@ -43,48 +64,140 @@ impl WindowHandle {
             // by joining on the event loop thread).
 
             self.close_requested.store(true, Ordering::Relaxed);
+            self.wake();
         }
     }
 
     pub fn is_open(&self) -> bool {
         self.is_open.load(Ordering::Relaxed)
     }
+
+    pub fn process_pending_events(&self) {
+        // On the runner thread the events will be processed as soon as the current event has been
+        // handled, and waiting for the runner would deadlock
+        if !self.is_open() || runner::is_runner_thread() {
+            return;
+        }
+
+        let (tx, rx) = mpsc::sync_channel(1);
+        if self.commands.send(HandleCommand::ProcessPendingEvents(tx)).is_ok() {
+            self.wake();
+
+            // This returns an error if the window closed before handling the command
+            let _ = rx.recv();
+        }
+    }
+
+    pub fn try_raw_window_handle(&self) -> Option<RawWindowHandle> {
+        self.raw_window_handle.filter(|_| self.is_open.load(Ordering::Relaxed))
+    }
+
+    pub fn raise(&self) {
+        self.restack(StackMode::ABOVE);
+    }
+
+    pub fn lower(&self) {
+        self.restack(StackMode::BELOW);
+    }
+
+    pub fn send_event(&self, event: Event) {
+        self.send_command(HandleCommand::SendEvent(event));
+    }
+
+    pub fn resize(&self, size: Size) {
+        self.send_command(HandleCommand::Resize(size));
+    }
+
+    pub fn send_message(&self, message: Box<dyn Any + Send>) {
+        self.send_command(HandleCommand::SendMessage(message));
+    }
+
+    fn send_command(&self, command: HandleCommand) {
+        if self.is_open() && self.commands.send(command).is_ok() {
+            self.wake();
+        }
+    }
+
+    fn restack(&self, stack_mode: StackMode) {
+        self.send_command(HandleCommand::Restack(stack_mode));
+    }
+
+    fn wake(&self) {
+        // If the socket's buffer is full then the event loop has already been woken up
+        let _ = (&self.waker).write(&[0]);
+    }
 }
 
 unsafe impl HasRawWindowHandle for WindowHandle {
     fn raw_window_handle(&self) -> RawWindowHandle {
-        if let Some(raw_window_handle) = self.raw_window_handle {
-            if self.is_open.load(Ordering::Relaxed) {
-                return raw_window_handle;
-            }
-        }
-
-        RawWindowHandle::Xlib(XlibWindowHandle::empty())
+        self.try_raw_window_handle().unwrap_or(RawWindowHandle::Xlib(XlibWindowHandle::empty()))
     }
 }
 
+/// Commands sent from a [`WindowHandle`] to the window's event loop.
+pub(crate) enum HandleCommand {
+    /// Handle all pending X11 events, and then signal the sender.
+    ProcessPendingEvents(mpsc::SyncSender<()>),
+    /// Move the window above or below its siblings.
+    Restack(StackMode),
+    /// Send a synthetic event to the window handler.
+    SendEvent(Event),
+    /// Resize the window to a logical size.
+    Resize(Size),
+    /// Send a message to the window handler's `on_message()`.
+    SendMessage(Box<dyn Any + Send>),
+}
+
 pub(crate) struct ParentHandle {
     close_requested: Arc<AtomicBool>,
     is_open: Arc<AtomicBool>,
+    commands: mpsc::Receiver<HandleCommand>,
+    wake_receiver: UnixStream,
 }
 
 impl ParentHandle {
-    pub fn new() -> (Self, WindowHandle) {
+    pub fn new() -> io::Result<(Self, WindowHandle)> {
         let close_requested = Arc::new(AtomicBool::new(false));
         let is_open = Arc::new(AtomicBool::new(true));
+        let (commands_tx, commands_rx) = mpsc::channel();
+        let (waker, wake_receiver) = UnixStream::pair()?;
+        waker.set_nonblocking(true)?;
+        wake_receiver.set_nonblocking(true)?;
 
         let handle = WindowHandle {
             raw_window_handle: None,
             close_requested: Arc::clone(&close_requested),
             is_open: Arc::clone(&is_open),
+            commands: commands_tx,
+            waker,
         };
 
-        (Self { close_requested, is_open }, handle)
+        Ok((Self { close_requested, is_open, commands: commands_rx, wake_receiver }, handle))
     }
 
     pub fn parent_did_drop(&self) -> bool {
         self.close_requested.load(Ordering::Relaxed)
     }
+
+    /// Make the [`WindowHandle`] report the window as closed right away, instead of only once the
+    /// event loop has been torn down and this handle has been dropped.
+    pub fn mark_closed(&self) {
+        self.is_open.store(false, Ordering::Relaxed);
+    }
+
+    /// The file descriptor that becomes readable when the [`WindowHandle`] wants the event loop
+    /// to wake up.
+    pub fn wake_fd(&self) -> RawFd {
+        self.wake_receiver.as_raw_fd()
+    }
+
+    /// Reset the wake up signal and return all commands sent by the [`WindowHandle`].
+    pub fn take_commands(&self) -> Vec<HandleCommand> {
+        let mut buf = [0; 64];
+        while matches!((&self.wake_receiver).read(&mut buf), Ok(n) if n > 0) {}
+
+        self.commands.try_iter().collect()
+    }
 }
 
 impl Drop for ParentHandle {
@@ -93,17 +206,507 @@ impl Drop for ParentHandle {
     }
 }
 
+pub struct Application {
+    /// Receivers for the windows opened through this application. Their senders are dropped when
+    /// the windows close.
+    windows_closed: Vec<mpsc::Receiver<()>>,
+}
+
+impl Application {
+    pub fn new() -> Self {
+        Self { windows_closed: Vec::new() }
+    }
+
+    pub fn open_window<H, B>(&mut self, options: WindowOpenOptions, build: B) -> WindowHandle
+    where
+        H: WindowHandler + 'static,
+        B: FnOnce(&mut crate::Window) -> H,
+        B: Send + 'static,
+    {
+        let (window_handle, closed) = Window::open_standalone(options, build);
+        self.windows_closed.push(closed);
+
+        window_handle
+    }
+
+    pub fn run(self) {
+        assert!(
+            !runner::is_runner_thread(),
+            "`Application::run()` can't be called from within a window handler"
+        );
+
+        // The windows' event loops all run on the runner thread
+        for closed in self.windows_closed {
+            // This returns an error once the window has closed
+            let _ = closed.recv();
+        }
+    }
+}
+
 pub(crate) struct WindowInner {
-    pub(crate) xcb_connection: XcbConnection,
-    window_id: XWindow,
+    /// The connection is shared by every window. See the [`runner`] module.
+    pub(crate) xcb_connection: Rc<XcbConnection>,
+    pub(crate) window_id: XWindow,
+    /// The window this window is embedded in, if it was opened as a parented window.
+    pub(crate) parent_id: Option<XWindow>,
+    /// The window this popup belongs to, if this window was opened as a popup. See
+    /// [`crate::Window::open_popup`].
+    pub(crate) popup_owner: Option<XWindow>,
     pub(crate) window_info: WindowInfo,
     visual_id: Visualid,
+    colormap: Option<Colormap>,
+    /// Used for presenting framebuffers.
+    gc_id: Gcontext,
+    depth: u8,
     mouse_cursor: Cell<MouseCursor>,
+    cursor_auto_hide: CursorAutoHide,
+    pub(crate) relative_drag: RelativeDrag,
+    /// The area set with [`Window::confine_cursor`], in logical coordinates.
+    pub(crate) cursor_confinement: Cell<Option<Rect>>,
+    /// Whether the mouse has been captured explicitly through [`Window::set_mouse_capture`].
+    mouse_capture: Cell<bool>,
+    /// The button whose `ButtonPressed` event returned
+    /// [`EventStatus::CaptureMouse`][crate::EventStatus::CaptureMouse]. The pointer stays grabbed
+    /// until this button is released.
+    capture_button: Cell<Option<MouseButton>>,
+    frame_interval: Cell<Duration>,
+    /// The display's refresh period while the window uses [`FramePacing::DisplayLink`]. This
+    /// replaces `frame_interval`.
+    refresh_period: Cell<Option<Duration>>,
+    /// Set by [`Window::render_now`], and cleared when the event loop draws the requested frame.
+    pub(crate) render_requested: Cell<bool>,
+    keep_aspect_ratio: Option<(u32, u32)>,
+    resize_increments: Option<Size>,
+    resizable: Cell<bool>,
+    /// Whether this is a standalone window with window manager decorations.
+    pub(crate) decorated: bool,
+    /// Whether the window is currently mapped, as last reported by the X server.
+    pub(crate) visible: Cell<bool>,
+    /// Whether the urgency hint in `WM_HINTS` is set. This is cleared when the window gets focused.
+    urgent: Cell<bool>,
+    pub(crate) coalesce_mouse_moves: bool,
+    pub(crate) scale_policy: WindowScalePolicy,
+    /// Set by [`Window::set_scale_policy`], and applied by the event loop once the window handler
+    /// returns.
+    pub(crate) new_scale_policy: Cell<Option<WindowScalePolicy>>,
 
     pub(crate) close_requested: Cell<bool>,
+    /// Requests made through [`Window`] that are handled once the window handler returns.
+    deferred_tasks: RefCell<VecDeque<WindowTask>>,
+
+    event_clock: EventClock,
+    /// The time at which the last input event was generated.
+    event_timestamp: Cell<Option<Instant>>,
+    /// The modifier keys held down during the last input event. X11 only reports side-agnostic
+    /// modifiers, so this is tracked from key events.
+    modifier_sides: Cell<ModifierSides>,
+    /// The kind of device that generated the last mouse event.
+    pub(crate) pointer_kind: Cell<PointerKind>,
 
     #[cfg(feature = "opengl")]
-    gl_context: Option<GlContext>,
+    gl_context: GlContextCell,
+    /// Why the requested OpenGL context could not be created, if it couldn't.
+    #[cfg(feature = "opengl")]
+    gl_context_error: Option<GlError>,
+}
+
+/// Changes to the window that are made once the window handler returns, so they're handled the
+/// same way and in the same order on every platform. See [`WindowInner::handle_deferred_tasks`].
+enum WindowTask {
+    Resize(Size),
+    SetTitle(String),
+    Close,
+}
+
+impl WindowInner {
+    /// The time between two calls to `on_frame()`.
+    pub(crate) fn frame_interval(&self) -> Duration {
+        self.refresh_period.get().unwrap_or_else(|| self.frame_interval.get())
+    }
+
+    /// Handle the tasks that were queued while the window handler was running.
+    pub(crate) fn handle_deferred_tasks(&self) {
+        loop {
+            // The borrow needs to end before the task is handled
+            let task = match self.deferred_tasks.borrow_mut().pop_front() {
+                Some(task) => task,
+                None => break,
+            };
+
+            match task {
+                WindowTask::Resize(size) => self.resize(size),
+                WindowTask::SetTitle(title) => self.set_title(&title),
+                WindowTask::Close => self.close_requested.set(true),
+            }
+        }
+    }
+
+    pub(crate) fn resize(&self, size: Size) {
+        let scaling = self.window_info.scale();
+        let new_window_info = WindowInfo::from_logical_size(size, scaling);
+
+        // The size hints of non-resizable windows need to include the new size, or the window
+        // manager will not allow the resize
+        if !self.resizable.get() {
+            self.update_size_hints(new_window_info.physical_size());
+        }
+
+        let _ = self.xcb_connection.conn.configure_window(
+            self.window_id,
+            &ConfigureWindowAux::new()
+                .width(new_window_info.physical_size().width)
+                .height(new_window_info.physical_size().height),
+        );
+
+        // This will trigger a `ConfigureNotify` event which will in turn change `self.window_info`
+        // and notify the window handler about it
+    }
+
+    /// Grab or release the pointer for [`Window::set_mouse_capture`] and
+    /// [`EventStatus::CaptureMouse`][crate::EventStatus::CaptureMouse]. Popups always grab the
+    /// pointer, so this does nothing for them.
+    fn grab_pointer(&self, grab: bool) {
+        if self.popup_owner.is_some() {
+            return;
+        }
+
+        let conn = &self.xcb_connection.conn;
+        if grab {
+            let _ = conn.grab_pointer(
+                true,
+                self.window_id,
+                EventMask::POINTER_MOTION
+                    | EventMask::BUTTON_PRESS
+                    | EventMask::BUTTON_RELEASE
+                    | EventMask::ENTER_WINDOW
+                    | EventMask::LEAVE_WINDOW,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+                x11rb::NONE,
+                x11rb::NONE,
+                x11rb::CURRENT_TIME,
+            );
+        } else {
+            let _ = conn.ungrab_pointer(x11rb::CURRENT_TIME);
+        }
+    }
+
+    /// Keep the pointer grabbed until `button` is released after its press returned
+    /// [`EventStatus::CaptureMouse`][crate::EventStatus::CaptureMouse].
+    pub(crate) fn capture_until_released(&self, button: MouseButton) {
+        self.capture_button.set(Some(button));
+        self.grab_pointer(true);
+    }
+
+    /// Release the grab from [`Self::capture_until_released`] if `button` is the button that
+    /// requested it, unless the mouse has also been captured explicitly.
+    pub(crate) fn button_released(&self, button: MouseButton) {
+        if self.capture_button.get() == Some(button) {
+            self.capture_button.set(None);
+            if !self.mouse_capture.get() {
+                self.grab_pointer(false);
+            }
+        }
+    }
+
+    /// Grab the pointer so a popup also receives button presses outside of its bounds, which close
+    /// the popup. This only works while the popup is mapped.
+    fn grab_popup_pointer(&self) {
+        let _ = self.xcb_connection.conn.grab_pointer(
+            false,
+            self.window_id,
+            EventMask::POINTER_MOTION
+                | EventMask::BUTTON_PRESS
+                | EventMask::BUTTON_RELEASE
+                | EventMask::ENTER_WINDOW
+                | EventMask::LEAVE_WINDOW,
+            GrabMode::ASYNC,
+            GrabMode::ASYNC,
+            x11rb::NONE,
+            x11rb::NONE,
+            x11rb::CURRENT_TIME,
+        );
+    }
+
+    fn set_title(&self, title: &str) {
+        let conn = &self.xcb_connection.conn;
+
+        // `WM_NAME` is the legacy property, and most window managers prefer `_NET_WM_NAME`
+        let _ = conn.change_property8(
+            PropMode::REPLACE,
+            self.window_id,
+            AtomEnum::WM_NAME,
+            AtomEnum::STRING,
+            title.as_bytes(),
+        );
+        let _ = conn.change_property8(
+            PropMode::REPLACE,
+            self.window_id,
+            self.xcb_connection.atoms._NET_WM_NAME,
+            self.xcb_connection.atoms.UTF8_STRING,
+            title.as_bytes(),
+        );
+    }
+
+    /// Record the server timestamp of the input event that's about to be handled.
+    pub(crate) fn set_event_time(&self, time: Timestamp) {
+        self.event_timestamp.set(Some(self.event_clock.to_instant(time)));
+    }
+
+    /// The cursor's position relative to the window in logical pixels along with the current
+    /// modifier and button state, if the cursor is inside of the window.
+    pub(crate) fn query_cursor(&self) -> Option<(Point, KeyButMask)> {
+        let reply = self.xcb_connection.conn.query_pointer(self.window_id).ok()?.reply().ok()?;
+        if !reply.same_screen {
+            return None;
+        }
+
+        let window_info = &self.window_info;
+        let physical_pos = PhyPoint::new(reply.win_x as i32, reply.win_y as i32);
+        let physical_size = window_info.physical_size();
+        if physical_pos.x < 0
+            || physical_pos.y < 0
+            || physical_pos.x >= physical_size.width as i32
+            || physical_pos.y >= physical_size.height as i32
+        {
+            return None;
+        }
+
+        Some((physical_pos.to_logical(window_info), reply.mask))
+    }
+
+    /// Forget about modifier keys that were released while another window had keyboard focus,
+    /// using the modifier state from the input event that's about to be handled.
+    pub(crate) fn retain_modifier_sides(&self, modifiers: Modifiers) {
+        let mut sides = self.modifier_sides.get();
+        sides.retain_modifiers(modifiers);
+        self.modifier_sides.set(sides);
+    }
+
+    /// Track the modifier key that was pressed or released in `key_event`, if it's a modifier key.
+    pub(crate) fn update_modifier_sides(&self, key_event: &KeyboardEvent) {
+        let Some(side) = ModifierSides::from_code(key_event.code) else { return };
+
+        let mut sides = self.modifier_sides.get();
+        match key_event.state {
+            KeyState::Down => sides.insert(side),
+            KeyState::Up => sides.remove(side),
+        }
+        self.modifier_sides.set(sides);
+    }
+
+    /// Whether the given position relative to the window, in physical pixels, is inside of the
+    /// window.
+    pub(crate) fn contains(&self, x: i16, y: i16) -> bool {
+        let size = self.window_info.physical_size();
+
+        x >= 0 && y >= 0 && (x as u32) < size.width && (y as u32) < size.height
+    }
+
+    /// The position of the window's top left corner in physical screen coordinates.
+    pub(crate) fn screen_position(&self) -> Option<PhyPoint> {
+        let conn = &self.xcb_connection.conn;
+        let root = self.xcb_connection.screen().root;
+        let reply = conn.translate_coordinates(self.window_id, root, 0, 0).ok()?.reply().ok()?;
+
+        Some(PhyPoint::new(reply.dst_x as i32, reply.dst_y as i32))
+    }
+
+    /// Update the window's `WM_NORMAL_HINTS` property so the window manager knows how the window
+    /// may be resized. `size` is the window's current or requested size in physical pixels.
+    pub(crate) fn update_size_hints(&self, size: PhySize) {
+        let _ = self.size_hints(size).set_normal_hints(&self.xcb_connection.conn, self.window_id);
+    }
+
+    /// Change the cursor shown over the window without changing `mouse_cursor`.
+    fn apply_mouse_cursor(&self, mouse_cursor: MouseCursor) {
+        let Ok(xid) = self.xcb_connection.get_cursor(mouse_cursor) else { return };
+        let _ = self.xcb_connection.conn.change_window_attributes(
+            self.window_id,
+            &ChangeWindowAttributesAux::new().cursor(xid),
+        );
+    }
+
+    /// Called when the cursor moves over the window, so it can be shown again if it was hidden by
+    /// [`Window::set_cursor_auto_hide`].
+    pub(crate) fn cursor_moved(&self, position: PhyPoint) {
+        if self.cursor_auto_hide.cursor_moved((position.x, position.y))
+            && !self.relative_drag.is_active()
+        {
+            self.apply_mouse_cursor(self.mouse_cursor.get());
+        }
+    }
+
+    /// Move the cursor to a position in physical pixels relative to the window.
+    fn warp_cursor(&self, (x, y): (i32, i32)) {
+        let conn = &self.xcb_connection.conn;
+        let _ = conn.warp_pointer(x11rb::NONE, self.window_id, 0, 0, 0, 0, x as i16, y as i16);
+    }
+
+    /// Move the cursor back into the area set with [`Window::confine_cursor`] if it is outside of
+    /// that area. Returns `true` if the cursor was moved, in which case the X server sends another
+    /// motion event for the new position.
+    pub(crate) fn confine_cursor(&self, position: PhyPoint) -> bool {
+        let Some(rect) = self.cursor_confinement.get() else { return false };
+
+        let rect = rect.to_physical(&self.window_info);
+        let max_x = rect.x + (rect.width as i32 - 1).max(0);
+        let max_y = rect.y + (rect.height as i32 - 1).max(0);
+        let confined = (position.x.clamp(rect.x, max_x), position.y.clamp(rect.y, max_y));
+        if confined == (position.x, position.y) {
+            return false;
+        }
+
+        self.warp_cursor(confined);
+        true
+    }
+
+    /// Move the cursor back to the origin of the [relative drag][Window::begin_relative_drag]
+    /// after it has moved. The X server sends a motion event for the warp once it's been
+    /// processed.
+    pub(crate) fn warp_to_relative_drag_origin(&self) {
+        if let Some(origin) = self.relative_drag.warp_target() {
+            self.warp_cursor(origin);
+            self.relative_drag.cursor_warped(false);
+        }
+    }
+
+    /// Hide the cursor if it hasn't moved for long enough. This is called every frame.
+    pub(crate) fn update_cursor_auto_hide(&self, now: Instant) {
+        if self.cursor_auto_hide.should_hide(now) {
+            self.apply_mouse_cursor(MouseCursor::Hidden);
+        }
+    }
+
+    /// Set or clear the urgency hint in the window's `WM_HINTS` property. Window managers
+    /// typically keep highlighting urgent windows until the hint is cleared again.
+    pub(crate) fn set_urgency_hint(&self, urgent: bool) {
+        if self.urgent.replace(urgent) == urgent {
+            return;
+        }
+
+        let conn = &self.xcb_connection.conn;
+        let Ok(Ok(hints)) = WmHints::get(conn, self.window_id).map(|cookie| cookie.reply()) else {
+            return;
+        };
+
+        let mut hints = hints.unwrap_or_default();
+        hints.urgent = urgent;
+        let _ = hints.set(conn, self.window_id);
+    }
+
+    /// The `WM_NORMAL_HINTS` for the window's resizing behavior. See
+    /// [`update_size_hints()`][Self::update_size_hints()].
+    fn size_hints(&self, size: PhySize) -> WmSizeHints {
+        let mut size_hints = WmSizeHints::new();
+        if !self.resizable.get() {
+            // Window managers don't allow resizing windows whose minimum and maximum sizes match
+            size_hints.min_size = Some((size.width as i32, size.height as i32));
+            size_hints.max_size = Some((size.width as i32, size.height as i32));
+        }
+        if let Some((width, height)) = self.keep_aspect_ratio {
+            let aspect_ratio = AspectRatio::new(width as i32, height as i32);
+            size_hints.aspect = Some((aspect_ratio, aspect_ratio));
+        }
+        if let Some(increments) = self.resize_increments {
+            // The base size anchors the increments to the window's current size
+            let scale = self.window_info.scale();
+            let width_inc = ((increments.width * scale).round() as i32).max(1);
+            let height_inc = ((increments.height * scale).round() as i32).max(1);
+            size_hints.size_increment = Some((width_inc, height_inc));
+            size_hints.base_size =
+                Some((size.width as i32 % width_inc, size.height as i32 % height_inc));
+        }
+
+        size_hints
+    }
+
+    /// Ask the window manager to start moving or resizing the window using the
+    /// `_NET_WM_MOVERESIZE` protocol. The coordinates are the pointer's position relative to the
+    /// root window.
+    pub(crate) fn begin_move_resize(
+        &self, hit_test_result: HitTestResult, root_x: i16, root_y: i16, button: u8,
+    ) {
+        let direction = match hit_test_result {
+            HitTestResult::Client => return,
+            HitTestResult::TitleBar => 8,
+            HitTestResult::Resize(edge) => match edge {
+                ResizeEdge::TopLeft => 0,
+                ResizeEdge::Top => 1,
+                ResizeEdge::TopRight => 2,
+                ResizeEdge::Right => 3,
+                ResizeEdge::BottomRight => 4,
+                ResizeEdge::Bottom => 5,
+                ResizeEdge::BottomLeft => 6,
+                ResizeEdge::Left => 7,
+            },
+        };
+
+        let conn = &self.xcb_connection.conn;
+
+        // The window manager can only grab the pointer once the implicit grab from the button
+        // press has been released
+        let _ = conn.ungrab_pointer(x11rb::CURRENT_TIME);
+
+        let event = ClientMessageEvent::new(
+            32,
+            self.window_id,
+            self.xcb_connection.atoms._NET_WM_MOVERESIZE,
+            [root_x as u32, root_y as u32, direction, button as u32, 1],
+        );
+        let _ = conn.send_event(
+            false,
+            self.xcb_connection.screen().root,
+            EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+            event,
+        );
+    }
+
+    /// Send a button press or release event that the window handler ignored to the parent
+    /// window, so the host can handle it instead. This does nothing for standalone windows.
+    pub(crate) fn forward_button_event(&self, mut event: ButtonPressEvent) {
+        let Some(parent_id) = self.parent_id else { return };
+
+        let conn = &self.xcb_connection.conn;
+
+        // The event's coordinates need to be relative to the parent window
+        let Ok(Ok(translated)) = conn
+            .translate_coordinates(self.window_id, parent_id, event.event_x, event.event_y)
+            .map(|cookie| cookie.reply())
+        else {
+            return;
+        };
+
+        let event_mask = if event.response_type & 0x7f == BUTTON_PRESS_EVENT {
+            EventMask::BUTTON_PRESS
+        } else {
+            EventMask::BUTTON_RELEASE
+        };
+
+        event.event = parent_id;
+        event.child = self.window_id;
+        event.event_x = translated.dst_x;
+        event.event_y = translated.dst_y;
+
+        let _ = conn.send_event(false, parent_id, event_mask, event);
+    }
+}
+
+impl Drop for WindowInner {
+    fn drop(&mut self) {
+        // The connection outlives the window, so everything that was created for the window needs
+        // to be freed explicitly
+        #[cfg(feature = "opengl")]
+        drop(self.gl_context.take());
+
+        let conn = &self.xcb_connection.conn;
+        let _ = conn.destroy_window(self.window_id);
+        let _ = conn.free_gc(self.gc_id);
+        if let Some(colormap) = self.colormap {
+            let _ = conn.free_colormap(colormap);
+        }
+        let _ = conn.flush();
+    }
 }
 
 pub struct Window<'a> {
@@ -115,10 +718,25 @@ struct SendableRwh(RawWindowHandle);
 
 unsafe impl Send for SendableRwh {}
 
-type WindowOpenResult = Result<SendableRwh, ()>;
+type WindowOpenResult = Result<SendableRwh, WindowError>;
+
+/// The kind of window [`Window::open`] creates.
+enum WindowKind {
+    Standalone,
+    /// A window embedded in the given parent window.
+    Parented(XWindow),
+    /// An override-redirect window belonging to `owner`, placed at `position` in root window
+    /// coordinates.
+    Popup {
+        owner: XWindow,
+        position: PhyPoint,
+    },
+}
 
 impl<'a> Window<'a> {
-    pub fn open_parented<P, H, B>(parent: &P, options: WindowOpenOptions, build: B) -> WindowHandle
+    pub fn try_open_parented<P, H, B>(
+        parent: &P, options: WindowOpenOptions, build: B,
+    ) -> Result<WindowHandle, WindowError>
     where
         P: HasRawWindowHandle,
         H: WindowHandler + 'static,
@@ -129,67 +747,143 @@ impl<'a> Window<'a> {
         let parent_id = match parent.raw_window_handle() {
             RawWindowHandle::Xlib(h) => h.window as u32,
             RawWindowHandle::Xcb(h) => h.window,
-            h => panic!("unsupported parent handle type {:?}", h),
+            // This includes Wayland parents, since an X11 window can't be embedded in a Wayland
+            // surface
+            _ => return Err(WindowError::UnsupportedParent),
         };
 
-        let (tx, rx) = mpsc::sync_channel::<WindowOpenResult>(1);
+        let (parent_handle, mut window_handle) =
+            ParentHandle::new().map_err(|err| WindowError::CreationFailed(err.to_string()))?;
+
+        let (raw_window_handle, _) =
+            Self::open(WindowKind::Parented(parent_id), options, build, Some(parent_handle))?;
+        window_handle.raw_window_handle = Some(raw_window_handle);
 
-        let (parent_handle, mut window_handle) = ParentHandle::new();
+        Ok(window_handle)
+    }
 
-        thread::spawn(move || {
-            Self::window_thread(Some(parent_id), options, build, tx.clone(), Some(parent_handle))
-                .unwrap();
-        });
+    pub fn open_blocking<H, B>(options: WindowOpenOptions, build: B)
+    where
+        H: WindowHandler + 'static,
+        B: FnOnce(&mut crate::Window) -> H,
+        B: Send + 'static,
+    {
+        assert!(
+            !runner::is_runner_thread(),
+            "Windows can't be opened with `open_blocking()` from within a window handler"
+        );
 
-        let raw_window_handle = rx.recv().unwrap().unwrap();
-        window_handle.raw_window_handle = Some(raw_window_handle.0);
+        let (_, closed) = Self::open(WindowKind::Standalone, options, build, None)
+            .unwrap_or_else(|err| panic!("Could not open the window: {}", err));
 
-        window_handle
+        // This returns an error once the window has closed
+        let _ = closed.recv();
     }
 
-    pub fn open_blocking<H, B>(options: WindowOpenOptions, build: B)
+    /// Open a standalone window without blocking until it closes. The returned receiver can be
+    /// used to wait for the window to close.
+    fn open_standalone<H, B>(
+        options: WindowOpenOptions, build: B,
+    ) -> (WindowHandle, mpsc::Receiver<()>)
     where
         H: WindowHandler + 'static,
         B: FnOnce(&mut crate::Window) -> H,
         B: Send + 'static,
     {
-        let (tx, rx) = mpsc::sync_channel::<WindowOpenResult>(1);
+        let (parent_handle, mut window_handle) =
+            ParentHandle::new().expect("Could not create the window handle");
 
-        let thread = thread::spawn(move || {
-            Self::window_thread(None, options, build, tx, None).unwrap();
-        });
+        let (raw_window_handle, closed) =
+            Self::open(WindowKind::Standalone, options, build, Some(parent_handle))
+                .unwrap_or_else(|err| panic!("Could not open the window: {}", err));
+        window_handle.raw_window_handle = Some(raw_window_handle);
 
-        let _ = rx.recv().unwrap().unwrap();
+        (window_handle, closed)
+    }
 
-        thread.join().unwrap_or_else(|err| {
-            eprintln!("Window thread panicked: {:#?}", err);
-        });
+    pub fn open_popup<H, B>(
+        &mut self, options: WindowOpenOptions, position: Point, build: B,
+    ) -> WindowHandle
+    where
+        H: WindowHandler + 'static,
+        B: FnOnce(&mut crate::Window) -> H,
+        B: Send + 'static,
+    {
+        let inner = &self.inner;
+        let position = position.to_physical(&inner.window_info);
+
+        // Popups are top level windows, so they're positioned relative to the root window
+        let root = inner.xcb_connection.screen().root;
+        let position = inner
+            .xcb_connection
+            .conn
+            .translate_coordinates(inner.window_id, root, position.x as i16, position.y as i16)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .map_or(position, |reply| PhyPoint::new(reply.dst_x as i32, reply.dst_y as i32));
+
+        let (parent_handle, mut window_handle) =
+            ParentHandle::new().expect("Could not create the window handle");
+
+        let kind = WindowKind::Popup { owner: inner.window_id, position };
+        let (raw_window_handle, _) = Self::open(kind, options, build, Some(parent_handle))
+            .unwrap_or_else(|err| panic!("Could not open the window: {}", err));
+        window_handle.raw_window_handle = Some(raw_window_handle);
+
+        window_handle
+    }
+
+    /// Open a window on the runner thread, and wait until it has been created. The returned
+    /// receiver's sender is dropped when the window closes.
+    fn open<H, B>(
+        kind: WindowKind, options: WindowOpenOptions, build: B, parent_handle: Option<ParentHandle>,
+    ) -> Result<(RawWindowHandle, mpsc::Receiver<()>), WindowError>
+    where
+        H: WindowHandler + 'static,
+        B: FnOnce(&mut crate::Window) -> H,
+        B: Send + 'static,
+    {
+        let (tx, rx) = mpsc::sync_channel::<WindowOpenResult>(1);
+        let (close_notifier, closed) = mpsc::sync_channel(0);
+
+        runner::run_on_runner_thread(move || {
+            let result = Self::create(kind, options, build, parent_handle, close_notifier);
+            let _ = tx.send(result.map_err(|err| WindowError::CreationFailed(err.to_string())));
+        })?;
+
+        // The task is dropped without running if the runner's event loop stops first, for instance
+        // because the connection to the X server was lost
+        let raw_window_handle = rx.recv().unwrap_or_else(|_| {
+            Err(WindowError::ConnectionFailed(
+                "the X11 event loop stopped before the window could be opened".into(),
+            ))
+        })?;
+
+        Ok((raw_window_handle.0, closed))
     }
 
-    fn window_thread<H, B>(
-        parent: Option<u32>, options: WindowOpenOptions, build: B,
-        tx: mpsc::SyncSender<WindowOpenResult>, parent_handle: Option<ParentHandle>,
-    ) -> Result<(), Box<dyn Error>>
+    /// Create the window and hand it over to the runner. This is called on the runner thread.
+    fn create<H, B>(
+        kind: WindowKind, options: WindowOpenOptions, build: B,
+        parent_handle: Option<ParentHandle>, close_notifier: mpsc::SyncSender<()>,
+    ) -> Result<SendableRwh, Box<dyn Error>>
     where
         H: WindowHandler + 'static,
         B: FnOnce(&mut crate::Window) -> H,
         B: Send + 'static,
     {
-        // Connect to the X server
-        // FIXME: baseview error type instead of unwrap()
-        let xcb_connection = XcbConnection::new()?;
+        let xcb_connection = runner::connection();
+
+        let (parent, popup_owner, position) = match kind {
+            WindowKind::Standalone => (None, None, None),
+            WindowKind::Parented(parent_id) => (Some(parent_id), None, None),
+            WindowKind::Popup { owner, position } => (None, Some(owner), Some(position)),
+        };
 
         // Get screen information
         let screen = xcb_connection.screen();
         let parent_id = parent.unwrap_or(screen.root);
 
-        let gc_id = xcb_connection.conn.generate_id()?;
-        xcb_connection.conn.create_gc(
-            gc_id,
-            parent_id,
-            &CreateGCAux::new().foreground(screen.black_pixel).graphics_exposures(0),
-        )?;
-
         let scaling = match options.scale {
             WindowScalePolicy::SystemScaleFactor => xcb_connection.get_scaling().unwrap_or(1.0),
             WindowScalePolicy::ScaleFactor(scale) => scale,
@@ -197,53 +891,118 @@ impl<'a> Window<'a> {
 
         let window_info = WindowInfo::from_logical_size(options.size, scaling);
 
+        // Only standalone windows can be placed by the user
+        let requested_position = match kind {
+            WindowKind::Standalone => {
+                options.position.map(|position| position.to_physical(&window_info))
+            }
+            _ => None,
+        };
+        let position = position.or(requested_position).unwrap_or(PhyPoint::new(0, 0));
+
         #[cfg(feature = "opengl")]
-        let visual_info =
-            WindowVisualConfig::find_best_visual_config_for_gl(&xcb_connection, options.gl_config)?;
+        let mut visual_info = WindowVisualConfig::find_best_visual_config_for_gl(
+            &xcb_connection,
+            options.gl_config,
+            options.transparent,
+        )?;
 
         #[cfg(not(feature = "opengl"))]
         let visual_info = WindowVisualConfig::find_best_visual_config(&xcb_connection)?;
 
+        // A 32-bit visual's zero pixel is fully transparent, so transparent windows are cleared
+        // to that instead of being filled with the background color
+        let background_pixel = if options.transparent && visual_info.visual_depth == 32 {
+            Some(0)
+        } else {
+            options.background_color.and_then(|color| visual_info.color_to_pixel(screen, color))
+        };
+
+        // The X server reports errors asynchronously, so the request needs to be checked
+        // explicitly to find out whether the window could be created
         let window_id = xcb_connection.conn.generate_id()?;
-        xcb_connection.conn.create_window(
-            visual_info.visual_depth,
-            window_id,
-            parent_id,
-            0,                                         // x coordinate of the new window
-            0,                                         // y coordinate of the new window
-            window_info.physical_size().width as u16,  // window width
-            window_info.physical_size().height as u16, // window height
-            0,                                         // window border
-            WindowClass::INPUT_OUTPUT,
-            visual_info.visual_id,
-            &CreateWindowAux::new()
-                .event_mask(
-                    EventMask::EXPOSURE
-                        | EventMask::POINTER_MOTION
-                        | EventMask::BUTTON_PRESS
-                        | EventMask::BUTTON_RELEASE
-                        | EventMask::KEY_PRESS
-                        | EventMask::KEY_RELEASE
-                        | EventMask::STRUCTURE_NOTIFY
-                        | EventMask::ENTER_WINDOW
-                        | EventMask::LEAVE_WINDOW,
-                )
-                // As mentioned above, these two values are needed to be able to create a window
-                // with a depth of 32-bits when the parent window has a different depth
-                .colormap(visual_info.color_map)
-                .border_pixel(0),
-        )?;
-        xcb_connection.conn.map_window(window_id)?;
+        xcb_connection
+            .conn
+            .create_window(
+                visual_info.visual_depth,
+                window_id,
+                parent_id,
+                position.x as i16, // x coordinate of the new window
+                position.y as i16, // y coordinate of the new window
+                window_info.physical_size().width as u16, // window width
+                window_info.physical_size().height as u16, // window height
+                0,                 // window border
+                WindowClass::INPUT_OUTPUT,
+                visual_info.visual_id,
+                &CreateWindowAux::new()
+                    .event_mask(
+                        EventMask::EXPOSURE
+                            | EventMask::POINTER_MOTION
+                            | EventMask::BUTTON_PRESS
+                            | EventMask::BUTTON_RELEASE
+                            | EventMask::KEY_PRESS
+                            | EventMask::KEY_RELEASE
+                            | EventMask::STRUCTURE_NOTIFY
+                            | EventMask::FOCUS_CHANGE
+                            | EventMask::ENTER_WINDOW
+                            | EventMask::LEAVE_WINDOW,
+                    )
+                    // As mentioned above, these two values are needed to be able to create a window
+                    // with a depth of 32-bits when the parent window has a different depth
+                    .colormap(visual_info.color_map)
+                    .border_pixel(0)
+                    .background_pixel(background_pixel)
+                    // Without a cursor of its own, the window shows its parent's cursor. A window
+                    // embedded in another window would then change its cursor along with the
+                    // parent's instead of showing the default cursor.
+                    .cursor(xcb_connection.get_cursor(MouseCursor::Default).ok())
+                    // Popups are placed and stacked without involving the window manager
+                    .override_redirect(popup_owner.is_some() as u32),
+            )?
+            .check()?;
+
+        // When available, pointer motion and button events are received through XInput 2
+        // instead, since those events contain sub-pixel positions. The button events are needed
+        // as well because motion events during a drag are sent the same way as the button press
+        // that started the drag.
+        if xcb_connection.xinput2 {
+            xcb_connection.conn.xinput_xi_select_events(
+                window_id,
+                &[xinput::EventMask {
+                    deviceid: xinput::Device::ALL_MASTER.into(),
+                    mask: vec![
+                        XIEventMask::MOTION
+                            | XIEventMask::BUTTON_PRESS
+                            | XIEventMask::BUTTON_RELEASE,
+                    ],
+                }],
+            )?;
+        }
 
-        // Change window title
-        let title = options.title;
-        xcb_connection.conn.change_property8(
-            PropMode::REPLACE,
+        if xcb_connection.xfixes {
+            xcb_connection.conn.xfixes_select_selection_input(
+                window_id,
+                xcb_connection.atoms.CLIPBOARD,
+                SelectionEventMask::SET_SELECTION_OWNER
+                    | SelectionEventMask::SELECTION_WINDOW_DESTROY
+                    | SelectionEventMask::SELECTION_CLIENT_CLOSE,
+            )?;
+        }
+
+        // The graphics context is used to present framebuffers, so it needs to be created for the
+        // window itself since the parent window's depth may differ from the window's depth
+        let gc_id = xcb_connection.conn.generate_id()?;
+        xcb_connection.conn.create_gc(
+            gc_id,
             window_id,
-            AtomEnum::WM_NAME,
-            AtomEnum::STRING,
-            title.as_bytes(),
+            &CreateGCAux::new().foreground(screen.black_pixel).graphics_exposures(0),
         )?;
+        // Only windows that copy their parent's visual need a round trip to find out their depth
+        let depth = if visual_info.visual_id == COPY_FROM_PARENT {
+            xcb_connection.conn.get_geometry(window_id)?.reply()?.depth
+        } else {
+            visual_info.visual_depth
+        };
 
         xcb_connection.conn.change_property32(
             PropMode::REPLACE,
@@ -253,37 +1012,158 @@ impl<'a> Window<'a> {
             &[xcb_connection.atoms.WM_DELETE_WINDOW],
         )?;
 
-        xcb_connection.conn.flush()?;
+        if options.drag_n_drop {
+            xcb_connection.conn.change_property32(
+                PropMode::REPLACE,
+                window_id,
+                xcb_connection.atoms.XdndAware,
+                AtomEnum::ATOM,
+                &[XDND_VERSION],
+            )?;
+        }
+
+        // Window managers use these to associate top level windows with the application
+        if parent.is_none() {
+            if let Some(app_id) = &options.app_id {
+                // The instance and class names, both terminated by a null byte
+                let wm_class = format!("{}\0{}\0", app_id, app_id);
+                xcb_connection.conn.change_property8(
+                    PropMode::REPLACE,
+                    window_id,
+                    AtomEnum::WM_CLASS,
+                    AtomEnum::STRING,
+                    wm_class.as_bytes(),
+                )?;
+            }
+
+            xcb_connection.conn.change_property32(
+                PropMode::REPLACE,
+                window_id,
+                xcb_connection.atoms._NET_WM_PID,
+                AtomEnum::CARDINAL,
+                &[std::process::id()],
+            )?;
+        }
+
+        if parent.is_none() && popup_owner.is_none() && options.no_focus_on_open {
+            // A user time of zero asks the window manager not to focus the window when it gets
+            // mapped, and clearing the input hint keeps it from focusing the window on clicks
+            xcb_connection.conn.change_property32(
+                PropMode::REPLACE,
+                window_id,
+                xcb_connection.atoms._NET_WM_USER_TIME,
+                AtomEnum::CARDINAL,
+                &[0],
+            )?;
+
+            let mut hints = WmHints::new();
+            hints.input = Some(false);
+            hints.set(&xcb_connection.conn, window_id)?;
+        }
 
+        if parent.is_none() && popup_owner.is_none() && !options.decorations {
+            // There's no standard way to do this, but practically every window manager supports
+            // the Motif hints. The first field indicates that the third field (the decorations)
+            // is set.
+            xcb_connection.conn.change_property32(
+                PropMode::REPLACE,
+                window_id,
+                xcb_connection.atoms._MOTIF_WM_HINTS,
+                xcb_connection.atoms._MOTIF_WM_HINTS,
+                &[2, 0, 0, 0, 0],
+            )?;
+        }
+
+        // The window's properties are sent to the X server in one go together with the title,
+        // the size hints and the map request below. The window itself already exists since
+        // creating it was checked, so GLX can use it before then.
         // TODO: These APIs could use a couple tweaks now that everything is internal and there is
         //       no error handling anymore at this point. Everything is more or less unchanged
         //       compared to when raw-gl-context was a separate crate.
         #[cfg(feature = "opengl")]
-        let gl_context = visual_info.fb_config.map(|fb_config| {
+        let (gl_context, gl_context_error) = {
             use std::ffi::c_ulong;
 
             let window = window_id as c_ulong;
             let display = xcb_connection.dpy;
 
             // Because of the visual negotation we had to take some extra steps to create this context
-            let context = unsafe { platform::GlContext::create(window, display, fb_config) }
-                .expect("Could not create OpenGL context");
-            GlContext::new(context)
-        });
+            match visual_info.fb_config {
+                Some(fb_config) => {
+                    match unsafe { platform::GlContext::create(window, display, fb_config) } {
+                        Ok(context) => (GlContextCell::new(Some(GlContext::new(context))), None),
+                        Err(err) => (GlContextCell::new(None), Some(err)),
+                    }
+                }
+                None => (GlContextCell::new(None), visual_info.gl_error.take()),
+            }
+        };
 
         let mut inner = WindowInner {
             xcb_connection,
             window_id,
+            parent_id: parent,
+            popup_owner,
             window_info,
             visual_id: visual_info.visual_id,
+            colormap: visual_info.color_map,
+            gc_id,
+            depth,
             mouse_cursor: Cell::new(MouseCursor::default()),
+            cursor_auto_hide: CursorAutoHide::new(),
+            relative_drag: RelativeDrag::new(),
+            cursor_confinement: Cell::new(None),
+            mouse_capture: Cell::new(false),
+            capture_button: Cell::new(None),
+            frame_interval: Cell::new(Duration::from_millis(15)),
+            refresh_period: Cell::new(None),
+            render_requested: Cell::new(false),
+            keep_aspect_ratio: options.keep_aspect_ratio,
+            resize_increments: options.resize_increments,
+            resizable: Cell::new(true),
+            decorated: parent.is_none() && popup_owner.is_none() && options.decorations,
+            visible: Cell::new(options.visible),
+            urgent: Cell::new(false),
+            coalesce_mouse_moves: options.coalesce_mouse_moves,
+            scale_policy: options.scale,
+            new_scale_policy: Cell::new(None),
 
             close_requested: Cell::new(false),
+            deferred_tasks: RefCell::new(VecDeque::new()),
+
+            event_clock: EventClock::new(),
+            event_timestamp: Cell::new(None),
+            modifier_sides: Cell::new(ModifierSides::empty()),
+            pointer_kind: Cell::new(PointerKind::Mouse),
 
             #[cfg(feature = "opengl")]
             gl_context,
+            #[cfg(feature = "opengl")]
+            gl_context_error,
         };
 
+        inner.set_title(&options.title);
+
+        // Window managers only look at the requested position when the window gets mapped, so
+        // these hints need to be set before that
+        let mut size_hints = inner.size_hints(window_info.physical_size());
+        if let Some(position) = requested_position {
+            // With static gravity the position refers to the window's contents instead of to the
+            // window manager's frame, like on the other platforms
+            size_hints.position =
+                Some((WmSizeHintsSpecification::UserSpecified, position.x, position.y));
+            size_hints.win_gravity = Some(Gravity::STATIC);
+        }
+        size_hints.set_normal_hints(&inner.xcb_connection.conn, window_id)?;
+
+        if options.visible {
+            inner.xcb_connection.conn.map_window(window_id)?;
+            if popup_owner.is_some() {
+                inner.grab_popup_pointer();
+            }
+        }
+        inner.xcb_connection.conn.flush()?;
+
         let mut window = crate::Window::new(Window { inner: &mut inner });
 
         let mut handler = build(&mut window);
@@ -292,33 +1172,168 @@ impl<'a> Window<'a> {
         // the correct dpi scaling.
         handler.on_event(&mut window, Event::Window(WindowEvent::Resized(window_info)));
 
-        let _ = tx.send(Ok(SendableRwh(window.raw_window_handle())));
+        let raw_window_handle = SendableRwh(window.raw_window_handle());
+        inner.handle_deferred_tasks();
+        // The event loop isn't running yet, so the requests made by the window handler while
+        // it's being built need to be sent here
+        inner.xcb_connection.conn.flush()?;
 
-        EventLoop::new(inner, handler, parent_handle).run()?;
+        runner::add_window(EventLoop::new(inner, handler, parent_handle, close_notifier));
 
-        Ok(())
+        Ok(raw_window_handle)
     }
 
-    pub fn set_mouse_cursor(&self, mouse_cursor: MouseCursor) {
-        if self.inner.mouse_cursor.get() == mouse_cursor {
+    pub fn physical_size(&self) -> PhySize {
+        self.inner.window_info.physical_size()
+    }
+
+    pub fn present(&mut self, framebuffer: &Framebuffer) {
+        let size = framebuffer.size();
+        if size.width == 0 || size.height == 0 {
             return;
         }
 
-        let xid = self.inner.xcb_connection.get_cursor(mouse_cursor).unwrap();
-
-        if xid != 0 {
-            let _ = self.inner.xcb_connection.conn.change_window_attributes(
+        let conn = &self.inner.xcb_connection.conn;
+
+        // Large framebuffers don't fit in a single request, so they're sent in bands of rows. A
+        // `PutImage` request has a 24 byte header.
+        let width = size.width as usize;
+        let rows_per_request = ((conn.maximum_request_bytes() - 24) / (width * 4)).max(1);
+        let mut opaque_rows = Vec::new();
+        for (i, rows) in framebuffer.pixels().chunks(width * rows_per_request).enumerate() {
+            // The upper 8 bits are the alpha channel in 32-bit visuals. Those are ignored for
+            // framebuffers, so they need to be set explicitly to keep compositors from treating
+            // the pixels as transparent.
+            let rows = if self.inner.depth == 32 {
+                opaque_rows.clear();
+                opaque_rows.extend(rows.iter().map(|pixel| pixel | 0xff00_0000));
+                &opaque_rows[..]
+            } else {
+                rows
+            };
+
+            // The pixels are stored in the client's native byte order, which `ZPixmap` images
+            // with 24 or 32 bits of depth use as well
+            let data =
+                unsafe { std::slice::from_raw_parts(rows.as_ptr() as *const u8, rows.len() * 4) };
+
+            let _ = conn.put_image(
+                ImageFormat::Z_PIXMAP,
                 self.inner.window_id,
-                &ChangeWindowAttributesAux::new().cursor(xid),
+                self.inner.gc_id,
+                size.width as u16,
+                (rows.len() / width) as u16,
+                0,
+                (i * rows_per_request) as i16,
+                0,
+                self.inner.depth,
+                data,
             );
-            let _ = self.inner.xcb_connection.conn.flush();
         }
+    }
+
+    pub fn set_mouse_cursor(&self, mouse_cursor: MouseCursor) {
+        if self.inner.mouse_cursor.replace(mouse_cursor) == mouse_cursor {
+            return;
+        }
+
+        // The new cursor is shown once the cursor moves again, or when the relative drag ends
+        if !self.inner.cursor_auto_hide.is_hidden() && !self.inner.relative_drag.is_active() {
+            self.inner.apply_mouse_cursor(mouse_cursor);
+        }
+    }
+
+    pub fn set_cursor_auto_hide(&mut self, timeout: Option<Duration>) {
+        if self.inner.cursor_auto_hide.set_timeout(timeout) {
+            self.inner.apply_mouse_cursor(self.inner.mouse_cursor.get());
+        }
+    }
 
-        self.inner.mouse_cursor.set(mouse_cursor);
+    pub fn begin_relative_drag(&mut self, origin: Point) {
+        let origin = origin.to_physical(&self.inner.window_info);
+        self.inner.relative_drag.begin((origin.x, origin.y));
+        self.inner.apply_mouse_cursor(MouseCursor::Hidden);
+        self.set_mouse_capture(true);
+    }
+
+    pub fn end_relative_drag(&mut self) {
+        let Some(origin) = self.inner.relative_drag.end() else { return };
+
+        self.inner.warp_cursor(origin);
+        self.set_mouse_capture(false);
+        if !self.inner.cursor_auto_hide.is_hidden() {
+            self.inner.apply_mouse_cursor(self.inner.mouse_cursor.get());
+        }
     }
 
     pub fn close(&mut self) {
-        self.inner.close_requested.set(true);
+        self.inner.deferred_tasks.borrow_mut().push_back(WindowTask::Close);
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        let conn = &self.inner.xcb_connection.conn;
+        if visible {
+            let _ = conn.map_window(self.inner.window_id);
+            if self.inner.popup_owner.is_some() {
+                self.inner.grab_popup_pointer();
+            }
+        } else {
+            let _ = conn.unmap_window(self.inner.window_id);
+        }
+    }
+
+    pub fn scale_policy(&self) -> WindowScalePolicy {
+        self.inner.scale_policy
+    }
+
+    pub fn set_scale_policy(&mut self, policy: WindowScalePolicy) {
+        self.inner.new_scale_policy.set(Some(policy));
+    }
+
+    pub fn set_mouse_capture(&mut self, capture: bool) {
+        self.inner.mouse_capture.set(capture);
+        if capture || self.inner.capture_button.get().is_none() {
+            self.inner.grab_pointer(capture);
+        }
+    }
+
+    pub fn confine_cursor(&mut self, rect: Option<Rect>) {
+        // The cursor is moved back into the area on the next motion event
+        self.inner.cursor_confinement.set(rect);
+    }
+
+    pub fn begin_move_drag(&mut self) {
+        self.begin_move_resize_drag(HitTestResult::TitleBar);
+    }
+
+    pub fn begin_resize_drag(&mut self, edge: ResizeEdge) {
+        self.begin_move_resize_drag(HitTestResult::Resize(edge));
+    }
+
+    fn begin_move_resize_drag(&mut self, hit_test_result: HitTestResult) {
+        if self.inner.parent_id.is_some() {
+            return;
+        }
+
+        let conn = &self.inner.xcb_connection.conn;
+        let Ok(Ok(pointer)) = conn.query_pointer(self.inner.window_id).map(|cookie| cookie.reply())
+        else {
+            return;
+        };
+
+        // The window manager needs to know which button is being held down
+        let button = [
+            KeyButMask::BUTTON1,
+            KeyButMask::BUTTON2,
+            KeyButMask::BUTTON3,
+            KeyButMask::BUTTON4,
+            KeyButMask::BUTTON5,
+        ]
+        .iter()
+        .position(|&mask| pointer.mask.contains(mask))
+        .map_or(1, |index| index as u8 + 1);
+
+        self.inner.begin_move_resize(hit_test_result, pointer.root_x, pointer.root_y, button);
     }
 
     pub fn has_focus(&mut self) -> bool {
@@ -329,25 +1344,213 @@ impl<'a> Window<'a> {
         unimplemented!()
     }
 
-    pub fn resize(&mut self, size: Size) {
-        let scaling = self.inner.window_info.scale();
-        let new_window_info = WindowInfo::from_logical_size(size, scaling);
+    pub fn request_user_attention(&mut self, kind: UserAttention) {
+        if self.inner.parent_id.is_some() {
+            return;
+        }
+
+        let conn = &self.inner.xcb_connection.conn;
+        let atoms = &self.inner.xcb_connection.atoms;
+        if let Ok(Ok(focus)) = conn.get_input_focus().map(|cookie| cookie.reply()) {
+            if focus.focus == self.inner.window_id {
+                return;
+            }
+        }
 
-        let _ = self.inner.xcb_connection.conn.configure_window(
+        // `_NET_WM_STATE_ADD`, with a regular application as the source of the request. The
+        // window manager removes the state again once the window gets focused.
+        let event = ClientMessageEvent::new(
+            32,
             self.inner.window_id,
-            &ConfigureWindowAux::new()
-                .width(new_window_info.physical_size().width)
-                .height(new_window_info.physical_size().height),
+            atoms._NET_WM_STATE,
+            [1, atoms._NET_WM_STATE_DEMANDS_ATTENTION, 0, 1, 0],
+        );
+        let _ = conn.send_event(
+            false,
+            self.inner.xcb_connection.screen().root,
+            EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+            event,
         );
+
+        if kind == UserAttention::Critical {
+            self.inner.set_urgency_hint(true);
+        }
+    }
+
+    pub fn set_backdrop(&mut self, backdrop: Backdrop) {
+        if self.inner.parent_id.is_some() {
+            return;
+        }
+
+        let conn = &self.inner.xcb_connection.conn;
+        let atom = self.inner.xcb_connection.atoms._KDE_NET_WM_BLUR_BEHIND_REGION;
+        match backdrop {
+            // An empty region blurs the entire window
+            Backdrop::Blur | Backdrop::Vibrancy => {
+                let _ = conn.change_property32(
+                    PropMode::REPLACE,
+                    self.inner.window_id,
+                    atom,
+                    AtomEnum::CARDINAL,
+                    &[],
+                );
+            }
+            Backdrop::None => {
+                let _ = conn.delete_property(self.inner.window_id, atom);
+            }
+        }
+    }
+
+    pub fn flush(&mut self) {
         let _ = self.inner.xcb_connection.conn.flush();
+    }
 
-        // This will trigger a `ConfigureNotify` event which will in turn change `self.window_info`
-        // and notify the window handler about it
+    pub fn has_window_manager(&self) -> bool {
+        self.inner.xcb_connection.has_window_manager().unwrap_or(false)
+    }
+
+    pub fn event_timestamp(&self) -> Option<Instant> {
+        self.inner.event_timestamp.get()
+    }
+
+    pub fn modifier_sides(&self) -> ModifierSides {
+        self.inner.modifier_sides.get()
+    }
+
+    pub fn pointer_kind(&self) -> PointerKind {
+        self.inner.pointer_kind.get()
+    }
+
+    pub fn cursor_position(&self) -> Option<Point> {
+        self.inner.query_cursor().map(|(position, _)| position)
+    }
+
+    pub fn set_frame_interval(&mut self, interval: Duration) {
+        // The event loop picks this up when scheduling the next frame
+        self.inner.frame_interval.set(interval);
+    }
+
+    pub fn set_frame_pacing(&mut self, pacing: FramePacing) {
+        // There's no portable way to wait for vertical blanking outside of OpenGL's swap interval,
+        // so this only matches the timer to the display's current refresh rate
+        let refresh_period = match pacing {
+            FramePacing::Timer => None,
+            FramePacing::DisplayLink => self
+                .refresh_rate()
+                .filter(|rate| *rate > 0.0)
+                .map(|rate| Duration::from_secs_f64(1.0 / rate)),
+        };
+        self.inner.refresh_period.set(refresh_period);
+    }
+
+    pub fn render_now(&mut self) {
+        // The event loop draws the frame once the handler returns, without waiting in `poll()`
+        self.inner.render_requested.set(true);
+    }
+
+    pub fn refresh_rate(&self) -> Option<f64> {
+        self.inner.xcb_connection.get_refresh_rate(self.inner.window_id).ok().flatten()
+    }
+
+    pub fn window_info(&self) -> WindowInfo {
+        self.inner.window_info
+    }
+
+    pub fn position(&self) -> Option<Point> {
+        let position = self.inner.screen_position()?;
+
+        Some(position.to_logical(&self.inner.window_info))
+    }
+
+    pub fn resize(&mut self, size: Size) {
+        self.inner.deferred_tasks.borrow_mut().push_back(WindowTask::Resize(size));
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.inner.deferred_tasks.borrow_mut().push_back(WindowTask::SetTitle(title.to_owned()));
+    }
+
+    pub fn set_resizable(&mut self, resizable: bool) {
+        self.inner.resizable.set(resizable);
+        self.inner.update_size_hints(self.inner.window_info.physical_size());
+    }
+
+    pub fn set_input_region(&mut self, region: Option<&[Rect]>) {
+        let conn = &self.inner.xcb_connection.conn;
+        let window_id = self.inner.window_id;
+
+        match region {
+            Some(region) => {
+                let rectangles: Vec<Rectangle> = region
+                    .iter()
+                    .map(|rect| {
+                        let rect = rect.to_physical(&self.inner.window_info);
+                        Rectangle {
+                            x: rect.x as i16,
+                            y: rect.y as i16,
+                            width: rect.width as u16,
+                            height: rect.height as u16,
+                        }
+                    })
+                    .collect();
+
+                let _ = conn.shape_rectangles(
+                    SO::SET,
+                    SK::INPUT,
+                    ClipOrdering::UNSORTED,
+                    window_id,
+                    0,
+                    0,
+                    &rectangles,
+                );
+            }
+            // Setting the shape to `None` restores the default rectangular input shape
+            None => {
+                let _ = conn.shape_mask(SO::SET, SK::INPUT, window_id, 0, 0, x11rb::NONE);
+            }
+        }
+    }
+
+    pub fn x11_window_id(&self) -> u32 {
+        self.inner.window_id
     }
 
     #[cfg(feature = "opengl")]
     pub fn gl_context(&self) -> Option<&crate::gl::GlContext> {
-        self.inner.gl_context.as_ref()
+        self.inner.gl_context.get()
+    }
+
+    #[cfg(feature = "opengl")]
+    pub fn gl_context_error(&self) -> Option<&GlError> {
+        self.inner.gl_context_error.as_ref()
+    }
+
+    #[cfg(feature = "opengl")]
+    pub fn create_gl_context(&mut self, config: GlConfig) -> Result<&GlContext, GlError> {
+        if let Some(gl_context) = self.inner.gl_context.get() {
+            return Ok(gl_context);
+        }
+
+        // The context needs to use the visual the window was created with
+        let conn = &self.inner.xcb_connection.conn;
+        let visual = conn
+            .get_window_attributes(self.inner.window_id)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .map(|attributes| attributes.visual)
+            .ok_or(GlError::CreationFailed(platform::CreationFailedError::NoVisual))?;
+
+        let display = self.inner.xcb_connection.dpy;
+        let context = unsafe {
+            let fb_config = platform::GlContext::get_fb_config_for_visual(display, config, visual)?;
+            platform::GlContext::create(
+                self.inner.window_id as std::os::raw::c_ulong,
+                display,
+                fb_config,
+            )?
+        };
+
+        Ok(self.inner.gl_context.get_or_set(GlContext::new(context)))
     }
 }
 
@@ -374,6 +1577,28 @@ unsafe impl<'a> HasRawDisplayHandle for Window<'a> {
     }
 }
 
-pub fn copy_to_clipboard(_data: &str) {
-    todo!()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mark_closed_closes_the_window_handle() {
+        let (parent_handle, window_handle) = ParentHandle::new().unwrap();
+        assert!(window_handle.is_open());
+
+        window_handle.send_message(Box::new(()));
+        assert_eq!(parent_handle.take_commands().len(), 1);
+
+        // This happens when the window gets closed by the user or the window manager, while the
+        // event loop and the `ParentHandle` are still around
+        parent_handle.mark_closed();
+        assert!(!window_handle.is_open());
+
+        // Messages sent after the window has closed are dropped
+        window_handle.send_message(Box::new(()));
+        assert!(parent_handle.take_commands().is_empty());
+
+        drop(parent_handle);
+        assert!(!window_handle.is_open());
+    }
 }
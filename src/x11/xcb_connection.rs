@@ -4,13 +4,16 @@ use std::error::Error;
 
 use x11::{xlib, xlib::Display, xlib_xcb};
 
-use x11rb::connection::Connection;
+use x11rb::connection::{Connection, RequestConnection};
 use x11rb::cursor::Handle as CursorHandle;
-use x11rb::protocol::xproto::{Cursor, Screen};
+use x11rb::protocol::randr::{self, ConnectionExt as _, ModeFlag};
+use x11rb::protocol::xfixes::{self, ConnectionExt as _};
+use x11rb::protocol::xinput::{self, ConnectionExt as _};
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt as _, Cursor, Screen, Window};
 use x11rb::resource_manager;
 use x11rb::xcb_ffi::XCBConnection;
 
-use crate::MouseCursor;
+use crate::{MouseCursor, PointerKind};
 
 use super::cursor;
 
@@ -18,6 +21,33 @@ x11rb::atom_manager! {
     pub Atoms: AtomsCookie {
         WM_PROTOCOLS,
         WM_DELETE_WINDOW,
+        UTF8_STRING,
+        CLIPBOARD,
+        _NET_WM_NAME,
+        _NET_WM_PID,
+        _NET_WM_USER_TIME,
+        _NET_WM_MOVERESIZE,
+        _NET_WM_STATE,
+        _NET_WM_STATE_DEMANDS_ATTENTION,
+        _MOTIF_WM_HINTS,
+        _KDE_NET_WM_BLUR_BEHIND_REGION,
+        _NET_SUPPORTING_WM_CHECK,
+
+        XdndAware,
+        XdndEnter,
+        XdndPosition,
+        XdndStatus,
+        XdndLeave,
+        XdndDrop,
+        XdndFinished,
+        XdndSelection,
+        XdndTypeList,
+        XdndActionCopy,
+        XdndActionMove,
+        XdndActionLink,
+        XdndActionPrivate,
+        TextUriList: b"text/uri-list",
+        BASEVIEW_DND_DATA,
     }
 }
 
@@ -31,7 +61,15 @@ pub struct XcbConnection {
     pub(crate) atoms: Atoms,
     pub(crate) resources: resource_manager::Database,
     pub(crate) cursor_handle: CursorHandle,
+    /// Whether the X server supports XInput 2, which is used to receive pointer events with
+    /// sub-pixel positions.
+    pub(crate) xinput2: bool,
+    /// Whether the X server supports XFixes, which is used to get notified when the clipboard's
+    /// owner changes.
+    pub(crate) xfixes: bool,
     pub(super) cursor_cache: RefCell<HashMap<MouseCursor, u32>>,
+    /// The kinds of the XInput 2 pointer devices that have generated events so far.
+    pointer_kinds: RefCell<HashMap<xinput::DeviceId, PointerKind>>,
 }
 
 impl XcbConnection {
@@ -49,6 +87,20 @@ impl XcbConnection {
         let atoms = Atoms::new(&conn)?.reply()?;
         let resources = resource_manager::new_from_default(&conn)?;
         let cursor_handle = CursorHandle::new(&conn, screen, &resources)?.reply()?;
+        let xinput2 = supports_xinput2(&conn);
+        let xfixes = supports_xfixes(&conn);
+
+        // Devices can be added, removed, or reassigned while the connection is open, which also
+        // means that device IDs get reused. Those changes are reported on the root window.
+        if xinput2 {
+            conn.xinput_xi_select_events(
+                conn.setup().roots[screen].root,
+                &[xinput::EventMask {
+                    deviceid: xinput::Device::ALL.into(),
+                    mask: vec![xinput::XIEventMask::HIERARCHY],
+                }],
+            )?;
+        }
 
         Ok(Self {
             dpy,
@@ -57,7 +109,10 @@ impl XcbConnection {
             atoms,
             resources,
             cursor_handle,
+            xinput2,
+            xfixes,
             cursor_cache: RefCell::new(HashMap::new()),
+            pointer_kinds: RefCell::new(HashMap::new()),
         })
     }
 
@@ -96,6 +151,55 @@ impl XcbConnection {
         yres / 96.0
     }
 
+    /// The kind of the XInput 2 slave device with the given ID. X11 doesn't directly report this,
+    /// so this is based on the device's touch capabilities and its name, like other toolkits do.
+    pub(crate) fn pointer_kind(&self, device: xinput::DeviceId) -> PointerKind {
+        *self
+            .pointer_kinds
+            .borrow_mut()
+            .entry(device)
+            .or_insert_with(|| self.query_pointer_kind(device).unwrap_or(PointerKind::Mouse))
+    }
+
+    /// Forget the pointer kinds queried so far. Called when an XInput 2 device has been added,
+    /// removed, or changed, since its ID may now refer to a different device.
+    pub(crate) fn clear_pointer_kinds(&self) {
+        self.pointer_kinds.borrow_mut().clear();
+    }
+
+    fn query_pointer_kind(&self, device: xinput::DeviceId) -> Result<PointerKind, Box<dyn Error>> {
+        let reply = self.conn.xinput_xi_query_device(device)?.reply()?;
+        let Some(info) = reply.infos.first() else { return Ok(PointerKind::Mouse) };
+
+        for class in &info.classes {
+            match &class.data {
+                xinput::DeviceClassData::Touch(touch)
+                    if touch.mode == xinput::TouchMode::DIRECT =>
+                {
+                    return Ok(PointerKind::Touch);
+                }
+                xinput::DeviceClassData::Touch(_) | xinput::DeviceClassData::Gesture(_) => {
+                    return Ok(PointerKind::Touchpad);
+                }
+                _ => {}
+            }
+        }
+
+        let name = String::from_utf8_lossy(&info.name).to_lowercase();
+        let has_word = |words: &[&str]| {
+            name.split(|c: char| !c.is_alphanumeric()).any(|word| words.contains(&word))
+        };
+        if has_word(&["touchpad", "trackpad", "synaptics"]) {
+            Ok(PointerKind::Touchpad)
+        } else if has_word(&["pen", "stylus", "eraser", "wacom"]) {
+            Ok(PointerKind::Pen)
+        } else if has_word(&["touchscreen"]) {
+            Ok(PointerKind::Touch)
+        } else {
+            Ok(PointerKind::Mouse)
+        }
+    }
+
     #[inline]
     pub fn get_scaling(&self) -> Result<f64, Box<dyn Error>> {
         Ok(self.get_scaling_xft()?.unwrap_or(self.get_scaling_screen_dimensions()))
@@ -118,11 +222,115 @@ impl XcbConnection {
         }
     }
 
+    /// Whether a window manager is managing the screen. ICCCM compliant window managers own the
+    /// `WM_S<screen>` selection, and EWMH compliant window managers point the root window's
+    /// `_NET_SUPPORTING_WM_CHECK` property at a child window that has the same property.
+    pub fn has_window_manager(&self) -> Result<bool, Box<dyn Error>> {
+        let selection = format!("WM_S{}", self.screen);
+        let selection = self.conn.intern_atom(false, selection.as_bytes())?.reply()?.atom;
+        if self.conn.get_selection_owner(selection)?.reply()?.owner != x11rb::NONE {
+            return Ok(true);
+        }
+
+        let check_window = |window: Window| -> Option<Window> {
+            let property = self
+                .conn
+                .get_property(
+                    false,
+                    window,
+                    self.atoms._NET_SUPPORTING_WM_CHECK,
+                    AtomEnum::WINDOW,
+                    0,
+                    1,
+                )
+                .ok()?
+                .reply()
+                .ok()?;
+            let window = property.value32()?.next();
+            window
+        };
+
+        // A window manager that has exited may leave the root window's property behind, in which
+        // case the window it points to no longer exists
+        let Some(window) = check_window(self.screen().root) else { return Ok(false) };
+        Ok(check_window(window) == Some(window))
+    }
+
+    /// Get the refresh rate in Hz of the CRTC the window's top left corner is on using RandR.
+    /// Returns `None` if the window isn't on any active CRTC.
+    pub fn get_refresh_rate(&self, window: Window) -> Result<Option<f64>, Box<dyn Error>> {
+        let root = self.screen().root;
+        let position = self.conn.translate_coordinates(window, root, 0, 0)?.reply()?;
+        let (x, y) = (position.dst_x as i32, position.dst_y as i32);
+
+        let resources = self.conn.randr_get_screen_resources_current(root)?.reply()?;
+        for crtc in resources.crtcs {
+            let info = self.conn.randr_get_crtc_info(crtc, resources.config_timestamp)?.reply()?;
+            if info.mode == 0
+                || x < info.x as i32
+                || y < info.y as i32
+                || x >= info.x as i32 + info.width as i32
+                || y >= info.y as i32 + info.height as i32
+            {
+                continue;
+            }
+
+            return Ok(resources
+                .modes
+                .iter()
+                .find(|mode| mode.id == info.mode)
+                .and_then(mode_rate));
+        }
+
+        Ok(None)
+    }
+
     pub fn screen(&self) -> &Screen {
         &self.conn.setup().roots[self.screen]
     }
 }
 
+fn supports_xfixes(conn: &XCBConnection) -> bool {
+    if !matches!(conn.extension_information(xfixes::X11_EXTENSION_NAME), Ok(Some(_))) {
+        return false;
+    }
+
+    // The version needs to be negotiated before any other XFixes requests can be made
+    match conn.xfixes_query_version(1, 0).map(|cookie| cookie.reply()) {
+        Ok(Ok(version)) => version.major_version >= 1,
+        _ => false,
+    }
+}
+
+fn supports_xinput2(conn: &XCBConnection) -> bool {
+    if !matches!(conn.extension_information(xinput::X11_EXTENSION_NAME), Ok(Some(_))) {
+        return false;
+    }
+
+    // The server replies with the newest version it supports that's not newer than the requested
+    // version
+    match conn.xinput_xi_query_version(2, 0).map(|cookie| cookie.reply()) {
+        Ok(Ok(version)) => version.major_version >= 2,
+        _ => false,
+    }
+}
+
+fn mode_rate(mode: &randr::ModeInfo) -> Option<f64> {
+    let mut vtotal = mode.vtotal as f64;
+    if mode.mode_flags.contains(ModeFlag::DOUBLE_SCAN) {
+        vtotal *= 2.0;
+    }
+    if mode.mode_flags.contains(ModeFlag::INTERLACE) {
+        vtotal /= 2.0;
+    }
+
+    if mode.htotal == 0 || vtotal == 0.0 {
+        return None;
+    }
+
+    Some(mode.dot_clock as f64 / (mode.htotal as f64 * vtotal))
+}
+
 impl Drop for XcbConnection {
     fn drop(&mut self) {
         unsafe {